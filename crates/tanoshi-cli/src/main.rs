@@ -2,10 +2,20 @@ extern crate log;
 
 use std::path::PathBuf;
 
+use base64::{engine::general_purpose, Engine};
 use clap::{Parser, Subcommand};
+use ring::{
+    rand::SystemRandom,
+    signature::{Ed25519KeyPair, KeyPair},
+};
 use serde::Serialize;
 use tanoshi_lib::prelude::SourceInfo;
-use tanoshi_vm::{prelude::ExtensionManager, PLUGIN_EXTENSION};
+use tanoshi_vm::{
+    extension::{sha256_hex, PluginIndex},
+    prelude::ExtensionManager,
+    PLUGIN_EXTENSION,
+};
+use tokio::io::AsyncWriteExt;
 
 const TARGET: &str = env!("TARGET");
 
@@ -22,6 +32,18 @@ struct Opts {
 enum Command {
     /// Generate index.json
     GenerateJson,
+    /// Generate ed25519 key pair used to sign extensions
+    GenerateKey {
+        /// Path to write private key
+        #[clap(long, default_value = "tanoshi.key")]
+        output: PathBuf,
+    },
+    /// Add checksum and signature of every extension to index.json made by generate-json
+    Sign {
+        /// Path to private key made by generate-key
+        #[clap(long)]
+        key: PathBuf,
+    },
 }
 
 #[derive(Debug, Serialize)]
@@ -74,6 +96,75 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             let json = serde_json::to_string(&indexes)?;
             tokio::fs::write(target_dir_path.join("index").with_extension("json"), json).await?;
         }
+        Command::GenerateKey { output } => {
+            let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+                .map_err(|_| "failed to generate key")?;
+            let key_pair =
+                Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).map_err(|_| "failed to generate key")?;
+
+            // never overwrite a key, extensions signed with it could no longer be verified
+            let mut options = tokio::fs::OpenOptions::new();
+            options.write(true).create_new(true);
+            #[cfg(unix)]
+            options.mode(0o600);
+            let mut file = options.open(&output).await.map_err(|e| match e.kind() {
+                std::io::ErrorKind::AlreadyExists => {
+                    format!("{} already exists, refusing to overwrite", output.display())
+                }
+                _ => format!("failed to create {}: {e}", output.display()),
+            })?;
+            file.write_all(general_purpose::STANDARD.encode(pkcs8.as_ref()).as_bytes())
+                .await?;
+
+            println!(
+                "private key written to {}, keep it secret",
+                output.display()
+            );
+            println!(
+                "public key: {}",
+                general_purpose::STANDARD.encode(key_pair.public_key().as_ref())
+            );
+        }
+        Command::Sign { key } => {
+            let target_dir_path = PathBuf::new().join("output").join(TARGET);
+
+            let pkcs8 =
+                general_purpose::STANDARD.decode(tokio::fs::read_to_string(&key).await?.trim())?;
+            let key_pair = Ed25519KeyPair::from_pkcs8(&pkcs8).map_err(|_| "invalid private key")?;
+
+            let index_path = target_dir_path.join("index").with_extension("json");
+            let mut indexes: Vec<serde_json::Value> =
+                serde_json::from_str(&tokio::fs::read_to_string(&index_path).await?)?;
+
+            for index in indexes.iter_mut() {
+                let plugin_index = PluginIndex {
+                    id: index["id"].as_i64().ok_or("index without id")?,
+                    name: index["name"]
+                        .as_str()
+                        .ok_or("index without name")?
+                        .to_string(),
+                    version: index["version"]
+                        .as_str()
+                        .ok_or("index without version")?
+                        .to_string(),
+                    ..Default::default()
+                };
+
+                let contents = tokio::fs::read(
+                    target_dir_path
+                        .join(plugin_index.name.to_lowercase())
+                        .with_extension(PLUGIN_EXTENSION),
+                )
+                .await?;
+                let sha256 = sha256_hex(&contents);
+                let signature = key_pair.sign(plugin_index.signed_message(&sha256).as_bytes());
+
+                index["sha256"] = sha256.into();
+                index["signature"] = general_purpose::STANDARD.encode(signature.as_ref()).into();
+            }
+
+            tokio::fs::write(&index_path, serde_json::to_string(&indexes)?).await?;
+        }
     }
 
     Ok(())
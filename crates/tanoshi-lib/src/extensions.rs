@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-use crate::models::{ChapterInfo, Input, MangaInfo, SourceInfo};
+use crate::models::{ChapterInfo, HttpProfile, Input, MangaInfo, ResolvedUrl, SourceInfo};
 use anyhow::Result;
 
 pub trait Extension: Send + Sync {
@@ -10,6 +10,12 @@ pub trait Extension: Send + Sync {
         HashMap::new()
     }
 
+    /// Called by host when http profile for this source changes, the default stores it
+    /// for `tanoshi_util::http` requests, see [`HttpProfile::current`]
+    fn set_http_profile(&mut self, profile: HttpProfile) {
+        HttpProfile::set_current(profile);
+    }
+
     fn filter_list(&self) -> Vec<Input> {
         vec![]
     }
@@ -38,6 +44,12 @@ pub trait Extension: Send + Sync {
     fn get_chapters(&self, path: String) -> Result<Vec<ChapterInfo>>;
 
     fn get_pages(&self, path: String) -> Result<Vec<String>>;
+
+    /// Convert a manga or chapter url of this source into its path,
+    /// return `None` if the url is not recognized
+    fn resolve_url(&self, _url: String) -> Result<Option<ResolvedUrl>> {
+        Ok(None)
+    }
 }
 
 /// A type represents an extension
@@ -0,0 +1,68 @@
+use std::{collections::HashMap, sync::RwLock};
+
+use serde::{Deserialize, Serialize};
+
+/// Profile set by host, every extension library has its own copy
+static CURRENT: RwLock<Option<HttpProfile>> = RwLock::new(None);
+
+/// A type represent http settings applied to requests made for a source
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct HttpProfile {
+    /// proxy url, `http`, `https` and `socks5` scheme are supported,
+    /// `none` to not use a proxy from a profile it is merged into
+    #[serde(default)]
+    pub proxy: Option<String>,
+    /// `none` to not use an user agent from a profile it is merged into
+    #[serde(default)]
+    pub user_agent: Option<String>,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// path to a persistent cookie jar shared between host and extension,
+    /// `none` to not use a cookie jar from a profile it is merged into
+    #[serde(default)]
+    pub cookie_jar: Option<String>,
+}
+
+impl HttpProfile {
+    /// Value that clears a setting of the profile it is merged into
+    pub const NONE: &'static str = "none";
+
+    /// Merge two profiles, values from `other` take precedence,
+    /// including [`HttpProfile::NONE`]
+    pub fn merge(&self, other: &HttpProfile) -> HttpProfile {
+        let mut headers = self.headers.clone();
+        headers.extend(other.headers.clone());
+
+        HttpProfile {
+            proxy: other.proxy.clone().or_else(|| self.proxy.clone()),
+            user_agent: other.user_agent.clone().or_else(|| self.user_agent.clone()),
+            headers,
+            cookie_jar: other.cookie_jar.clone().or_else(|| self.cookie_jar.clone()),
+        }
+    }
+
+    /// Profile ready to be used for requests, with [`HttpProfile::NONE`] values removed.
+    /// Only call this once every profile is merged.
+    pub fn resolve(self) -> HttpProfile {
+        let resolve =
+            |value: Option<String>| value.filter(|value| !value.eq_ignore_ascii_case(Self::NONE));
+
+        HttpProfile {
+            proxy: resolve(self.proxy),
+            user_agent: resolve(self.user_agent),
+            headers: self.headers,
+            cookie_jar: resolve(self.cookie_jar),
+        }
+    }
+
+    /// Profile host set for this extension with [`crate::extensions::Extension::set_http_profile`]
+    pub fn current() -> Option<HttpProfile> {
+        CURRENT.read().ok().and_then(|profile| profile.clone())
+    }
+
+    pub fn set_current(profile: HttpProfile) {
+        if let Ok(mut current) = CURRENT.write() {
+            *current = Some(profile);
+        }
+    }
+}
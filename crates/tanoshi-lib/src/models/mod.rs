@@ -12,3 +12,9 @@ pub use input::*;
 
 pub mod version;
 pub use version::*;
+
+pub mod http_profile;
+pub use http_profile::*;
+
+pub mod resolved_url;
+pub use resolved_url::*;
@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+
+/// A type represent where a url points to in a source
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ResolvedUrl {
+    Manga {
+        path: String,
+    },
+    Chapter {
+        /// path of manga the chapter belongs to
+        manga_path: String,
+        path: String,
+    },
+}
@@ -3,6 +3,7 @@ pub mod model;
 use graphql_client::GraphQLQuery;
 use model::Input;
 
+type NaiveDate = String;
 type NaiveDateTime = String;
 
 type InputList = Vec<Input>;
@@ -223,6 +224,14 @@ pub struct UninstallSource;
 )]
 pub struct UserLogin;
 
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "graphql/schema.graphql",
+    query_path = "graphql/refresh_token.graphql",
+    response_derives = "Debug"
+)]
+pub struct RefreshToken;
+
 #[derive(GraphQLQuery)]
 #[graphql(
     schema_path = "graphql/schema.graphql",
@@ -439,6 +448,78 @@ pub struct AnilistLoginStart;
 )]
 pub struct AnilistLoginEnd;
 
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "graphql/schema.graphql",
+    query_path = "graphql/oidc_login_start.graphql",
+    response_derives = "Debug"
+)]
+pub struct OidcLoginStart;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "graphql/schema.graphql",
+    query_path = "graphql/oidc_login_end.graphql",
+    response_derives = "Debug"
+)]
+pub struct OidcLoginEnd;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "graphql/schema.graphql",
+    query_path = "graphql/link_oidc_account.graphql",
+    response_derives = "Debug"
+)]
+pub struct LinkOidcAccount;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "graphql/schema.graphql",
+    query_path = "graphql/unlink_oidc_account.graphql",
+    response_derives = "Debug"
+)]
+pub struct UnlinkOidcAccount;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "graphql/schema.graphql",
+    query_path = "graphql/fetch_linked_accounts.graphql",
+    response_derives = "Debug"
+)]
+pub struct FetchLinkedAccounts;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "graphql/schema.graphql",
+    query_path = "graphql/enroll_totp.graphql",
+    response_derives = "Debug"
+)]
+pub struct EnrollTotp;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "graphql/schema.graphql",
+    query_path = "graphql/confirm_totp.graphql",
+    response_derives = "Debug"
+)]
+pub struct ConfirmTotp;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "graphql/schema.graphql",
+    query_path = "graphql/disable_totp.graphql",
+    response_derives = "Debug"
+)]
+pub struct DisableTotp;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "graphql/schema.graphql",
+    query_path = "graphql/regenerate_recovery_codes.graphql",
+    response_derives = "Debug"
+)]
+pub struct RegenerateRecoveryCodes;
+
 #[derive(GraphQLQuery)]
 #[graphql(
     schema_path = "graphql/schema.graphql",
@@ -486,3 +567,147 @@ pub struct UpdateTrackerStatus;
     response_derives = "Debug"
 )]
 pub struct RefreshChapters;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "graphql/schema.graphql",
+    query_path = "graphql/fetch_user_preferences.graphql",
+    response_derives = "Debug"
+)]
+pub struct FetchUserPreferences;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "graphql/schema.graphql",
+    query_path = "graphql/set_user_preference.graphql",
+    response_derives = "Debug"
+)]
+pub struct SetUserPreference;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "graphql/schema.graphql",
+    query_path = "graphql/delete_user_preference.graphql",
+    response_derives = "Debug"
+)]
+pub struct DeleteUserPreference;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "graphql/schema.graphql",
+    query_path = "graphql/subscribe_user_preference_changes.graphql",
+    response_derives = "Debug"
+)]
+pub struct SubscribeUserPreferenceChanges;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "graphql/schema.graphql",
+    query_path = "graphql/fetch_library.graphql",
+    response_derives = "Debug"
+)]
+pub struct FetchLibrary;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "graphql/schema.graphql",
+    query_path = "graphql/search_library.graphql",
+    response_derives = "Debug"
+)]
+pub struct SearchLibrary;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "graphql/schema.graphql",
+    query_path = "graphql/fetch_reading_statistics.graphql",
+    response_derives = "Debug"
+)]
+pub struct FetchReadingStatistics;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "graphql/schema.graphql",
+    query_path = "graphql/set_manga_metadata_override.graphql",
+    response_derives = "Debug"
+)]
+pub struct SetMangaMetadataOverride;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "graphql/schema.graphql",
+    query_path = "graphql/upload_manga_cover.graphql",
+    response_derives = "Debug"
+)]
+pub struct UploadMangaCover;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "graphql/schema.graphql",
+    query_path = "graphql/remove_manga_cover.graphql",
+    response_derives = "Debug"
+)]
+pub struct RemoveMangaCover;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "graphql/schema.graphql",
+    query_path = "graphql/reset_manga_metadata.graphql",
+    response_derives = "Debug"
+)]
+pub struct ResetMangaMetadata;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "graphql/schema.graphql",
+    query_path = "graphql/set_manga_tags.graphql",
+    response_derives = "Debug"
+)]
+pub struct SetMangaTags;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "graphql/schema.graphql",
+    query_path = "graphql/set_manga_rating.graphql",
+    response_derives = "Debug"
+)]
+pub struct SetMangaRating;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "graphql/schema.graphql",
+    query_path = "graphql/set_manga_note.graphql",
+    response_derives = "Debug"
+)]
+pub struct SetMangaNote;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "graphql/schema.graphql",
+    query_path = "graphql/fetch_tags.graphql",
+    response_derives = "Debug"
+)]
+pub struct FetchTags;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "graphql/schema.graphql",
+    query_path = "graphql/set_scanlator_setting.graphql",
+    response_derives = "Debug"
+)]
+pub struct SetScanlatorSetting;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "graphql/schema.graphql",
+    query_path = "graphql/global_search.graphql",
+    response_derives = "Debug"
+)]
+pub struct GlobalSearch;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "graphql/schema.graphql",
+    query_path = "graphql/resolve_url.graphql",
+    response_derives = "Debug"
+)]
+pub struct ResolveUrl;
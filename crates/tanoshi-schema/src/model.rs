@@ -11,6 +11,9 @@ pub struct Source {
     pub icon: String,
     pub has_update: bool,
     pub installed: bool,
+    pub repository: Option<String>,
+    /// other repositories providing a source with the same id
+    pub conflicts: Vec<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -26,6 +29,8 @@ pub struct ServerStatus {
     pub activated: bool,
     pub version: String,
     pub loggedin: bool,
+    pub oidc_enabled: bool,
+    pub password_login_enabled: bool,
 }
 
 impl Default for ServerStatus {
@@ -34,6 +39,8 @@ impl Default for ServerStatus {
             activated: false,
             version: "0.0.0".to_string(),
             loggedin: false,
+            oidc_enabled: false,
+            password_login_enabled: true,
         }
     }
 }
@@ -53,6 +60,8 @@ pub struct DownloadQueue {
 pub struct Category {
     pub id: Option<i64>,
     pub name: String,
+    /// filter expression of a smart category
+    pub rule: Option<String>,
     pub count: i64,
 }
 
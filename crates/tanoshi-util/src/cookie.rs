@@ -0,0 +1,632 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, RwLock, Weak},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Jars opened in this process by file, so host and extension requests share one jar
+/// and its lock instead of overwriting each other's cookies
+static JARS: Mutex<Option<HashMap<PathBuf, Weak<CookieJar>>>> = Mutex::new(None);
+
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Cookie {
+    name: String,
+    value: String,
+    domain: String,
+    /// Cookie without domain attribute, only sent to the host that set it
+    host_only: bool,
+    path: String,
+    secure: bool,
+    /// Unix timestamp in seconds, `None` for a session cookie which is never persisted
+    expires: Option<u64>,
+}
+
+impl Cookie {
+    fn is_expired(&self, now: u64) -> bool {
+        self.expires.map(|expires| expires <= now).unwrap_or(false)
+    }
+
+    /// A cookie replaces another with the same name, domain and path
+    fn replaces(&self, other: &Cookie) -> bool {
+        self.name == other.name && self.domain == other.domain && self.path == other.path
+    }
+
+    fn matches(&self, secure: bool, host: &str, path: &str) -> bool {
+        let domain_matches = if self.host_only {
+            host == self.domain
+        } else {
+            domain_match(host, &self.domain)
+        };
+
+        (secure || !self.secure) && domain_matches && path_match(path, &self.path)
+    }
+}
+
+#[derive(Default)]
+struct State {
+    modified: Option<SystemTime>,
+    persistent: Vec<Cookie>,
+    session: Vec<Cookie>,
+}
+
+/// A cookie jar persisted to a file. The file is reloaded when changed by another
+/// process, session cookies are kept in memory only.
+pub struct CookieJar {
+    path: PathBuf,
+    state: RwLock<State>,
+}
+
+impl CookieJar {
+    /// Open the jar persisted at `path`, a jar already open on the same file is shared
+    pub fn open<P: AsRef<Path>>(path: P) -> Arc<Self> {
+        let path = path.as_ref().to_path_buf();
+
+        let mut jars = JARS.lock().unwrap_or_else(|e| e.into_inner());
+        let jars = jars.get_or_insert_with(HashMap::new);
+        if let Some(jar) = jars.get(&path).and_then(Weak::upgrade) {
+            return jar;
+        }
+        jars.retain(|_, jar| jar.strong_count() > 0);
+
+        let jar = Arc::new(Self {
+            path: path.clone(),
+            state: RwLock::new(State::default()),
+        });
+        jar.reload();
+        jars.insert(path, Arc::downgrade(&jar));
+
+        jar
+    }
+
+    fn modified(&self) -> Option<SystemTime> {
+        std::fs::metadata(&self.path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+
+    fn read_file(&self) -> Option<Vec<Cookie>> {
+        std::fs::read_to_string(&self.path)
+            .ok()
+            .and_then(|content| ron::from_str(&content).ok())
+    }
+
+    fn reload(&self) {
+        let modified = self.modified();
+        let mut state = match self.state.write() {
+            Ok(state) => state,
+            Err(_) => return,
+        };
+
+        if modified.is_none() || state.modified == modified {
+            return;
+        }
+
+        if let Some(cookies) = self.read_file() {
+            state.modified = modified;
+            state.persistent = cookies;
+        }
+    }
+
+    /// Returns value for `Cookie` header of a request to `host` and `path`,
+    /// `secure` is whether the request goes over https
+    pub fn cookie_header(&self, secure: bool, host: &str, path: &str) -> Option<String> {
+        self.reload();
+
+        let host = host.to_lowercase();
+        let now = now();
+        let state = self.state.read().ok()?;
+        let mut cookies = state
+            .persistent
+            .iter()
+            .chain(state.session.iter())
+            .filter(|cookie| !cookie.is_expired(now) && cookie.matches(secure, &host, path))
+            .collect::<Vec<_>>();
+        // more specific paths first, as browsers send them
+        cookies.sort_by_key(|cookie| std::cmp::Reverse(cookie.path.len()));
+
+        let value = cookies
+            .iter()
+            .map(|cookie| format!("{}={}", cookie.name, cookie.value))
+            .collect::<Vec<_>>()
+            .join("; ");
+
+        if value.is_empty() {
+            None
+        } else {
+            Some(value)
+        }
+    }
+
+    /// Store cookies from `Set-Cookie` headers of a response from `host` and `path`,
+    /// `secure` is whether the response came over https
+    pub fn store<'a, I: IntoIterator<Item = &'a str>>(
+        &self,
+        secure: bool,
+        host: &str,
+        path: &str,
+        set_cookies: I,
+    ) {
+        let host = host.to_lowercase();
+        let now = now();
+        let cookies = set_cookies
+            .into_iter()
+            .filter_map(|set_cookie| SetCookie::parse(set_cookie, now))
+            .filter_map(|set_cookie| set_cookie.into_cookie(secure, &host, path))
+            .collect::<Vec<_>>();
+        if cookies.is_empty() {
+            return;
+        }
+
+        let mut state = match self.state.write() {
+            Ok(state) => state,
+            Err(_) => return,
+        };
+
+        // merge into the file as it is now rather than overwrite it with what was last read
+        let modified = self.modified();
+        if let Some(persistent) = self.read_file() {
+            state.modified = modified;
+            state.persistent = persistent;
+        }
+
+        let count = state.persistent.len();
+        state.persistent.retain(|cookie| !cookie.is_expired(now));
+        let mut changed = state.persistent.len() != count;
+
+        for cookie in cookies {
+            let count = state.persistent.len();
+            state.persistent.retain(|stored| !cookie.replaces(stored));
+            state.session.retain(|stored| !cookie.replaces(stored));
+            changed |= state.persistent.len() != count;
+
+            // an expiry in the past only deletes the cookie
+            if cookie.is_expired(now) {
+                continue;
+            }
+
+            if cookie.expires.is_some() {
+                state.persistent.push(cookie);
+                changed = true;
+            } else {
+                state.session.push(cookie);
+            }
+        }
+
+        if !changed {
+            return;
+        }
+
+        if let Some(parent) = self.path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+
+        let content = match ron::to_string(&state.persistent) {
+            Ok(content) => content,
+            Err(e) => {
+                crate::error!("failed to serialize cookies: {}", e);
+                return;
+            }
+        };
+
+        // replace the file at once so another process never reads half of it
+        let tmp = self.path.with_extension("tmp");
+        if std::fs::write(&tmp, content).is_ok() && std::fs::rename(&tmp, &self.path).is_ok() {
+            state.modified = self.modified();
+        }
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+/// Whether `host` is `domain` or one of its subdomains. Without a public suffix list,
+/// a domain of a single label such as `com` is taken as a public suffix and only
+/// matches itself, as does an ip address.
+fn domain_match(host: &str, domain: &str) -> bool {
+    if host == domain {
+        return true;
+    }
+
+    domain.contains('.')
+        && host.parse::<IpAddr>().is_err()
+        && host
+            .strip_suffix(domain)
+            .map(|subdomain| subdomain.ends_with('.'))
+            .unwrap_or(false)
+}
+
+fn path_match(path: &str, cookie_path: &str) -> bool {
+    path == cookie_path
+        || (path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || path[cookie_path.len()..].starts_with('/')))
+}
+
+/// Path of a cookie without path attribute, the directory of the request path
+fn default_path(path: &str) -> &str {
+    match path.rfind('/') {
+        Some(index) if index > 0 && path.starts_with('/') => &path[..index],
+        _ => "/",
+    }
+}
+
+/// Parse a date of `Expires` attribute the lenient way browsers do, see
+/// RFC 6265 section 5.1.1, returns unix timestamp in seconds
+fn parse_cookie_date(date: &str) -> Option<u64> {
+    let mut time = None;
+    let mut day = None;
+    let mut month = None;
+    let mut year = None;
+
+    let tokens = date
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == ':'))
+        .filter(|token| !token.is_empty());
+    for token in tokens {
+        let is_number = token.bytes().all(|b| b.is_ascii_digit());
+        if time.is_none() && token.contains(':') {
+            let parts = token
+                .split(':')
+                .map(|part| part.parse::<i64>().ok().filter(|_| part.len() <= 2))
+                .collect::<Option<Vec<_>>>();
+            if let Some([hour, minute, second]) = parts.as_deref() {
+                time = Some((*hour, *minute, *second));
+                continue;
+            }
+        }
+        if day.is_none() && is_number && token.len() <= 2 {
+            day = token.parse::<i64>().ok();
+        } else if month.is_none() && !is_number && token.len() >= 3 {
+            month = MONTHS
+                .iter()
+                .position(|month| token[..3].eq_ignore_ascii_case(month))
+                .map(|index| index as i64 + 1);
+        } else if year.is_none() && is_number && (2..=4).contains(&token.len()) {
+            year = token.parse::<i64>().ok();
+        }
+    }
+
+    let (hour, minute, second) = time?;
+    let (day, month, mut year) = (day?, month?, year?);
+    if (70..=99).contains(&year) {
+        year += 1900;
+    } else if (0..=69).contains(&year) {
+        year += 2000;
+    }
+
+    if !(1..=31).contains(&day) || year < 1601 || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+
+    let timestamp = days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second;
+    Some(timestamp.max(0) as u64)
+}
+
+/// Days since 1970-01-01 of a date in the proleptic gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146097 + day_of_era - 719468
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct SetCookie<'a> {
+    name: &'a str,
+    value: &'a str,
+    domain: Option<&'a str>,
+    path: Option<&'a str>,
+    secure: bool,
+    /// Unix timestamp in seconds, `Max-Age` takes precedence over `Expires`
+    expires: Option<u64>,
+}
+
+impl<'a> SetCookie<'a> {
+    fn parse(set_cookie: &'a str, now: u64) -> Option<Self> {
+        let mut parts = set_cookie.split(';');
+        let (name, value) = parts.next()?.split_once('=')?;
+        let (name, value) = (name.trim(), value.trim());
+        if name.is_empty() {
+            return None;
+        }
+
+        let mut domain = None;
+        let mut path = None;
+        let mut secure = false;
+        let mut max_age = None;
+        let mut expires = None;
+        for attribute in parts {
+            let (key, val) = attribute
+                .split_once('=')
+                .map(|(key, val)| (key.trim(), val.trim()))
+                .unwrap_or((attribute.trim(), ""));
+            if key.eq_ignore_ascii_case("domain") && !val.is_empty() {
+                domain = Some(val);
+            } else if key.eq_ignore_ascii_case("path") {
+                path = Some(val).filter(|val| val.starts_with('/'));
+            } else if key.eq_ignore_ascii_case("secure") {
+                secure = true;
+            } else if key.eq_ignore_ascii_case("max-age") {
+                if let Ok(age) = val.parse::<i64>() {
+                    max_age = Some(if age <= 0 {
+                        0
+                    } else {
+                        now.saturating_add(age as u64)
+                    });
+                }
+            } else if key.eq_ignore_ascii_case("expires") {
+                expires = parse_cookie_date(val).or(expires);
+            }
+        }
+
+        Some(Self {
+            name,
+            value,
+            domain,
+            path,
+            secure,
+            expires: max_age.or(expires),
+        })
+    }
+
+    /// Cookie as set by a response from `host` and `path`, `None` when the response
+    /// is not allowed to set it
+    fn into_cookie(self, secure: bool, host: &str, path: &str) -> Option<Cookie> {
+        // only a secure origin can set a secure cookie
+        if self.secure && !secure {
+            return None;
+        }
+
+        let domain = self
+            .domain
+            .map(|domain| domain.trim_start_matches('.').to_lowercase())
+            .filter(|domain| !domain.is_empty());
+        let (domain, host_only) = match domain {
+            Some(domain) if domain_match(host, &domain) => (domain, false),
+            Some(_) => return None,
+            None => (host.to_string(), true),
+        };
+
+        Some(Cookie {
+            name: self.name.to_string(),
+            value: self.value.to_string(),
+            domain,
+            host_only,
+            path: self.path.unwrap_or_else(|| default_path(path)).to_string(),
+            secure: self.secure,
+            expires: self.expires,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+
+    #[test]
+    fn test_parse_cookie_date() {
+        let expected = Some(1445412480);
+        assert_eq!(parse_cookie_date("Wed, 21 Oct 2015 07:28:00 GMT"), expected);
+        assert_eq!(parse_cookie_date("Wed, 21-Oct-2015 07:28:00 GMT"), expected);
+        assert_eq!(
+            parse_cookie_date("Wednesday, 21-Oct-15 07:28:00 GMT"),
+            expected
+        );
+        assert_eq!(parse_cookie_date("Wed Oct 21 07:28:00 2015"), expected);
+        assert_eq!(parse_cookie_date("Thu, 01 Jan 1970 00:00:00 GMT"), Some(0));
+        assert_eq!(
+            parse_cookie_date("Sat, 29 Feb 2020 12:00:00 GMT"),
+            Some(1582977600)
+        );
+
+        assert_eq!(parse_cookie_date("Wed, 21 Oct 2015"), None);
+        assert_eq!(parse_cookie_date("Wed, 21 Foo 2015 07:28:00 GMT"), None);
+        assert_eq!(parse_cookie_date("Wed, 21 Oct 2015 25:28:00 GMT"), None);
+        assert_eq!(parse_cookie_date(""), None);
+    }
+
+    #[test]
+    fn test_parse_set_cookie() {
+        assert_eq!(
+            SetCookie::parse(
+                "id=abc; Domain=.Example.com; Path=/docs; Secure; HttpOnly; Max-Age=60",
+                NOW
+            ),
+            Some(SetCookie {
+                name: "id",
+                value: "abc",
+                domain: Some(".Example.com"),
+                path: Some("/docs"),
+                secure: true,
+                expires: Some(NOW + 60),
+            })
+        );
+
+        assert_eq!(
+            SetCookie::parse("id = a=b ", NOW),
+            Some(SetCookie {
+                name: "id",
+                value: "a=b",
+                domain: None,
+                path: None,
+                secure: false,
+                expires: None,
+            })
+        );
+
+        assert!(SetCookie::parse("=abc", NOW).is_none());
+        assert!(SetCookie::parse("abc", NOW).is_none());
+
+        // path attribute has to be absolute
+        let cookie = SetCookie::parse("id=abc; Path=docs", NOW).unwrap();
+        assert_eq!(cookie.path, None);
+    }
+
+    #[test]
+    fn test_parse_set_cookie_expiry() {
+        let expires = |set_cookie| SetCookie::parse(set_cookie, NOW).unwrap().expires;
+
+        assert_eq!(
+            expires("id=abc; Expires=Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(1445412480)
+        );
+        assert_eq!(expires("id=abc; Max-Age=0"), Some(0));
+        assert_eq!(expires("id=abc; Max-Age=-1"), Some(0));
+        assert_eq!(expires("id=abc; Expires=invalid"), None);
+        assert_eq!(expires("id=abc; Max-Age=invalid"), None);
+
+        // max-age wins regardless of order
+        assert_eq!(
+            expires("id=abc; Max-Age=60; Expires=Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(NOW + 60)
+        );
+        assert_eq!(
+            expires("id=abc; Expires=Wed, 21 Oct 2015 07:28:00 GMT; Max-Age=60"),
+            Some(NOW + 60)
+        );
+    }
+
+    #[test]
+    fn test_into_cookie() {
+        let cookie = |set_cookie, secure, host, path| {
+            SetCookie::parse(set_cookie, NOW)
+                .unwrap()
+                .into_cookie(secure, host, path)
+        };
+
+        let host_only = cookie("id=abc", true, "www.example.com", "/docs/page").unwrap();
+        assert_eq!(host_only.domain, "www.example.com");
+        assert!(host_only.host_only);
+        assert_eq!(host_only.path, "/docs");
+
+        let parent = cookie("id=abc; Domain=.Example.com", true, "www.example.com", "/").unwrap();
+        assert_eq!(parent.domain, "example.com");
+        assert!(!parent.host_only);
+
+        assert!(cookie("id=abc; Domain=other.com", true, "www.example.com", "/").is_none());
+        assert!(cookie("id=abc; Domain=com", true, "example.com", "/").is_none());
+        assert!(cookie("id=abc; Secure", false, "example.com", "/").is_none());
+        assert!(cookie("id=abc; Secure", true, "example.com", "/").is_some());
+    }
+
+    #[test]
+    fn test_domain_match() {
+        assert!(domain_match("example.com", "example.com"));
+        assert!(domain_match("www.example.com", "example.com"));
+        assert!(!domain_match("wwwexample.com", "example.com"));
+        assert!(!domain_match("example.com", "www.example.com"));
+        assert!(!domain_match("example.com", "com"));
+        assert!(!domain_match("1.2.3.4", "2.3.4"));
+        assert!(domain_match("1.2.3.4", "1.2.3.4"));
+    }
+
+    #[test]
+    fn test_path_match() {
+        assert!(path_match("/", "/"));
+        assert!(path_match("/docs", "/docs"));
+        assert!(path_match("/docs/page", "/docs"));
+        assert!(path_match("/docs/page", "/docs/"));
+        assert!(path_match("/docs/page", "/"));
+        assert!(!path_match("/docsearch", "/docs"));
+        assert!(!path_match("/", "/docs"));
+
+        assert_eq!(default_path("/docs/page"), "/docs");
+        assert_eq!(default_path("/page"), "/");
+        assert_eq!(default_path("/"), "/");
+        assert_eq!(default_path(""), "/");
+    }
+
+    #[test]
+    fn test_cookie_jar() {
+        let dir = std::env::temp_dir().join(format!("tanoshi-cookie-{}", std::process::id()));
+        let path = dir.join("jar.ron");
+        let jar = CookieJar::open(&path);
+
+        jar.store(
+            true,
+            "www.example.com",
+            "/",
+            [
+                "session=1",
+                "persistent=2; Max-Age=3600",
+                "secure=3; Secure; Max-Age=3600",
+                "docs=4; Path=/docs; Max-Age=3600",
+                "parent=5; Domain=example.com; Max-Age=3600",
+            ],
+        );
+
+        assert_eq!(
+            jar.cookie_header(true, "www.example.com", "/docs/page")
+                .unwrap()
+                .split("; ")
+                .next(),
+            Some("docs=4")
+        );
+        let header = jar.cookie_header(false, "www.example.com", "/").unwrap();
+        assert!(header.contains("session=1"));
+        assert!(header.contains("persistent=2"));
+        assert!(!header.contains("secure=3"));
+        assert!(!header.contains("docs=4"));
+        assert_eq!(
+            jar.cookie_header(true, "example.com", "/").as_deref(),
+            Some("parent=5")
+        );
+
+        // a jar on the same file is the same jar
+        assert!(Arc::ptr_eq(&jar, &CookieJar::open(&path)));
+
+        // session cookies are not written to the file
+        let persisted: Vec<Cookie> =
+            ron::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(persisted.len(), 4);
+        assert!(persisted.iter().all(|cookie| cookie.name != "session"));
+
+        // an expiry in the past deletes the cookie
+        jar.store(
+            true,
+            "www.example.com",
+            "/",
+            ["persistent=; Expires=Thu, 01 Jan 1970 00:00:00 GMT"],
+        );
+        let header = jar.cookie_header(true, "www.example.com", "/").unwrap();
+        assert!(!header.contains("persistent"));
+        let persisted: Vec<Cookie> =
+            ron::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(persisted.len(), 3);
+
+        // cookies written by another process are merged, not overwritten
+        let mut other = persisted;
+        other.push(Cookie {
+            name: "other".to_string(),
+            value: "6".to_string(),
+            domain: "www.example.com".to_string(),
+            host_only: true,
+            path: "/".to_string(),
+            secure: false,
+            expires: Some(now() + 3600),
+        });
+        std::fs::write(&path, ron::to_string(&other).unwrap()).unwrap();
+        jar.store(true, "www.example.com", "/", ["mine=7; Max-Age=3600"]);
+        let persisted: Vec<Cookie> =
+            ron::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert!(persisted.iter().any(|cookie| cookie.name == "other"));
+        assert!(persisted.iter().any(|cookie| cookie.name == "mine"));
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
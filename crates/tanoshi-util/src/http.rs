@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+pub use tanoshi_lib::models::HttpProfile;
 
 pub type Headers = HashMap<String, Vec<String>>;
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -87,13 +88,42 @@ extern "C" {
     fn host_http_request();
 }
 
+/// Requests use proxy, user agent, headers and cookie jar of the http profile set by host,
+/// see [`HttpProfile::current`]
 #[cfg(any(feature = "__test", feature = "host"))]
 pub fn http_request(req: Request) -> Response {
     use log::debug;
 
-    let agent = ureq::builder().user_agent("Tanoshi/0.1.0").build();
+    let profile = HttpProfile::current().unwrap_or_default();
+    let jar = profile
+        .cookie_jar
+        .as_deref()
+        .map(crate::cookie::CookieJar::open);
+
+    let mut builder =
+        ureq::builder().user_agent(profile.user_agent.as_deref().unwrap_or("Tanoshi/0.1.0"));
+    if let Some(proxy) = profile.proxy.as_deref() {
+        match ureq::Proxy::new(proxy) {
+            Ok(proxy) => builder = builder.proxy(proxy),
+            Err(err) => {
+                debug!("invalid proxy {} => {:?}", proxy, err);
+            }
+        }
+    }
+    let agent = builder.build();
 
     let mut request = agent.request(&req.method, &req.url);
+    for (key, value) in profile.headers.iter() {
+        if !req
+            .headers
+            .as_ref()
+            .map(|headers| headers.contains_key(key))
+            .unwrap_or(false)
+        {
+            request = request.set(key, value);
+        }
+    }
+
     if let Some(headers) = req.headers.as_ref() {
         for (key, values) in headers {
             for value in values {
@@ -102,6 +132,13 @@ pub fn http_request(req: Request) -> Response {
         }
     }
 
+    let url = request.request_url().ok();
+    if let (Some(jar), Some(url)) = (jar.as_ref(), url.as_ref()) {
+        if let Some(cookie) = jar.cookie_header(url.scheme() == "https", url.host(), url.path()) {
+            request = request.set("Cookie", &cookie);
+        }
+    }
+
     let res = if let Some(body) = req.body {
         debug!("request => {:?}", request);
         request.send_string(&body)
@@ -115,6 +152,15 @@ pub fn http_request(req: Request) -> Response {
             debug!("response ok => {:?}", response);
 
             let status = response.status();
+            if let (Some(jar), Some(url)) = (jar.as_ref(), url.as_ref()) {
+                jar.store(
+                    url.scheme() == "https",
+                    url.host(),
+                    url.path(),
+                    response.all("set-cookie"),
+                );
+            }
+
             Response {
                 headers: {
                     let mut headers = Headers::new();
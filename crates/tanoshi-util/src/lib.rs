@@ -1,3 +1,5 @@
+#[cfg(any(feature = "__test", feature = "host"))]
+pub mod cookie;
 pub mod http;
 pub mod log;
 pub mod shim;
@@ -0,0 +1,68 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use reqwest::{
+    cookie::CookieStore,
+    header::{HeaderMap, HeaderName, HeaderValue},
+    Url,
+};
+use tanoshi_lib::prelude::HttpProfile;
+use tanoshi_util::cookie::CookieJar;
+
+/// Adapter to use a persistent [`CookieJar`] as reqwest cookie store
+struct PersistentCookieStore(Arc<CookieJar>);
+
+impl CookieStore for PersistentCookieStore {
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &Url) {
+        if let Some(host) = url.host_str() {
+            self.0.store(
+                url.scheme() == "https",
+                host,
+                url.path(),
+                cookie_headers.filter_map(|value| value.to_str().ok()),
+            );
+        }
+    }
+
+    fn cookies(&self, url: &Url) -> Option<HeaderValue> {
+        url.host_str()
+            .and_then(|host| {
+                self.0
+                    .cookie_header(url.scheme() == "https", host, url.path())
+            })
+            .and_then(|cookie| HeaderValue::from_str(&cookie).ok())
+    }
+}
+
+/// Build a client with proxy, user agent, default headers and cookie jar from profile
+pub fn build_client(profile: &HttpProfile) -> Result<reqwest::Client> {
+    Ok(client_builder(profile)?.build()?)
+}
+
+/// Client builder configured from profile, for clients that need more settings than
+/// [`build_client`] applies
+pub fn client_builder(profile: &HttpProfile) -> Result<reqwest::ClientBuilder> {
+    let mut headers = HeaderMap::new();
+    for (name, value) in profile.headers.iter() {
+        headers.insert(
+            HeaderName::from_bytes(name.as_bytes())?,
+            HeaderValue::from_str(value)?,
+        );
+    }
+
+    let mut builder = reqwest::Client::builder().default_headers(headers);
+
+    if let Some(user_agent) = profile.user_agent.as_ref() {
+        builder = builder.user_agent(user_agent);
+    }
+
+    if let Some(proxy) = profile.proxy.as_ref() {
+        builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+    }
+
+    if let Some(path) = profile.cookie_jar.as_ref() {
+        builder = builder.cookie_provider(Arc::new(PersistentCookieStore(CookieJar::open(path))));
+    }
+
+    Ok(builder)
+}
@@ -6,14 +6,25 @@ use std::{
 use anyhow::{anyhow, bail, Result};
 use fnv::FnvHashMap;
 use libloading::Library;
-use tanoshi_lib::prelude::{Input, PluginDeclaration, SourceInfo};
-
-use crate::{prelude::Source, PLUGIN_EXTENSION};
+use std::collections::HashMap;
+use tanoshi_lib::prelude::{HttpProfile, Input, PluginDeclaration, ResolvedUrl, SourceInfo};
+
+use crate::{
+    extension::{
+        http::build_client,
+        repository::{fetch_repository_file, PluginIndex, TrustPolicy},
+    },
+    prelude::Source,
+    PLUGIN_EXTENSION,
+};
 
 #[derive(Clone)]
 pub struct ExtensionManager {
     dir: PathBuf,
     extensions: Arc<RwLock<FnvHashMap<i64, Source>>>,
+    default_http_profile: Arc<RwLock<HttpProfile>>,
+    http_profiles: Arc<RwLock<FnvHashMap<i64, HttpProfile>>>,
+    http_clients: Arc<RwLock<FnvHashMap<i64, reqwest::Client>>>,
 }
 
 impl ExtensionManager {
@@ -21,6 +32,9 @@ impl ExtensionManager {
         Self {
             dir: PathBuf::new().join(extension_dir),
             extensions: Arc::new(RwLock::new(FnvHashMap::default())),
+            default_http_profile: Arc::new(RwLock::new(HttpProfile::default())),
+            http_profiles: Arc::new(RwLock::new(FnvHashMap::default())),
+            http_clients: Arc::new(RwLock::new(FnvHashMap::default())),
         }
     }
 
@@ -63,36 +77,85 @@ impl ExtensionManager {
             .collect())
     }
 
-    pub async fn install(&self, repo_url: &str, name: &str) -> Result<()> {
-        let source_file_url = format!(
-            "{}/{}/{}.{}",
-            repo_url,
+    /// Download plugin from repository and verify it against index
+    async fn download(
+        &self,
+        repo_url: &str,
+        index: &PluginIndex,
+        policy: &TrustPolicy,
+    ) -> Result<Vec<u8>> {
+        let source_file_path = format!(
+            "{}/{}.{}",
             env!("TARGET"),
-            name.to_lowercase(),
+            index.name.to_lowercase(),
             PLUGIN_EXTENSION
         );
 
-        info!("downloading {}", source_file_url);
+        info!("downloading {source_file_path} from {repo_url}");
 
-        let contents = reqwest::get(&source_file_url).await?.bytes().await?;
+        let contents = fetch_repository_file(repo_url, &source_file_path).await?;
+        index.verify(&contents, policy)?;
 
-        tokio::fs::write(
-            self.dir
-                .join(&name.to_lowercase())
-                .with_extension(PLUGIN_EXTENSION),
-            contents,
-        )
-        .await?;
+        Ok(contents)
+    }
+
+    fn library_path(&self, name: &str) -> PathBuf {
+        self.dir
+            .join(name.to_lowercase())
+            .with_extension(PLUGIN_EXTENSION)
+    }
+
+    async fn write_and_load(&self, name: &str, contents: Vec<u8>) -> Result<()> {
+        let library_path = self.library_path(name);
+        tokio::fs::write(&library_path, contents).await?;
 
-        let source = self.load_library(&name.to_lowercase())?;
+        let source = self.load_library(&library_path)?;
         self.insert(source).await
     }
 
-    fn load_library(&self, name: &str) -> Result<Source> {
-        let library_path = PathBuf::new()
-            .join(&self.dir)
-            .join(name)
-            .with_extension(PLUGIN_EXTENSION);
+    /// Plugin is only written and loaded after passing verification
+    pub async fn install(
+        &self,
+        repo_url: &str,
+        index: &PluginIndex,
+        policy: &TrustPolicy,
+    ) -> Result<()> {
+        let contents = self.download(repo_url, index, policy).await?;
+        self.write_and_load(&index.name, contents).await
+    }
+
+    /// Installed plugin is kept if the new one fails verification or loading, new plugin is
+    /// loaded from a temporary file which only replaces the installed one after that
+    pub async fn update(
+        &self,
+        repo_url: &str,
+        index: &PluginIndex,
+        policy: &TrustPolicy,
+    ) -> Result<()> {
+        let contents = self.download(repo_url, index, policy).await?;
+
+        // not ending with plugin extension, so it is never picked up by `load_all`
+        let library_path = self.library_path(&index.name);
+        let tmp_path = library_path.with_extension(format!("{PLUGIN_EXTENSION}.tmp"));
+        tokio::fs::write(&tmp_path, contents).await?;
+
+        let source = match self.load_library(&tmp_path) {
+            Ok(source) => source,
+            Err(e) => {
+                if let Err(e) = tokio::fs::remove_file(&tmp_path).await {
+                    warn!("failed to remove {}: {e}", tmp_path.display());
+                }
+                return Err(e);
+            }
+        };
+
+        // loaded library stays mapped after its file is renamed
+        self.remove(index.id).await?;
+        tokio::fs::rename(&tmp_path, &library_path).await?;
+        self.insert(source).await
+    }
+
+    fn load_library(&self, library_path: &Path) -> Result<Source> {
         info!("load {:?}", library_path.display());
 
         #[cfg(target_os = "macos")]
@@ -107,7 +170,7 @@ impl ExtensionManager {
         }
 
         unsafe {
-            let library = Library::new(&library_path)?;
+            let library = Library::new(library_path)?;
 
             let decl = library
                 .get::<*mut PluginDeclaration>(b"plugin_declaration\0")?
@@ -137,7 +200,8 @@ impl ExtensionManager {
     }
 
     pub async fn load(&self, name: &str) -> Result<()> {
-        let mut source = self.load_library(name)?;
+        let mut source =
+            self.load_library(&self.dir.join(name).with_extension(PLUGIN_EXTENSION))?;
         let source_name = source
             .extension
             .get()
@@ -162,13 +226,16 @@ impl ExtensionManager {
         self.insert(source).await
     }
 
-    pub async fn insert(&self, source: Source) -> Result<()> {
-        let info = source
-            .extension
-            .get()
-            .map(|s| s.get_source_info())
-            .ok_or_else(|| anyhow!("error"))?;
+    pub async fn insert(&self, mut source: Source) -> Result<()> {
+        let extension = source.extension.get_mut().ok_or_else(|| anyhow!("error"))?;
+        let info = extension.get_source_info();
+
+        let profile = self.merge_http_profile(info.id, extension.headers())?;
+        extension.set_http_profile(profile);
+
         self.write()?.insert(info.id, source);
+        self.invalidate_http_client(info.id)?;
+
         Ok(())
     }
 
@@ -178,11 +245,7 @@ impl ExtensionManager {
             .remove(&source_id)
             .and_then(|s| s.extension.get().map(|s| s.get_source_info()))
         {
-            std::fs::remove_file(
-                self.dir
-                    .join(&source.name.to_lowercase())
-                    .with_extension(PLUGIN_EXTENSION),
-            )?;
+            std::fs::remove_file(self.library_path(&source.name))?;
         }
         Ok(())
     }
@@ -210,6 +273,128 @@ impl ExtensionManager {
             .get_source_info())
     }
 
+    fn merge_http_profile(
+        &self,
+        source_id: i64,
+        headers: HashMap<String, String>,
+    ) -> Result<HttpProfile> {
+        let cookie_jar = self
+            .dir
+            .join("cookies")
+            .join(source_id.to_string())
+            .with_extension("ron");
+
+        let base = HttpProfile {
+            headers,
+            cookie_jar: Some(cookie_jar.display().to_string()),
+            ..Default::default()
+        };
+
+        let default = self
+            .default_http_profile
+            .read()
+            .map_err(|e| anyhow!("failed to lock read: {e}"))?
+            .clone();
+
+        let profile = self
+            .http_profiles
+            .read()
+            .map_err(|e| anyhow!("failed to lock read: {e}"))?
+            .get(&source_id)
+            .cloned()
+            .unwrap_or_default();
+
+        Ok(base.merge(&default).merge(&profile).resolve())
+    }
+
+    fn invalidate_http_client(&self, source_id: i64) -> Result<()> {
+        self.http_clients
+            .write()
+            .map_err(|e| anyhow!("failed to lock write: {e}"))?
+            .remove(&source_id);
+
+        Ok(())
+    }
+
+    fn apply_http_profile(&self, source_id: i64) -> Result<()> {
+        let mut lock = self.write()?;
+        if let Some(extension) = lock
+            .get_mut(&source_id)
+            .and_then(|source| source.extension.get_mut())
+        {
+            let profile = self.merge_http_profile(source_id, extension.headers())?;
+            extension.set_http_profile(profile);
+        }
+        drop(lock);
+
+        self.invalidate_http_client(source_id)
+    }
+
+    /// Set http profile applied to every source, source profile takes precedence
+    pub fn set_default_http_profile(&self, profile: HttpProfile) -> Result<()> {
+        *self
+            .default_http_profile
+            .write()
+            .map_err(|e| anyhow!("failed to lock write: {e}"))? = profile;
+
+        let source_ids: Vec<i64> = self.read()?.keys().copied().collect();
+        for source_id in source_ids {
+            self.apply_http_profile(source_id)?;
+        }
+
+        Ok(())
+    }
+
+    /// Set or remove http profile of a source
+    pub fn set_http_profile(&self, source_id: i64, profile: Option<HttpProfile>) -> Result<()> {
+        {
+            let mut profiles = self
+                .http_profiles
+                .write()
+                .map_err(|e| anyhow!("failed to lock write: {e}"))?;
+            match profile {
+                Some(profile) => profiles.insert(source_id, profile),
+                None => profiles.remove(&source_id),
+            };
+        }
+
+        self.apply_http_profile(source_id)
+    }
+
+    /// Returns effective http profile of a source, with extension headers merged
+    pub fn http_profile(&self, source_id: i64) -> Result<HttpProfile> {
+        let headers = self
+            .read()?
+            .get(&source_id)
+            .ok_or_else(|| anyhow!("no such source"))?
+            .extension
+            .get()
+            .ok_or_else(|| anyhow!("uninitiated"))?
+            .headers();
+
+        self.merge_http_profile(source_id, headers)
+    }
+
+    /// Returns http client configured with http profile of a source
+    pub fn http_client(&self, source_id: i64) -> Result<reqwest::Client> {
+        if let Some(client) = self
+            .http_clients
+            .read()
+            .map_err(|e| anyhow!("failed to lock read: {e}"))?
+            .get(&source_id)
+        {
+            return Ok(client.clone());
+        }
+
+        let client = build_client(&self.http_profile(source_id)?)?;
+        self.http_clients
+            .write()
+            .map_err(|e| anyhow!("failed to lock write: {e}"))?
+            .insert(source_id, client.clone());
+
+        Ok(client)
+    }
+
     pub fn filter_list(&self, source_id: i64) -> Result<Vec<Input>> {
         Ok(self
             .read()?
@@ -369,4 +554,20 @@ impl ExtensionManager {
         })
         .await?
     }
+
+    pub async fn resolve_url(&self, source_id: i64, url: String) -> Result<Option<ResolvedUrl>> {
+        let extensions = self.extensions.clone();
+        tokio::task::spawn_blocking(move || {
+            extensions
+                .read()
+                .map_err(|e| anyhow!("failed to lock read: {e}"))?
+                .get(&source_id)
+                .ok_or_else(|| anyhow!("no such source"))?
+                .extension
+                .get()
+                .ok_or_else(|| anyhow!("uninitiated"))?
+                .resolve_url(url)
+        })
+        .await?
+    }
 }
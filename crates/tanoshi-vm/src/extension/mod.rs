@@ -3,3 +3,8 @@ pub use source::*;
 
 pub mod manager;
 pub use manager::*;
+
+pub mod http;
+
+pub mod repository;
+pub use repository::*;
@@ -0,0 +1,234 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use base64::{engine::general_purpose, Engine};
+use ring::{
+    digest::{digest, SHA256},
+    signature::{UnparsedPublicKey, ED25519},
+};
+
+/// Returns directory of a repository on local filesystem, `None` if it is served over http
+pub fn local_repository_path(repo_url: &str) -> Option<PathBuf> {
+    if let Some(path) = repo_url.strip_prefix("file://") {
+        Some(PathBuf::from(path))
+    } else if repo_url.starts_with("http://") || repo_url.starts_with("https://") {
+        None
+    } else {
+        Some(PathBuf::from(repo_url))
+    }
+}
+
+/// Read a file of a repository, `path` is relative to the repository root
+pub async fn fetch_repository_file(repo_url: &str, path: &str) -> Result<Vec<u8>> {
+    match local_repository_path(repo_url) {
+        Some(dir) => Ok(tokio::fs::read(dir.join(path)).await?),
+        None => {
+            let url = format!("{}/{}", repo_url.trim_end_matches('/'), path);
+            debug!("fetching {url}");
+            let contents = reqwest::get(&url)
+                .await?
+                .error_for_status()?
+                .bytes()
+                .await?;
+            Ok(contents.to_vec())
+        }
+    }
+}
+
+/// Keys trusted to sign plugins of a repository
+#[derive(Debug, Clone, Default)]
+pub struct TrustPolicy {
+    /// Base64 encoded ed25519 public keys
+    pub trusted_keys: Vec<String>,
+    /// Install plugins without a valid signature of a trusted key
+    pub allow_unsigned: bool,
+}
+
+/// Plugin listed in repository index, used to verify the downloaded file
+#[derive(Debug, Clone, Default)]
+pub struct PluginIndex {
+    pub id: i64,
+    pub name: String,
+    pub version: String,
+    /// Hex encoded sha256 of plugin file
+    pub sha256: Option<String>,
+    /// Base64 encoded ed25519 signature of `PluginIndex::signed_message`
+    pub signature: Option<String>,
+}
+
+impl PluginIndex {
+    /// Signing the hash together with id and version prevents a signed plugin from
+    /// being served as another source or version
+    pub fn signed_message(&self, sha256: &str) -> String {
+        format!(
+            "{}\n{}\n{}\n{}",
+            self.id,
+            self.name.to_lowercase(),
+            self.version,
+            sha256
+        )
+    }
+
+    /// Checksum mismatch is always refused, missing or untrusted signature is refused
+    /// unless policy allows unsigned plugins
+    pub fn verify(&self, contents: &[u8], policy: &TrustPolicy) -> Result<()> {
+        let sha256 = sha256_hex(contents);
+        if let Some(expected) = self.sha256.as_ref() {
+            if !expected.eq_ignore_ascii_case(&sha256) {
+                bail!(
+                    "checksum mismatch for {}, expected {expected} got {sha256}",
+                    self.name
+                );
+            }
+        }
+
+        let trusted = match self.signature.as_ref() {
+            // a signature that can't be decoded is no better than a missing one
+            Some(signature) => match general_purpose::STANDARD.decode(signature) {
+                Ok(signature) => {
+                    let message = self.signed_message(&sha256);
+
+                    policy.trusted_keys.iter().any(|key| {
+                        match general_purpose::STANDARD.decode(key) {
+                            Ok(key) => UnparsedPublicKey::new(&ED25519, key)
+                                .verify(message.as_bytes(), &signature)
+                                .is_ok(),
+                            Err(e) => {
+                                warn!("invalid trusted key {key}: {e}");
+                                false
+                            }
+                        }
+                    })
+                }
+                Err(e) => {
+                    warn!("invalid signature of {}: {e}", self.name);
+                    false
+                }
+            },
+            None => false,
+        };
+
+        if trusted {
+            return Ok(());
+        }
+
+        if policy.allow_unsigned {
+            warn!("{} is not signed by a trusted key", self.name);
+            return Ok(());
+        }
+
+        bail!(
+            "{} is not signed by a trusted key, refusing to install",
+            self.name
+        )
+    }
+}
+
+pub fn sha256_hex(contents: &[u8]) -> String {
+    digest(&SHA256, contents)
+        .as_ref()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use ring::{
+        rand::SystemRandom,
+        signature::{Ed25519KeyPair, KeyPair},
+    };
+
+    use super::*;
+
+    const CONTENTS: &[u8] = b"plugin";
+
+    fn key_pair() -> Ed25519KeyPair {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap()
+    }
+
+    fn policy(key_pair: &Ed25519KeyPair, allow_unsigned: bool) -> TrustPolicy {
+        TrustPolicy {
+            trusted_keys: vec![general_purpose::STANDARD.encode(key_pair.public_key().as_ref())],
+            allow_unsigned,
+        }
+    }
+
+    fn index() -> PluginIndex {
+        PluginIndex {
+            id: 1,
+            name: "Source".to_string(),
+            version: "0.1.0".to_string(),
+            sha256: Some(sha256_hex(CONTENTS)),
+            signature: None,
+        }
+    }
+
+    /// Index signed with `signed` as the listed plugin, which may differ from `index`
+    fn sign(key_pair: &Ed25519KeyPair, index: PluginIndex, signed: &PluginIndex) -> PluginIndex {
+        let message = signed.signed_message(&sha256_hex(CONTENTS));
+        PluginIndex {
+            signature: Some(general_purpose::STANDARD.encode(key_pair.sign(message.as_bytes()))),
+            ..index
+        }
+    }
+
+    #[test]
+    fn test_verify_signed() {
+        let key_pair = key_pair();
+        let index = sign(&key_pair, index(), &index());
+
+        assert!(index.verify(CONTENTS, &policy(&key_pair, false)).is_ok());
+    }
+
+    #[test]
+    fn test_verify_checksum_mismatch() {
+        let key_pair = key_pair();
+        let index = sign(&key_pair, index(), &index());
+
+        assert!(index
+            .verify(b"tampered", &policy(&key_pair, false))
+            .is_err());
+        assert!(index.verify(b"tampered", &policy(&key_pair, true)).is_err());
+    }
+
+    #[test]
+    fn test_verify_wrong_key() {
+        let index = sign(&key_pair(), index(), &index());
+
+        assert!(index.verify(CONTENTS, &policy(&key_pair(), false)).is_err());
+    }
+
+    #[test]
+    fn test_verify_signed_for_other_plugin() {
+        let key_pair = key_pair();
+        let policy = policy(&key_pair, false);
+
+        let other_id = PluginIndex { id: 2, ..index() };
+        let index_with_other_id = sign(&key_pair, index(), &other_id);
+        assert!(index_with_other_id.verify(CONTENTS, &policy).is_err());
+
+        let other_version = PluginIndex {
+            version: "0.0.1".to_string(),
+            ..index()
+        };
+        let index_with_other_version = sign(&key_pair, index(), &other_version);
+        assert!(index_with_other_version.verify(CONTENTS, &policy).is_err());
+    }
+
+    #[test]
+    fn test_verify_allow_unsigned() {
+        let trusted = key_pair();
+        let invalid_signature = PluginIndex {
+            signature: Some("not base64!".to_string()),
+            ..index()
+        };
+        let wrong_key = sign(&key_pair(), index(), &index());
+
+        for index in [index(), invalid_signature, wrong_key] {
+            assert!(index.verify(CONTENTS, &policy(&trusted, false)).is_err());
+            assert!(index.verify(CONTENTS, &policy(&trusted, true)).is_ok());
+        }
+    }
+}
@@ -6,25 +6,28 @@ use futures_signals::{
     signal::{Mutable, Signal, SignalExt},
 };
 use gloo_timers::future::TimeoutFuture;
-use wasm_bindgen::UnwrapThrowExt;
 use wasm_bindgen_futures::spawn_local;
 
 use crate::{
     catalogue::Catalogue,
     catalogue_list::CatalogueList,
-    common::{snackbar, Bottombar, LibrarySettings, Route, ServerStatus, SettingCategory, Spinner},
+    common::{
+        preference_sync, snackbar, Bottombar, LibrarySettings, Route, ServerStatus,
+        SettingCategory, Spinner,
+    },
     histories::Histories,
     library::Library,
     library_list::LibraryList,
     login::Login,
     manga::Manga,
+    oidc_redirect::OidcRedirect,
     query,
     reader::Reader,
     settings::Settings,
     tracker_login::TrackerLogin,
     tracker_redirect::TrackerRedirect,
     updates::Updates,
-    utils::{local_storage, AsyncLoader},
+    utils::{clear_auth_token, window, AsyncLoader},
 };
 
 pub struct App {
@@ -49,7 +52,9 @@ impl App {
                     app.server_status.set_neq(Some(ServerStatus {
                         activated: server_status.activated,
                         version: server_status.version,
-                        loggedin: server_status.loggedin
+                        loggedin: server_status.loggedin,
+                        oidc_enabled: server_status.oidc_enabled,
+                        password_login_enabled: server_status.password_login_enabled,
                     }));
                 }
                 Err(e) => {
@@ -77,11 +82,25 @@ impl App {
                 if let Some(server_status) = server_status {
                     if !server_status.activated {
                         info!("server inactivated, go to login");
-                        local_storage().delete("token").unwrap_throw();
+                        clear_auth_token();
                         routing::go_to_url(&Route::Login.url());
                     } else if server_status.activated && !server_status.loggedin {
-                        routing::go_to_url(&Route::Login.url());
+                        // single sign-on redirect logs in by itself
+                        let pathname = window().location().pathname().unwrap_or_default();
+                        if !pathname.starts_with("/login/oidc") {
+                            routing::go_to_url(&Route::Login.url());
+                        }
                     } else if server_status.loggedin {
+                        spawn_local(async {
+                            preference_sync::pull(None).await;
+                            loop {
+                                info!("subscribing preference changes");
+                                preference_sync::subscribe().await;
+
+                                info!("reconnecting in 30s..");
+                                TimeoutFuture::new(30_000).await;
+                            }
+                        });
                         spawn_local(async {
                             loop {
                                 info!("subscribing recent updates");
@@ -148,6 +167,9 @@ impl App {
                     Route::TrackerLogin(tracker) => {
                         Some(TrackerLogin::render(TrackerLogin::new(tracker)))
                     }
+                    Route::OidcRedirect{code, state} => {
+                        Some(OidcRedirect::render(OidcRedirect::new(code, state), app.clone()))
+                    }
                     Route::TrackerRedirect{tracker, code, state} => {
                         Some(TrackerRedirect::render(TrackerRedirect::new(tracker, code, state)))
                     }
@@ -32,6 +32,8 @@ struct Source {
 struct SourceManga {
     name: String,
     covers: Vec<Cover>,
+    #[serde(default)]
+    error: Option<String>,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -76,40 +78,53 @@ impl CatalogueList {
         serde_json::to_string(self).unwrap_throw()
     }
 
+    /// Open manga or chapter directly when a link from source website is pasted
+    pub fn open_url(catalogue: Rc<Self>) {
+        let url = catalogue.keyword.get_cloned().trim().to_string();
+        catalogue.loader.load(async move {
+            match query::resolve_url(url).await {
+                Ok(result) => {
+                    let route = match result.chapter {
+                        Some(chapter) => Route::Chapter(chapter.id, 0),
+                        None => Route::Manga(result.manga.id),
+                    };
+                    routing::go_to_url(route.url().as_str());
+                }
+                Err(e) => {
+                    snackbar::show(format!("failed to open link: {}", e));
+                }
+            }
+        });
+    }
+
     pub fn fetch_manga_from_all_sources(catalogue: Rc<Self>) {
-        let sources: Vec<Source> = catalogue.sources.lock_ref().iter().cloned().collect();
         let keyword = catalogue.keyword.get_cloned();
         catalogue.loader.load(clone!(catalogue => async move {
-            for source in sources {
-                match query::fetch_manga_from_source(source.id, 1, Some(keyword.clone()), None).await {
-                    Ok(data) => {
-                        let covers = data
-                            .browse_source
-                            .iter()
-                            .map(|item| {
-                                Cover::new(
-                                    item.id,
-                                    source.id,
-                                    item.path.clone(),
-                                    item.title.clone(),
-                                    item.cover_url.clone(),
-                                    item.is_favorite,
-                                    None,
-                                    0,
-                                )
-                            })
-                            .collect();
-                        let mut cover_list = catalogue.cover_list_map.lock_mut();
-                        cover_list.insert_cloned(source.id, SourceManga { name: source.name.clone(), covers } );
-                    }
-                    Err(e) => {
-                        // snackbar::show(format!("Fetch manga from source failed: {}", e))
-                        error!("Fetch manga from source failed: {}", e);
-                    }
-                }
+            let res = query::global_search(keyword, clone!(catalogue => move |result| {
+                let covers = result
+                    .manga
+                    .iter()
+                    .map(|item| {
+                        Cover::new(
+                            item.id,
+                            result.source.id,
+                            item.path.clone(),
+                            item.title.clone(),
+                            item.cover_url.clone(),
+                            item.is_favorite,
+                            None,
+                            0,
+                        )
+                    })
+                    .collect();
+                catalogue.cover_list_map.lock_mut().insert_cloned(result.source.id, SourceManga { name: result.source.name.clone(), covers, error: result.error });
 
                 let state = catalogue.serialize_into_json();
-                local_storage().set("catalogue_list", state.as_str()).unwrap_throw();
+                local_storage().set(STORAGE_KEY, state.as_str()).unwrap_throw();
+            })).await;
+
+            if let Err(e) = res {
+                snackbar::show(format!("global search failed: {}", e));
             }
         }));
     }
@@ -127,7 +142,7 @@ impl CatalogueList {
 
                     let mut cover_list_map = catalogue.cover_list_map.lock_mut();
                     for source in sources.iter() {
-                        cover_list_map.insert_cloned(source.id, SourceManga{name: source.name.clone(), covers: vec![]});
+                        cover_list_map.insert_cloned(source.id, SourceManga{name: source.name.clone(), covers: vec![], error: None});
                     }
 
                     catalogue.sources.lock_mut().replace_cloned(sources);
@@ -152,7 +167,7 @@ impl CatalogueList {
                 if is_search {
                     Some(html!("input" => HtmlInputElement, {
                         .style("width", "100%")
-                        .attr("placeholder", "Search")
+                        .attr("placeholder", "Search or paste a link")
                         .attr("type", "text")
                         .attr("value", &catalogue.keyword.get_cloned())
                         .with_node!(input => {
@@ -162,7 +177,12 @@ impl CatalogueList {
                             .event_with_options(&EventOptions::preventable(), clone!(catalogue => move |event: events::KeyDown| {
                                 if event.key() == "Enter" {
                                     event.prevent_default();
-                                    Self::fetch_manga_from_all_sources(catalogue.clone());
+                                    let keyword = catalogue.keyword.get_cloned();
+                                    if keyword.trim().starts_with("http://") || keyword.trim().starts_with("https://") {
+                                        Self::open_url(catalogue.clone());
+                                    } else {
+                                        Self::fetch_manga_from_all_sources(catalogue.clone());
+                                    }
                                 }
                             }))
                         })
@@ -309,11 +329,16 @@ impl CatalogueList {
                         .style("overflow-y", "hidden")
                         .style("white-space", " nowrap")
                         .style("border-radius", "0.375rem")
-                        .child_signal(catalogue.loader.is_loading().map(clone!(source_manga => move |is_loading| if is_loading && source_manga.covers.is_empty() {
+                        .child_signal(catalogue.loader.is_loading().map(clone!(source_manga => move |is_loading| if is_loading && source_manga.covers.is_empty() && source_manga.error.is_none() {
                             Some(Spinner::render_spinner(false))
                         } else {
                             None
                         })))
+                        .apply_if(source_manga.error.is_some(), clone!(source_manga => move |dom| dom.child(html!("span", {
+                            .style("margin", "0.25rem")
+                            .style("font-size", "0.875rem")
+                            .text(&source_manga.error.unwrap_or_default())
+                        }))))
                         .children_signal_vec(signal_vec::always(source_manga.covers).map(|cover| link!(cover.link(), {
                             .style("margin", "0.25rem")
                             .style("position", "relative")
@@ -323,6 +348,16 @@ impl CatalogueList {
                                     .style("border-radius", "0.375rem")
                                     .attr("src", &cover.cover_url)
                                 }),
+                                html!("span", {
+                                    .visible(cover.is_favorite.get())
+                                    .style("position", "absolute")
+                                    .style("top", "0px")
+                                    .style("left", "0px")
+                                    .style("margin", "0.25rem")
+                                    .style("font-size", "0.75rem")
+                                    .class("chip")
+                                    .text("In Library")
+                                }),
                                 html!("div", {
                                     .style("position", "absolute")
                                     .style("bottom", "0px")
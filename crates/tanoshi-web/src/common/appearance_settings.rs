@@ -6,7 +6,10 @@ use futures_signals::{
 use serde::{Deserialize, Serialize};
 use std::rc::Rc;
 
-use crate::utils::{apply_theme, local_storage};
+use crate::{
+    common::preference_sync,
+    utils::{apply_theme, local_storage},
+};
 
 #[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
 pub enum Theme {
@@ -41,6 +44,8 @@ pub struct AppearanceSettings {
 }
 
 impl AppearanceSettings {
+    pub const SCHEMA_VERSION: i64 = 1;
+
     pub fn new() -> Rc<Self> {
         let settings = if let Ok(Some(settings)) = local_storage().get_item("settings:appearance") {
             serde_json::from_str::<AppearanceSettings>(&settings).unwrap_or_default()
@@ -53,9 +58,10 @@ impl AppearanceSettings {
 
     pub fn save(&self) {
         let theme = self.theme.get().to_string();
-        let _ =
-            local_storage().set_item("settings:appearance", &serde_json::to_string(self).unwrap());
+        let value = serde_json::to_string(self).unwrap();
+        let _ = local_storage().set_item("settings:appearance", &value);
         let _ = local_storage().set_item("theme", &theme);
+        preference_sync::push("appearance", 0, value);
     }
 
     fn signal(&self) -> impl Signal<Item = AppearanceSettingsSignal> {
@@ -7,7 +7,10 @@ use futures_signals::{
 };
 use serde::{Deserialize, Serialize};
 
-use crate::{common::events, utils::local_storage};
+use crate::{
+    common::{events, preference_sync},
+    utils::local_storage,
+};
 
 const KEY: &str = "settings:chapter";
 
@@ -73,6 +76,8 @@ pub struct ChapterSettings {
 }
 
 impl ChapterSettings {
+    pub const SCHEMA_VERSION: i64 = 1;
+
     pub fn new(show: bool, use_modal: bool) -> Rc<Self> {
         Self::load(show, use_modal, 0)
     }
@@ -129,7 +134,9 @@ impl ChapterSettings {
             key = [key, (*self.manga_id.lock_ref()).to_string()].join(":");
         }
 
-        let _ = local_storage().set_item(&key, &serde_json::to_string(self).unwrap());
+        let value = serde_json::to_string(self).unwrap();
+        let _ = local_storage().set_item(&key, &value);
+        preference_sync::push("chapter", *self.manga_id.lock_ref(), value);
         if self.use_modal {
             self.show.set_neq(false);
         }
@@ -0,0 +1,274 @@
+use crate::{
+    common::{snackbar, Modal, Spinner},
+    query,
+    utils::AsyncLoader,
+};
+use base64::{engine::general_purpose, Engine};
+use dominator::{clone, events, html, with_node, Dom};
+use futures_signals::signal::{Mutable, SignalExt};
+use std::rc::Rc;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{HtmlInputElement, HtmlTextAreaElement};
+
+/// Metadata overridden by user, unset fields come from source
+#[derive(Debug, Default, Clone)]
+pub struct MetadataOverride {
+    pub title: Option<String>,
+    pub author: Option<Vec<String>>,
+    pub genre: Option<Vec<String>>,
+    pub status: Option<String>,
+    pub description: Option<String>,
+    pub custom_cover: bool,
+}
+
+pub struct EditMetadataModal {
+    manga_id: i64,
+    title: Mutable<String>,
+    author: Mutable<String>,
+    genre: Mutable<String>,
+    status: Mutable<String>,
+    description: Mutable<String>,
+    custom_cover: Mutable<bool>,
+    modal: Rc<Modal>,
+    loader: AsyncLoader,
+}
+
+impl EditMetadataModal {
+    pub fn new(manga_id: i64, metadata_override: MetadataOverride) -> Rc<Self> {
+        Rc::new(Self {
+            manga_id,
+            title: Mutable::new(metadata_override.title.unwrap_or_default()),
+            author: Mutable::new(metadata_override.author.unwrap_or_default().join(", ")),
+            genre: Mutable::new(metadata_override.genre.unwrap_or_default().join(", ")),
+            status: Mutable::new(metadata_override.status.unwrap_or_default()),
+            description: Mutable::new(metadata_override.description.unwrap_or_default()),
+            custom_cover: Mutable::new(metadata_override.custom_cover),
+            modal: Modal::new_with_default(true),
+            loader: AsyncLoader::new(),
+        })
+    }
+
+    fn save<F>(self: &Rc<Self>, f: F) where F: Fn() + Clone + 'static {
+        let edit = self.clone();
+        self.loader.load(clone!(edit => async move {
+            let list = |value: String| {
+                let values: Vec<String> = value.split(',').map(|value| value.trim().to_string()).filter(|value| !value.is_empty()).collect();
+                (!values.is_empty()).then(|| values)
+            };
+            let text = |value: String| (!value.trim().is_empty()).then(|| value);
+
+            match query::set_manga_metadata_override(
+                edit.manga_id,
+                text(edit.title.get_cloned()),
+                list(edit.author.get_cloned()),
+                list(edit.genre.get_cloned()),
+                text(edit.status.get_cloned()),
+                text(edit.description.get_cloned()),
+            ).await {
+                Ok(_) => {
+                    edit.modal.hide();
+                    f();
+                }
+                Err(e) => {
+                    snackbar::show(format!("failed to save metadata {}", e));
+                }
+            }
+        }));
+    }
+
+    fn upload_cover<F>(self: &Rc<Self>, input: HtmlInputElement, f: F) where F: Fn() + Clone + 'static {
+        let file = match input.files().and_then(|files| files.get(0)) {
+            Some(file) => file,
+            None => return,
+        };
+
+        let edit = self.clone();
+        self.loader.load(clone!(edit => async move {
+            let image = match JsFuture::from(file.array_buffer()).await {
+                Ok(buffer) => general_purpose::STANDARD.encode(js_sys::Uint8Array::new(&buffer).to_vec()),
+                Err(_) => {
+                    snackbar::show("failed to read image".to_string());
+                    return;
+                }
+            };
+
+            match query::upload_manga_cover(edit.manga_id, image).await {
+                Ok(_) => {
+                    edit.custom_cover.set(true);
+                    f();
+                }
+                Err(e) => {
+                    snackbar::show(format!("failed to upload cover {}", e));
+                }
+            }
+        }));
+    }
+
+    fn remove_cover<F>(self: &Rc<Self>, f: F) where F: Fn() + Clone + 'static {
+        let edit = self.clone();
+        self.loader.load(clone!(edit => async move {
+            match query::remove_manga_cover(edit.manga_id).await {
+                Ok(_) => {
+                    edit.custom_cover.set(false);
+                    f();
+                }
+                Err(e) => {
+                    snackbar::show(format!("failed to remove cover {}", e));
+                }
+            }
+        }));
+    }
+
+    fn reset<F>(self: &Rc<Self>, f: F) where F: Fn() + Clone + 'static {
+        let edit = self.clone();
+        self.loader.load(clone!(edit => async move {
+            match query::reset_manga_metadata(edit.manga_id).await {
+                Ok(_) => {
+                    edit.modal.hide();
+                    f();
+                }
+                Err(e) => {
+                    snackbar::show(format!("failed to reset metadata {}", e));
+                }
+            }
+        }));
+    }
+
+    pub fn render_header<F>(self: &Rc<Self>, f: F) -> Dom where F: Fn() + Clone + 'static {
+        let edit = self.clone();
+        html!("div", {
+            .style("display", "flex")
+            .style("justify-content", "space-between")
+            .style("margin-bottom", "0.5rem")
+            .children(&mut [
+                html!("span", {
+                    .style("font-size", "large")
+                    .text("Edit Metadata")
+                }),
+                html!("div", {
+                    .children(&mut [
+                        html!("button", {
+                            .style("margin-right", "0.5rem")
+                            .text("Reset")
+                            .event(clone!(edit, f => move |_: events::Click| {
+                                edit.reset(f.clone());
+                            }))
+                        }),
+                        html!("button", {
+                            .text("Save")
+                            .event(clone!(edit, f => move |_: events::Click| {
+                                edit.save(f.clone());
+                            }))
+                        }),
+                    ])
+                }),
+            ])
+        })
+    }
+
+    fn render_input(name: &str, placeholder: &str, state: &Mutable<String>) -> Dom {
+        html!("div", {
+            .style("margin-top", "0.25rem")
+            .style("margin-bottom", "0.25rem")
+            .children(&mut [
+                html!("label", {
+                    .text(name)
+                }),
+                html!("div", {
+                    .class("reader-settings-row")
+                    .style("display", "flex")
+                    .children(&mut [
+                        html!("input" => HtmlInputElement, {
+                            .style("width", "100%")
+                            .attr("type", "text")
+                            .attr("placeholder", placeholder)
+                            .attr("value", &state.get_cloned())
+                            .with_node!(input => {
+                                .event(clone!(state => move |_: events::Input| {
+                                    state.set(input.value());
+                                }))
+                            })
+                        })
+                    ])
+                })
+            ])
+        })
+    }
+
+    pub fn render_main<F>(self: &Rc<Self>, f: F) -> Dom where F: Fn() + Clone + 'static {
+        let edit = self.clone();
+        html!("div", {
+            .style("overflow-y", "auto")
+            .children(&mut [
+                Self::render_input("Title", "Title from source", &edit.title),
+                Self::render_input("Author", "Comma separated, authors from source", &edit.author),
+                Self::render_input("Genre", "Comma separated, genres from source", &edit.genre),
+                Self::render_input("Status", "Status from source", &edit.status),
+                html!("div", {
+                    .style("margin-top", "0.25rem")
+                    .style("margin-bottom", "0.25rem")
+                    .children(&mut [
+                        html!("label", {
+                            .text("Description")
+                        }),
+                        html!("div", {
+                            .class("reader-settings-row")
+                            .style("display", "flex")
+                            .children(&mut [
+                                html!("textarea" => HtmlTextAreaElement, {
+                                    .style("width", "100%")
+                                    .attr("rows", "4")
+                                    .attr("placeholder", "Description from source")
+                                    .text(&edit.description.get_cloned())
+                                    .with_node!(textarea => {
+                                        .event(clone!(edit => move |_: events::Input| {
+                                            edit.description.set(textarea.value());
+                                        }))
+                                    })
+                                })
+                            ])
+                        })
+                    ])
+                }),
+                html!("div", {
+                    .style("margin-top", "0.25rem")
+                    .style("margin-bottom", "0.25rem")
+                    .style("display", "flex")
+                    .style("justify-content", "space-between")
+                    .style("align-items", "center")
+                    .children(&mut [
+                        html!("label", {
+                            .text("Cover")
+                        }),
+                        html!("input" => HtmlInputElement, {
+                            .attr("type", "file")
+                            .attr("accept", "image/png,image/jpeg,image/gif,image/webp")
+                            .with_node!(input => {
+                                .event(clone!(edit, f => move |_: events::Change| {
+                                    edit.upload_cover(input.clone(), f.clone());
+                                }))
+                            })
+                        }),
+                    ])
+                    .child_signal(edit.custom_cover.signal().map(clone!(edit, f => move |custom_cover| custom_cover.then(|| html!("button", {
+                        .text("Remove Cover")
+                        .event(clone!(edit, f => move |_: events::Click| {
+                            edit.remove_cover(f.clone());
+                        }))
+                    })))))
+                }),
+            ])
+        })
+    }
+
+    pub fn render<F>(self: &Rc<Self>, f: F) -> Dom where F: Fn() + Clone + 'static {
+        let edit = self.clone();
+        self.modal.render(&mut [
+            edit.render_header(f.clone()),
+            edit.render_main(f),
+            html!("div", {
+                .child_signal(edit.loader.is_loading().map(|is_loading| is_loading.then(|| Spinner::render_spinner(true))))
+            }),
+        ])
+    }
+}
@@ -23,6 +23,22 @@ const KEY: &str = "settings:library";
 pub enum LibrarySortBy {
     Alphabetical,
     RecentlyRead,
+    LastUpdated,
+    DateAdded,
+    UnreadCount,
+}
+
+impl LibrarySortBy {
+    /// Sort key accepted by server
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LibrarySortBy::Alphabetical => "title",
+            LibrarySortBy::RecentlyRead => "last_read",
+            LibrarySortBy::LastUpdated => "last_updated",
+            LibrarySortBy::DateAdded => "date_added",
+            LibrarySortBy::UnreadCount => "unread_count",
+        }
+    }
 }
 
 #[derive(Clone, Copy, Deserialize, Serialize)]
@@ -51,6 +67,8 @@ pub enum LibraryFilter {
     None,
     Read,
     Unread,
+    Downloaded,
+    Tracked,
 }
 
 impl Default for LibraryFilter {
@@ -83,6 +101,8 @@ pub struct LibrarySettings {
 }
 
 impl LibrarySettings {
+    pub const SCHEMA_VERSION: i64 = 1;
+
     pub fn new(show: bool, use_modal: bool) -> Rc<Self> {
         Self::load(show, use_modal)
     }
@@ -109,7 +129,9 @@ impl LibrarySettings {
 
     fn save(&self) {
         let key = KEY.to_string();
-        let _ = local_storage().set_item(&key, &serde_json::to_string(self).unwrap());
+        let value = serde_json::to_string(self).unwrap();
+        let _ = local_storage().set_item(&key, &value);
+        preference_sync::push("library", 0, value);
         if self.use_modal {
             self.show.set_neq(false);
         }
@@ -124,6 +146,7 @@ impl LibrarySettings {
                         categories.push_cloned(Category {
                             id: c.id,
                             name: c.name.clone(),
+                            rule: c.rule.clone(),
                             count: c.count,
                         });
                     }
@@ -226,6 +249,29 @@ impl LibrarySettings {
                             .event(clone!(settings => move |_: events::Click| settings.sort.set(LibrarySort { by: LibrarySortBy::RecentlyRead, order: settings.sort.get_cloned().order})))
                         }),
                     ])
+                }),
+                html!("div", {
+                    .class("reader-settings-row")
+                    .children(&mut [
+                        html!("button", {
+                            .style("width", "33%")
+                            .class_signal("active", settings.sort.signal_cloned().map(|sort| matches!(sort.by, LibrarySortBy::LastUpdated)))
+                            .text("Last Updated")
+                            .event(clone!(settings => move |_: events::Click| settings.sort.set(LibrarySort { by: LibrarySortBy::LastUpdated, order: settings.sort.get_cloned().order})))
+                        }),
+                        html!("button", {
+                            .style("width", "33%")
+                            .class_signal("active", settings.sort.signal_cloned().map(|sort| matches!(sort.by, LibrarySortBy::DateAdded)))
+                            .text("Date Added")
+                            .event(clone!(settings => move |_: events::Click| settings.sort.set(LibrarySort { by: LibrarySortBy::DateAdded, order: settings.sort.get_cloned().order})))
+                        }),
+                        html!("button", {
+                            .style("width", "33%")
+                            .class_signal("active", settings.sort.signal_cloned().map(|sort| matches!(sort.by, LibrarySortBy::UnreadCount)))
+                            .text("Unread Count")
+                            .event(clone!(settings => move |_: events::Click| settings.sort.set(LibrarySort { by: LibrarySortBy::UnreadCount, order: settings.sort.get_cloned().order})))
+                        }),
+                    ])
                 })
             ])
         })
@@ -288,6 +334,23 @@ impl LibrarySettings {
                             .event(clone!(settings => move |_: events::Click| settings.filter.set(LibraryFilter::Unread)))
                         }),
                     ])
+                }),
+                html!("div", {
+                    .class("reader-settings-row")
+                    .children(&mut [
+                        html!("button", {
+                            .style("width", "50%")
+                            .class_signal("active", settings.filter.signal_cloned().map(|x| matches!(x, LibraryFilter::Downloaded)))
+                            .text("Downloaded")
+                            .event(clone!(settings => move |_: events::Click| settings.filter.set(LibraryFilter::Downloaded)))
+                        }),
+                        html!("button", {
+                            .style("width", "50%")
+                            .class_signal("active", settings.filter.signal_cloned().map(|x| matches!(x, LibraryFilter::Tracked)))
+                            .text("Tracked")
+                            .event(clone!(settings => move |_: events::Click| settings.filter.set(LibraryFilter::Tracked)))
+                        }),
+                    ])
                 })
             ])
         })
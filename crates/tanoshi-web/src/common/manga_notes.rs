@@ -0,0 +1,220 @@
+use crate::{
+    common::{snackbar, Modal, Spinner},
+    query,
+    utils::AsyncLoader,
+};
+use dominator::{clone, events, html, with_node, Dom};
+use futures_signals::{
+    signal::{Mutable, SignalExt},
+    signal_vec::{MutableVec, SignalVecExt},
+};
+use std::rc::Rc;
+use web_sys::{HtmlInputElement, HtmlSelectElement, HtmlTextAreaElement};
+
+pub struct MangaNotesModal {
+    manga_id: i64,
+    tags: Mutable<String>,
+    rating: Mutable<Option<i64>>,
+    note: Mutable<String>,
+    suggestions: MutableVec<String>,
+    modal: Rc<Modal>,
+    loader: AsyncLoader,
+}
+
+impl MangaNotesModal {
+    pub fn new(manga_id: i64, tags: Vec<String>, rating: Option<i64>, note: Option<String>) -> Rc<Self> {
+        Rc::new(Self {
+            manga_id,
+            tags: Mutable::new(tags.join(", ")),
+            rating: Mutable::new(rating),
+            note: Mutable::new(note.unwrap_or_default()),
+            suggestions: MutableVec::new(),
+            modal: Modal::new_with_default(true),
+            loader: AsyncLoader::new(),
+        })
+    }
+
+    fn fetch_tags(self: &Rc<Self>) {
+        let notes = self.clone();
+        self.loader.load(clone!(notes => async move {
+            match query::fetch_tags().await {
+                Ok(tags) => {
+                    notes.suggestions.lock_mut().replace_cloned(tags.into_iter().map(|tag| tag.name).collect());
+                }
+                Err(e) => {
+                    snackbar::show(format!("failed to fetch tags {}", e));
+                }
+            }
+        }));
+    }
+
+    fn add_tag(&self, tag: &str) {
+        let mut tags: Vec<String> = self.tags.get_cloned().split(',').map(|tag| tag.trim().to_string()).filter(|tag| !tag.is_empty()).collect();
+        if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            tags.push(tag.to_string());
+        }
+        self.tags.set(tags.join(", "));
+    }
+
+    fn save<F>(self: &Rc<Self>, f: F) where F: Fn() + Clone + 'static {
+        let notes = self.clone();
+        self.loader.load(clone!(notes => async move {
+            let tags: Vec<String> = notes.tags.get_cloned().split(',').map(|tag| tag.trim().to_string()).filter(|tag| !tag.is_empty()).collect();
+            let note = notes.note.get_cloned();
+            let note = (!note.trim().is_empty()).then(|| note);
+
+            let res = async {
+                query::set_manga_tags(notes.manga_id, tags).await?;
+                query::set_manga_rating(notes.manga_id, notes.rating.get()).await?;
+                query::set_manga_note(notes.manga_id, note).await
+            };
+
+            match res.await {
+                Ok(_) => {
+                    notes.modal.hide();
+                    f();
+                }
+                Err(e) => {
+                    snackbar::show(format!("failed to save notes {}", e));
+                }
+            }
+        }));
+    }
+
+    pub fn render_header<F>(self: &Rc<Self>, f: F) -> Dom where F: Fn() + Clone + 'static {
+        let notes = self.clone();
+        html!("div", {
+            .style("display", "flex")
+            .style("justify-content", "space-between")
+            .style("margin-bottom", "0.5rem")
+            .children(&mut [
+                html!("span", {
+                    .style("font-size", "large")
+                    .text("Notes")
+                }),
+                html!("button", {
+                    .text("Save")
+                    .event(clone!(notes, f => move |_: events::Click| {
+                        notes.save(f.clone());
+                    }))
+                }),
+            ])
+        })
+    }
+
+    pub fn render_main(self: &Rc<Self>) -> Dom {
+        let notes = self.clone();
+        html!("div", {
+            .style("overflow-y", "auto")
+            .children(&mut [
+                html!("div", {
+                    .style("margin-top", "0.25rem")
+                    .style("margin-bottom", "0.25rem")
+                    .children(&mut [
+                        html!("label", {
+                            .text("Tags")
+                        }),
+                        html!("div", {
+                            .class("reader-settings-row")
+                            .style("display", "flex")
+                            .children(&mut [
+                                html!("input" => HtmlInputElement, {
+                                    .style("width", "100%")
+                                    .attr("type", "text")
+                                    .attr("placeholder", "Comma separated, e.g. favorite, to-reread")
+                                    .prop_signal("value", notes.tags.signal_cloned())
+                                    .with_node!(input => {
+                                        .event(clone!(notes => move |_: events::Input| {
+                                            notes.tags.set(input.value());
+                                        }))
+                                    })
+                                })
+                            ])
+                        }),
+                        html!("div", {
+                            .style("display", "flex")
+                            .style("flex-wrap", "wrap")
+                            .children_signal_vec(notes.suggestions.signal_vec_cloned().map(clone!(notes => move |tag| html!("span", {
+                                .class("chip")
+                                .style("cursor", "pointer")
+                                .text(&format!("#{tag}"))
+                                .event(clone!(notes => move |_: events::Click| {
+                                    notes.add_tag(&tag);
+                                }))
+                            }))))
+                        })
+                    ])
+                }),
+                html!("div", {
+                    .style("margin-top", "0.25rem")
+                    .style("margin-bottom", "0.25rem")
+                    .style("display", "flex")
+                    .style("justify-content", "space-between")
+                    .style("align-items", "center")
+                    .children(&mut [
+                        html!("label", {
+                            .text("Rating")
+                        }),
+                        html!("select" => HtmlSelectElement, {
+                            .children(&mut [
+                                html!("option", {
+                                    .attr("value", "")
+                                    .text("None")
+                                    .apply_if(notes.rating.get().is_none(), |dom| dom.attr("selected", ""))
+                                })
+                            ])
+                            .children((1..=10).map(|rating| html!("option", {
+                                .attr("value", &rating.to_string())
+                                .text(&format!("{}/10", rating))
+                                .apply_if(notes.rating.get() == Some(rating), |dom| dom.attr("selected", ""))
+                            })))
+                            .with_node!(select => {
+                                .event(clone!(notes => move |_: events::Change| {
+                                    notes.rating.set(select.value().parse().ok());
+                                }))
+                            })
+                        }),
+                    ])
+                }),
+                html!("div", {
+                    .style("margin-top", "0.25rem")
+                    .style("margin-bottom", "0.25rem")
+                    .children(&mut [
+                        html!("label", {
+                            .text("Note")
+                        }),
+                        html!("div", {
+                            .class("reader-settings-row")
+                            .style("display", "flex")
+                            .children(&mut [
+                                html!("textarea" => HtmlTextAreaElement, {
+                                    .style("width", "100%")
+                                    .attr("rows", "6")
+                                    .attr("placeholder", "Only visible to you")
+                                    .text(&notes.note.get_cloned())
+                                    .with_node!(textarea => {
+                                        .event(clone!(notes => move |_: events::Input| {
+                                            notes.note.set(textarea.value());
+                                        }))
+                                    })
+                                })
+                            ])
+                        })
+                    ])
+                }),
+            ])
+        })
+    }
+
+    pub fn render<F>(self: &Rc<Self>, f: F) -> Dom where F: Fn() + Clone + 'static {
+        let notes = self.clone();
+        notes.fetch_tags();
+        self.modal.render(&mut [
+            notes.render_header(f),
+            notes.render_main(),
+            html!("div", {
+                .child_signal(notes.loader.is_loading().map(|is_loading| is_loading.then(|| Spinner::render_spinner(true))))
+            }),
+        ])
+    }
+}
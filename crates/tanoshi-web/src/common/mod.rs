@@ -18,6 +18,8 @@ pub use reader_settings::*;
 
 pub mod events;
 
+pub mod preference_sync;
+
 mod login;
 pub use login::Login;
 
@@ -46,4 +48,13 @@ pub use select_category::SelectCategoryModal;
 mod select_track_manga;
 pub use select_track_manga::{SelectTrackMangaModal, TrackerStatus};
 
+mod edit_metadata;
+pub use edit_metadata::{EditMetadataModal, MetadataOverride};
+
+mod manga_notes;
+pub use manga_notes::MangaNotesModal;
+
+mod scanlator_settings;
+pub use scanlator_settings::{ScanlatorSetting, ScanlatorSettingsModal};
+
 pub mod icons;
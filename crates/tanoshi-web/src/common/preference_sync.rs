@@ -0,0 +1,116 @@
+//! Keeps settings in localStorage in sync with preferences saved on the server, so they follow
+//! the user across devices. A preference `key` maps to `settings:{key}` for global settings and
+//! `settings:{key}:{manga_id}` for per manga settings.
+
+use wasm_bindgen_futures::spawn_local;
+
+use crate::{
+    common::{AppearanceSettings, ChapterSettings, LibrarySettings, ReaderSettings},
+    query,
+    utils::{apply_theme, local_storage},
+};
+
+/// Latest schema version of each document this client understands
+fn schema_version(key: &str) -> Option<i64> {
+    match key {
+        "appearance" => Some(AppearanceSettings::SCHEMA_VERSION),
+        "chapter" => Some(ChapterSettings::SCHEMA_VERSION),
+        "library" => Some(LibrarySettings::SCHEMA_VERSION),
+        "reader" => Some(ReaderSettings::SCHEMA_VERSION),
+        _ => None,
+    }
+}
+
+fn storage_key(key: &str, manga_id: Option<i64>) -> String {
+    match manga_id {
+        Some(manga_id) if manga_id > 0 => format!("settings:{key}:{manga_id}"),
+        _ => format!("settings:{key}"),
+    }
+}
+
+/// Write a preference received from server to localStorage, `None` value deletes it
+fn apply(key: &str, manga_id: Option<i64>, version: i64, value: Option<&str>) {
+    match schema_version(key) {
+        // saved by a newer client, leave local settings alone
+        Some(supported) if supported >= version => {}
+        _ => return,
+    }
+
+    let storage_key = storage_key(key, manga_id);
+    match value {
+        Some(value) => {
+            let _ = local_storage().set_item(&storage_key, value);
+        }
+        None => {
+            let _ = local_storage().delete(&storage_key);
+        }
+    }
+
+    if key == "appearance" {
+        let theme = value
+            .and_then(|value| serde_json::from_str::<AppearanceSettings>(value).ok())
+            .map(|settings| settings.theme.get().to_string())
+            .unwrap_or_default();
+        let _ = local_storage().set_item("theme", &theme);
+        apply_theme(Some(theme));
+    }
+}
+
+/// Save settings to server, `manga_id` 0 means global settings
+pub fn push(key: &'static str, manga_id: i64, value: String) {
+    let version = schema_version(key).unwrap_or(1);
+    let manga_id = Some(manga_id).filter(|manga_id| *manga_id > 0);
+    spawn_local(async move {
+        if let Err(e) = query::set_user_preference(key.to_string(), manga_id, version, value).await
+        {
+            error!("failed to save {key} preference: {e}");
+        }
+    });
+}
+
+/// Delete settings from server, `manga_id` 0 means global settings
+pub fn remove(key: &'static str, manga_id: i64) {
+    let manga_id = Some(manga_id).filter(|manga_id| *manga_id > 0);
+    spawn_local(async move {
+        if let Err(e) = query::delete_user_preference(key.to_string(), manga_id).await {
+            error!("failed to delete {key} preference: {e}");
+        }
+    });
+}
+
+/// Fetch global preferences, or preferences of a manga, into localStorage
+pub async fn pull(manga_id: Option<i64>) {
+    match query::fetch_user_preferences(manga_id).await {
+        Ok(preferences) => {
+            for preference in preferences {
+                apply(
+                    &preference.key,
+                    preference.manga_id,
+                    preference.schema_version,
+                    Some(&preference.value),
+                );
+            }
+        }
+        Err(e) => {
+            error!("failed to fetch preferences: {e}");
+        }
+    }
+}
+
+/// Apply preferences changed from other devices until the connection closes
+pub async fn subscribe() {
+    let res = query::subscribe_user_preference_changes(|change| {
+        let preference = change.preference;
+        apply(
+            &preference.key,
+            preference.manga_id,
+            preference.schema_version,
+            (!change.deleted).then_some(preference.value.as_str()),
+        );
+    })
+    .await;
+
+    if let Err(e) = res {
+        error!("preference subscription error: {e}");
+    }
+}
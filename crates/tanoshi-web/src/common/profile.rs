@@ -2,6 +2,7 @@ use std::rc::Rc;
 
 use dominator::{clone, html, routing, Dom};
 use dominator::{with_node, EventOptions};
+use futures_signals::map_ref;
 use futures_signals::signal::Mutable;
 use futures_signals::signal::SignalExt;
 use wasm_bindgen::prelude::Closure;
@@ -10,8 +11,9 @@ use wasm_bindgen_futures::JsFuture;
 use web_sys::{HtmlInputElement, Notification, NotificationPermission};
 
 use crate::common::{events, snackbar, Route};
+use crate::oidc_redirect::OidcRedirect;
 use crate::query;
-use crate::utils::{local_storage, AsyncLoader};
+use crate::utils::{clear_auth_token, AsyncLoader};
 
 pub struct Profile {
     old_password: Mutable<String>,
@@ -22,6 +24,15 @@ pub struct Profile {
     gotify_token: Mutable<Option<String>>,
     myanimelist_status: Mutable<bool>,
     anilist_status: Mutable<bool>,
+    oidc_enabled: Mutable<bool>,
+    oidc_linked: Mutable<bool>,
+    totp_enabled: Mutable<bool>,
+    recovery_codes_count: Mutable<i64>,
+    /// secret and otpauth uri while enrolling
+    totp_enrollment: Mutable<Option<(String, String)>>,
+    totp_code: Mutable<String>,
+    /// only shown once after they are generated
+    recovery_codes: Mutable<Vec<String>>,
     notification_cb: Closure<dyn FnMut(JsValue) -> ()>,
     pub loader: AsyncLoader,
 }
@@ -37,6 +48,13 @@ impl Profile {
             gotify_token: Mutable::new(None),
             myanimelist_status: Mutable::new(false),
             anilist_status: Mutable::new(false),
+            oidc_enabled: Mutable::new(false),
+            oidc_linked: Mutable::new(false),
+            totp_enabled: Mutable::new(false),
+            recovery_codes_count: Mutable::new(0),
+            totp_enrollment: Mutable::new(None),
+            totp_code: Mutable::new("".to_string()),
+            recovery_codes: Mutable::new(vec![]),
             notification_cb: Closure::wrap(Box::new(|value| {
                 let permission = NotificationPermission::from_js_value(&value)
                     .unwrap_or(NotificationPermission::Default);
@@ -60,14 +78,94 @@ impl Profile {
                     profile.gotify_token.set(result.gotify_token);
                     profile.myanimelist_status.set(result.myanimelist_status);
                     profile.anilist_status.set(result.anilist_status);
+                    profile.totp_enabled.set(result.totp_enabled);
+                    profile.recovery_codes_count.set(result.recovery_codes_count);
                 },
                 Err(err) => {
                     snackbar::show(format!("{}", err));
                 }
             }
+
+            if let Ok(status) = query::fetch_server_status().await {
+                profile.oidc_enabled.set(status.oidc_enabled);
+            }
+
+            if profile.oidc_enabled.get() {
+                match query::fetch_linked_accounts().await {
+                    Ok(accounts) => profile.oidc_linked.set(!accounts.is_empty()),
+                    Err(err) => {
+                        snackbar::show(format!("{}", err));
+                    }
+                }
+            }
         }));
     }
 
+    fn unlink_oidc_account(profile: Rc<Self>) {
+        profile.loader.load(clone!(profile => async move {
+            match query::unlink_oidc_account().await {
+                Ok(_) => Self::fetch_me(profile),
+                Err(err) => {
+                    snackbar::show(format!("{}", err));
+                }
+            }
+        }))
+    }
+
+    fn enroll_totp(profile: Rc<Self>) {
+        profile.loader.load(clone!(profile => async move {
+            match query::enroll_totp().await {
+                Ok(enrollment) => profile.totp_enrollment.set(Some((enrollment.secret, enrollment.uri))),
+                Err(err) => {
+                    snackbar::show(format!("{}", err));
+                }
+            }
+        }))
+    }
+
+    fn confirm_totp(profile: Rc<Self>) {
+        profile.loader.load(clone!(profile => async move {
+            match query::confirm_totp(profile.totp_code.replace("".to_string())).await {
+                Ok(codes) => {
+                    profile.totp_enrollment.set(None);
+                    profile.recovery_codes.set(codes);
+                    Self::fetch_me(profile);
+                }
+                Err(err) => {
+                    snackbar::show(format!("{}", err));
+                }
+            }
+        }))
+    }
+
+    fn disable_totp(profile: Rc<Self>) {
+        profile.loader.load(clone!(profile => async move {
+            match query::disable_totp(profile.totp_code.replace("".to_string())).await {
+                Ok(_) => {
+                    profile.recovery_codes.set(vec![]);
+                    Self::fetch_me(profile);
+                }
+                Err(err) => {
+                    snackbar::show(format!("{}", err));
+                }
+            }
+        }))
+    }
+
+    fn regenerate_recovery_codes(profile: Rc<Self>) {
+        profile.loader.load(clone!(profile => async move {
+            match query::regenerate_recovery_codes(profile.totp_code.replace("".to_string())).await {
+                Ok(codes) => {
+                    profile.recovery_codes.set(codes);
+                    Self::fetch_me(profile);
+                }
+                Err(err) => {
+                    snackbar::show(format!("{}", err));
+                }
+            }
+        }))
+    }
+
     fn test_telegram(profile: Rc<Self>) {
         if let Some(chat_id) = profile
             .telegram_chat_id
@@ -151,7 +249,9 @@ impl Profile {
             let new_password = profile.new_password.get_cloned();
             match query::change_password(old_password, new_password).await {
                 Ok(_) => {
-                    // routing::go_to_url(Route::Settings(SettingCategory::None).url().as_str());
+                    // every session is revoked after password change
+                    clear_auth_token();
+                    routing::go_to_url(&Route::Login.url());
                 },
                 Err(e) => {
                     snackbar::show(format!("change password error: {}", e));
@@ -504,20 +604,202 @@ impl Profile {
         })
     }
 
+    fn render_oidc_setting(profile: Rc<Self>) -> Dom {
+        html!("div", {
+            .child_signal(profile.oidc_enabled.signal().map(clone!(profile => move |enabled| enabled.then(|| html!("form", {
+                .class("content")
+                .style("display", "flex")
+                .style("max-width", "1024px")
+                .style("margin-left", "auto")
+                .style("margin-right", "auto")
+                .style("margin-bottom", "0.5rem")
+                .style("padding", "0.5rem")
+                .style("border-radius", "0.5rem")
+                .style("border", "var(--list-group-border)")
+                .children(&mut [
+                    html!("span", {
+                        .style("margin-left", "0.25rem")
+                        .style("width", "100%")
+                        .text("Single Sign-On")
+                    }),
+                ])
+                .child_signal(profile.oidc_linked.signal().map(clone!(profile => move |linked| if linked {
+                    Some(html!("button", {
+                        .style("color", "red")
+                        .text("Unlink")
+                        .event_with_options(&EventOptions::preventable(), clone!(profile => move |e: events::Click| {
+                            e.prevent_default();
+                            Self::unlink_oidc_account(profile.clone());
+                        }))
+                    }))
+                } else {
+                    Some(html!("button", {
+                        .text("Link")
+                        .event_with_options(&EventOptions::preventable(), clone!(profile => move |e: events::Click| {
+                            e.prevent_default();
+                            profile.loader.load(OidcRedirect::start(true));
+                        }))
+                    }))
+                })))
+            })))))
+        })
+    }
+
+    fn render_totp_code_input(profile: Rc<Self>) -> Dom {
+        html!("input" => HtmlInputElement, {
+            .style("width", "100%")
+            .attr("type", "text")
+            .attr("inputmode", "numeric")
+            .attr("autocomplete", "one-time-code")
+            .attr("placeholder", "Code from authenticator app")
+            .prop_signal("value", profile.totp_code.signal_cloned())
+            .with_node!(input => {
+                .event(clone!(profile => move |_: events::Input| {
+                    profile.totp_code.set(input.value());
+                }))
+            })
+        })
+    }
+
+    fn render_totp_setting(profile: Rc<Self>) -> Dom {
+        html!("form", {
+            .class("content")
+            .style("display", "flex")
+            .style("flex-direction", "column")
+            .style("max-width", "1024px")
+            .style("margin-left", "auto")
+            .style("margin-right", "auto")
+            .style("margin-bottom", "0.5rem")
+            .style("padding", "0.5rem")
+            .style("border-radius", "0.5rem")
+            .style("border", "var(--list-group-border)")
+            .children(&mut [
+                html!("span", {
+                    .style("margin-left", "0.25rem")
+                    .style("margin-bottom", "0.5rem")
+                    .text("Two-Factor Authentication")
+                }),
+            ])
+            .child_signal(profile.recovery_codes.signal_cloned().map(|codes| (!codes.is_empty()).then(|| html!("div", {
+                .style("display", "flex")
+                .style("flex-direction", "column")
+                .style("margin", "0.25rem")
+                .children(&mut [
+                    html!("span", {
+                        .style("margin-bottom", "0.5rem")
+                        .text("Save these recovery codes, each can be used once to login and they will not be shown again")
+                    }),
+                ])
+                .children(codes.into_iter().map(|code| html!("code", {
+                    .text(&code)
+                })))
+            }))))
+            .child_signal(map_ref! {
+                let enabled = profile.totp_enabled.signal(),
+                let enrollment = profile.totp_enrollment.signal_cloned() =>
+                (*enabled, enrollment.clone())
+            }.map(clone!(profile => move |(enabled, enrollment)| match (enabled, enrollment) {
+                (true, _) => Some(html!("div", {
+                    .style("display", "flex")
+                    .style("flex-direction", "column")
+                    .children(&mut [
+                        html!("span", {
+                            .style("margin", "0.25rem")
+                            .text_signal(profile.recovery_codes_count.signal().map(|count| format!("Enabled, {count} recovery codes left")))
+                        }),
+                        Self::render_totp_code_input(profile.clone()),
+                        html!("div", {
+                            .style("display", "flex")
+                            .style("justify-content", "flex-end")
+                            .style("margin-top", "0.5rem")
+                            .children(&mut [
+                                html!("button", {
+                                    .text("Regenerate Recovery Codes")
+                                    .event_with_options(&EventOptions::preventable(), clone!(profile => move |e: events::Click| {
+                                        e.prevent_default();
+                                        Self::regenerate_recovery_codes(profile.clone());
+                                    }))
+                                }),
+                                html!("button", {
+                                    .style("color", "red")
+                                    .text("Disable")
+                                    .event_with_options(&EventOptions::preventable(), clone!(profile => move |e: events::Click| {
+                                        e.prevent_default();
+                                        Self::disable_totp(profile.clone());
+                                    }))
+                                }),
+                            ])
+                        }),
+                    ])
+                })),
+                (false, Some((secret, uri))) => Some(html!("div", {
+                    .style("display", "flex")
+                    .style("flex-direction", "column")
+                    .children(&mut [
+                        html!("span", {
+                            .style("margin", "0.25rem")
+                            .text("Add this secret to an authenticator app, then enter the code it shows")
+                        }),
+                        html!("a", {
+                            .style("margin", "0.25rem")
+                            .style("word-break", "break-all")
+                            .attr("href", &uri)
+                            .children(&mut [
+                                html!("code", {
+                                    .text(&secret)
+                                })
+                            ])
+                        }),
+                        Self::render_totp_code_input(profile.clone()),
+                        html!("div", {
+                            .style("display", "flex")
+                            .style("justify-content", "flex-end")
+                            .style("margin-top", "0.5rem")
+                            .children(&mut [
+                                html!("button", {
+                                    .text("Confirm")
+                                    .event_with_options(&EventOptions::preventable(), clone!(profile => move |e: events::Click| {
+                                        e.prevent_default();
+                                        Self::confirm_totp(profile.clone());
+                                    }))
+                                }),
+                            ])
+                        }),
+                    ])
+                })),
+                (false, None) => Some(html!("div", {
+                    .style("display", "flex")
+                    .style("justify-content", "flex-end")
+                    .children(&mut [
+                        html!("button", {
+                            .text("Enable")
+                            .event_with_options(&EventOptions::preventable(), clone!(profile => move |e: events::Click| {
+                                e.prevent_default();
+                                Self::enroll_totp(profile.clone());
+                            }))
+                        }),
+                    ])
+                })),
+            })))
+        })
+    }
+
     pub fn render(profile: Rc<Self>) -> Dom {
         Self::fetch_me(profile.clone());
 
         html!("div", {
             .children(&mut [
                 Self::render_change_password(profile.clone()),
+                Self::render_totp_setting(profile.clone()),
                 Self::render_notification_setting(profile.clone()),
-                Self::render_tracker_setting(profile),
+                Self::render_tracker_setting(profile.clone()),
+                Self::render_oidc_setting(profile),
                 html!("div", {
                     .style("max-width", "1024px")
                     .style("margin-left", "auto")
                     .style("margin-right", "auto")
                     .event(|_: events::Click| {
-                        local_storage().delete("token").unwrap_throw();
+                        clear_auth_token();
                         routing::go_to_url("/login");
                     })
                     .children(&mut [
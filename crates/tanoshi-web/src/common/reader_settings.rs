@@ -6,7 +6,10 @@ use futures_signals::{
 use serde::{Deserialize, Serialize};
 use std::rc::Rc;
 
-use crate::utils::{document, local_storage};
+use crate::{
+    common::preference_sync,
+    utils::{document, local_storage},
+};
 
 const KEY: &str = "settings:reader";
 
@@ -130,6 +133,8 @@ pub struct ReaderSettings {
 }
 
 impl ReaderSettings {
+    pub const SCHEMA_VERSION: i64 = 1;
+
     pub fn new(show: bool, use_modal: bool) -> Rc<Self> {
         Self::load(show, use_modal, 0)
     }
@@ -182,16 +187,18 @@ impl ReaderSettings {
     }
 
     fn save(&self) {
+        self.save_with_manga_id(*self.manga_id.lock_ref());
+    }
+
+    fn save_with_manga_id(&self, manga_id: i64) {
         let mut key = KEY.to_string();
-        if *self.manga_id.lock_ref() > 0 {
-            key = [key, (*self.manga_id.lock_ref()).to_string()].join(":");
+        if manga_id > 0 {
+            key = [key, manga_id.to_string()].join(":");
         }
 
-        self.save_with_key(&key);
-    }
-
-    fn save_with_key(&self, key: &str) {
-        let _ = local_storage().set_item(key, &serde_json::to_string(self).unwrap());
+        let value = serde_json::to_string(self).unwrap();
+        let _ = local_storage().set_item(&key, &value);
+        preference_sync::push("reader", manga_id, value);
         if self.use_modal {
             self.show.set_neq(false);
         }
@@ -204,6 +211,10 @@ impl ReaderSettings {
         }
 
         let _ = local_storage().delete(&key);
+        // global settings are saved again right after reset
+        if *self.manga_id.lock_ref() > 0 {
+            preference_sync::remove("reader", *self.manga_id.lock_ref());
+        }
         if self.use_modal {
             self.show.set_neq(false);
         }
@@ -225,7 +236,7 @@ impl ReaderSettings {
                     .text("Default")
                     .event(clone!(settings => move |_: events::Click| {
                         settings.reset();
-                        settings.save_with_key(KEY);
+                        settings.save_with_manga_id(0);
                     }))
                 }),
                 html!("button", {
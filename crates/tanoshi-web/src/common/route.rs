@@ -18,12 +18,17 @@ pub enum SettingCategory {
     CreateUser,
     User,
     DownloadQueue,
+    Statistics,
 }
 
 #[derive(Debug, Clone)]
 pub enum Route {
     Root,
     Login,
+    OidcRedirect {
+        code: String,
+        state: String,
+    },
     LibraryList,
     Library(Option<i64>),
     CatalogueList,
@@ -58,6 +63,13 @@ impl Route {
 
                 match paths.as_slice() {
                     ["login"] => Route::Login,
+                    ["login", "oidc", "redirect"] => {
+                        let params = url.search_params();
+                        match (params.get("code"), params.get("state")) {
+                            (Some(code), Some(state)) => Route::OidcRedirect { code, state },
+                            _ => Route::NotFound,
+                        }
+                    }
                     [] => Route::Root,
                     ["libraries"] => Route::LibraryList,
                     ["library"] => Route::Library(None),
@@ -137,6 +149,7 @@ impl Route {
                         "users" => Route::Settings(SettingCategory::Users),
                         "user" => Route::Settings(SettingCategory::User),
                         "downloads-queue" => Route::Settings(SettingCategory::DownloadQueue),
+                        "statistics" => Route::Settings(SettingCategory::Statistics),
                         _ => Route::NotFound,
                     },
                     ["settings", "users", "create"] => Route::Settings(SettingCategory::CreateUser),
@@ -179,6 +192,9 @@ impl Route {
         match self {
             Route::Root => "/".to_string(),
             Route::Login => "/login".to_string(),
+            Route::OidcRedirect { code, state } => {
+                format!("/login/oidc/redirect?code={code}&state={state}")
+            }
             Route::LibraryList => "/libraries".to_string(),
             Route::Library(category_id) => {
                 if let Some(id) = category_id {
@@ -228,6 +244,7 @@ impl Route {
             Route::Settings(SettingCategory::DownloadQueue) => {
                 "/settings/downloads-queue".to_string()
             }
+            Route::Settings(SettingCategory::Statistics) => "/settings/statistics".to_string(),
             Route::TrackerLogin(tracker) => format!("/tracker/{tracker}/login"),
             Route::TrackerRedirect {
                 tracker,
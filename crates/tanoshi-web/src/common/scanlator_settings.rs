@@ -0,0 +1,213 @@
+use crate::{
+    common::{snackbar, Modal, Spinner},
+    query,
+    utils::AsyncLoader,
+};
+use dominator::{clone, events, html, with_node, Dom};
+use futures_signals::{
+    signal::{Mutable, SignalExt},
+    signal_vec::{MutableVec, SignalVecExt},
+};
+use std::rc::Rc;
+use web_sys::HtmlInputElement;
+
+/// Scanlator preference of a manga, shared by every user
+#[derive(Debug, Default, Clone)]
+pub struct ScanlatorSetting {
+    pub dedup: bool,
+    pub priority: Vec<String>,
+    pub blocklist: Vec<String>,
+    pub scanlators: Vec<String>,
+}
+
+#[derive(Clone)]
+struct ScanlatorItem {
+    name: String,
+    blocked: Mutable<bool>,
+}
+
+pub struct ScanlatorSettingsModal {
+    manga_id: i64,
+    dedup: Mutable<bool>,
+    // ordered by preference
+    scanlators: MutableVec<ScanlatorItem>,
+    is_ordered: Mutable<bool>,
+    modal: Rc<Modal>,
+    loader: AsyncLoader,
+}
+
+impl ScanlatorSettingsModal {
+    pub fn new(manga_id: i64, setting: ScanlatorSetting) -> Rc<Self> {
+        let is_blocked = |name: &str| setting.blocklist.iter().any(|b| b.eq_ignore_ascii_case(name));
+
+        // preferred scanlators first, then the rest as listed by server
+        let mut names: Vec<String> = setting.priority.clone();
+        for name in setting.scanlators.iter().chain(setting.blocklist.iter()) {
+            if !names.iter().any(|n| n.eq_ignore_ascii_case(name)) {
+                names.push(name.clone());
+            }
+        }
+
+        let scanlators = names
+            .into_iter()
+            .map(|name| ScanlatorItem {
+                blocked: Mutable::new(is_blocked(&name)),
+                name,
+            })
+            .collect();
+
+        Rc::new(Self {
+            manga_id,
+            dedup: Mutable::new(setting.dedup),
+            scanlators: MutableVec::new_with_values(scanlators),
+            is_ordered: Mutable::new(!setting.priority.is_empty()),
+            modal: Modal::new_with_default(true),
+            loader: AsyncLoader::new(),
+        })
+    }
+
+    fn move_scanlator(&self, index: usize, up: bool) {
+        let mut scanlators = self.scanlators.lock_mut();
+        let target = if up { index.checked_sub(1) } else { Some(index + 1) };
+        if let Some(target) = target.filter(|target| *target < scanlators.len()) {
+            scanlators.swap(index, target);
+            self.is_ordered.set(true);
+        }
+    }
+
+    fn save<F>(self: &Rc<Self>, f: F) where F: Fn() + Clone + 'static {
+        let settings = self.clone();
+        self.loader.load(clone!(settings => async move {
+            let scanlators = settings.scanlators.lock_ref().to_vec();
+            let blocklist: Vec<String> = scanlators.iter().filter(|s| s.blocked.get()).map(|s| s.name.clone()).collect();
+            // only keep an order once user picked one, otherwise ties go to the earliest upload
+            let priority: Vec<String> = if settings.is_ordered.get() {
+                scanlators.iter().filter(|s| !s.blocked.get()).map(|s| s.name.clone()).collect()
+            } else {
+                vec![]
+            };
+
+            match query::set_scanlator_setting(settings.manga_id, settings.dedup.get(), priority, blocklist).await {
+                Ok(_) => {
+                    settings.modal.hide();
+                    f();
+                }
+                Err(e) => {
+                    snackbar::show(format!("failed to save scanlator settings {}", e));
+                }
+            }
+        }));
+    }
+
+    pub fn render_header<F>(self: &Rc<Self>, f: F) -> Dom where F: Fn() + Clone + 'static {
+        let settings = self.clone();
+        html!("div", {
+            .style("display", "flex")
+            .style("justify-content", "space-between")
+            .style("margin-bottom", "0.5rem")
+            .children(&mut [
+                html!("span", {
+                    .style("font-size", "large")
+                    .text("Scanlators")
+                }),
+                html!("button", {
+                    .text("Save")
+                    .event(clone!(settings, f => move |_: events::Click| {
+                        settings.save(f.clone());
+                    }))
+                }),
+            ])
+        })
+    }
+
+    pub fn render_main(self: &Rc<Self>) -> Dom {
+        let settings = self.clone();
+        html!("div", {
+            .style("overflow-y", "auto")
+            .children(&mut [
+                html!("div", {
+                    .class("reader-settings-row")
+                    .style("display", "flex")
+                    .style("justify-content", "space-between")
+                    .style("align-items", "center")
+                    .children(&mut [
+                        html!("label", {
+                            .attr("for", "dedup")
+                            .text("Hide duplicate chapters")
+                        }),
+                        html!("input" => HtmlInputElement, {
+                            .attr("type", "checkbox")
+                            .attr("id", "dedup")
+                            .prop_signal("checked", settings.dedup.signal())
+                            .with_node!(input => {
+                                .event(clone!(settings => move |_: events::Change| {
+                                    settings.dedup.set_neq(input.checked());
+                                }))
+                            })
+                        }),
+                    ])
+                }),
+                html!("span", {
+                    .style("font-size", "small")
+                    .text("Chapters with the same number are shown from the topmost scanlator, blocked scanlators are hidden")
+                }),
+                html!("ul", {
+                    .class("list")
+                    .children_signal_vec(settings.scanlators.signal_vec_cloned().enumerate().map(clone!(settings => move |(index, scanlator)| html!("li", {
+                        .class("list-item")
+                        .style("display", "flex")
+                        .style("justify-content", "space-between")
+                        .style("align-items", "center")
+                        .style_signal("text-decoration", scanlator.blocked.signal().map(|blocked| blocked.then(|| "line-through")))
+                        .children(&mut [
+                            html!("span", {
+                                .text(&scanlator.name)
+                            }),
+                            html!("div", {
+                                .style("display", "flex")
+                                .children(&mut [
+                                    html!("button", {
+                                        .style("margin", "0.25rem")
+                                        .text("Up")
+                                        .event(clone!(settings, index => move |_: events::Click| {
+                                            if let Some(index) = index.get() {
+                                                settings.move_scanlator(index, true);
+                                            }
+                                        }))
+                                    }),
+                                    html!("button", {
+                                        .style("margin", "0.25rem")
+                                        .text("Down")
+                                        .event(clone!(settings, index => move |_: events::Click| {
+                                            if let Some(index) = index.get() {
+                                                settings.move_scanlator(index, false);
+                                            }
+                                        }))
+                                    }),
+                                    html!("button", {
+                                        .style("margin", "0.25rem")
+                                        .text_signal(scanlator.blocked.signal().map(|blocked| if blocked { "Unblock" } else { "Block" }))
+                                        .event(clone!(scanlator => move |_: events::Click| {
+                                            scanlator.blocked.set(!scanlator.blocked.get());
+                                        }))
+                                    }),
+                                ])
+                            }),
+                        ])
+                    }))))
+                }),
+            ])
+        })
+    }
+
+    pub fn render<F>(self: &Rc<Self>, f: F) -> Dom where F: Fn() + Clone + 'static {
+        let settings = self.clone();
+        self.modal.render(&mut [
+            settings.render_header(f),
+            settings.render_main(),
+            html!("div", {
+                .child_signal(settings.loader.is_loading().map(|is_loading| is_loading.then(|| Spinner::render_spinner(true))))
+            }),
+        ])
+    }
+}
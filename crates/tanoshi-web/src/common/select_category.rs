@@ -32,13 +32,14 @@ impl SelectCategoryModal {
         self.loader.load(clone!(select => async move {
             match query::fetch_categories().await {
                 Ok(res) => {
-                    if res.len() == 1 {
+                    // smart categories pick their manga by rule
+                    if res.iter().all(|c| c.id.is_none() || c.rule.is_some()) {
                         f(vec![]);
                         return;
                     }
                     
                     select.modal.show();
-                    select.categories.lock_mut().replace_cloned(res.into_iter().filter_map(|c| (c.id.is_some()).then(|| Category{
+                    select.categories.lock_mut().replace_cloned(res.into_iter().filter_map(|c| (c.id.is_some() && c.rule.is_none()).then(|| Category{
                         id: c.id,
                         name: c.name.clone(),
                         selected: Mutable::new(false),
@@ -10,6 +10,7 @@ mod library;
 mod library_list;
 mod login;
 mod manga;
+mod oidc_redirect;
 #[allow(dead_code)]
 mod query;
 mod reader;
@@ -18,6 +19,7 @@ mod settings_categories;
 mod settings_download_queue;
 mod settings_manage_downloads;
 mod settings_source;
+mod settings_statistics;
 mod tracker_login;
 mod tracker_redirect;
 mod updates;
@@ -9,10 +9,7 @@ use futures_signals::{
 use web_sys::HtmlInputElement;
 
 use crate::{
-    common::{
-        events, snackbar, Cover, LibraryFilter, LibraryOrder, LibrarySettings, LibrarySort,
-        LibrarySortBy, Route, Spinner,
-    },
+    common::{events, snackbar, Cover, LibrarySettings, Route, Spinner},
     query,
     utils::{is_tauri_signal, AsyncLoader},
 };
@@ -25,6 +22,7 @@ pub struct Library {
     loader: AsyncLoader,
     spinner: Rc<Spinner>,
     cover_list: MutableVec<Cover>,
+    next_cursor: Mutable<Option<String>>,
     categories_available: Mutable<bool>,
     library_settings: Rc<LibrarySettings>,
 }
@@ -39,6 +37,7 @@ impl Library {
             loader: AsyncLoader::new(),
             spinner: Spinner::new_with_fullscreen(true),
             cover_list: MutableVec::new(),
+            next_cursor: Mutable::new(None),
             categories_available: Mutable::new(category_id.is_some()),
             library_settings: LibrarySettings::new(false, true),
         })
@@ -51,7 +50,6 @@ impl Library {
                 match query::fetch_category_detail(category_id).await {
                     Ok(res) => {
                         library.category_name.set(res.name);
-                        Self::fetch_libraries(library.clone());
                     }
                     Err(e) => {
                         snackbar::show(format!("failed to fetch library {}", e));
@@ -64,7 +62,6 @@ impl Library {
                 match query::fetch_categories().await {
                     Ok(res) => {
                         library.categories_available.set(res.len() > 1);
-                        Self::fetch_libraries(library.clone());
                     }
                     Err(e) => {
                         snackbar::show(format!("failed to fetch categories {}", e));
@@ -75,29 +72,38 @@ impl Library {
         }
     }
 
-    pub fn fetch_libraries(library: Rc<Self>) {
+    /// Fetch first page, or next page if `more` is true, sorted and filtered by server
+    pub fn fetch_libraries(library: Rc<Self>, more: bool) {
         let category_id = library.category_id;
+        let keyword = library.keyword.get_cloned();
+        let after = if more {
+            library.next_cursor.get_cloned()
+        } else {
+            None
+        };
         library.spinner.set_active(true);
         library.loader.load(clone!(library => async move {
-            match query::fetch_manga_from_favorite(category_id).await {
-                Ok(mut covers) => {
-                    let sort = library.library_settings.sort.get();
-                    covers.sort_by(|a, b| match sort {
-                        LibrarySort { by: LibrarySortBy::Alphabetical, order: LibraryOrder::Asc } => a.title.partial_cmp(&b.title).unwrap_or(std::cmp::Ordering::Equal),
-                        LibrarySort { by: LibrarySortBy::Alphabetical, order: LibraryOrder::Desc } => b.title.partial_cmp(&a.title).unwrap_or(std::cmp::Ordering::Equal),
-                        LibrarySort { by: LibrarySortBy::RecentlyRead, order: LibraryOrder::Asc} => {
-                            let a = a.last_read_at.unwrap_or_default();
-                            let b = b.last_read_at.unwrap_or_default();
-                            a.cmp(&b)
-                        },
-                        LibrarySort { by: LibrarySortBy::RecentlyRead, order: LibraryOrder::Desc} => {
-                            let a = a.last_read_at.unwrap_or_default();
-                            let b = b.last_read_at.unwrap_or_default();
-                            b.cmp(&a)
-                        },
-                    });
+            // `#tag` lists manga with the tag instead of searching
+            let tag = keyword.trim().strip_prefix('#').map(|tag| tag.trim().to_string()).filter(|tag| !tag.is_empty());
+            let res = if keyword.trim().is_empty() || tag.is_some() {
+                let sort = library.library_settings.sort.get();
+                let filter = library.library_settings.filter.get();
+                query::fetch_library(category_id, sort, filter, tag, after).await
+            } else {
+                query::search_library(keyword, category_id).await.map(|covers| (covers, None))
+            };
 
-                    library.cover_list.lock_mut().replace_cloned(covers);
+            match res {
+                Ok((covers, next_cursor)) => {
+                    if more {
+                        let mut cover_list = library.cover_list.lock_mut();
+                        for cover in covers {
+                            cover_list.push_cloned(cover);
+                        }
+                    } else {
+                        library.cover_list.lock_mut().replace_cloned(covers);
+                    }
+                    library.next_cursor.set(next_cursor);
                 }
                 Err(e) => {
                     snackbar::show(format!("failed to fetch library {}", e));
@@ -266,24 +272,25 @@ impl Library {
         })
     }
 
-    pub fn render_main(library: Rc<Self>, keyword: String) -> Dom {
+    pub fn render_main(library: Rc<Self>) -> Dom {
         html!("div", {
             .class("main")
             .style("padding", "0.5rem")
-            .child_signal(library.library_settings.filter.signal_cloned().map(clone!(library => move |filter| Some(html!("div", {
-                .class("manga-grid")
-                .children_signal_vec(library.cover_list.signal_vec_cloned().filter_map(clone!(filter, keyword => move |cover| {
-                    if keyword.is_empty() || cover.title.to_ascii_lowercase().contains(&keyword.to_ascii_lowercase()) {
-                        match filter {
-                            LibraryFilter::None => Some(cover.render()),
-                            LibraryFilter::Read => (cover.unread_chapter_count == 0).then(|| cover.render()),
-                            LibraryFilter::Unread => (cover.unread_chapter_count > 0).then(|| cover.render())
-                        }
-                    } else {
-                        None
-                    }
-                })))
-            })))))
+            .children(&mut [
+                html!("div", {
+                    .class("manga-grid")
+                    .children_signal_vec(library.cover_list.signal_vec_cloned().map(|cover| cover.render()))
+                }),
+                html!("div", {
+                    .class("load-more-btn")
+                    .child_signal(library.next_cursor.signal_cloned().map(clone!(library => move |next_cursor| next_cursor.map(|_| html!("button", {
+                        .text("Load More")
+                        .event(clone!(library => move |_: events::Click| {
+                            Self::fetch_libraries(library.clone(), true);
+                        }))
+                    })))))
+                })
+            ])
         })
     }
 
@@ -291,23 +298,12 @@ impl Library {
         Self::fetch_category_detail(library.clone());
 
         html!("div", {
-            .future(library.library_settings.sort.signal_cloned().for_each(clone!(library => move |sort| {
-                let mut covers = library.cover_list.lock_ref().to_vec();
-                covers.sort_by(|a, b| match sort {
-                    LibrarySort { by: LibrarySortBy::Alphabetical, order: LibraryOrder::Asc } => a.title.partial_cmp(&b.title).unwrap_or(std::cmp::Ordering::Equal),
-                    LibrarySort { by: LibrarySortBy::Alphabetical, order: LibraryOrder::Desc } => b.title.partial_cmp(&a.title).unwrap_or(std::cmp::Ordering::Equal),
-                    LibrarySort { by: LibrarySortBy::RecentlyRead, order: LibraryOrder::Asc} => {
-                        let a = a.last_read_at.unwrap_or_default();
-                        let b = b.last_read_at.unwrap_or_default();
-                        a.cmp(&b)
-                    },
-                    LibrarySort { by: LibrarySortBy::RecentlyRead, order: LibraryOrder::Desc} => {
-                        let a = a.last_read_at.unwrap_or_default();
-                        let b = b.last_read_at.unwrap_or_default();
-                        b.cmp(&a)
-                    },
-                });
-                library.cover_list.lock_mut().replace_cloned(covers);
+            .future(map_ref! {
+                let _sort = library.library_settings.sort.signal(),
+                let _filter = library.library_settings.filter.signal(),
+                let _keyword = library.keyword.signal_cloned() => ()
+            }.for_each(clone!(library => move |_| {
+                Self::fetch_libraries(library.clone(), false);
 
                 async {}
             })))
@@ -319,11 +315,7 @@ impl Library {
                 Spinner::render(library.spinner.clone()),
                 LibrarySettings::render(library.library_settings.clone()),
             ])
-            .child_signal(
-                library.keyword.signal_cloned().map(
-                    clone!(library => move |keyword| Some(Self::render_main(library.clone(), keyword)))
-                )
-            )
+            .child(Self::render_main(library.clone()))
         })
     }
 }
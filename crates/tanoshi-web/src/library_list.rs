@@ -38,6 +38,7 @@ impl LibraryList {
                     library.categories.lock_mut().replace_cloned(res.into_iter().map(|c| Category{
                         id: c.id,
                         name: c.name.clone(),
+                        rule: c.rule.clone(),
                         count: c.count,
                     }).collect());
                 }
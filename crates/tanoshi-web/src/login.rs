@@ -4,18 +4,23 @@ use dominator::{clone, html, Dom, EventOptions};
 use dominator::{routing, with_node};
 use futures_signals::signal::Mutable;
 use futures_signals::signal::SignalExt;
-use wasm_bindgen::UnwrapThrowExt;
 use web_sys::HtmlInputElement;
 
 use crate::app::App;
 use crate::common::{events, snackbar, Route};
+use crate::oidc_redirect::OidcRedirect;
 use crate::query;
-use crate::utils::local_storage;
+use crate::utils::set_auth_token;
 use crate::utils::AsyncLoader;
 
 pub struct Login {
     username: Mutable<String>,
     password: Mutable<String>,
+    totp_code: Mutable<String>,
+    totp_required: Mutable<bool>,
+    /// enrollment token, secret and uri when admin has to enable two-factor authentication
+    totp_enrollment: Mutable<Option<(String, String, String)>>,
+    recovery_codes: Mutable<Vec<String>>,
     loader: AsyncLoader,
 }
 
@@ -24,6 +29,10 @@ impl Login {
         Rc::new(Self {
             username: Mutable::new("".to_string()),
             password: Mutable::new("".to_string()),
+            totp_code: Mutable::new("".to_string()),
+            totp_required: Mutable::new(false),
+            totp_enrollment: Mutable::new(None),
+            recovery_codes: Mutable::new(vec![]),
             loader: AsyncLoader::new(),
         })
     }
@@ -31,18 +40,53 @@ impl Login {
     pub fn login(login: Rc<Self>, app: Rc<App>) {
         let username = login.username.get_cloned();
         let password = login.password.get_cloned();
-        login.loader.load(async move {
-            match query::user_login(username, password).await {
+        let totp_code = login
+            .totp_required
+            .get()
+            .then(|| login.totp_code.get_cloned());
+        login.loader.load(clone!(login => async move {
+            match query::user_login(username, password, totp_code).await {
+                Ok(token) if token.totp_enrollment => {
+                    match query::enroll_totp_with_token(&token.access_token).await {
+                        Ok(enrollment) => login.totp_enrollment.set(Some((token.access_token, enrollment.secret, enrollment.uri))),
+                        Err(e) => snackbar::show(format!("Login failed: {}", e)),
+                    }
+                }
                 Ok(token) => {
-                    local_storage().set("token", &token).unwrap_throw();
+                    set_auth_token(&token.access_token, &token.refresh_token, token.expires_at);
                     routing::go_to_url(&Route::Root.url());
                     App::fetch_server_status(app);
                 }
+                Err(e) if e.to_string().contains(query::TOTP_REQUIRED) => {
+                    login.totp_required.set(true);
+                }
                 Err(e) => {
+                    login.totp_code.set("".to_string());
                     snackbar::show(format!("Login failed: {}", e));
                 }
             }
-        });
+        }));
+    }
+
+    /// Enable two-factor authentication with the enrollment token, then login again with a code
+    pub fn confirm_totp(login: Rc<Self>) {
+        let token = match login.totp_enrollment.get_cloned() {
+            Some((token, _, _)) => token,
+            None => return,
+        };
+        let code = login.totp_code.replace("".to_string());
+        login.loader.load(clone!(login => async move {
+            match query::confirm_totp_with_token(&token, code).await {
+                Ok(codes) => {
+                    login.totp_enrollment.set(None);
+                    login.recovery_codes.set(codes);
+                    login.totp_required.set(true);
+                }
+                Err(e) => {
+                    snackbar::show(format!("{}", e));
+                }
+            }
+        }));
     }
 
     pub fn register(login: Rc<Self>, app: Rc<App>) {
@@ -107,6 +151,9 @@ impl Login {
                 html!("form", {
                     .style("display", "flex")
                     .style("flex-direction", "column")
+                    .visible_signal(app.server_status.signal_cloned().map(|status| {
+                        status.map(|status| status.password_login_enabled || !status.activated).unwrap_or(true)
+                    }))
                     .event_with_options(&EventOptions::preventable(), |e: events::KeyDown| {
                         if e.key() == "enter" {
                             e.prevent_default();
@@ -133,6 +180,19 @@ impl Login {
                                 }))
                             })
                         }),
+                        html!("input" => HtmlInputElement, {
+                            .visible_signal(login.totp_required.signal())
+                            .attr("type", "text")
+                            .attr("inputmode", "numeric")
+                            .attr("autocomplete", "one-time-code")
+                            .attr("placeholder", "Two-factor code or recovery code")
+                            .prop_signal("value", login.totp_code.signal_cloned())
+                            .with_node!(input => {
+                                .event(clone!(login => move |_: events::Input| {
+                                    login.totp_code.set(input.value());
+                                }))
+                            })
+                        }),
                         html!("div", {
                             .style("display", "flex")
                             .style("justify-content", "flex-end")
@@ -163,6 +223,78 @@ impl Login {
                     ])
                 })
             ])
+            .child_signal(login.recovery_codes.signal_cloned().map(|codes| (!codes.is_empty()).then(|| html!("div", {
+                .style("display", "flex")
+                .style("flex-direction", "column")
+                .style("margin", "0.5rem")
+                .children(&mut [
+                    html!("span", {
+                        .style("margin-bottom", "0.5rem")
+                        .text("Two-factor authentication enabled, save these recovery codes then login with a new code. Each can be used once to login and they will not be shown again")
+                    }),
+                ])
+                .children(codes.into_iter().map(|code| html!("code", {
+                    .text(&code)
+                })))
+            }))))
+            .child_signal(login.totp_enrollment.signal_cloned().map(clone!(login => move |enrollment| enrollment.map(|(_, secret, uri)| html!("form", {
+                .style("display", "flex")
+                .style("flex-direction", "column")
+                .children(&mut [
+                    html!("span", {
+                        .style("margin", "0.5rem")
+                        .text("Admins have to enable two-factor authentication. Add this secret to an authenticator app, then enter the code it shows")
+                    }),
+                    html!("a", {
+                        .style("margin", "0.5rem")
+                        .style("word-break", "break-all")
+                        .attr("href", &uri)
+                        .children(&mut [
+                            html!("code", {
+                                .text(&secret)
+                            })
+                        ])
+                    }),
+                    html!("input" => HtmlInputElement, {
+                        .attr("type", "text")
+                        .attr("inputmode", "numeric")
+                        .attr("autocomplete", "one-time-code")
+                        .attr("placeholder", "Two-factor code")
+                        .prop_signal("value", login.totp_code.signal_cloned())
+                        .with_node!(input => {
+                            .event(clone!(login => move |_: events::Input| {
+                                login.totp_code.set(input.value());
+                            }))
+                        })
+                    }),
+                    html!("div", {
+                        .style("display", "flex")
+                        .style("justify-content", "flex-end")
+                        .children(&mut [
+                            html!("button", {
+                                .text("Confirm")
+                                .event_with_options(&EventOptions::preventable(), clone!(login => move |e: events::Click| {
+                                    e.prevent_default();
+                                    Self::confirm_totp(login.clone());
+                                }))
+                            }),
+                        ])
+                    }),
+                ])
+            })))))
+            .child_signal(app.server_status.signal_cloned().map(clone!(login => move |status| {
+                match status {
+                    Some(status) if status.activated && status.oidc_enabled => Some(html!("button", {
+                        .style("margin", "0.5rem")
+                        .text("Login with Single Sign-On")
+                        .event_with_options(&EventOptions::preventable(), clone!(login => move |e: events::Click| {
+                            e.prevent_default();
+                            login.loader.load(OidcRedirect::start(false));
+                        }))
+                    })),
+                    _ => None,
+                }
+            })))
         })
     }
 
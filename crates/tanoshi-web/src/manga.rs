@@ -1,6 +1,6 @@
 use crate::{
     common::{
-        ChapterSettings, ChapterSort, Filter,  Order, Route, Sort, Spinner, snackbar, SelectCategoryModal, SelectTrackMangaModal, TrackerStatus, icons
+        ChapterSettings, ChapterSort, EditMetadataModal, Filter, MangaNotesModal, MetadataOverride, ScanlatorSetting, ScanlatorSettingsModal, Order, Route, Sort, Spinner, snackbar, SelectCategoryModal, SelectTrackMangaModal, TrackerStatus, icons, preference_sync
     }, 
     query, 
     utils::{AsyncLoader, proxied_image_url, window}
@@ -37,7 +37,10 @@ struct Chapter {
 enum SelectState {
     None,
     Category,
-    Tracker
+    Tracker,
+    Metadata,
+    Notes,
+    Scanlator
 }
 
 pub struct Manga {
@@ -60,6 +63,11 @@ pub struct Manga {
     is_tracker_available: Mutable<bool>,
     num_tracked: Mutable<i64>,
     trackers: MutableVec<TrackerStatus>,
+    metadata_override: Mutable<MetadataOverride>,
+    tags: MutableVec<String>,
+    rating: Mutable<Option<i64>>,
+    note: Mutable<Option<String>>,
+    scanlator_setting: Mutable<ScanlatorSetting>,
     chapter_settings: Rc<ChapterSettings>,
     select_state: Mutable<SelectState>,
     loader: Rc<AsyncLoader>,
@@ -94,6 +102,11 @@ impl Manga {
             is_tracker_available: Mutable::new(false),
             num_tracked: Mutable::new(0),
             trackers: MutableVec::new(),
+            metadata_override: Mutable::new(MetadataOverride::default()),
+            tags: MutableVec::new(),
+            rating: Mutable::new(None),
+            note: Mutable::new(None),
+            scanlator_setting: Mutable::new(ScanlatorSetting::default()),
             chapter_settings: ChapterSettings::new(false, true),
             select_state: Mutable::new(SelectState::None),
             loader,
@@ -137,6 +150,23 @@ impl Manga {
                         tracker_manga_id: Mutable::new(t.tracker_manga_id.clone()),
                         ..Default::default()
                     }).collect());
+                    manga.metadata_override.set(result.metadata_override.map(|metadata_override| MetadataOverride {
+                        title: metadata_override.title,
+                        author: metadata_override.author,
+                        genre: metadata_override.genre,
+                        status: metadata_override.status,
+                        description: metadata_override.description,
+                        custom_cover: metadata_override.custom_cover,
+                    }).unwrap_or_default());
+                    manga.tags.lock_mut().replace_cloned(result.tags);
+                    manga.rating.set_neq(result.rating);
+                    manga.note.set_neq(result.note);
+                    manga.scanlator_setting.set(ScanlatorSetting {
+                        dedup: result.scanlator_setting.dedup,
+                        priority: result.scanlator_setting.priority,
+                        blocklist: result.scanlator_setting.blocklist,
+                        scanlators: result.scanlator_setting.scanlators,
+                    });
                     manga.chapters.lock_mut().replace_cloned(result.chapters.iter().map(|chapter| Rc::new(Chapter{
                         id: chapter.id,
                         title: chapter.title.clone(),
@@ -152,6 +182,7 @@ impl Manga {
                         download_status: chapter.download_status.as_ref().map(|queue| (queue.downloaded, queue.total))
                     })).collect());
 
+                    preference_sync::pull(Some(manga.id.get())).await;
                     manga.chapter_settings.load_by_manga_id(manga.id.get());                    
                 },
                 Err(err) => {
@@ -206,6 +237,7 @@ impl Manga {
                         download_status: chapter.download_status.as_ref().map(|queue| (queue.downloaded, queue.total))
                     })).collect());
 
+                    preference_sync::pull(Some(manga.id.get())).await;
                     manga.chapter_settings.load_by_manga_id(manga.id.get());
                 },
                 Err(err) => {
@@ -656,6 +688,73 @@ impl Manga {
                     }))
                 })
             ))))
+            .child_signal(manga.id.signal().map(clone!(manga => move |id| (id != 0).then(|| 
+                html!("button", {
+                    .class("action-button")
+                    .style("display", "flex")
+                    .style("padding", "0.5rem")
+                    .style("margin-left", "0.5rem")
+                    .style("margin-top", "0.5rem")
+                    .style("margin-bottom", "0.5rem")
+                    .style("align-items", "center")
+                    .children(&mut [
+                        svg!("svg", {
+                            .attr("xmlns", "http://www.w3.org/2000/svg")
+                            .attr("fill", "currentColor")
+                            .attr("viewBox", "0 0 20 20")
+                            .class("icon-sm")
+                            .children(&mut [
+                                svg!("path", {
+                                    .attr("d", "M13.586 3.586a2 2 0 112.828 2.828l-.793.793-2.828-2.828.793-.793zM11.379 5.793L3 14.172V17h2.828l8.38-8.379-2.83-2.828z")
+                                })
+                            ])
+                        }),
+                        html!("span", {
+                            .style("margin-left", "0.5rem")
+                            .text("Edit")
+                        })
+                    ])
+                    .event(clone!(manga => move |_: events::Click| {
+                        manga.select_state.set(SelectState::Metadata);
+                    }))
+                })
+            ))))
+            .child_signal(manga.id.signal().map(clone!(manga => move |id| (id != 0).then(|| 
+                html!("button", {
+                    .class("action-button")
+                    .style("display", "flex")
+                    .style("padding", "0.5rem")
+                    .style("margin-left", "0.5rem")
+                    .style("margin-top", "0.5rem")
+                    .style("margin-bottom", "0.5rem")
+                    .style("align-items", "center")
+                    .children(&mut [
+                        svg!("svg", {
+                            .attr("xmlns", "http://www.w3.org/2000/svg")
+                            .attr("fill", "currentColor")
+                            .attr("viewBox", "0 0 20 20")
+                            .class("icon-sm")
+                            .children(&mut [
+                                svg!("path", {
+                                    .attr("fill-rule", "evenodd")
+                                    .attr("d", "M17.707 9.293a1 1 0 010 1.414l-7 7a1 1 0 01-1.414 0l-7-7A.997.997 0 012 10V5a3 3 0 013-3h5c.256 0 .512.098.707.293l7 7zM5 6a1 1 0 100-2 1 1 0 000 2z")
+                                    .attr("clip-rule", "evenodd")
+                                })
+                            ])
+                        }),
+                        html!("span", {
+                            .style("margin-left", "0.5rem")
+                            .text_signal(manga.rating.signal().map(|rating| match rating {
+                                Some(rating) => format!("{rating}/10"),
+                                None => "Notes".to_string(),
+                            }))
+                        })
+                    ])
+                    .event(clone!(manga => move |_: events::Click| {
+                        manga.select_state.set(SelectState::Notes);
+                    }))
+                })
+            ))))
         })
     }
 
@@ -704,8 +803,23 @@ impl Manga {
                             .text(&x)
                         })
                     }))
+                }),
+                html!("div", {
+                    .style("display", "flex")
+                    .style("flex-wrap", "wrap")
+                    .children_signal_vec(manga.tags.signal_vec_cloned().map(|x| {
+                        html!("span", {
+                            .class("chip")
+                            .text(&format!("#{x}"))
+                        })
+                    }))
                 })
             ])
+            .child_signal(manga.note.signal_cloned().map(|note| note.map(|note| html!("p", {
+                .style("white-space", "pre-wrap")
+                .style("font-style", "italic")
+                .text(&note)
+            }))))
         })
     }
 
@@ -728,6 +842,30 @@ impl Manga {
                         }),
                         html!("div", {
                             .children(&mut [
+                                html!("button", {
+                                    .style("margin", "0.25rem")
+                                    .visible_signal(manga.id.signal().map(|id| id != 0))
+                                    .event(clone!(manga => move |_: events::Click| {
+                                        manga.select_state.set(SelectState::Scanlator);
+                                    }))
+                                    .children(&mut [
+                                        svg!("svg", {
+                                            .attr("xmlns", "http://www.w3.org/2000/svg")
+                                            .attr("fill", "none")
+                                            .attr("viewBox", "0 0 24 24")
+                                            .attr("stroke", "currentColor")
+                                            .class("icon")
+                                            .children(&mut [
+                                                svg!("path", {
+                                                    .attr("stroke-linecap", "round")
+                                                    .attr("stroke-linejoin", "round")
+                                                    .attr("stroke-width", "2")
+                                                    .attr("d", "M17 20h5v-2a3 3 0 00-5.356-1.857M17 20H7m10 0v-2c0-.656-.126-1.283-.356-1.857M7 20H2v-2a3 3 0 015.356-1.857M7 20v-2c0-.656.126-1.283.356-1.857m0 0a5.002 5.002 0 019.288 0M15 7a3 3 0 11-6 0 3 3 0 016 0zm6 3a2 2 0 11-4 0 2 2 0 014 0zM7 10a2 2 0 11-4 0 2 2 0 014 0z")
+                                                })
+                                            ])
+                                        }),
+                                    ])
+                                }),
                                 html!("button", {
                                     .style("margin", "0.25rem")
                                     .event(clone!(manga => move |_: events::Click| {
@@ -917,6 +1055,23 @@ impl Manga {
                             manga_page.select_state.set(SelectState::None);    
                         })))
                     }
+                    SelectState::Metadata => {
+                        Some(EditMetadataModal::new(manga_page.id.get(), manga_page.metadata_override.get_cloned()).render(clone!(manga_page => move || {
+                            Self::fetch_detail(manga_page.clone(), false);
+                        })))
+                    }
+                    SelectState::Scanlator => {
+                        Some(ScanlatorSettingsModal::new(manga_page.id.get(), manga_page.scanlator_setting.get_cloned()).render(clone!(manga_page => move || {
+                            Self::fetch_detail(manga_page.clone(), false);
+                            manga_page.select_state.set(SelectState::None);
+                        })))
+                    }
+                    SelectState::Notes => {
+                        Some(MangaNotesModal::new(manga_page.id.get(), manga_page.tags.lock_ref().to_vec(), manga_page.rating.get(), manga_page.note.get_cloned()).render(clone!(manga_page => move || {
+                            Self::fetch_detail(manga_page.clone(), false);
+                            manga_page.select_state.set(SelectState::None);
+                        })))
+                    }
                     _ => None
                 }
             })))
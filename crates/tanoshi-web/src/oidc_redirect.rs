@@ -0,0 +1,140 @@
+use std::rc::Rc;
+
+use dominator::{html, routing, Dom};
+use futures_signals::signal::{Mutable, SignalExt};
+use wasm_bindgen::UnwrapThrowExt;
+
+use crate::{
+    app::App,
+    common::{snackbar, Route, SettingCategory},
+    query,
+    utils::{session_storage, set_auth_token, window, AsyncLoader},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum AuthorizationState {
+    Authorizing,
+    Failed(String),
+}
+
+pub struct OidcRedirect {
+    code: String,
+    state: String,
+    authorization_state: Mutable<AuthorizationState>,
+    loader: AsyncLoader,
+}
+
+impl OidcRedirect {
+    pub fn new(code: String, state: String) -> Rc<Self> {
+        Rc::new(Self {
+            code,
+            state,
+            authorization_state: Mutable::new(AuthorizationState::Authorizing),
+            loader: AsyncLoader::new(),
+        })
+    }
+
+    /// Redirect to identity provider, if `link` is true the account is linked to current user instead of login
+    pub async fn start(link: bool) {
+        match query::oidc_login_start().await {
+            Ok(session) => {
+                let session_storage = session_storage();
+                session_storage
+                    .set("oidc-csrf-state", &session.csrf_state)
+                    .unwrap_throw();
+                if link {
+                    session_storage.set("oidc-link", "true").unwrap_throw();
+                } else {
+                    session_storage.delete("oidc-link").unwrap_throw();
+                }
+                window()
+                    .location()
+                    .replace(&session.authorize_url)
+                    .unwrap_throw();
+            }
+            Err(e) => {
+                snackbar::show(format!("error redirecting: {e}"));
+            }
+        }
+    }
+
+    fn fetch_oidc_login_end(self: Rc<Self>, app: Rc<App>) {
+        let code = self.code.clone();
+        let state = self.state.clone();
+        let oidc_redirect = self.clone();
+        self.loader.load(async move {
+            let session_storage = session_storage();
+            // provider redirected back to the browser that started the login
+            if session_storage.get("oidc-csrf-state").unwrap_throw() != Some(state.clone()) {
+                oidc_redirect
+                    .authorization_state
+                    .set_neq(AuthorizationState::Failed(
+                        "no login in progress".to_string(),
+                    ));
+                return;
+            }
+            let link = session_storage.get("oidc-link").unwrap_throw().is_some();
+            session_storage.delete("oidc-csrf-state").unwrap_throw();
+            session_storage.delete("oidc-link").unwrap_throw();
+
+            if link {
+                match query::link_oidc_account(code, state).await {
+                    Ok(()) => {
+                        routing::go_to_url(&Route::Settings(SettingCategory::User).url());
+                    }
+                    Err(e) => oidc_redirect
+                        .authorization_state
+                        .set_neq(AuthorizationState::Failed(format!("{e}"))),
+                }
+            } else {
+                match query::oidc_login_end(code, state).await {
+                    Ok(token) => {
+                        set_auth_token(&token.access_token, &token.refresh_token, token.expires_at);
+                        routing::go_to_url(&Route::Root.url());
+                        App::fetch_server_status(app);
+                    }
+                    Err(e) => oidc_redirect
+                        .authorization_state
+                        .set_neq(AuthorizationState::Failed(format!("{e}"))),
+                }
+            }
+        });
+    }
+
+    pub fn render(self: Rc<Self>, app: Rc<App>) -> Dom {
+        self.clone().fetch_oidc_login_end(app);
+
+        html!("div", {
+            .class("content")
+            .style("display", "flex")
+            .style("flex-direction", "column")
+            .style("max-width", "1024px")
+            .style("margin", "auto")
+            .style("padding", "0.5rem")
+            .children(&mut [
+                html!("img", {
+                    .style("width", "8rem")
+                    .style("height", "8rem")
+                    .style("border-radius", "0.5rem")
+                    .style("margin", "auto")
+                    .attr("src", "/icons/512.png")
+                }),
+                html!("div", {
+                    .style("padding", "0.5rem")
+                    .style("margin", "0.5rem")
+                    .style("text-align", "center")
+                    .text_signal(self.authorization_state.signal_cloned().map(|state| match state {
+                        AuthorizationState::Authorizing => "Logging in...".to_string(),
+                        AuthorizationState::Failed(e) => format!("Login failed: {e}"),
+                    }))
+                }),
+                html!("a", {
+                    .class("button")
+                    .style("margin", "auto")
+                    .attr("href", &Route::Login.url())
+                    .text("Back to login")
+                })
+            ])
+        })
+    }
+}
@@ -7,22 +7,75 @@ use wasm_bindgen::prelude::*;
 type NaiveDateTime = String;
 
 use crate::{
-    common::{Cover, Input},
-    utils::{graphql_host, graphql_ws_host, local_storage},
+    common::{Cover, Input, LibraryFilter, LibraryOrder, LibrarySort},
+    utils::{clear_auth_token, graphql_host, graphql_ws_host, local_storage, set_auth_token},
 };
 
 use tanoshi_schema::*;
 
+thread_local! {
+    // only one refresh at a time, refresh token can only be used once
+    static REFRESH_LOCK: std::rc::Rc<futures::lock::Mutex<()>> = Default::default();
+}
+
+fn token_expired() -> bool {
+    local_storage()
+        .get("token_expires_at")
+        .unwrap_throw()
+        .and_then(|expires_at| expires_at.parse::<i64>().ok())
+        // refresh a minute early
+        .map(|expires_at| expires_at - 60 <= (js_sys::Date::now() / 1000.0) as i64)
+        .unwrap_or(false)
+}
+
+/// Returns access token, refreshing it first if it is about to expire
+async fn access_token() -> String {
+    if token_expired() {
+        let lock = REFRESH_LOCK.with(|lock| lock.clone());
+        let _guard = lock.lock().await;
+
+        // another request may have refreshed while waiting
+        if let (true, Some(refresh_token)) = (
+            token_expired(),
+            local_storage().get("refresh_token").unwrap_throw(),
+        ) {
+            let var = refresh_token::Variables { refresh_token };
+            match send_graphql::<RefreshToken>(var, "").await {
+                Ok(data) => {
+                    let token = data.refresh_token;
+                    set_auth_token(&token.access_token, &token.refresh_token, token.expires_at);
+                }
+                Err(e) => {
+                    error!("failed to refresh token: {}", e);
+                    clear_auth_token();
+                }
+            }
+        }
+    }
+
+    local_storage()
+        .get("token")
+        .unwrap_throw()
+        .unwrap_or_else(|| "".to_string())
+}
+
 async fn post_graphql<Q>(var: Q::Variables) -> Result<Q::ResponseData, Box<dyn std::error::Error>>
+where
+    Q: GraphQLQuery,
+{
+    let token = access_token().await;
+    send_graphql::<Q>(var, &token).await
+}
+
+async fn send_graphql<Q>(
+    var: Q::Variables,
+    token: &str,
+) -> Result<Q::ResponseData, Box<dyn std::error::Error>>
 where
     Q: GraphQLQuery,
 {
     let url = graphql_host();
 
-    let token = local_storage()
-        .get("token")
-        .unwrap_throw()
-        .unwrap_or_else(|| "".to_string());
     let request_body = Q::build_query(var);
 
     let client = reqwest::Client::new();
@@ -92,14 +145,81 @@ pub async fn fetch_source_filters(
     Ok(data)
 }
 
-pub async fn fetch_manga_from_favorite(
+/// Returns a page of library and the cursor of next page if there is more
+pub async fn fetch_library(
+    category_id: Option<i64>,
+    sort: LibrarySort,
+    filter: LibraryFilter,
+    tag: Option<String>,
+    after: Option<String>,
+) -> Result<(Vec<Cover>, Option<String>), Box<dyn Error>> {
+    let mut input = fetch_library::LibraryFilterInput {
+        unread: None,
+        downloaded: None,
+        tracked: None,
+        status: None,
+        source_id: None,
+        genre: None,
+        tag,
+        min_rating: None,
+    };
+    match filter {
+        LibraryFilter::None => {}
+        LibraryFilter::Read => input.unread = Some(false),
+        LibraryFilter::Unread => input.unread = Some(true),
+        LibraryFilter::Downloaded => input.downloaded = Some(true),
+        LibraryFilter::Tracked => input.tracked = Some(true),
+    }
+
+    let var = fetch_library::Variables {
+        category_id,
+        sort_by: Some(sort.by.as_str().to_string()),
+        descending: matches!(sort.order, LibraryOrder::Desc),
+        filter: Some(input),
+        after,
+        first: Some(60),
+    };
+    let data = post_graphql::<FetchLibrary>(var).await?;
+
+    let connection = data.library_connection;
+    let next_cursor = if connection.page_info.has_next_page {
+        connection.edges.last().map(|edge| edge.cursor.clone())
+    } else {
+        None
+    };
+
+    let covers = connection
+        .edges
+        .iter()
+        .map(|edge| {
+            let item = &edge.node;
+            Cover::new(
+                item.id,
+                0,
+                item.path.clone(),
+                item.title.clone(),
+                item.cover_url.clone(),
+                false,
+                item.last_read_at.as_ref().and_then(|read_at| {
+                    chrono::NaiveDateTime::parse_from_str(read_at, "%Y-%m-%dT%H:%M:%S%.f").ok()
+                }),
+                item.unread_chapter_count,
+            )
+        })
+        .collect();
+
+    Ok((covers, next_cursor))
+}
+
+pub async fn search_library(
+    query: String,
     category_id: Option<i64>,
 ) -> Result<Vec<Cover>, Box<dyn Error>> {
-    let var = browse_favorites::Variables { category_id };
-    let data = post_graphql::<BrowseFavorites>(var).await?;
+    let var = search_library::Variables { query, category_id };
+    let data = post_graphql::<SearchLibrary>(var).await?;
 
     Ok(data
-        .library
+        .search_library
         .iter()
         .map(|item| {
             Cover::new(
@@ -118,6 +238,22 @@ pub async fn fetch_manga_from_favorite(
         .collect())
 }
 
+pub async fn fetch_reading_statistics(
+    from: Option<String>,
+    to: Option<String>,
+    utc_offset_minutes: i64,
+    period: Option<String>,
+) -> Result<fetch_reading_statistics::FetchReadingStatisticsReadingStatistics, Box<dyn Error>> {
+    let var = fetch_reading_statistics::Variables {
+        from,
+        to,
+        utc_offset_minutes,
+        period,
+    };
+    let data = post_graphql::<FetchReadingStatistics>(var).await?;
+    Ok(data.reading_statistics)
+}
+
 pub async fn fetch_manga_by_source_path(
     source_id: i64,
     path: String,
@@ -131,6 +267,13 @@ pub async fn fetch_manga_by_source_path(
     Ok(data.manga_by_source_path)
 }
 
+pub async fn resolve_url(url: String) -> Result<resolve_url::ResolveUrlResolveUrl, Box<dyn Error>> {
+    let var = resolve_url::Variables { url: Some(url) };
+    let data = post_graphql::<ResolveUrl>(var).await?;
+
+    Ok(data.resolve_url)
+}
+
 pub async fn fetch_manga_detail(
     id: i64,
     refresh: bool,
@@ -144,6 +287,112 @@ pub async fn fetch_manga_detail(
     Ok(data.manga)
 }
 
+pub async fn set_manga_metadata_override(
+    manga_id: i64,
+    title: Option<String>,
+    author: Option<Vec<String>>,
+    genre: Option<Vec<String>>,
+    status: Option<String>,
+    description: Option<String>,
+) -> Result<(), Box<dyn Error>> {
+    let var = set_manga_metadata_override::Variables {
+        manga_id: Some(manga_id),
+        title,
+        author,
+        genre,
+        status,
+        description,
+    };
+    let _ = post_graphql::<SetMangaMetadataOverride>(var).await?;
+
+    Ok(())
+}
+
+pub async fn upload_manga_cover(manga_id: i64, image: String) -> Result<(), Box<dyn Error>> {
+    let var = upload_manga_cover::Variables {
+        manga_id: Some(manga_id),
+        image: Some(image),
+    };
+    let _ = post_graphql::<UploadMangaCover>(var).await?;
+
+    Ok(())
+}
+
+pub async fn remove_manga_cover(manga_id: i64) -> Result<(), Box<dyn Error>> {
+    let var = remove_manga_cover::Variables {
+        manga_id: Some(manga_id),
+    };
+    let _ = post_graphql::<RemoveMangaCover>(var).await?;
+
+    Ok(())
+}
+
+pub async fn reset_manga_metadata(manga_id: i64) -> Result<(), Box<dyn Error>> {
+    let var = reset_manga_metadata::Variables {
+        manga_id: Some(manga_id),
+    };
+    let _ = post_graphql::<ResetMangaMetadata>(var).await?;
+
+    Ok(())
+}
+
+pub async fn set_scanlator_setting(
+    manga_id: i64,
+    dedup: bool,
+    priority: Vec<String>,
+    blocklist: Vec<String>,
+) -> Result<(), Box<dyn Error>> {
+    let var = set_scanlator_setting::Variables {
+        manga_id: Some(manga_id),
+        dedup: Some(dedup),
+        priority: Some(priority),
+        blocklist: Some(blocklist),
+    };
+    let _ = post_graphql::<SetScanlatorSetting>(var).await?;
+
+    Ok(())
+}
+
+pub async fn fetch_tags() -> Result<Vec<fetch_tags::FetchTagsTags>, Box<dyn Error>> {
+    let var = fetch_tags::Variables {};
+    let data = post_graphql::<FetchTags>(var).await?;
+
+    Ok(data.tags)
+}
+
+pub async fn set_manga_tags(
+    manga_id: i64,
+    tags: Vec<String>,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let var = set_manga_tags::Variables {
+        manga_id: Some(manga_id),
+        tags: Some(tags),
+    };
+    let data = post_graphql::<SetMangaTags>(var).await?;
+
+    Ok(data.set_manga_tags)
+}
+
+pub async fn set_manga_rating(manga_id: i64, rating: Option<i64>) -> Result<(), Box<dyn Error>> {
+    let var = set_manga_rating::Variables {
+        manga_id: Some(manga_id),
+        rating,
+    };
+    let _ = post_graphql::<SetMangaRating>(var).await?;
+
+    Ok(())
+}
+
+pub async fn set_manga_note(manga_id: i64, note: Option<String>) -> Result<(), Box<dyn Error>> {
+    let var = set_manga_note::Variables {
+        manga_id: Some(manga_id),
+        note,
+    };
+    let _ = post_graphql::<SetMangaNote>(var).await?;
+
+    Ok(())
+}
+
 pub async fn fetch_chapter(
     chapter_id: i64,
 ) -> Result<fetch_chapter::FetchChapterChapter, Box<dyn Error>> {
@@ -191,19 +440,25 @@ pub async fn fetch_categories(
     Ok(data.get_categories)
 }
 
-pub async fn create_category(name: &str) -> Result<(), Box<dyn Error>> {
+pub async fn create_category(name: &str, rule: Option<String>) -> Result<(), Box<dyn Error>> {
     let var = create_category::Variables {
         name: Some(name.to_string()),
+        rule,
     };
     let _ = post_graphql::<CreateCategory>(var).await?;
 
     Ok(())
 }
 
-pub async fn update_category(id: i64, name: &str) -> Result<(), Box<dyn Error>> {
+pub async fn update_category(
+    id: i64,
+    name: &str,
+    rule: Option<String>,
+) -> Result<(), Box<dyn Error>> {
     let var = update_category::Variables {
         id: Some(id),
         name: Some(name.to_string()),
+        rule,
     };
     let _ = post_graphql::<UpdateCategory>(var).await?;
 
@@ -259,10 +514,7 @@ pub async fn subscribe_recent_updates() -> Result<(), Box<dyn Error>> {
             .await?;
     let (sink, stream) = graphql_ws_client::wasm_websocket_combined_split(ws, wsio).await;
 
-    let token = local_storage()
-        .get("token")
-        .unwrap_throw()
-        .unwrap_or_else(|| "".to_string());
+    let token = access_token().await;
     let mut client = GraphQLClientClientBuilder::new()
         .payload(Payload { token })
         .build(stream, sink, async_executors::AsyncStd)
@@ -310,6 +562,123 @@ pub async fn subscribe_recent_updates() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+pub async fn subscribe_user_preference_changes(
+    on_change: impl Fn(
+        subscribe_user_preference_changes::SubscribeUserPreferenceChangesUserPreferenceChanged,
+    ),
+) -> Result<(), Box<dyn Error>> {
+    use futures::StreamExt;
+    use graphql_ws_client::{graphql::StreamingOperation, GraphQLClientClientBuilder};
+    use serde::Serialize;
+
+    #[derive(Serialize)]
+    struct Payload {
+        token: String,
+    }
+
+    let (ws, wsio) =
+        ws_stream_wasm::WsMeta::connect(graphql_ws_host(), Some(vec!["graphql-transport-ws"]))
+            .await?;
+    let (sink, stream) = graphql_ws_client::wasm_websocket_combined_split(ws, wsio).await;
+
+    let token = access_token().await;
+    let mut client = GraphQLClientClientBuilder::new()
+        .payload(Payload { token })
+        .build(stream, sink, async_executors::AsyncStd)
+        .await?;
+
+    let op: StreamingOperation<SubscribeUserPreferenceChanges> =
+        StreamingOperation::new(subscribe_user_preference_changes::Variables {});
+    let mut stream = client.streaming_operation(op).await?;
+
+    while let Some(Ok(item)) = stream.next().await {
+        if let Some(data) = item.data {
+            on_change(data.user_preference_changed);
+        }
+    }
+
+    debug!("subscribe_user_preference_changes");
+    Ok(())
+}
+
+pub async fn global_search(
+    keyword: String,
+    on_result: impl Fn(global_search::GlobalSearchGlobalSearch),
+) -> Result<(), Box<dyn Error>> {
+    use futures::StreamExt;
+    use graphql_ws_client::{graphql::StreamingOperation, GraphQLClientClientBuilder};
+    use serde::Serialize;
+
+    #[derive(Serialize)]
+    struct Payload {
+        token: String,
+    }
+
+    let (ws, wsio) =
+        ws_stream_wasm::WsMeta::connect(graphql_ws_host(), Some(vec!["graphql-transport-ws"]))
+            .await?;
+    let (sink, stream) = graphql_ws_client::wasm_websocket_combined_split(ws, wsio).await;
+
+    let token = access_token().await;
+    let mut client = GraphQLClientClientBuilder::new()
+        .payload(Payload { token })
+        .build(stream, sink, async_executors::AsyncStd)
+        .await?;
+
+    let op: StreamingOperation<GlobalSearch> = StreamingOperation::new(global_search::Variables {
+        query: Some(keyword),
+    });
+    let mut stream = client.streaming_operation(op).await?;
+
+    // server completes the stream once every source responded
+    while let Some(item) = stream.next().await {
+        let item = item?;
+        if let Some(errors) = item.errors {
+            if let Some(e) = errors.first() {
+                return Err(e.message.clone().into());
+            }
+        }
+        if let Some(data) = item.data {
+            on_result(data.global_search);
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn fetch_user_preferences(
+    manga_id: Option<i64>,
+) -> Result<Vec<fetch_user_preferences::FetchUserPreferencesUserPreferences>, Box<dyn Error>> {
+    let var = fetch_user_preferences::Variables { manga_id };
+    let data = post_graphql::<FetchUserPreferences>(var).await?;
+    Ok(data.user_preferences)
+}
+
+pub async fn set_user_preference(
+    key: String,
+    manga_id: Option<i64>,
+    schema_version: i64,
+    value: String,
+) -> Result<(), Box<dyn Error>> {
+    let var = set_user_preference::Variables {
+        key,
+        manga_id,
+        schema_version,
+        value,
+    };
+    let _ = post_graphql::<SetUserPreference>(var).await?;
+    Ok(())
+}
+
+pub async fn delete_user_preference(
+    key: String,
+    manga_id: Option<i64>,
+) -> Result<bool, Box<dyn Error>> {
+    let var = delete_user_preference::Variables { key, manga_id };
+    let data = post_graphql::<DeleteUserPreference>(var).await?;
+    Ok(data.delete_user_preference)
+}
+
 pub async fn fetch_histories(
     cursor: Option<String>,
 ) -> Result<fetch_histories::FetchHistoriesRecentChapters, Box<dyn Error>> {
@@ -377,11 +746,19 @@ pub async fn uninstall_source(source_id: i64) -> Result<i64, Box<dyn Error>> {
     Ok(data.uninstall_source)
 }
 
-pub async fn user_login(username: String, password: String) -> Result<String, Box<dyn Error>> {
+/// Error message returned by `login` when the account has two-factor authentication enabled
+pub const TOTP_REQUIRED: &str = "two-factor code required";
+
+pub async fn user_login(
+    username: String,
+    password: String,
+    totp_code: Option<String>,
+) -> Result<user_login::UserLoginLogin, Box<dyn Error>> {
     let var = user_login::Variables {
         login: user_login::LoginInput { username, password },
+        totp_code,
     };
-    let data = post_graphql::<UserLogin>(var).await?;
+    let data = send_graphql::<UserLogin>(var, "").await?;
     Ok(data.login)
 }
 
@@ -698,3 +1075,80 @@ pub async fn refresh_chapters(manga_id: Option<i64>, wait: bool) -> Result<(), B
 
     Ok(())
 }
+
+pub async fn oidc_login_start(
+) -> Result<oidc_login_start::OidcLoginStartOidcLoginStart, Box<dyn Error>> {
+    let var = oidc_login_start::Variables {};
+    let data = post_graphql::<OidcLoginStart>(var).await?;
+    Ok(data.oidc_login_start)
+}
+
+pub async fn oidc_login_end(
+    code: String,
+    state: String,
+) -> Result<oidc_login_end::OidcLoginEndOidcLoginEnd, Box<dyn Error>> {
+    let var = oidc_login_end::Variables { code, state };
+    let data = send_graphql::<OidcLoginEnd>(var, "").await?;
+    Ok(data.oidc_login_end)
+}
+
+pub async fn link_oidc_account(code: String, state: String) -> Result<(), Box<dyn Error>> {
+    let var = link_oidc_account::Variables { code, state };
+    let _ = post_graphql::<LinkOidcAccount>(var).await?;
+    Ok(())
+}
+
+pub async fn unlink_oidc_account() -> Result<(), Box<dyn Error>> {
+    let var = unlink_oidc_account::Variables {};
+    let _ = post_graphql::<UnlinkOidcAccount>(var).await?;
+    Ok(())
+}
+
+pub async fn fetch_linked_accounts(
+) -> Result<Vec<fetch_linked_accounts::FetchLinkedAccountsLinkedAccounts>, Box<dyn Error>> {
+    let var = fetch_linked_accounts::Variables {};
+    let data = post_graphql::<FetchLinkedAccounts>(var).await?;
+    Ok(data.linked_accounts)
+}
+
+pub async fn enroll_totp() -> Result<enroll_totp::EnrollTotpEnrollTotp, Box<dyn Error>> {
+    let var = enroll_totp::Variables {};
+    let data = post_graphql::<EnrollTotp>(var).await?;
+    Ok(data.enroll_totp)
+}
+
+pub async fn confirm_totp(code: String) -> Result<Vec<String>, Box<dyn Error>> {
+    let var = confirm_totp::Variables { code };
+    let data = post_graphql::<ConfirmTotp>(var).await?;
+    Ok(data.confirm_totp)
+}
+
+/// Enroll with the token `login` returns to an admin that has to enable two-factor authentication
+pub async fn enroll_totp_with_token(
+    token: &str,
+) -> Result<enroll_totp::EnrollTotpEnrollTotp, Box<dyn Error>> {
+    let var = enroll_totp::Variables {};
+    let data = send_graphql::<EnrollTotp>(var, token).await?;
+    Ok(data.enroll_totp)
+}
+
+pub async fn confirm_totp_with_token(
+    token: &str,
+    code: String,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let var = confirm_totp::Variables { code };
+    let data = send_graphql::<ConfirmTotp>(var, token).await?;
+    Ok(data.confirm_totp)
+}
+
+pub async fn disable_totp(code: String) -> Result<(), Box<dyn Error>> {
+    let var = disable_totp::Variables { code };
+    let _ = post_graphql::<DisableTotp>(var).await?;
+    Ok(())
+}
+
+pub async fn regenerate_recovery_codes(code: String) -> Result<Vec<String>, Box<dyn Error>> {
+    let var = regenerate_recovery_codes::Variables { code };
+    let data = post_graphql::<RegenerateRecoveryCodes>(var).await?;
+    Ok(data.regenerate_recovery_codes)
+}
@@ -1,6 +1,6 @@
 use std::rc::Rc;
 
-use crate::common::{Fit, ReaderSettings, Spinner, events, snackbar};
+use crate::common::{Fit, ReaderSettings, Spinner, events, preference_sync, snackbar};
 use crate::utils::{document, proxied_image_url, window, AsyncLoader, body};
 use crate::{
     common::{Background, Direction, DisplayMode, ReaderMode},
@@ -97,6 +97,7 @@ impl Reader {
                     this.next_chapter.set_neq(result.next);
                     this.prev_chapter.set_neq(result.prev);
 
+                    preference_sync::pull(Some(result.manga.id)).await;
                     this.reader_settings.load_by_manga_id(result.manga.id);
 
                     let page;
@@ -16,6 +16,7 @@ use crate::{
     query, 
     settings_categories::SettingsCategories, 
     settings_download_queue::SettingsDownloads, 
+    settings_statistics::SettingsStatistics, 
     utils::{AsyncLoader, is_tauri, window}, settings_source::SettingsSource
 };
 use dominator::svg;
@@ -84,6 +85,8 @@ impl Settings {
                         icon: s.icon.clone(),
                         has_update: s.has_update,
                         installed: true,
+                        repository: s.repository.clone(),
+                        conflicts: vec![],
                     }).collect());
 
                     settings.available_sources.lock_mut().replace_cloned(result.available_sources.iter().map(|s| Source {
@@ -93,6 +96,8 @@ impl Settings {
                         icon: s.icon.clone(),
                         has_update: false,
                         installed: false,
+                        repository: s.repository.clone(),
+                        conflicts: s.conflicts.clone(),
                     }).collect());                    
                 },
                 Err(err) => {
@@ -180,6 +185,8 @@ impl Settings {
                         icon: s.icon.clone(),
                         has_update: s.has_update,
                         installed: true,
+                        repository: s.repository.clone(),
+                        conflicts: vec![],
                     }).collect());
 
                     settings.available_sources.lock_mut().replace_cloned(result.available_sources.iter().map(|s| Source {
@@ -189,6 +196,8 @@ impl Settings {
                         icon: s.icon.clone(),
                         has_update: false,
                         installed: false,
+                        repository: s.repository.clone(),
+                        conflicts: s.conflicts.clone(),
                     }).collect());
                 },
                 Err(err) => {
@@ -219,6 +228,8 @@ impl Settings {
                         icon: s.icon.clone(),
                         has_update: s.has_update,
                         installed: true,
+                        repository: s.repository.clone(),
+                        conflicts: vec![],
                     }).collect());
 
                     settings.available_sources.lock_mut().replace_cloned(result.available_sources.iter().map(|s| Source {
@@ -228,6 +239,8 @@ impl Settings {
                         icon: s.icon.clone(),
                         has_update: false,
                         installed: false,
+                        repository: s.repository.clone(),
+                        conflicts: s.conflicts.clone(),
                     }).collect());
                 },
                 Err(err) => {
@@ -308,7 +321,8 @@ impl Settings {
                             SettingCategory::Users => "Users",
                             SettingCategory::CreateUser => "Create User",
                             SettingCategory::User => "User",
-                            SettingCategory::DownloadQueue => "Downloads Queue"
+                            SettingCategory::DownloadQueue => "Downloads Queue",
+                            SettingCategory::Statistics => "Statistics"
                         }
                     ))
                 }),
@@ -411,6 +425,10 @@ impl Settings {
                 link!(Route::Settings(SettingCategory::SourceList).url(), {
                     .class("list-item")
                     .text("Sources")
+                }),
+                link!(Route::Settings(SettingCategory::Statistics).url(), {
+                    .class("list-item")
+                    .text("Statistics")
                 })
             ])
             .child_signal(settings.me.signal_cloned().map(|me| {
@@ -684,7 +702,10 @@ impl Settings {
                                                         .text(&x.name)
                                                     }),
                                                     html!("span", {
-                                                        .text(&x.version)
+                                                        .text(&match &x.repository {
+                                                            Some(repository) => format!("{} · {}", x.version, repository),
+                                                            None => x.version.clone(),
+                                                        })
                                                     })
                                                 ])
                                             })
@@ -709,7 +730,15 @@ impl Settings {
                                                         .text(&x.name)
                                                     }),
                                                     html!("span", {
-                                                        .text(&x.version)
+                                                        .text(&match &x.repository {
+                                                            Some(repository) => format!("{} · {}", x.version, repository),
+                                                            None => x.version.clone(),
+                                                        })
+                                                    }),
+                                                    html!("span", {
+                                                        .style("font-size", "small")
+                                                        .visible(!x.conflicts.is_empty())
+                                                        .text(&format!("also in {}", x.conflicts.join(", ")))
                                                     })
                                                 ])
                                             })
@@ -797,6 +826,7 @@ impl Settings {
                     SettingCategory::User => Some(Profile::render(Profile::new())),
                     SettingCategory::CreateUser => Some(Login::render(Login::new())),
                     SettingCategory::DownloadQueue => Some(SettingsDownloads::render(SettingsDownloads::new())),
+                    SettingCategory::Statistics => Some(SettingsStatistics::render(SettingsStatistics::new())),
                 }
             }))            
         })
@@ -18,6 +18,7 @@ pub struct SettingsCategories {
     pub is_edit: Mutable<bool>,
     categories: MutableVec<Category>,
     new_category: Mutable<Option<String>>,
+    new_rule: Mutable<Option<String>>,
     loader: AsyncLoader,
 }
 
@@ -26,6 +27,7 @@ impl SettingsCategories {
         Rc::new(Self {
             is_edit: Mutable::new(false),
             new_category: Mutable::new(None),
+            new_rule: Mutable::new(None),
             categories: MutableVec::new(),
             loader: AsyncLoader::new(),
         })
@@ -35,9 +37,11 @@ impl SettingsCategories {
         AsyncLoader::new().load({
             let settings = self.clone();
             async move {
-                match query::create_category(&name).await {
+                let rule = settings.new_rule.get_cloned();
+                match query::create_category(&name, rule).await {
                     Ok(_) => {
                         settings.new_category.set(None);
+                        settings.new_rule.set(None);
                         settings.is_edit.set(false);
                         settings.fetch_categories();
                     }
@@ -65,11 +69,11 @@ impl SettingsCategories {
         });
     }
 
-    fn update_category(self: &Rc<Self>, id: i64, name: String) {
+    fn update_category(self: &Rc<Self>, id: i64, name: String, rule: Option<String>) {
         AsyncLoader::new().load({
             let settings = self.clone();
             async move {
-                match query::update_category(id, &name).await {
+                match query::update_category(id, &name, rule).await {
                     Ok(_) => {
                         settings.is_edit.set(false);
                         settings.fetch_categories();
@@ -90,6 +94,7 @@ impl SettingsCategories {
                     let res: Vec<Category> = res.into_iter().filter_map(|c| (c.id.is_some()).then(|| Category{
                         id: c.id,
                         name: c.name.clone(),
+                        rule: c.rule.clone(),
                         count: c.count,
                     })).collect();
                     settings.categories.lock_mut().replace_cloned(res);
@@ -142,7 +147,7 @@ impl SettingsCategories {
                                                         if event.key() == "Enter" {
                                                             event.prevent_default();
                                                             if let Some(cat_id) = cat.id {
-                                                                settings.update_category(cat_id, input.value());
+                                                                settings.update_category(cat_id, input.value(), None);
                                                             }
                                                         }
                                                     }))
@@ -157,11 +162,36 @@ impl SettingsCategories {
                                                         .text(&cat.name)
                                                     })
                                                 ])
+                                                .apply_if(cat.rule.is_some(), |dom| dom.child(html!("div", {
+                                                    .style("margin", "0.25rem")
+                                                    .style("font-size", "smaller")
+                                                    .text(cat.rule.as_deref().unwrap_or_default())
+                                                })))
                                             })
                                         };
 
                                         Some(dom)
                                     })))
+                                    .child_signal(settings.is_edit.signal().map(clone!(settings, cat => move |is_edit| {
+                                        (is_edit && cat.rule.is_some()).then(|| html!("input" => HtmlInputElement, {
+                                            .style("width", "100%")
+                                            .style("font-size", "smaller")
+                                            .style_important("background-color", "initial")
+                                            .style_important("margin", "0")
+                                            .attr("type", "text")
+                                            .attr("value", cat.rule.as_deref().unwrap_or_default())
+                                            .with_node!(input => {
+                                                .event_with_options(&EventOptions::preventable(), clone!(cat, input, settings => move |event: events::KeyDown| {
+                                                    if event.key() == "Enter" {
+                                                        event.prevent_default();
+                                                        if let Some(cat_id) = cat.id {
+                                                            settings.update_category(cat_id, cat.name.clone(), Some(input.value()));
+                                                        }
+                                                    }
+                                                }))
+                                            })
+                                        }))
+                                    })))
                                 }),
                             ])
                             .child_signal(settings.is_edit.signal().map(clone!(settings => move |is_edit| {
@@ -198,22 +228,40 @@ impl SettingsCategories {
                             .style("display", "flex")
                             .style("align-items", "center")
                             .children(&mut [
-                                html!("input" => HtmlInputElement, {
+                                html!("div", {
                                     .style("width", "100%")
-                                    .style_important("background-color", "initial")
-                                    .attr("type", "text")
-                                    .attr("placeholder", "New Category")
-                                    .with_node!(input => {
-                                        .event(clone!(input, settings => move |_: events::Change| {
-                                            settings.new_category.set(Some(input.value()));
-                                        }))
-                                        .event_with_options(&EventOptions::preventable(), clone!(input, settings => move |event: events::KeyDown| {
-                                            if event.key() == "Enter" {
-                                                event.prevent_default();
-                                                settings.create_category(input.value());
-                                            }
-                                        }))
-                                    })
+                                    .children(&mut [
+                                        html!("input" => HtmlInputElement, {
+                                            .style("width", "100%")
+                                            .style_important("background-color", "initial")
+                                            .attr("type", "text")
+                                            .attr("placeholder", "New Category")
+                                            .with_node!(input => {
+                                                .event(clone!(input, settings => move |_: events::Change| {
+                                                    settings.new_category.set(Some(input.value()));
+                                                }))
+                                                .event_with_options(&EventOptions::preventable(), clone!(input, settings => move |event: events::KeyDown| {
+                                                    if event.key() == "Enter" {
+                                                        event.prevent_default();
+                                                        settings.create_category(input.value());
+                                                    }
+                                                }))
+                                            })
+                                        }),
+                                        html!("input" => HtmlInputElement, {
+                                            .style("width", "100%")
+                                            .style("font-size", "smaller")
+                                            .style_important("background-color", "initial")
+                                            .attr("type", "text")
+                                            .attr("placeholder", "Rule for smart category, e.g. unread > 0 AND days_since_read >= 60")
+                                            .with_node!(input => {
+                                                .event(clone!(input, settings => move |_: events::Input| {
+                                                    let rule = input.value();
+                                                    settings.new_rule.set((!rule.trim().is_empty()).then_some(rule));
+                                                }))
+                                            })
+                                        }),
+                                    ])
                                 }),
                                 html!("button", {
                                     .style("margin-right","0.5rem")
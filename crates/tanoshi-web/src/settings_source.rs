@@ -35,6 +35,8 @@ impl SettingsSource {
                         icon: s.icon.clone(),
                         has_update: false,
                         installed: true,
+                        repository: None,
+                        conflicts: vec![],
                     }));
                     settings.input_list.set(s.preferences);
                 },
@@ -0,0 +1,256 @@
+use crate::{
+    common::{events, snackbar, Route},
+    query,
+    utils::AsyncLoader,
+};
+use chrono::{Duration, NaiveDate};
+use dominator::{clone, html, link, Dom};
+use futures_signals::signal::{Mutable, SignalExt};
+use std::rc::Rc;
+use tanoshi_schema::fetch_reading_statistics::FetchReadingStatisticsReadingStatistics as ReadingStatistics;
+
+pub struct SettingsStatistics {
+    year: Mutable<i32>,
+    statistics: Mutable<Option<Rc<ReadingStatistics>>>,
+    loader: AsyncLoader,
+}
+
+impl SettingsStatistics {
+    pub fn new() -> Rc<Self> {
+        Rc::new(Self {
+            year: Mutable::new(js_sys::Date::new_0().get_full_year() as i32),
+            statistics: Mutable::new(None),
+            loader: AsyncLoader::new(),
+        })
+    }
+
+    /// minutes ahead of utc, js reports it the other way around
+    fn utc_offset_minutes() -> i64 {
+        -(js_sys::Date::new_0().get_timezone_offset() as i64)
+    }
+
+    /// local midnight of january 1st as utc
+    fn start_of_year(year: i32, utc_offset_minutes: i64) -> Option<String> {
+        NaiveDate::from_ymd_opt(year, 1, 1)
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .map(|date| date - Duration::minutes(utc_offset_minutes))
+            .map(|date| date.format("%Y-%m-%dT%H:%M:%S").to_string())
+    }
+
+    fn fetch_statistics(self: &Rc<Self>) {
+        self.loader.load({
+            let settings = self.clone();
+            async move {
+                let year = settings.year.get();
+                let utc_offset_minutes = Self::utc_offset_minutes();
+                match query::fetch_reading_statistics(
+                    Self::start_of_year(year, utc_offset_minutes),
+                    Self::start_of_year(year + 1, utc_offset_minutes),
+                    utc_offset_minutes,
+                    Some("month".to_string()),
+                )
+                .await
+                {
+                    Ok(statistics) => {
+                        settings.statistics.set(Some(Rc::new(statistics)));
+                    }
+                    Err(err) => {
+                        snackbar::show(format!("{}", err));
+                    }
+                }
+            }
+        });
+    }
+
+    fn change_year(self: &Rc<Self>, delta: i32) {
+        self.year.replace_with(|year| *year + delta);
+        self.fetch_statistics();
+    }
+
+    fn render_row(label: &str, value: String) -> Dom {
+        html!("li", {
+            .class("list-item")
+            .style("display", "flex")
+            .style("justify-content", "space-between")
+            .children(&mut [
+                html!("span", {
+                    .text(label)
+                }),
+                html!("span", {
+                    .style("font-weight", "600")
+                    .text(&value)
+                }),
+            ])
+        })
+    }
+
+    fn render_bar(label: &str, value: i64, max: i64) -> Dom {
+        html!("li", {
+            .class("list-item")
+            .style("display", "flex")
+            .style("flex-direction", "column")
+            .children(&mut [
+                html!("div", {
+                    .style("display", "flex")
+                    .style("justify-content", "space-between")
+                    .style("width", "100%")
+                    .children(&mut [
+                        html!("span", {
+                            .text(label)
+                        }),
+                        html!("span", {
+                            .text(&format!("{}", value))
+                        }),
+                    ])
+                }),
+                html!("div", {
+                    .style("height", "0.5rem")
+                    .style("width", "100%")
+                    .style("margin-top", "0.25rem")
+                    .style("background-color", "var(--primary-color-300)")
+                    .children(&mut [
+                        html!("div", {
+                            .style("width", &format!("{}%", (value as f64 / max.max(1) as f64) * 100.0))
+                            .style("height", "100%")
+                            .style("background-color", "var(--primary-color)")
+                        })
+                    ])
+                }),
+            ])
+        })
+    }
+
+    fn render_section(title: &str, mut children: Vec<Dom>) -> Dom {
+        if children.is_empty() {
+            children.push(html!("li", {
+                .class("list-item")
+                .text("Nothing read yet")
+            }));
+        }
+
+        html!("div", {
+            .children(&mut [
+                html!("label", {
+                    .text(title)
+                }),
+                html!("ul", {
+                    .class(["list", "group"])
+                    .style("margin-bottom", "0.5rem")
+                    .children(&mut children)
+                }),
+            ])
+        })
+    }
+
+    fn render_statistics(statistics: &ReadingStatistics) -> Dom {
+        let summary = &statistics.summary;
+        let streak = &statistics.streak;
+
+        let max_chapters = statistics
+            .activity
+            .iter()
+            .map(|activity| activity.chapters)
+            .max()
+            .unwrap_or(0);
+
+        html!("div", {
+            .children(&mut [
+                Self::render_section("Year in Review", vec![
+                    Self::render_row("Chapters read", format!("{}", summary.chapters)),
+                    Self::render_row("Pages read", format!("{}", summary.pages)),
+                    Self::render_row("Manga read", format!("{}", summary.manga)),
+                    Self::render_row("Manga completed", format!("{}", summary.completed_manga)),
+                    Self::render_row("Days read", format!("{}", summary.days)),
+                    Self::render_row("Sessions", format!("{}", summary.sessions)),
+                    Self::render_row("Chapters per session", format!("{:.1}", summary.average_chapters_per_session)),
+                ]),
+                Self::render_section("Streak", vec![
+                    Self::render_row("Current streak", format!("{} days", streak.current)),
+                    Self::render_row("Longest streak", match (&streak.longest_start, &streak.longest_end) {
+                        (Some(start), Some(end)) => format!("{} days ({} - {})", streak.longest, start, end),
+                        _ => format!("{} days", streak.longest),
+                    }),
+                ]),
+                Self::render_section("Chapters per Month", statistics.activity.iter().map(|activity| {
+                    Self::render_bar(&activity.period, activity.chapters, max_chapters)
+                }).collect()),
+                Self::render_section("Top Genres", statistics.top_genres.iter().map(|genre| {
+                    Self::render_row(&genre.name, format!("{}", genre.chapters))
+                }).collect()),
+                Self::render_section("Top Authors", statistics.top_authors.iter().map(|author| {
+                    Self::render_row(&author.name, format!("{}", author.chapters))
+                }).collect()),
+                Self::render_section("Top Sources", statistics.top_sources.iter().map(|source| {
+                    Self::render_row(source.name.as_deref().unwrap_or("Uninstalled source"), format!("{}", source.chapters))
+                }).collect()),
+                Self::render_section("Manga", statistics.manga_completion.iter().map(|manga| {
+                    let read_at = |at: &str| at.split('T').next().unwrap_or_default().to_string();
+                    link!(Route::Manga(manga.manga_id).url(), {
+                        .class("list-item")
+                        .style("display", "flex")
+                        .style("flex-direction", "column")
+                        .children(&mut [
+                            html!("div", {
+                                .style("display", "flex")
+                                .style("justify-content", "space-between")
+                                .style("width", "100%")
+                                .children(&mut [
+                                    html!("span", {
+                                        .style("font-weight", "600")
+                                        .text(&manga.title)
+                                    }),
+                                    html!("span", {
+                                        .text(&format!("{}/{}", manga.chapters_read, manga.chapters_total))
+                                    }),
+                                ])
+                            }),
+                            html!("span", {
+                                .style("font-size", "smaller")
+                                .text(&match &manga.completed_at {
+                                    Some(completed_at) => format!("{} - {}", read_at(&manga.started_at), read_at(completed_at)),
+                                    None => format!("{} - {}", read_at(&manga.started_at), read_at(&manga.last_read_at)),
+                                })
+                            }),
+                        ])
+                    })
+                }).collect()),
+            ])
+        })
+    }
+
+    pub fn render(settings: Rc<Self>) -> Dom {
+        settings.fetch_statistics();
+        html!("div", {
+            .class("content")
+            .children(&mut [
+                html!("div", {
+                    .style("display", "flex")
+                    .style("justify-content", "space-between")
+                    .style("align-items", "center")
+                    .style("margin-bottom", "0.5rem")
+                    .children(&mut [
+                        html!("button", {
+                            .text("Previous")
+                            .event(clone!(settings => move |_: events::Click| {
+                                settings.change_year(-1);
+                            }))
+                        }),
+                        html!("span", {
+                            .style("font-weight", "600")
+                            .text_signal(settings.year.signal().map(|year| format!("{}", year)))
+                        }),
+                        html!("button", {
+                            .text("Next")
+                            .event(clone!(settings => move |_: events::Click| {
+                                settings.change_year(1);
+                            }))
+                        }),
+                    ])
+                }),
+            ])
+            .child_signal(settings.statistics.signal_cloned().map(|statistics| {
+                statistics.map(|statistics| Self::render_statistics(&statistics))
+            }))
+        })
+    }
+}
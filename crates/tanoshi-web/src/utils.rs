@@ -221,6 +221,23 @@ pub fn local_storage() -> Storage {
     LOCAL_STORAGE.with(|s| s.clone())
 }
 
+/// Store access token with its expiry and refresh token
+pub fn set_auth_token(access_token: &str, refresh_token: &str, expires_at: i64) {
+    let storage = local_storage();
+    storage.set("token", access_token).unwrap_throw();
+    storage.set("refresh_token", refresh_token).unwrap_throw();
+    storage
+        .set("token_expires_at", &expires_at.to_string())
+        .unwrap_throw();
+}
+
+pub fn clear_auth_token() {
+    let storage = local_storage();
+    storage.delete("token").unwrap_throw();
+    storage.delete("refresh_token").unwrap_throw();
+    storage.delete("token_expires_at").unwrap_throw();
+}
+
 pub fn session_storage() -> Storage {
     SESSION_STORAGE.with(|s| s.clone())
 }
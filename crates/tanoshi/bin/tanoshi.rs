@@ -7,21 +7,31 @@ use futures::future::OptionFuture;
 use tanoshi::{
     application::worker,
     domain::services::{
-        chapter::ChapterService, download::DownloadService, history::HistoryService,
-        image::ImageService, library::LibraryService, manga::MangaService, source::SourceService,
-        tracker::TrackerService, user::UserService,
+        api_key::ApiKeyService, audit_log::AuditLogService, chapter::ChapterService,
+        content_policy::ContentPolicyService, download::DownloadService, history::HistoryService,
+        http_profile::HttpProfileService, image::ImageService, library::LibraryService,
+        login_throttle::LoginThrottleService, manga::MangaService, migration::MigrationService,
+        oidc::OidcService, preference::PreferenceService, proxy_auth::ProxyAuthService,
+        session::SessionService, source::SourceService, statistics::StatisticsService,
+        totp::TotpService, tracker::TrackerService, user::UserService,
     },
     infrastructure::{
         config::{self, Config},
         database,
         domain::repositories::{
-            chapter::ChapterRepositoryImpl, download::DownloadRepositoryImpl,
-            history::HistoryRepositoryImpl, image::ImageRepositoryImpl,
-            image_cache::ImageCacheRepositoryImpl, library::LibraryRepositoryImpl,
-            manga::MangaRepositoryImpl, source::SourceRepositoryImpl,
+            api_key::ApiKeyRepositoryImpl, audit_log::AuditLogRepositoryImpl,
+            chapter::ChapterRepositoryImpl, content_policy::ContentPolicyRepositoryImpl,
+            download::DownloadRepositoryImpl, history::HistoryRepositoryImpl,
+            http_profile::HttpProfileRepositoryImpl, identity::IdentityRepositoryImpl,
+            image::ImageRepositoryImpl, image_cache::ImageCacheRepositoryImpl,
+            library::LibraryRepositoryImpl, manga::MangaRepositoryImpl,
+            migration::MigrationRepositoryImpl, preference::PreferenceRepositoryImpl,
+            session::SessionRepositoryImpl, source::SourceRepositoryImpl,
+            statistics::StatisticsRepositoryImpl, totp::TotpRepositoryImpl,
             tracker::TrackerRepositoryImpl, user::UserRepositoryImpl,
         },
         local, notification,
+        oidc::OidcClient,
     },
     presentation::{graphql::loader::DatabaseLoader, ServerBuilder},
 };
@@ -61,12 +71,28 @@ async fn main() -> Result<(), anyhow::Error> {
     let user_repo = UserRepositoryImpl::new(pool.clone());
     let user_svc = UserService::new(user_repo.clone());
 
+    let session_repo = SessionRepositoryImpl::new(pool.clone());
+    let session_svc = SessionService::new(
+        session_repo,
+        user_repo.clone(),
+        &config.secret,
+        config.access_token_ttl,
+        config.refresh_token_ttl,
+    );
+
+    let api_key_repo = ApiKeyRepositoryImpl::new(pool.clone());
+    let api_key_svc = ApiKeyService::new(api_key_repo, user_repo.clone());
+
     let extension_manager = ExtensionManager::new(&config.plugin_path);
 
     extension_manager.load_all().await?;
 
-    let source_repo = SourceRepositoryImpl::new(extension_manager.clone());
-    let source_svc = SourceService::new(source_repo);
+    let source_repo = SourceRepositoryImpl::new(pool.clone(), extension_manager.clone());
+    let source_svc = SourceService::new(
+        source_repo.clone(),
+        config.extension_repository_list(),
+        config.extension_trust_policy(),
+    );
 
     let manga_repo = MangaRepositoryImpl::new(pool.clone());
     let manga_svc = MangaService::new(manga_repo.clone(), extension_manager.clone());
@@ -105,6 +131,24 @@ async fn main() -> Result<(), anyhow::Error> {
         }
     }
 
+    let migration_repo = MigrationRepositoryImpl::new(pool.clone());
+    let migration_svc = MigrationService::new(
+        library_repo.clone(),
+        manga_repo.clone(),
+        chapter_repo.clone(),
+        migration_repo,
+        extension_manager.clone(),
+    );
+
+    let http_profile_repo = HttpProfileRepositoryImpl::new(pool.clone());
+    let http_profile_svc = HttpProfileService::new(
+        http_profile_repo,
+        extension_manager.clone(),
+        config.http.default_profile(),
+        config.http.sources.clone(),
+    );
+    http_profile_svc.apply_all().await?;
+
     let mut notifier_builder = notification::Builder::new(user_repo.clone());
 
     let mut telegram_bot_fut: OptionFuture<_> = None.into();
@@ -129,6 +173,9 @@ async fn main() -> Result<(), anyhow::Error> {
 
     let notifier = notifier_builder.finish();
 
+    let image_cache_repo =
+        ImageCacheRepositoryImpl::new(pool.clone(), &config.cache_path, config.cache_max_size);
+
     let (chapter_update_receiver, chapter_update_command_tx, update_worker_handle) =
         worker::updates::start(
             config.update_interval,
@@ -137,8 +184,8 @@ async fn main() -> Result<(), anyhow::Error> {
             chapter_repo.clone(),
             extension_manager.clone(),
             notifier.clone(),
-            config.extension_repository.clone(),
-            &config.cache_path,
+            source_svc.clone(),
+            image_cache_repo.clone(),
         );
 
     let (download_sender, download_receiver) = worker::downloads::channel();
@@ -193,12 +240,73 @@ async fn main() -> Result<(), anyhow::Error> {
         None
     };
 
+    let oidc_client = if let Some(oidc_cfg) = config.oidc.clone() {
+        if let Some(base_url) = config.base_url.as_ref() {
+            match OidcClient::discover(base_url, oidc_cfg).await {
+                Ok(client) => Some(client),
+                Err(e) => {
+                    error!("failed to discover oidc provider, single sign-on disabled: {e}");
+                    None
+                }
+            }
+        } else {
+            return Err(anyhow::anyhow!(
+                "Invalid config: OpenID Connect needs base_url to login"
+            ));
+        }
+    } else {
+        None
+    };
+
+    let identity_repo = IdentityRepositoryImpl::new(pool.clone());
+    let oidc_svc = OidcService::new(oidc_client, identity_repo, user_repo.clone());
+
+    if let Some(proxy_auth) = config.proxy_auth.as_ref() {
+        if proxy_auth.trusted_proxies.is_empty() {
+            warn!("proxy auth is enabled but no trusted proxies are configured");
+        }
+    }
+    let proxy_auth_svc = ProxyAuthService::new(config.proxy_auth.clone(), user_repo.clone());
+
+    let totp_repo = TotpRepositoryImpl::new(pool.clone());
+    let totp_svc = TotpService::new(totp_repo, user_repo.clone(), config.require_totp_for_admins);
+
+    let login_throttle_svc = LoginThrottleService::new(config.login_throttle.clone());
+
+    let audit_log_repo = AuditLogRepositoryImpl::new(pool.clone());
+    let audit_log_svc = AuditLogService::new(audit_log_repo);
+
+    let content_policy_repo = ContentPolicyRepositoryImpl::new(pool.clone());
+    let content_policy_svc = ContentPolicyService::new(content_policy_repo, source_repo);
+
+    let preference_repo = PreferenceRepositoryImpl::new(pool.clone());
+    let preference_svc = PreferenceService::new(preference_repo);
+
+    let statistics_repo = StatisticsRepositoryImpl::new(pool.clone());
+    let statistics_svc = StatisticsService::new(statistics_repo);
+
     let tracker_repo = TrackerRepositoryImpl::new(pool.clone(), mal_client.clone(), al_client);
     let tracker_svc = TrackerService::new(tracker_repo.clone());
 
-    let image_repo = ImageRepositoryImpl::new();
-    let image_cache_repo = ImageCacheRepositoryImpl::new(&config.cache_path);
-    let image_svc = ImageService::new(image_repo, image_cache_repo);
+    let image_repo =
+        ImageRepositoryImpl::new(extension_manager.clone(), config.http.image_hosts.clone());
+    let image_svc = ImageService::new(
+        image_repo,
+        image_cache_repo,
+        config.image_url_ttl,
+        &config.cover_path,
+    );
+
+    let (prefetch_sender, prefetch_receiver) = worker::prefetch::channel();
+
+    let prefetch_worker_handle = worker::prefetch::start(
+        chapter_repo.clone(),
+        image_svc.clone(),
+        extension_manager.clone(),
+        config.prefetch.concurrency,
+        config.prefetch.next_chapter_pages,
+        prefetch_receiver,
+    );
 
     let loader = DatabaseLoader::new(
         history_repo,
@@ -211,6 +319,16 @@ async fn main() -> Result<(), anyhow::Error> {
     let mut server_builder = ServerBuilder::new()
         .with_config(config.clone())
         .with_user_svc(user_svc)
+        .with_session_svc(session_svc)
+        .with_api_key_svc(api_key_svc)
+        .with_oidc_svc(oidc_svc)
+        .with_proxy_auth_svc(proxy_auth_svc)
+        .with_totp_svc(totp_svc)
+        .with_login_throttle_svc(login_throttle_svc)
+        .with_audit_log_svc(audit_log_svc)
+        .with_content_policy_svc(content_policy_svc)
+        .with_preference_svc(preference_svc)
+        .with_statistics_svc(statistics_svc)
         .with_tracker_svc(tracker_svc)
         .with_source_svc(source_svc)
         .with_manga_svc(manga_svc)
@@ -219,8 +337,11 @@ async fn main() -> Result<(), anyhow::Error> {
         .with_library_svc(libary_svc)
         .with_history_svc(history_svc)
         .with_download_svc(download_svc)
+        .with_http_profile_svc(http_profile_svc)
+        .with_migration_svc(migration_svc)
         .with_ext_manager(extension_manager)
         .with_download_tx(download_sender)
+        .with_prefetch_tx(prefetch_sender)
         .with_notifier(notifier)
         .with_chapter_update_receiver(chapter_update_receiver)
         .with_chapter_update_command_tx(chapter_update_command_tx)
@@ -242,6 +363,9 @@ async fn main() -> Result<(), anyhow::Error> {
         _ = download_worker_handle => {
             info!("download worker quit");
         }
+        _ = prefetch_worker_handle => {
+            info!("prefetch worker quit");
+        }
         Some(_) = telegram_bot_fut => {
             info!("worker shutdown");
         }
@@ -0,0 +1,13 @@
+CREATE TABLE image_cache (
+    key TEXT PRIMARY KEY,
+    source_id INTEGER,
+    manga_id INTEGER,
+    is_cover BOOLEAN NOT NULL DEFAULT false,
+    size INTEGER NOT NULL,
+    hits INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_accessed_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX idx_image_cache_last_accessed_at ON image_cache(last_accessed_at);
+CREATE INDEX idx_image_cache_manga_id ON image_cache(manga_id);
+CREATE INDEX idx_image_cache_source_id ON image_cache(source_id);
@@ -0,0 +1,7 @@
+CREATE TABLE source_http_profile (
+    source_id INTEGER PRIMARY KEY,
+    proxy TEXT,
+    user_agent TEXT,
+    headers TEXT NOT NULL DEFAULT '{}',
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,9 @@
+CREATE TABLE user_content_policy (
+    user_id INTEGER PRIMARY KEY NOT NULL,
+    hide_nsfw BOOLEAN NOT NULL DEFAULT false,
+    allowed_source_ids TEXT,
+    denied_source_ids TEXT NOT NULL DEFAULT '[]',
+    blocked_genres TEXT NOT NULL DEFAULT '[]',
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES user(id) ON DELETE CASCADE
+);
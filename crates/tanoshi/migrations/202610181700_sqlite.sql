@@ -0,0 +1,11 @@
+CREATE TABLE user_preference (
+    user_id INTEGER NOT NULL,
+    key TEXT NOT NULL,
+    -- 0 for preferences that apply to every manga
+    manga_id INTEGER NOT NULL DEFAULT 0,
+    schema_version INTEGER NOT NULL DEFAULT 1,
+    value TEXT NOT NULL,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, key, manga_id),
+    FOREIGN KEY (user_id) REFERENCES user(id) ON DELETE CASCADE
+);
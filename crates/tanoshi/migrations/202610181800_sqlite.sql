@@ -0,0 +1,17 @@
+CREATE VIRTUAL TABLE manga_fts USING fts5(
+    title,
+    author,
+    genre,
+    description,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+-- author and genre are json arrays, index them as plain words
+INSERT INTO manga_fts(rowid, title, author, genre, description)
+SELECT
+    id,
+    title,
+    CASE WHEN json_valid(author) THEN (SELECT group_concat(value, ' ') FROM json_each(manga.author)) END,
+    CASE WHEN json_valid(genre) THEN (SELECT group_concat(value, ' ') FROM json_each(manga.genre)) END,
+    description
+FROM manga;
@@ -0,0 +1,5 @@
+ALTER TABLE user_library ADD COLUMN date_added TIMESTAMP;
+
+UPDATE user_library SET date_added = (
+    SELECT manga.date_added FROM manga WHERE manga.id = user_library.manga_id
+);
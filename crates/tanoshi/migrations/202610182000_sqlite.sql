@@ -0,0 +1 @@
+ALTER TABLE user_category ADD COLUMN rule TEXT;
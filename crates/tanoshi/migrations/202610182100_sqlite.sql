@@ -0,0 +1,16 @@
+CREATE TABLE manga_override (
+    user_id INTEGER NOT NULL,
+    manga_id INTEGER NOT NULL,
+    -- NULL columns are not overridden and fall back to what the source returns
+    title TEXT,
+    author TEXT,
+    genre TEXT,
+    status TEXT,
+    description TEXT,
+    -- path of an uploaded cover under the cover directory
+    cover_path TEXT,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, manga_id),
+    FOREIGN KEY (user_id) REFERENCES user(id) ON DELETE CASCADE,
+    FOREIGN KEY (manga_id) REFERENCES manga(id) ON DELETE CASCADE
+);
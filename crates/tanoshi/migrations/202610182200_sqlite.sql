@@ -0,0 +1,20 @@
+CREATE TABLE user_manga_tag (
+    user_id INTEGER NOT NULL,
+    manga_id INTEGER NOT NULL,
+    tag TEXT NOT NULL COLLATE NOCASE,
+    PRIMARY KEY (user_id, manga_id, tag),
+    FOREIGN KEY (user_id) REFERENCES user(id) ON DELETE CASCADE,
+    FOREIGN KEY (manga_id) REFERENCES manga(id) ON DELETE CASCADE
+);
+CREATE INDEX idx_user_manga_tag_user_id_tag ON user_manga_tag(user_id, tag);
+
+CREATE TABLE user_manga_note (
+    user_id INTEGER NOT NULL,
+    manga_id INTEGER NOT NULL,
+    rating INTEGER CHECK (rating BETWEEN 1 AND 10),
+    note TEXT,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, manga_id),
+    FOREIGN KEY (user_id) REFERENCES user(id) ON DELETE CASCADE,
+    FOREIGN KEY (manga_id) REFERENCES manga(id) ON DELETE CASCADE
+);
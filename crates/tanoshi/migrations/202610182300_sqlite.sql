@@ -0,0 +1,61 @@
+CREATE TABLE manga_scanlator_setting (
+    manga_id INTEGER PRIMARY KEY,
+    -- collapse chapters with equal number to the preferred scanlator
+    dedup BOOLEAN NOT NULL DEFAULT false,
+    -- json array of scanlators, first is most preferred
+    priority TEXT NOT NULL DEFAULT '[]',
+    -- json array of scanlators whose chapters are hidden
+    blocklist TEXT NOT NULL DEFAULT '[]',
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (manga_id) REFERENCES manga(id) ON DELETE CASCADE
+);
+
+-- chapters after applying scanlator setting, has the same columns as chapter
+CREATE VIEW visible_chapter AS
+SELECT
+    chapter.id,
+    chapter.source_id,
+    chapter.manga_id,
+    chapter.title,
+    chapter.path,
+    chapter.number,
+    chapter.scanlator,
+    chapter.uploaded,
+    chapter.date_added,
+    chapter.downloaded_path
+FROM chapter
+LEFT JOIN manga_scanlator_setting setting ON setting.manga_id = chapter.manga_id
+WHERE setting.manga_id IS NULL OR (
+    NOT EXISTS (
+        SELECT 1 FROM json_each(setting.blocklist)
+        WHERE json_each.value = chapter.scanlator COLLATE NOCASE
+    )
+    AND (
+        NOT setting.dedup OR NOT EXISTS (
+            SELECT 1 FROM chapter other
+            WHERE other.manga_id = chapter.manga_id
+                AND other.number = chapter.number
+                AND other.id <> chapter.id
+                AND NOT EXISTS (
+                    SELECT 1 FROM json_each(setting.blocklist)
+                    WHERE json_each.value = other.scanlator COLLATE NOCASE
+                )
+                -- unlisted scanlators rank last, ties go to the earliest upload
+                AND (
+                    IFNULL(
+                        (SELECT json_each.key FROM json_each(setting.priority) WHERE json_each.value = other.scanlator COLLATE NOCASE),
+                        json_array_length(setting.priority)
+                    ),
+                    other.uploaded,
+                    other.id
+                ) < (
+                    IFNULL(
+                        (SELECT json_each.key FROM json_each(setting.priority) WHERE json_each.value = chapter.scanlator COLLATE NOCASE),
+                        json_array_length(setting.priority)
+                    ),
+                    chapter.uploaded,
+                    chapter.id
+                )
+        )
+    )
+);
@@ -0,0 +1,13 @@
+-- extension repositories added by admin, repositories from config are not stored
+CREATE TABLE extension_repository (
+    name TEXT PRIMARY KEY,
+    url TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- repository an installed source came from, used to update it from the same repository
+CREATE TABLE source_repository (
+    source_id INTEGER PRIMARY KEY,
+    repository TEXT NOT NULL,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,7 @@
+-- cache hit and miss counters, kept across restarts
+CREATE TABLE image_cache_stats (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    hits INTEGER NOT NULL DEFAULT 0,
+    misses INTEGER NOT NULL DEFAULT 0
+);
+INSERT INTO image_cache_stats(id) VALUES (1);
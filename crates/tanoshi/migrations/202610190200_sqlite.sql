@@ -0,0 +1,100 @@
+-- search index reads its content from this view, author and genre are json arrays
+-- which the tokenizer splits into plain words. title and author overrides of every
+-- user are indexed in their own columns, matches on them are limited to the user
+-- who set them when searching
+CREATE VIEW manga_search AS
+SELECT
+    manga.id AS id,
+    manga.title AS title,
+    manga.author AS author,
+    manga.genre AS genre,
+    manga.description AS description,
+    (
+        SELECT group_concat(title, ' ') FROM (
+            SELECT title FROM manga_override
+            WHERE manga_override.manga_id = manga.id AND title IS NOT NULL
+            ORDER BY user_id
+        )
+    ) AS override_title,
+    (
+        SELECT group_concat(author, ' ') FROM (
+            SELECT author FROM manga_override
+            WHERE manga_override.manga_id = manga.id AND author IS NOT NULL
+            ORDER BY user_id
+        )
+    ) AS override_author
+FROM manga;
+
+DROP TABLE manga_fts;
+
+CREATE VIRTUAL TABLE manga_fts USING fts5(
+    title,
+    author,
+    genre,
+    description,
+    override_title,
+    override_author,
+    content = 'manga_search',
+    content_rowid = 'id',
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+INSERT INTO manga_fts(manga_fts) VALUES ('rebuild');
+
+-- external content tables are not updated by themselves, old values are removed
+-- before a change while the view still returns them and new values are added after
+CREATE TRIGGER manga_fts_after_insert AFTER INSERT ON manga BEGIN
+    INSERT INTO manga_fts(rowid, title, author, genre, description, override_title, override_author)
+    SELECT id, title, author, genre, description, override_title, override_author FROM manga_search WHERE id = new.id;
+END;
+
+CREATE TRIGGER manga_fts_before_update BEFORE UPDATE ON manga BEGIN
+    INSERT INTO manga_fts(manga_fts, rowid, title, author, genre, description, override_title, override_author)
+    SELECT 'delete', id, title, author, genre, description, override_title, override_author FROM manga_search WHERE id = old.id;
+END;
+
+CREATE TRIGGER manga_fts_after_update AFTER UPDATE ON manga BEGIN
+    INSERT INTO manga_fts(rowid, title, author, genre, description, override_title, override_author)
+    SELECT id, title, author, genre, description, override_title, override_author FROM manga_search WHERE id = new.id;
+END;
+
+CREATE TRIGGER manga_fts_before_delete BEFORE DELETE ON manga BEGIN
+    INSERT INTO manga_fts(manga_fts, rowid, title, author, genre, description, override_title, override_author)
+    SELECT 'delete', id, title, author, genre, description, override_title, override_author FROM manga_search WHERE id = old.id;
+END;
+
+-- an upsert that hits an existing override fires BEFORE INSERT and then the update
+-- triggers, only remove the old values when the override is really new
+CREATE TRIGGER manga_override_fts_before_insert BEFORE INSERT ON manga_override
+WHEN NOT EXISTS (SELECT 1 FROM manga_override WHERE user_id = new.user_id AND manga_id = new.manga_id)
+BEGIN
+    INSERT INTO manga_fts(manga_fts, rowid, title, author, genre, description, override_title, override_author)
+    SELECT 'delete', id, title, author, genre, description, override_title, override_author FROM manga_search WHERE id = new.manga_id;
+END;
+
+CREATE TRIGGER manga_override_fts_after_insert AFTER INSERT ON manga_override BEGIN
+    INSERT INTO manga_fts(rowid, title, author, genre, description, override_title, override_author)
+    SELECT id, title, author, genre, description, override_title, override_author FROM manga_search WHERE id = new.manga_id;
+END;
+
+CREATE TRIGGER manga_override_fts_before_update BEFORE UPDATE ON manga_override BEGIN
+    INSERT INTO manga_fts(manga_fts, rowid, title, author, genre, description, override_title, override_author)
+    SELECT 'delete', id, title, author, genre, description, override_title, override_author FROM manga_search WHERE id = old.manga_id;
+END;
+
+CREATE TRIGGER manga_override_fts_after_update AFTER UPDATE ON manga_override BEGIN
+    INSERT INTO manga_fts(rowid, title, author, genre, description, override_title, override_author)
+    SELECT id, title, author, genre, description, override_title, override_author FROM manga_search WHERE id = new.manga_id;
+END;
+
+-- overrides removed by deleting their manga find no row in the view, the manga
+-- trigger has removed it from the index already
+CREATE TRIGGER manga_override_fts_before_delete BEFORE DELETE ON manga_override BEGIN
+    INSERT INTO manga_fts(manga_fts, rowid, title, author, genre, description, override_title, override_author)
+    SELECT 'delete', id, title, author, genre, description, override_title, override_author FROM manga_search WHERE id = old.manga_id;
+END;
+
+CREATE TRIGGER manga_override_fts_after_delete AFTER DELETE ON manga_override BEGIN
+    INSERT INTO manga_fts(rowid, title, author, genre, description, override_title, override_author)
+    SELECT id, title, author, genre, description, override_title, override_author FROM manga_search WHERE id = old.manga_id;
+END;
@@ -0,0 +1,73 @@
+-- overrides are personal, they move out of the shared search index into their own
+-- index that keeps the user of each override, so a search only matches overrides
+-- of the user who searches
+DROP TRIGGER manga_fts_after_insert;
+DROP TRIGGER manga_fts_before_update;
+DROP TRIGGER manga_fts_after_update;
+DROP TRIGGER manga_fts_before_delete;
+DROP TRIGGER manga_override_fts_before_insert;
+DROP TRIGGER manga_override_fts_after_insert;
+DROP TRIGGER manga_override_fts_before_update;
+DROP TRIGGER manga_override_fts_after_update;
+DROP TRIGGER manga_override_fts_before_delete;
+DROP TRIGGER manga_override_fts_after_delete;
+
+DROP TABLE manga_fts;
+DROP VIEW manga_search;
+
+-- author and genre are json arrays which the tokenizer splits into plain words
+CREATE VIRTUAL TABLE manga_fts USING fts5(
+    title,
+    author,
+    genre,
+    description,
+    content = 'manga',
+    content_rowid = 'id',
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+INSERT INTO manga_fts(manga_fts) VALUES ('rebuild');
+
+CREATE TRIGGER manga_fts_after_insert AFTER INSERT ON manga BEGIN
+    INSERT INTO manga_fts(rowid, title, author, genre, description)
+    VALUES (new.id, new.title, new.author, new.genre, new.description);
+END;
+
+CREATE TRIGGER manga_fts_after_update AFTER UPDATE ON manga BEGIN
+    INSERT INTO manga_fts(manga_fts, rowid, title, author, genre, description)
+    VALUES ('delete', old.id, old.title, old.author, old.genre, old.description);
+    INSERT INTO manga_fts(rowid, title, author, genre, description)
+    VALUES (new.id, new.title, new.author, new.genre, new.description);
+END;
+
+CREATE TRIGGER manga_fts_after_delete AFTER DELETE ON manga BEGIN
+    INSERT INTO manga_fts(manga_fts, rowid, title, author, genre, description)
+    VALUES ('delete', old.id, old.title, old.author, old.genre, old.description);
+END;
+
+-- manga_override has no integer key to use as rowid, rows are found by user and manga
+CREATE VIRTUAL TABLE manga_override_fts USING fts5(
+    user_id UNINDEXED,
+    manga_id UNINDEXED,
+    title,
+    author,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+INSERT INTO manga_override_fts(user_id, manga_id, title, author)
+SELECT user_id, manga_id, title, author FROM manga_override;
+
+CREATE TRIGGER manga_override_fts_after_insert AFTER INSERT ON manga_override BEGIN
+    INSERT INTO manga_override_fts(user_id, manga_id, title, author)
+    VALUES (new.user_id, new.manga_id, new.title, new.author);
+END;
+
+CREATE TRIGGER manga_override_fts_after_update AFTER UPDATE ON manga_override BEGIN
+    DELETE FROM manga_override_fts WHERE user_id = old.user_id AND manga_id = old.manga_id;
+    INSERT INTO manga_override_fts(user_id, manga_id, title, author)
+    VALUES (new.user_id, new.manga_id, new.title, new.author);
+END;
+
+CREATE TRIGGER manga_override_fts_after_delete AFTER DELETE ON manga_override BEGIN
+    DELETE FROM manga_override_fts WHERE user_id = old.user_id AND manga_id = old.manga_id;
+END;
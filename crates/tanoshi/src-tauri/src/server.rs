@@ -9,21 +9,31 @@ use tauri::{
 use tanoshi::{
   application::worker,
   domain::services::{
-    chapter::ChapterService, download::DownloadService, history::HistoryService,
-    image::ImageService, library::LibraryService, manga::MangaService, source::SourceService,
-    tracker::TrackerService, user::UserService,
+    api_key::ApiKeyService, audit_log::AuditLogService, chapter::ChapterService,
+    content_policy::ContentPolicyService, download::DownloadService, history::HistoryService,
+    http_profile::HttpProfileService, image::ImageService, library::LibraryService,
+    login_throttle::LoginThrottleService, manga::MangaService, migration::MigrationService,
+    oidc::OidcService, preference::PreferenceService, proxy_auth::ProxyAuthService,
+    session::SessionService, source::SourceService, statistics::StatisticsService,
+    totp::TotpService, tracker::TrackerService, user::UserService,
   },
   infrastructure::{
     config::{self, Config},
     database,
     domain::repositories::{
-      chapter::ChapterRepositoryImpl, download::DownloadRepositoryImpl,
-      history::HistoryRepositoryImpl, image::ImageRepositoryImpl,
-      image_cache::ImageCacheRepositoryImpl, library::LibraryRepositoryImpl,
-      manga::MangaRepositoryImpl, source::SourceRepositoryImpl, tracker::TrackerRepositoryImpl,
-      user::UserRepositoryImpl,
+      api_key::ApiKeyRepositoryImpl, audit_log::AuditLogRepositoryImpl,
+      chapter::ChapterRepositoryImpl, content_policy::ContentPolicyRepositoryImpl,
+      download::DownloadRepositoryImpl, history::HistoryRepositoryImpl,
+      http_profile::HttpProfileRepositoryImpl, identity::IdentityRepositoryImpl,
+      image::ImageRepositoryImpl, image_cache::ImageCacheRepositoryImpl,
+      library::LibraryRepositoryImpl, manga::MangaRepositoryImpl,
+      migration::MigrationRepositoryImpl, preference::PreferenceRepositoryImpl,
+      session::SessionRepositoryImpl, source::SourceRepositoryImpl,
+      statistics::StatisticsRepositoryImpl, totp::TotpRepositoryImpl,
+      tracker::TrackerRepositoryImpl, user::UserRepositoryImpl,
     },
     local, notification,
+    oidc::OidcClient,
   },
   presentation::{graphql::schema::DatabaseLoader, ServerBuilder},
 };
@@ -65,12 +75,28 @@ impl<R: Runtime> Plugin<R> for Server {
       let user_repo = UserRepositoryImpl::new(pool.clone());
       let user_svc = UserService::new(user_repo.clone());
 
+      let session_repo = SessionRepositoryImpl::new(pool.clone());
+      let session_svc = SessionService::new(
+        session_repo,
+        user_repo.clone(),
+        &config.secret,
+        config.access_token_ttl,
+        config.refresh_token_ttl,
+      );
+
+      let api_key_repo = ApiKeyRepositoryImpl::new(pool.clone());
+      let api_key_svc = ApiKeyService::new(api_key_repo, user_repo.clone());
+
       let extension_manager = ExtensionManager::new(&config.plugin_path);
 
       let _ = extension_manager.load_all().await;
 
-      let source_repo = SourceRepositoryImpl::new(extension_manager.clone());
-      let source_svc = SourceService::new(source_repo);
+      let source_repo = SourceRepositoryImpl::new(pool.clone(), extension_manager.clone());
+      let source_svc = SourceService::new(
+        source_repo.clone(),
+        config.extension_repository_list(),
+        config.extension_trust_policy(),
+      );
 
       let manga_repo = MangaRepositoryImpl::new(pool.clone());
       let manga_svc = MangaService::new(manga_repo.clone(), extension_manager.clone());
@@ -109,8 +135,31 @@ impl<R: Runtime> Plugin<R> for Server {
         }
       }
 
+      let migration_repo = MigrationRepositoryImpl::new(pool.clone());
+      let migration_svc = MigrationService::new(
+        library_repo.clone(),
+        manga_repo.clone(),
+        chapter_repo.clone(),
+        migration_repo,
+        extension_manager.clone(),
+      );
+
+      let http_profile_repo = HttpProfileRepositoryImpl::new(pool.clone());
+      let http_profile_svc = HttpProfileService::new(
+        http_profile_repo,
+        extension_manager.clone(),
+        config.http.default_profile(),
+        config.http.sources.clone(),
+      );
+      if let Err(e) = http_profile_svc.apply_all().await {
+        println!("failed to apply http profiles: {e}");
+      }
+
       let notifier = notification::Builder::new(user_repo.clone()).finish();
 
+      let image_cache_repo =
+        ImageCacheRepositoryImpl::new(pool.clone(), &config.cache_path, config.cache_max_size);
+
       let (chapter_update_receiver, chapter_update_command_tx, update_worker_handle) =
         worker::updates::start(
           config.update_interval,
@@ -119,8 +168,8 @@ impl<R: Runtime> Plugin<R> for Server {
           chapter_repo.clone(),
           extension_manager.clone(),
           notifier.clone(),
-          config.extension_repository.clone(),
-          &config.cache_path,
+          source_svc.clone(),
+          image_cache_repo.clone(),
         );
 
       let (download_sender, download_receiver) = worker::downloads::channel();
@@ -157,12 +206,61 @@ impl<R: Runtime> Plugin<R> for Server {
           AniList::new(&base_url, al_cfg.client_id.clone(), al_cfg.client_secret).ok()
         });
 
+      let oidc_client = match config.base_url.clone().zip(config.oidc.clone()) {
+        Some((base_url, oidc_cfg)) => match OidcClient::discover(&base_url, oidc_cfg).await {
+          Ok(client) => Some(client),
+          Err(e) => {
+            println!("failed to discover oidc provider: {e}");
+            None
+          }
+        },
+        None => None,
+      };
+
+      let identity_repo = IdentityRepositoryImpl::new(pool.clone());
+      let oidc_svc = OidcService::new(oidc_client, identity_repo, user_repo.clone());
+
+      let proxy_auth_svc = ProxyAuthService::new(config.proxy_auth.clone(), user_repo.clone());
+
+      let totp_repo = TotpRepositoryImpl::new(pool.clone());
+      let totp_svc = TotpService::new(totp_repo, user_repo.clone(), config.require_totp_for_admins);
+
+      let login_throttle_svc = LoginThrottleService::new(config.login_throttle.clone());
+
+      let audit_log_repo = AuditLogRepositoryImpl::new(pool.clone());
+      let audit_log_svc = AuditLogService::new(audit_log_repo);
+
+      let content_policy_repo = ContentPolicyRepositoryImpl::new(pool.clone());
+      let content_policy_svc = ContentPolicyService::new(content_policy_repo, source_repo);
+
+      let preference_repo = PreferenceRepositoryImpl::new(pool.clone());
+      let preference_svc = PreferenceService::new(preference_repo);
+
+      let statistics_repo = StatisticsRepositoryImpl::new(pool.clone());
+      let statistics_svc = StatisticsService::new(statistics_repo);
+
       let tracker_repo = TrackerRepositoryImpl::new(pool.clone(), mal_client.clone(), al_client);
       let tracker_svc = TrackerService::new(tracker_repo.clone());
 
-      let image_repo = ImageRepositoryImpl::new();
-      let image_cache_repo = ImageCacheRepositoryImpl::new(&config.cache_path);
-      let image_svc = ImageService::new(image_repo, image_cache_repo);
+      let image_repo =
+        ImageRepositoryImpl::new(extension_manager.clone(), config.http.image_hosts.clone());
+      let image_svc = ImageService::new(
+        image_repo,
+        image_cache_repo,
+        config.image_url_ttl,
+        &config.cover_path,
+      );
+
+      let (prefetch_sender, prefetch_receiver) = worker::prefetch::channel();
+
+      let prefetch_worker_handle = worker::prefetch::start(
+        chapter_repo.clone(),
+        image_svc.clone(),
+        extension_manager.clone(),
+        config.prefetch.concurrency,
+        config.prefetch.next_chapter_pages,
+        prefetch_receiver,
+      );
 
       let loader = DatabaseLoader::new(
         history_repo,
@@ -175,6 +273,16 @@ impl<R: Runtime> Plugin<R> for Server {
       let mut server_builder = ServerBuilder::new()
         .with_config(config.clone())
         .with_user_svc(user_svc)
+        .with_session_svc(session_svc)
+        .with_api_key_svc(api_key_svc)
+        .with_oidc_svc(oidc_svc)
+        .with_proxy_auth_svc(proxy_auth_svc)
+        .with_totp_svc(totp_svc)
+        .with_login_throttle_svc(login_throttle_svc)
+        .with_audit_log_svc(audit_log_svc)
+        .with_content_policy_svc(content_policy_svc)
+        .with_preference_svc(preference_svc)
+        .with_statistics_svc(statistics_svc)
         .with_tracker_svc(tracker_svc)
         .with_source_svc(source_svc)
         .with_manga_svc(manga_svc)
@@ -183,8 +291,11 @@ impl<R: Runtime> Plugin<R> for Server {
         .with_library_svc(libary_svc)
         .with_history_svc(history_svc)
         .with_download_svc(download_svc)
+        .with_http_profile_svc(http_profile_svc)
+        .with_migration_svc(migration_svc)
         .with_ext_manager(extension_manager)
         .with_download_tx(download_sender)
+        .with_prefetch_tx(prefetch_sender)
         .with_notifier(notifier)
         .with_chapter_update_receiver(chapter_update_receiver)
         .with_chapter_update_command_tx(chapter_update_command_tx)
@@ -206,6 +317,9 @@ impl<R: Runtime> Plugin<R> for Server {
           _ = download_worker_handle => {
               println!("download worker quit");
           }
+          _ = prefetch_worker_handle => {
+              println!("prefetch worker quit");
+          }
           _ = tokio::signal::ctrl_c() => {
               println!("ctrl+c signal");
           }
@@ -252,8 +252,12 @@ where
             .map(|s| s.url)
             .unwrap_or_default();
 
-        let contents = self
-            .client
+        let client = self
+            .ext
+            .http_client(queue.source_id)
+            .unwrap_or_else(|_| self.client.clone());
+
+        let contents = client
             .request(reqwest::Method::GET, url.clone())
             .header("referer", referrer)
             .send()
@@ -1,2 +1,3 @@
 pub mod downloads;
+pub mod prefetch;
 pub mod updates;
@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+
+use futures::StreamExt;
+use tanoshi_vm::extension::ExtensionManager;
+use tokio::{
+    sync::mpsc::{UnboundedReceiver, UnboundedSender},
+    task::JoinHandle,
+};
+
+use crate::domain::{
+    entities::{chapter::Chapter, image::ImageOrigin},
+    repositories::{
+        chapter::ChapterRepository, image::ImageRepository, image_cache::ImageCacheRepository,
+    },
+    services::image::ImageService,
+};
+
+pub type PrefetchSender = UnboundedSender<Command>;
+type PrefetchReceiver = UnboundedReceiver<Command>;
+
+#[derive(Debug)]
+pub enum Command {
+    /// Warm the cache for a chapter a user is reading, starting after `page`.
+    /// `pages` can be passed when the caller already fetched them.
+    Chapter {
+        user_id: i64,
+        chapter_id: i64,
+        page: usize,
+        pages: Option<Vec<String>>,
+    },
+}
+
+#[derive(Clone)]
+struct Prefetcher<C, I, R>
+where
+    C: ChapterRepository + Clone + 'static,
+    I: ImageCacheRepository + Clone + 'static,
+    R: ImageRepository + Clone + 'static,
+{
+    chapter_repo: C,
+    image_svc: ImageService<I, R>,
+    ext: ExtensionManager,
+    concurrency: usize,
+    next_chapter_pages: usize,
+}
+
+impl<C, I, R> Prefetcher<C, I, R>
+where
+    C: ChapterRepository + Clone + 'static,
+    I: ImageCacheRepository + Clone + 'static,
+    R: ImageRepository + Clone + 'static,
+{
+    async fn prefetch(
+        &self,
+        chapter_id: i64,
+        page: usize,
+        pages: Option<Vec<String>>,
+    ) -> Result<(), anyhow::Error> {
+        let chapter = self.chapter_repo.get_chapter_by_id(chapter_id).await?;
+        self.prefetch_chapter(&chapter, pages, page, usize::MAX)
+            .await?;
+
+        if let Some(next_chapter_id) = chapter.next {
+            let next_chapter = self.chapter_repo.get_chapter_by_id(next_chapter_id).await?;
+            self.prefetch_chapter(&next_chapter, None, 0, self.next_chapter_pages)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn prefetch_chapter(
+        &self,
+        chapter: &Chapter,
+        pages: Option<Vec<String>>,
+        skip: usize,
+        take: usize,
+    ) -> Result<(), anyhow::Error> {
+        // local and downloaded chapters are read from disk
+        if chapter.source_id >= 10000 || chapter.downloaded_path.is_some() || take == 0 {
+            return Ok(());
+        }
+
+        let pages = match pages {
+            Some(pages) => pages,
+            None => {
+                self.ext
+                    .get_pages(chapter.source_id, chapter.path.clone())
+                    .await?
+            }
+        };
+
+        let referer = self.ext.get_source_info(chapter.source_id)?.url;
+        let origin = ImageOrigin::page(chapter.source_id, chapter.manga_id);
+
+        futures::stream::iter(pages.into_iter().skip(skip).take(take))
+            .for_each_concurrent(self.concurrency, |url| {
+                let referer = &referer;
+                async move {
+                    if let Err(e) = self
+                        .image_svc
+                        .prefetch_image(&url, origin, Some(referer))
+                        .await
+                    {
+                        debug!("failed to prefetch {url}: {e}");
+                    }
+                }
+            })
+            .await;
+
+        Ok(())
+    }
+}
+
+pub struct PrefetchWorker<C, I, R>
+where
+    C: ChapterRepository + Clone + 'static,
+    I: ImageCacheRepository + Clone + 'static,
+    R: ImageRepository + Clone + 'static,
+{
+    prefetcher: Prefetcher<C, I, R>,
+    rx: PrefetchReceiver,
+    // one prefetch per user, keyed by user id with the chapter and page it is warming from
+    tasks: HashMap<i64, (i64, usize, JoinHandle<()>)>,
+}
+
+impl<C, I, R> PrefetchWorker<C, I, R>
+where
+    C: ChapterRepository + Clone + 'static,
+    I: ImageCacheRepository + Clone + 'static,
+    R: ImageRepository + Clone + 'static,
+{
+    pub fn new(
+        chapter_repo: C,
+        image_svc: ImageService<I, R>,
+        ext: ExtensionManager,
+        concurrency: usize,
+        next_chapter_pages: usize,
+        rx: PrefetchReceiver,
+    ) -> Self {
+        Self {
+            prefetcher: Prefetcher {
+                chapter_repo,
+                image_svc,
+                ext,
+                concurrency: concurrency.max(1),
+                next_chapter_pages,
+            },
+            rx,
+            tasks: HashMap::new(),
+        }
+    }
+
+    pub async fn run(mut self) {
+        while let Some(cmd) = self.rx.recv().await {
+            match cmd {
+                Command::Chapter {
+                    user_id,
+                    chapter_id,
+                    page,
+                    pages,
+                } => {
+                    // finished prefetch is not kept, pages evicted since then are warmed again
+                    self.tasks.retain(|_, (_, _, handle)| !handle.is_finished());
+
+                    if let Some((prefetching_chapter_id, prefetching_page, handle)) =
+                        self.tasks.get(&user_id)
+                    {
+                        if *prefetching_chapter_id == chapter_id && *prefetching_page == page {
+                            continue;
+                        }
+
+                        // user moved to another chapter or page, stop warming from the previous one
+                        handle.abort();
+                    }
+
+                    let prefetcher = self.prefetcher.clone();
+                    let handle = tokio::spawn(async move {
+                        if let Err(e) = prefetcher.prefetch(chapter_id, page, pages).await {
+                            error!("failed to prefetch chapter {chapter_id}: {e}");
+                        }
+                    });
+
+                    self.tasks.insert(user_id, (chapter_id, page, handle));
+                }
+            }
+        }
+    }
+}
+
+pub fn channel() -> (PrefetchSender, PrefetchReceiver) {
+    tokio::sync::mpsc::unbounded_channel::<Command>()
+}
+
+pub fn start<C, I, R>(
+    chapter_repo: C,
+    image_svc: ImageService<I, R>,
+    ext: ExtensionManager,
+    concurrency: usize,
+    next_chapter_pages: usize,
+    prefetch_receiver: PrefetchReceiver,
+) -> JoinHandle<()>
+where
+    C: ChapterRepository + Clone + 'static,
+    I: ImageCacheRepository + Clone + 'static,
+    R: ImageRepository + Clone + 'static,
+{
+    let prefetch_worker = PrefetchWorker::new(
+        chapter_repo,
+        image_svc,
+        ext,
+        concurrency,
+        next_chapter_pages,
+        prefetch_receiver,
+    );
+
+    tokio::spawn(prefetch_worker.run())
+}
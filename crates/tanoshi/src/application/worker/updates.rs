@@ -1,9 +1,4 @@
-use std::{
-    collections::{HashMap, HashSet},
-    fmt::Display,
-    path::{Path, PathBuf},
-    str::FromStr,
-};
+use std::{collections::HashSet, fmt::Display, str::FromStr};
 
 use futures::StreamExt;
 use rayon::prelude::*;
@@ -16,10 +11,15 @@ use crate::{
     domain::{
         entities::{chapter::Chapter, manga::Manga},
         repositories::{
-            chapter::ChapterRepository, library::LibraryRepository, manga::MangaRepository,
+            chapter::ChapterRepository, image_cache::ImageCacheRepository,
+            library::LibraryRepository, manga::MangaRepository,
         },
+        services::source::SourceService,
+    },
+    infrastructure::{
+        domain::repositories::{source::SourceRepositoryImpl, user::UserRepositoryImpl},
+        notification::Notification,
     },
-    infrastructure::{domain::repositories::user::UserRepositoryImpl, notification::Notification},
 };
 use tokio::{
     task::JoinHandle,
@@ -57,21 +57,12 @@ impl Display for ChapterUpdateCommand {
 pub type ChapterUpdateCommandReceiver = flume::Receiver<ChapterUpdateCommand>;
 pub type ChapterUpdateCommandSender = flume::Sender<ChapterUpdateCommand>;
 
-#[derive(Debug, Clone, Deserialize)]
-pub struct SourceInfo {
-    pub id: i64,
-    pub name: String,
-    pub url: String,
-    pub version: String,
-    pub icon: String,
-    pub nsfw: bool,
-}
-
-struct UpdatesWorker<C, M, L>
+struct UpdatesWorker<C, M, L, I>
 where
     C: ChapterRepository + 'static,
     M: MangaRepository + 'static,
     L: LibraryRepository + 'static,
+    I: ImageCacheRepository + 'static,
 {
     period: u64,
     client: reqwest::Client,
@@ -80,28 +71,29 @@ where
     chapter_repo: C,
     extensions: ExtensionManager,
     notifier: Notification<UserRepositoryImpl>,
-    extension_repository: String,
-    cache_path: PathBuf,
+    source_svc: SourceService<SourceRepositoryImpl>,
+    image_cache_repo: I,
     broadcast_tx: ChapterUpdateSender,
     command_rx: ChapterUpdateCommandReceiver,
 }
 
-impl<C, M, L> UpdatesWorker<C, M, L>
+impl<C, M, L, I> UpdatesWorker<C, M, L, I>
 where
     C: ChapterRepository + 'static,
     M: MangaRepository + 'static,
     L: LibraryRepository + 'static,
+    I: ImageCacheRepository + 'static,
 {
-    fn new<P: AsRef<Path>>(
+    fn new(
         period: u64,
         library_repo: L,
         manga_repo: M,
         chapter_repo: C,
         extensions: ExtensionManager,
         notifier: Notification<UserRepositoryImpl>,
-        extension_repository: String,
+        source_svc: SourceService<SourceRepositoryImpl>,
         broadcast_tx: ChapterUpdateSender,
-        cache_path: P,
+        image_cache_repo: I,
     ) -> (Self, ChapterUpdateCommandSender) {
         #[cfg(not(debug_assertions))]
         let period = if period > 0 && period < 3600 {
@@ -122,8 +114,8 @@ where
                 chapter_repo,
                 extensions,
                 notifier,
-                extension_repository,
-                cache_path: PathBuf::new().join(cache_path),
+                source_svc,
+                image_cache_repo,
                 broadcast_tx,
                 command_rx,
             },
@@ -297,40 +289,23 @@ where
     }
 
     async fn check_extension_update(&self) -> Result<(), anyhow::Error> {
-        let url = format!("{}/index.json", self.extension_repository);
+        let installed_sources = self.source_svc.get_installed_sources(true).await?;
 
-        let available_sources_map = self
-            .client
-            .get(&url)
-            .send()
-            .await?
-            .json::<Vec<SourceInfo>>()
-            .await?
-            .into_par_iter()
-            .map(|source| (source.id, source))
-            .collect::<HashMap<i64, SourceInfo>>();
-
-        let installed_sources = self.extensions.list().await?;
-
-        for source in installed_sources {
-            if available_sources_map
-                .get(&source.id)
-                .and_then(|index| Version::from_str(&index.version).ok())
-                .map(|v| v > Version::from_str(source.version).unwrap_or_default())
-                .unwrap_or(false)
-            {
-                let message = format!("{} extension update available", source.name);
-                if let Err(e) = self.notifier.send_all_to_admins(None, &message).await {
-                    error!("failed to send extension update to admin, {}", e);
-                }
+        for source in installed_sources
+            .into_iter()
+            .filter(|source| source.has_update)
+        {
+            let message = format!("{} extension update available", source.name);
+            if let Err(e) = self.notifier.send_all_to_admins(None, &message).await {
+                error!("failed to send extension update to admin, {}", e);
+            }
 
-                #[cfg(feature = "desktop")]
-                if let Err(e) = self
-                    .notifier
-                    .send_desktop_notification(Some("Extension Update".to_string()), &message)
-                {
-                    error!("failed to send notification, reason {}", e);
-                }
+            #[cfg(feature = "desktop")]
+            if let Err(e) = self
+                .notifier
+                .send_desktop_notification(Some("Extension Update".to_string()), &message)
+            {
+                error!("failed to send notification, reason {}", e);
             }
         }
 
@@ -372,27 +347,8 @@ where
     }
 
     async fn clear_cache(&self) -> Result<(), anyhow::Error> {
-        let mut read_dir = tokio::fs::read_dir(&self.cache_path).await?;
-        while let Ok(Some(entry)) = read_dir.next_entry().await {
-            if let Some(created) = entry
-                .metadata()
-                .await?
-                .created()
-                .ok()
-                .and_then(|created| created.elapsed().ok())
-                .map(|elapsed| {
-                    chrono::Duration::from_std(elapsed)
-                        .unwrap_or_else(|_| chrono::Duration::max_value())
-                })
-            {
-                if created.num_days() >= 10 {
-                    info!("removing {}", entry.path().display());
-                    if let Err(e) = tokio::fs::remove_file(entry.path()).await {
-                        error!("failed to remove {}: {e}", entry.path().display());
-                    }
-                }
-            }
-        }
+        let removed = self.image_cache_repo.evict().await?;
+        info!("removed {removed} cached images");
 
         Ok(())
     }
@@ -401,7 +357,7 @@ where
         let period = if self.period == 0 { 3600 } else { self.period };
         let mut chapter_update_interval = time::interval(time::Duration::from_secs(period));
         let mut server_update_interval = time::interval(time::Duration::from_secs(86400));
-        let mut clear_cache_interval = time::interval(time::Duration::from_secs(3 * 86400));
+        let mut clear_cache_interval = time::interval(time::Duration::from_secs(86400));
 
         loop {
             tokio::select! {
@@ -470,15 +426,15 @@ where
     }
 }
 
-pub fn start<C, M, L, P>(
+pub fn start<C, M, L, I>(
     period: u64,
     library_repo: L,
     manga_repo: M,
     chapter_repo: C,
     extensions: ExtensionManager,
     notifier: Notification<UserRepositoryImpl>,
-    extension_repository: String,
-    cache_path: P,
+    source_svc: SourceService<SourceRepositoryImpl>,
+    image_cache_repo: I,
 ) -> (
     ChapterUpdateReceiver,
     ChapterUpdateCommandSender,
@@ -488,7 +444,7 @@ where
     C: ChapterRepository + 'static,
     M: MangaRepository + 'static,
     L: LibraryRepository + 'static,
-    P: AsRef<Path>,
+    I: ImageCacheRepository + 'static,
 {
     let (broadcast_tx, broadcast_rx) = tokio::sync::broadcast::channel(10);
     let (worker, command_tx) = UpdatesWorker::new(
@@ -498,9 +454,9 @@ where
         chapter_repo,
         extensions,
         notifier,
-        extension_repository,
+        source_svc,
         broadcast_tx,
-        cache_path,
+        image_cache_repo,
     );
 
     let handle = tokio::spawn(worker.run());
@@ -0,0 +1,58 @@
+use std::{fmt, str::FromStr};
+
+use chrono::NaiveDateTime;
+
+/// Permission granted to an api key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ApiKeyScope {
+    LibraryRead,
+    HistoryWrite,
+    DownloadsAdmin,
+}
+
+impl ApiKeyScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiKeyScope::LibraryRead => "library:read",
+            ApiKeyScope::HistoryWrite => "history:write",
+            ApiKeyScope::DownloadsAdmin => "downloads:admin",
+        }
+    }
+
+    /// Scope can only be used by admin
+    pub fn requires_admin(&self) -> bool {
+        matches!(self, ApiKeyScope::DownloadsAdmin)
+    }
+}
+
+impl fmt::Display for ApiKeyScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ApiKeyScope {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "library:read" => Ok(ApiKeyScope::LibraryRead),
+            "history:write" => Ok(ApiKeyScope::HistoryWrite),
+            "downloads:admin" => Ok(ApiKeyScope::DownloadsAdmin),
+            _ => Err(anyhow::anyhow!("unknown scope {s}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ApiKey {
+    pub id: i64,
+    pub user_id: i64,
+    pub name: String,
+    /// first characters of the key, to tell keys apart
+    pub prefix: String,
+    pub scopes: Vec<ApiKeyScope>,
+    pub created_at: NaiveDateTime,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub expires_at: Option<NaiveDateTime>,
+}
@@ -0,0 +1,70 @@
+use std::fmt;
+
+use chrono::NaiveDateTime;
+
+/// Action recorded in the audit log
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    Login,
+    LoginFailed,
+    LoginLocked,
+    Register,
+    DeleteUser,
+    ResetUserTotp,
+    SetUserContentPolicy,
+    InstallSource,
+    UninstallSource,
+    UpdateSource,
+    SetSourcePreferences,
+    SetSourceHttpProfile,
+    ResetSourceHttpProfile,
+    AddExtensionRepository,
+    RemoveExtensionRepository,
+    RemoveChapter,
+    RemoveDownloadedChapters,
+    PurgeImageCache,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::Login => "login",
+            AuditAction::LoginFailed => "login_failed",
+            AuditAction::LoginLocked => "login_locked",
+            AuditAction::Register => "register",
+            AuditAction::DeleteUser => "delete_user",
+            AuditAction::ResetUserTotp => "reset_user_totp",
+            AuditAction::SetUserContentPolicy => "set_user_content_policy",
+            AuditAction::InstallSource => "install_source",
+            AuditAction::UninstallSource => "uninstall_source",
+            AuditAction::UpdateSource => "update_source",
+            AuditAction::SetSourcePreferences => "set_source_preferences",
+            AuditAction::SetSourceHttpProfile => "set_source_http_profile",
+            AuditAction::ResetSourceHttpProfile => "reset_source_http_profile",
+            AuditAction::AddExtensionRepository => "add_extension_repository",
+            AuditAction::RemoveExtensionRepository => "remove_extension_repository",
+            AuditAction::RemoveChapter => "remove_chapter",
+            AuditAction::RemoveDownloadedChapters => "remove_downloaded_chapters",
+            AuditAction::PurgeImageCache => "purge_image_cache",
+        }
+    }
+}
+
+impl fmt::Display for AuditAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    pub id: i64,
+    /// None if the user is unknown, e.g. failed login with a wrong username
+    pub user_id: Option<i64>,
+    pub username: Option<String>,
+    pub action: String,
+    /// What the action was performed on, e.g. user or source id
+    pub target: Option<String>,
+    pub ip: Option<String>,
+    pub created_at: NaiveDateTime,
+}
@@ -0,0 +1,601 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::{anyhow, bail};
+
+/// Longest rule text accepted, in bytes
+pub const MAX_RULE_LENGTH: usize = 1024;
+/// Deepest nesting of AND, OR, NOT and parentheses accepted
+pub const MAX_RULE_DEPTH: usize = 32;
+
+/// Manga attribute a smart category rule can test
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleField {
+    /// number of unread chapters
+    Unread,
+    /// number of downloaded chapters
+    Downloaded,
+    /// whether manga is linked to any tracker
+    Tracked,
+    Status,
+    /// source id
+    Source,
+    Title,
+    /// matches if any of manga genre matches
+    Genre,
+    /// matches if any of manga author matches
+    Author,
+    /// days since a chapter was last read, never read manga don't match
+    DaysSinceRead,
+    /// days since the newest chapter was uploaded
+    DaysSinceUpdate,
+    /// days since manga was added to library
+    DaysSinceAdded,
+    /// matches if any of user's tags on manga matches
+    Tag,
+    /// user's rating from 1 to 10, unrated manga don't match
+    Rating,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleFieldType {
+    Number,
+    Text,
+    Boolean,
+}
+
+impl RuleField {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RuleField::Unread => "unread",
+            RuleField::Downloaded => "downloaded",
+            RuleField::Tracked => "tracked",
+            RuleField::Status => "status",
+            RuleField::Source => "source",
+            RuleField::Title => "title",
+            RuleField::Genre => "genre",
+            RuleField::Author => "author",
+            RuleField::DaysSinceRead => "days_since_read",
+            RuleField::DaysSinceUpdate => "days_since_update",
+            RuleField::DaysSinceAdded => "days_since_added",
+            RuleField::Tag => "tag",
+            RuleField::Rating => "rating",
+        }
+    }
+
+    pub fn field_type(&self) -> RuleFieldType {
+        match self {
+            RuleField::Unread
+            | RuleField::Downloaded
+            | RuleField::Source
+            | RuleField::DaysSinceRead
+            | RuleField::DaysSinceUpdate
+            | RuleField::DaysSinceAdded
+            | RuleField::Rating => RuleFieldType::Number,
+            RuleField::Status
+            | RuleField::Title
+            | RuleField::Genre
+            | RuleField::Author
+            | RuleField::Tag => RuleFieldType::Text,
+            RuleField::Tracked => RuleFieldType::Boolean,
+        }
+    }
+}
+
+impl FromStr for RuleField {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "unread" => Ok(RuleField::Unread),
+            "downloaded" => Ok(RuleField::Downloaded),
+            "tracked" => Ok(RuleField::Tracked),
+            "status" => Ok(RuleField::Status),
+            "source" => Ok(RuleField::Source),
+            "title" => Ok(RuleField::Title),
+            "genre" => Ok(RuleField::Genre),
+            "author" => Ok(RuleField::Author),
+            "days_since_read" => Ok(RuleField::DaysSinceRead),
+            "days_since_update" => Ok(RuleField::DaysSinceUpdate),
+            "days_since_added" => Ok(RuleField::DaysSinceAdded),
+            "tag" => Ok(RuleField::Tag),
+            "rating" => Ok(RuleField::Rating),
+            _ => Err(anyhow!("unknown field {s}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleOperator {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl RuleOperator {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RuleOperator::Eq => "=",
+            RuleOperator::Ne => "!=",
+            RuleOperator::Lt => "<",
+            RuleOperator::Le => "<=",
+            RuleOperator::Gt => ">",
+            RuleOperator::Ge => ">=",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleValue {
+    Number(i64),
+    Text(String),
+    Boolean(bool),
+}
+
+impl RuleValue {
+    fn value_type(&self) -> RuleFieldType {
+        match self {
+            RuleValue::Number(_) => RuleFieldType::Number,
+            RuleValue::Text(_) => RuleFieldType::Text,
+            RuleValue::Boolean(_) => RuleFieldType::Boolean,
+        }
+    }
+}
+
+impl fmt::Display for RuleValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleValue::Number(n) => write!(f, "{n}"),
+            RuleValue::Text(s) => write!(f, "\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\"")),
+            RuleValue::Boolean(b) => write!(f, "{b}"),
+        }
+    }
+}
+
+/// Filter expression of a smart category, e.g.
+/// `unread > 0 AND status = "Ongoing" AND source IN (1, 2)` or `days_since_read >= 60`.
+///
+/// Text comparisons are case insensitive and text fields only support `=`, `!=` and `IN`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CategoryRule {
+    And(Box<CategoryRule>, Box<CategoryRule>),
+    Or(Box<CategoryRule>, Box<CategoryRule>),
+    Not(Box<CategoryRule>),
+    Compare {
+        field: RuleField,
+        operator: RuleOperator,
+        value: RuleValue,
+    },
+    In {
+        field: RuleField,
+        values: Vec<RuleValue>,
+    },
+}
+
+impl CategoryRule {
+    /// Number of nested AND, OR and NOT down to the deepest condition, a single condition is 1
+    pub fn depth(&self) -> usize {
+        match self {
+            CategoryRule::And(lhs, rhs) | CategoryRule::Or(lhs, rhs) => {
+                1 + lhs.depth().max(rhs.depth())
+            }
+            CategoryRule::Not(rule) => 1 + rule.depth(),
+            CategoryRule::Compare { .. } | CategoryRule::In { .. } => 1,
+        }
+    }
+}
+
+impl fmt::Display for CategoryRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CategoryRule::And(lhs, rhs) => write!(f, "({lhs} AND {rhs})"),
+            CategoryRule::Or(lhs, rhs) => write!(f, "({lhs} OR {rhs})"),
+            CategoryRule::Not(rule) => write!(f, "NOT {rule}"),
+            CategoryRule::Compare {
+                field,
+                operator,
+                value,
+            } => write!(f, "{} {} {value}", field.as_str(), operator.as_str()),
+            CategoryRule::In { field, values } => {
+                let values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+                write!(f, "{} IN ({})", field.as_str(), values.join(", "))
+            }
+        }
+    }
+}
+
+impl FromStr for CategoryRule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() > MAX_RULE_LENGTH {
+            bail!("rule is longer than {MAX_RULE_LENGTH} characters");
+        }
+
+        let tokens = tokenize(s)?;
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            depth: 0,
+        };
+        let rule = parser.parse_or()?;
+        if let Some(token) = parser.peek() {
+            bail!("unexpected {token:?}");
+        }
+        // a long chain of AND or OR nests as deep as parentheses do
+        if rule.depth() > MAX_RULE_DEPTH {
+            bail!("rule is nested deeper than {MAX_RULE_DEPTH} levels");
+        }
+
+        Ok(rule)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(i64),
+    Text(String),
+    Operator(RuleOperator),
+    LeftParen,
+    RightParen,
+    Comma,
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, anyhow::Error> {
+    let mut tokens = vec![];
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::LeftParen,
+            ')' => Token::RightParen,
+            ',' => Token::Comma,
+            '=' => Token::Operator(RuleOperator::Eq),
+            '!' if chars.next_if_eq(&'=').is_some() => Token::Operator(RuleOperator::Ne),
+            '<' if chars.next_if_eq(&'=').is_some() => Token::Operator(RuleOperator::Le),
+            '<' if chars.next_if_eq(&'>').is_some() => Token::Operator(RuleOperator::Ne),
+            '<' => Token::Operator(RuleOperator::Lt),
+            '>' if chars.next_if_eq(&'=').is_some() => Token::Operator(RuleOperator::Ge),
+            '>' => Token::Operator(RuleOperator::Gt),
+            '"' | '\'' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('\\') => text.extend(chars.next()),
+                        Some(end) if end == c => break,
+                        Some(ch) => text.push(ch),
+                        None => bail!("unterminated string"),
+                    }
+                }
+                Token::Text(text)
+            }
+            c if c.is_ascii_digit() || c == '-' => {
+                let mut number = c.to_string();
+                while let Some(digit) = chars.next_if(|ch| ch.is_ascii_digit()) {
+                    number.push(digit);
+                }
+                Token::Number(
+                    number
+                        .parse()
+                        .map_err(|_| anyhow!("invalid number {number}"))?,
+                )
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut ident = c.to_string();
+                while let Some(ch) = chars.next_if(|ch| ch.is_alphanumeric() || *ch == '_') {
+                    ident.push(ch);
+                }
+                Token::Ident(ident)
+            }
+            c => bail!("unexpected character {c}"),
+        };
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    /// current nesting of parentheses and NOT, bounded so parsing can't overflow stack
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn next_if_keyword(&mut self, keyword: &str) -> bool {
+        match self.peek() {
+            Some(Token::Ident(ident)) if ident.eq_ignore_ascii_case(keyword) => {
+                self.pos += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), anyhow::Error> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => bail!("expected {expected:?}, found {token:?}"),
+            None => bail!("expected {expected:?}, found end of rule"),
+        }
+    }
+
+    fn enter(&mut self) -> Result<(), anyhow::Error> {
+        self.depth += 1;
+        if self.depth > MAX_RULE_DEPTH {
+            bail!("rule is nested deeper than {MAX_RULE_DEPTH} levels");
+        }
+
+        Ok(())
+    }
+
+    fn parse_or(&mut self) -> Result<CategoryRule, anyhow::Error> {
+        self.enter()?;
+        let mut rule = self.parse_and()?;
+        while self.next_if_keyword("or") {
+            rule = CategoryRule::Or(Box::new(rule), Box::new(self.parse_and()?));
+        }
+        self.depth -= 1;
+
+        Ok(rule)
+    }
+
+    fn parse_and(&mut self) -> Result<CategoryRule, anyhow::Error> {
+        let mut rule = self.parse_not()?;
+        while self.next_if_keyword("and") {
+            rule = CategoryRule::And(Box::new(rule), Box::new(self.parse_not()?));
+        }
+
+        Ok(rule)
+    }
+
+    fn parse_not(&mut self) -> Result<CategoryRule, anyhow::Error> {
+        if self.next_if_keyword("not") {
+            self.enter()?;
+            let rule = CategoryRule::Not(Box::new(self.parse_not()?));
+            self.depth -= 1;
+            return Ok(rule);
+        }
+
+        if self.peek() == Some(&Token::LeftParen) {
+            self.pos += 1;
+            let rule = self.parse_or()?;
+            self.expect(Token::RightParen)?;
+            return Ok(rule);
+        }
+
+        self.parse_condition()
+    }
+
+    fn parse_condition(&mut self) -> Result<CategoryRule, anyhow::Error> {
+        let field: RuleField = match self.next() {
+            Some(Token::Ident(ident)) => ident.parse()?,
+            Some(token) => bail!("expected field, found {token:?}"),
+            None => bail!("expected field, found end of rule"),
+        };
+
+        if self.next_if_keyword("in") {
+            self.expect(Token::LeftParen)?;
+            let mut values = vec![self.parse_value(field)?];
+            while self.peek() == Some(&Token::Comma) {
+                self.pos += 1;
+                values.push(self.parse_value(field)?);
+            }
+            self.expect(Token::RightParen)?;
+
+            return Ok(CategoryRule::In { field, values });
+        }
+
+        let operator = match self.next() {
+            Some(Token::Operator(operator)) => operator,
+            Some(token) => bail!("expected operator, found {token:?}"),
+            None => bail!("expected operator, found end of rule"),
+        };
+        if field.field_type() != RuleFieldType::Number
+            && !matches!(operator, RuleOperator::Eq | RuleOperator::Ne)
+        {
+            bail!("{} only supports = and !=", field.as_str());
+        }
+
+        let value = self.parse_value(field)?;
+
+        Ok(CategoryRule::Compare {
+            field,
+            operator,
+            value,
+        })
+    }
+
+    fn parse_value(&mut self, field: RuleField) -> Result<RuleValue, anyhow::Error> {
+        let value = match self.next() {
+            Some(Token::Number(n)) => RuleValue::Number(n),
+            Some(Token::Text(s)) => RuleValue::Text(s),
+            Some(Token::Ident(ident)) if ident.eq_ignore_ascii_case("true") => {
+                RuleValue::Boolean(true)
+            }
+            Some(Token::Ident(ident)) if ident.eq_ignore_ascii_case("false") => {
+                RuleValue::Boolean(false)
+            }
+            Some(token) => bail!("expected value, found {token:?}"),
+            None => bail!("expected value, found end of rule"),
+        };
+
+        if value.value_type() != field.field_type() {
+            bail!("invalid value {value} for {}", field.as_str());
+        }
+
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn compare(field: RuleField, operator: RuleOperator, value: RuleValue) -> CategoryRule {
+        CategoryRule::Compare {
+            field,
+            operator,
+            value,
+        }
+    }
+
+    #[test]
+    fn test_precedence() {
+        let unread = compare(RuleField::Unread, RuleOperator::Gt, RuleValue::Number(0));
+        let tracked = compare(
+            RuleField::Tracked,
+            RuleOperator::Eq,
+            RuleValue::Boolean(true),
+        );
+        let rating = compare(RuleField::Rating, RuleOperator::Ge, RuleValue::Number(5));
+
+        let rule: CategoryRule = "unread > 0 or tracked = true and rating >= 5"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            rule,
+            CategoryRule::Or(
+                Box::new(unread.clone()),
+                Box::new(CategoryRule::And(
+                    Box::new(tracked.clone()),
+                    Box::new(rating.clone())
+                ))
+            )
+        );
+
+        let rule: CategoryRule = "(unread > 0 OR tracked = true) AND rating >= 5"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            rule,
+            CategoryRule::And(
+                Box::new(CategoryRule::Or(
+                    Box::new(unread.clone()),
+                    Box::new(tracked.clone())
+                )),
+                Box::new(rating)
+            )
+        );
+
+        let rule: CategoryRule = "NOT unread > 0 AND tracked = true".parse().unwrap();
+        assert_eq!(
+            rule,
+            CategoryRule::And(
+                Box::new(CategoryRule::Not(Box::new(unread))),
+                Box::new(tracked)
+            )
+        );
+    }
+
+    #[test]
+    fn test_in() {
+        let rule: CategoryRule = "source IN (1, 2,3)".parse().unwrap();
+        assert_eq!(
+            rule,
+            CategoryRule::In {
+                field: RuleField::Source,
+                values: vec![
+                    RuleValue::Number(1),
+                    RuleValue::Number(2),
+                    RuleValue::Number(3)
+                ],
+            }
+        );
+
+        let rule: CategoryRule = r#"genre in ("Action")"#.parse().unwrap();
+        assert_eq!(
+            rule,
+            CategoryRule::In {
+                field: RuleField::Genre,
+                values: vec![RuleValue::Text("Action".to_string())],
+            }
+        );
+
+        assert!("source IN ()".parse::<CategoryRule>().is_err());
+        assert!("source IN (1, 2".parse::<CategoryRule>().is_err());
+    }
+
+    #[test]
+    fn test_quoting() {
+        let rule: CategoryRule = r#"title = "say \"hi\"""#.parse().unwrap();
+        assert_eq!(
+            rule,
+            compare(
+                RuleField::Title,
+                RuleOperator::Eq,
+                RuleValue::Text(r#"say "hi""#.to_string())
+            )
+        );
+
+        let rule: CategoryRule = r#"title != 'it\'s "ok"'"#.parse().unwrap();
+        assert_eq!(
+            rule,
+            compare(
+                RuleField::Title,
+                RuleOperator::Ne,
+                RuleValue::Text(r#"it's "ok""#.to_string())
+            )
+        );
+
+        // displayed rule parses back to the same rule
+        let rule: CategoryRule = r#"tag = "a\\b" OR NOT title = 'x "y"'"#.parse().unwrap();
+        assert_eq!(rule.to_string().parse::<CategoryRule>().unwrap(), rule);
+
+        assert!(r#"title = "unterminated"#.parse::<CategoryRule>().is_err());
+    }
+
+    #[test]
+    fn test_type_errors() {
+        assert!(r#"unread = "many""#.parse::<CategoryRule>().is_err());
+        assert!("tracked = 1".parse::<CategoryRule>().is_err());
+        assert!("title = true".parse::<CategoryRule>().is_err());
+        assert!(r#"title > "a""#.parse::<CategoryRule>().is_err());
+        assert!(r#"source IN (1, "2")"#.parse::<CategoryRule>().is_err());
+        assert!("unknown = 1".parse::<CategoryRule>().is_err());
+    }
+
+    #[test]
+    fn test_depth_cap() {
+        let nested = |depth: usize| {
+            format!(
+                "{}unread > 0{}",
+                "(".repeat(depth - 1),
+                ")".repeat(depth - 1)
+            )
+        };
+        assert!(nested(MAX_RULE_DEPTH).parse::<CategoryRule>().is_ok());
+        assert!(nested(MAX_RULE_DEPTH + 1).parse::<CategoryRule>().is_err());
+
+        let not = |depth: usize| format!("{}unread > 0", "NOT ".repeat(depth - 1));
+        assert!(not(MAX_RULE_DEPTH).parse::<CategoryRule>().is_ok());
+        assert!(not(MAX_RULE_DEPTH + 1).parse::<CategoryRule>().is_err());
+
+        let chain = |depth: usize| vec!["unread > 0"; depth].join(" OR ");
+        assert_eq!(
+            chain(MAX_RULE_DEPTH)
+                .parse::<CategoryRule>()
+                .unwrap()
+                .depth(),
+            MAX_RULE_DEPTH
+        );
+        assert!(chain(MAX_RULE_DEPTH + 1).parse::<CategoryRule>().is_err());
+
+        let long = format!("title = \"{}\"", "a".repeat(MAX_RULE_LENGTH));
+        assert!(long.parse::<CategoryRule>().is_err());
+    }
+}
@@ -34,3 +34,14 @@ impl From<tanoshi_lib::models::ChapterInfo> for Chapter {
         }
     }
 }
+
+/// Per manga scanlator preference, applied to chapter list, unread count, next chapter and auto download
+#[derive(Debug, Default, Clone)]
+pub struct ScanlatorSetting {
+    pub manga_id: i64,
+    /// collapse chapters with equal number to the preferred scanlator
+    pub dedup: bool,
+    /// first is most preferred, unlisted scanlators come last
+    pub priority: Vec<String>,
+    pub blocklist: Vec<String>,
+}
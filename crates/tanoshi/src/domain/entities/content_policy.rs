@@ -0,0 +1,55 @@
+use chrono::NaiveDateTime;
+
+/// Restrictions on what sources and manga a user can see, set by admins
+#[derive(Debug, Clone, Default)]
+pub struct ContentPolicy {
+    pub user_id: i64,
+    pub hide_nsfw: bool,
+    /// if set, only these sources are allowed
+    pub allowed_source_ids: Option<Vec<i64>>,
+    pub denied_source_ids: Vec<i64>,
+    /// manga with any of these genres are hidden, compared case insensitively
+    pub blocked_genres: Vec<String>,
+    pub updated_at: NaiveDateTime,
+}
+
+/// Content policy with nsfw sources resolved to source ids, so it can be applied by a query
+#[derive(Debug, Clone, Default)]
+pub struct ContentRestriction {
+    /// if set, only manga of these sources are allowed
+    pub allowed_source_ids: Option<Vec<i64>>,
+    pub denied_source_ids: Vec<i64>,
+    /// compared case insensitively
+    pub blocked_genres: Vec<String>,
+}
+
+impl ContentPolicy {
+    pub fn is_unrestricted(&self) -> bool {
+        !self.hide_nsfw
+            && self.allowed_source_ids.is_none()
+            && self.denied_source_ids.is_empty()
+            && self.blocked_genres.is_empty()
+    }
+
+    pub fn is_source_allowed(&self, source_id: i64, nsfw: bool) -> bool {
+        if self.hide_nsfw && nsfw {
+            return false;
+        }
+
+        if let Some(allowed_source_ids) = &self.allowed_source_ids {
+            if !allowed_source_ids.contains(&source_id) {
+                return false;
+            }
+        }
+
+        !self.denied_source_ids.contains(&source_id)
+    }
+
+    pub fn is_genre_allowed(&self, genre: &[String]) -> bool {
+        !genre.iter().any(|genre| {
+            self.blocked_genres
+                .iter()
+                .any(|blocked| blocked.trim().eq_ignore_ascii_case(genre.trim()))
+        })
+    }
+}
@@ -0,0 +1,11 @@
+use chrono::NaiveDateTime;
+
+/// Account of an external identity provider linked to a user
+#[derive(Debug, Clone)]
+pub struct UserIdentity {
+    pub id: i64,
+    pub user_id: i64,
+    pub issuer: String,
+    pub subject: String,
+    pub created_at: NaiveDateTime,
+}
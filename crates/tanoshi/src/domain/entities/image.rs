@@ -1,17 +1,64 @@
-use aes::cipher::{block_padding::Pkcs7, BlockDecryptMut, BlockEncryptMut, KeyIvInit};
-use anyhow::anyhow;
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm, Key, Nonce,
+};
+use anyhow::{anyhow, bail};
 use base64::{engine::general_purpose, Engine};
 use bytes::Bytes;
+use chrono::NaiveDateTime;
 use fancy_regex::Regex;
+use hmac::{Hmac, Mac};
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
-use std::convert::TryFrom;
+use sha2::{Digest, Sha256};
+use std::{
+    convert::{TryFrom, TryInto},
+    path::PathBuf,
+};
 
 use crate::infrastructure::local::SUPPORTED_FILES;
 
-// create an alias for convenience
-type Aes128CbcEnc = cbc::Encryptor<aes::Aes128>;
-type Aes128CbcDec = cbc::Decryptor<aes::Aes128>;
+type HmacSha256 = Hmac<Sha256>;
+
+const IMAGE_URL_VERSION: u8 = 1;
+/// version, expiry and user id
+const IMAGE_URL_HEADER_LEN: usize = 1 + 8 + 8;
+const IMAGE_URL_NONCE_LEN: usize = 12;
+const IMAGE_URL_MAC_LEN: usize = 32;
+
+const ENCRYPTION_KEY_PURPOSE: &str = "tanoshi image url encryption";
+const NONCE_KEY_PURPOSE: &str = "tanoshi image url nonce";
+const SIGNATURE_KEY_PURPOSE: &str = "tanoshi image url signature";
+
+/// Derive a key for one purpose so the secret itself is never used directly
+fn derive_key(secret: &str, purpose: &str) -> [u8; 32] {
+    Sha256::new()
+        .chain_update(purpose.as_bytes())
+        .chain_update(secret.as_bytes())
+        .finalize()
+        .into()
+}
+
+fn keyed_mac(secret: &str, purpose: &str) -> HmacSha256 {
+    <HmacSha256 as Mac>::new_from_slice(&derive_key(secret, purpose))
+        .expect("hmac accepts keys of any length")
+}
+
+fn cipher(secret: &str) -> Aes256Gcm {
+    Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&derive_key(
+        secret,
+        ENCRYPTION_KEY_PURPOSE,
+    )))
+}
+
+/// Signed part of an image url, readable by the server without decrypting the image uri
+#[derive(Debug, Clone, Copy)]
+pub struct ImageUrlClaims {
+    /// User the url was created for
+    pub user_id: i64,
+    /// Unix timestamp after which the url is rejected
+    pub expires_at: i64,
+}
 
 pub enum ImageUri {
     Remote(String),
@@ -51,38 +98,89 @@ impl TryFrom<&str> for ImageUri {
 }
 
 impl ImageUri {
-    pub fn from_encrypted(secret: &str, encrypted: &str) -> Result<Self, anyhow::Error> {
-        let mut decoded = general_purpose::URL_SAFE_NO_PAD.decode(encrypted)?;
-        trace!("decoded: {:?}", decoded);
+    /// Verify and decrypt an url created by [`ImageUri::into_signed`], expiry is left to the caller
+    pub fn from_signed(
+        secret: &str,
+        token: &str,
+    ) -> Result<(Self, ImageOrigin, ImageUrlClaims), anyhow::Error> {
+        let token = general_purpose::URL_SAFE_NO_PAD.decode(token)?;
+        if token.len() < IMAGE_URL_HEADER_LEN + IMAGE_URL_NONCE_LEN + IMAGE_URL_MAC_LEN
+            || token[0] != IMAGE_URL_VERSION
+        {
+            bail!("invalid image url");
+        }
 
-        let iv = [0_u8; 16];
+        let (signed, signature) = token.split_at(token.len() - IMAGE_URL_MAC_LEN);
+        let mut mac = keyed_mac(secret, SIGNATURE_KEY_PURPOSE);
+        mac.update(signed);
+        mac.verify_slice(signature)
+            .map_err(|_| anyhow!("invalid image url signature"))?;
 
-        let bytes = Aes128CbcDec::new(secret.as_bytes().into(), &iv.into())
-            .decrypt_padded_mut::<Pkcs7>(&mut decoded)
-            .map_err(|e| anyhow::anyhow!("error decrypt url {e}"))?
-            .to_vec();
+        let (header, rest) = signed.split_at(IMAGE_URL_HEADER_LEN);
+        let (nonce, ciphertext) = rest.split_at(IMAGE_URL_NONCE_LEN);
+        let claims = ImageUrlClaims {
+            expires_at: i64::from_be_bytes(header[1..9].try_into()?),
+            user_id: i64::from_be_bytes(header[9..17].try_into()?),
+        };
+
+        let plaintext = cipher(secret)
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: header,
+                },
+            )
+            .map_err(|e| anyhow!("error decrypt url {e}"))?;
 
-        let url = String::from_utf8(bytes)?;
+        let (origin, url): (ImageOrigin, String) = bincode::deserialize(&plaintext)?;
         let uri = ImageUri::try_from(url.as_str())?;
 
-        Ok(uri)
+        Ok((uri, origin, claims))
     }
 
-    pub fn into_encrypted(self, secret: &str) -> Result<String, anyhow::Error> {
-        let uri = self.to_string();
-        let pos = uri.len();
+    /// Encrypt the uri with its origin and sign it with the claims, the url consists of
+    /// version, expiry, user id, nonce, ciphertext and hmac of everything before it
+    pub fn into_signed(
+        self,
+        secret: &str,
+        origin: ImageOrigin,
+        claims: ImageUrlClaims,
+    ) -> Result<String, anyhow::Error> {
+        let plaintext = bincode::serialize(&(origin, self.to_string()))?;
 
-        let mut buffer = vec![0_u8; pos * 2];
-        buffer.splice(..pos, uri.as_bytes().to_vec());
+        let mut header = Vec::with_capacity(IMAGE_URL_HEADER_LEN);
+        header.push(IMAGE_URL_VERSION);
+        header.extend_from_slice(&claims.expires_at.to_be_bytes());
+        header.extend_from_slice(&claims.user_id.to_be_bytes());
 
-        let iv = [0_u8; 16];
-        let chipertext = Aes128CbcEnc::new(secret.as_bytes().into(), &iv.into())
-            .encrypt_padded_mut::<Pkcs7>(&mut buffer, pos)
+        // nonce is derived from the content so an image gets the same url until it expires
+        // and browsers can cache it, a nonce only repeats for the exact same plaintext
+        let mut nonce_mac = keyed_mac(secret, NONCE_KEY_PURPOSE);
+        nonce_mac.update(&header);
+        nonce_mac.update(&plaintext);
+        let nonce_bytes = nonce_mac.finalize().into_bytes();
+        let nonce = Nonce::from_slice(&nonce_bytes[..IMAGE_URL_NONCE_LEN]);
+
+        let ciphertext = cipher(secret)
+            .encrypt(
+                nonce,
+                Payload {
+                    msg: &plaintext,
+                    aad: &header,
+                },
+            )
             .map_err(|e| anyhow!("error encrypt url {e}"))?;
 
-        let encoded = general_purpose::URL_SAFE_NO_PAD.encode(chipertext);
+        let mut token = header;
+        token.extend_from_slice(nonce);
+        token.extend_from_slice(&ciphertext);
+
+        let mut mac = keyed_mac(secret, SIGNATURE_KEY_PURPOSE);
+        mac.update(&token);
+        token.extend_from_slice(&mac.finalize().into_bytes());
 
-        Ok(encoded)
+        Ok(general_purpose::URL_SAFE_NO_PAD.encode(token))
     }
 }
 
@@ -100,4 +198,241 @@ impl ToString for ImageUri {
 pub struct Image {
     pub content_type: String,
     pub data: Bytes,
+    /// Strong validator computed from the content hash
+    pub etag: String,
+    pub last_modified: Option<NaiveDateTime>,
+}
+
+impl Image {
+    pub fn new(content_type: String, data: Bytes, last_modified: Option<NaiveDateTime>) -> Self {
+        let etag = format!("\"{:x}\"", Sha256::digest(&data));
+
+        Self {
+            content_type,
+            data,
+            etag,
+            last_modified,
+        }
+    }
+}
+
+/// Content type of a local image by its file name
+pub fn content_type_from_path(path: &str) -> String {
+    mime_guess::from_path(path)
+        .first_or_octet_stream()
+        .to_string()
+}
+
+/// Size and modification time of a local file, enough to answer conditional and
+/// range requests without reading it
+#[derive(Debug, Clone)]
+pub struct ImageFileMetadata {
+    pub len: u64,
+    pub last_modified: Option<NaiveDateTime>,
+}
+
+impl ImageFileMetadata {
+    /// Validator from size and modification time, `entry` tells apart images
+    /// inside the same archive
+    pub fn etag(&self, entry: Option<&str>) -> String {
+        let modified = self
+            .last_modified
+            .map(|modified| {
+                format!(
+                    "{:x}.{:x}",
+                    modified.timestamp(),
+                    modified.timestamp_subsec_nanos()
+                )
+            })
+            .unwrap_or_default();
+
+        match entry {
+            Some(entry) => format!(
+                "\"{:x}-{modified}-{:x}\"",
+                self.len,
+                Sha256::digest(entry.as_bytes())
+            ),
+            None => format!("\"{:x}-{modified}\"", self.len),
+        }
+    }
+}
+
+/// Image of a signed url, local images are only read when and as far as the
+/// response needs them
+pub enum ImageContent {
+    /// Remote image, served from the cache
+    Memory(Image),
+    File {
+        path: PathBuf,
+        metadata: ImageFileMetadata,
+    },
+    /// Image inside an archive, only extracted when the client has no valid copy
+    Archive {
+        archive: PathBuf,
+        filename: String,
+        metadata: ImageFileMetadata,
+    },
+}
+
+impl ImageContent {
+    pub fn content_type(&self) -> String {
+        match self {
+            ImageContent::Memory(image) => image.content_type.clone(),
+            ImageContent::File { path, .. } => content_type_from_path(&path.to_string_lossy()),
+            ImageContent::Archive { filename, .. } => content_type_from_path(filename),
+        }
+    }
+
+    pub fn etag(&self) -> String {
+        match self {
+            ImageContent::Memory(image) => image.etag.clone(),
+            ImageContent::File { metadata, .. } => metadata.etag(None),
+            ImageContent::Archive {
+                filename, metadata, ..
+            } => metadata.etag(Some(filename)),
+        }
+    }
+
+    /// Size of the image, unknown for an image inside an archive until it is extracted
+    pub fn size(&self) -> Option<u64> {
+        match self {
+            ImageContent::Memory(image) => Some(image.data.len() as u64),
+            ImageContent::File { metadata, .. } => Some(metadata.len),
+            ImageContent::Archive { .. } => None,
+        }
+    }
+
+    pub fn last_modified(&self) -> Option<NaiveDateTime> {
+        match self {
+            ImageContent::Memory(image) => image.last_modified,
+            ImageContent::File { metadata, .. } | ImageContent::Archive { metadata, .. } => {
+                metadata.last_modified
+            }
+        }
+    }
+}
+
+/// Where an image is used, carried inside the encrypted url so cached
+/// entries can be grouped by manga and source.
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize)]
+pub struct ImageOrigin {
+    pub source_id: Option<i64>,
+    pub manga_id: Option<i64>,
+    pub is_cover: bool,
+}
+
+impl ImageOrigin {
+    pub fn cover(source_id: i64, manga_id: i64) -> Self {
+        Self {
+            source_id: Some(source_id),
+            manga_id: (manga_id > 0).then_some(manga_id),
+            is_cover: true,
+        }
+    }
+
+    pub fn page(source_id: i64, manga_id: i64) -> Self {
+        Self {
+            source_id: Some(source_id),
+            manga_id: (manga_id > 0).then_some(manga_id),
+            is_cover: false,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ImageCacheStats {
+    pub entries: i64,
+    pub bytes: i64,
+    pub max_bytes: i64,
+    pub cover_entries: i64,
+    pub cover_bytes: i64,
+    pub hits: i64,
+    pub misses: i64,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SECRET: &str = "secret";
+    const URL: &str = "https://example.com/page.png";
+
+    fn signed_url(user_id: i64, expires_at: i64) -> String {
+        ImageUri::Remote(URL.to_string())
+            .into_signed(
+                SECRET,
+                ImageOrigin::page(1, 2),
+                ImageUrlClaims {
+                    user_id,
+                    expires_at,
+                },
+            )
+            .unwrap()
+    }
+
+    /// Flip a bit of the decoded url at `index`, counted from the end if negative
+    fn tamper(url: &str, index: isize) -> String {
+        let mut token = general_purpose::URL_SAFE_NO_PAD.decode(url).unwrap();
+        let index = if index < 0 {
+            (token.len() as isize + index) as usize
+        } else {
+            index as usize
+        };
+        token[index] ^= 1;
+        general_purpose::URL_SAFE_NO_PAD.encode(token)
+    }
+
+    #[test]
+    fn test_signed_url_round_trip() {
+        let url = signed_url(1, 1000);
+        let (uri, origin, claims) = ImageUri::from_signed(SECRET, &url).unwrap();
+
+        assert!(matches!(uri, ImageUri::Remote(remote) if remote == URL));
+        assert_eq!(origin.source_id, Some(1));
+        assert_eq!(origin.manga_id, Some(2));
+        assert!(!origin.is_cover);
+        assert_eq!(claims.user_id, 1);
+        assert_eq!(claims.expires_at, 1000);
+
+        // same content signs to the same url so browsers can cache it
+        assert_eq!(url, signed_url(1, 1000));
+        assert_ne!(url, signed_url(2, 1000));
+    }
+
+    #[test]
+    fn test_signed_url_hides_uri() {
+        let token = general_purpose::URL_SAFE_NO_PAD
+            .decode(signed_url(1, 1000))
+            .unwrap();
+        assert!(!token
+            .windows(b"example.com".len())
+            .any(|window| window == b"example.com"));
+    }
+
+    #[test]
+    fn test_signed_url_tampered() {
+        let url = signed_url(1, 1000);
+
+        // expiry, user id, nonce, ciphertext and hmac
+        for index in [1, 9, IMAGE_URL_HEADER_LEN as isize, -33, -1] {
+            assert!(
+                ImageUri::from_signed(SECRET, &tamper(&url, index)).is_err(),
+                "byte {index} is not covered by signature"
+            );
+        }
+    }
+
+    #[test]
+    fn test_signed_url_wrong_secret() {
+        assert!(ImageUri::from_signed("other secret", &signed_url(1, 1000)).is_err());
+    }
+
+    #[test]
+    fn test_signed_url_malformed() {
+        let url = signed_url(1, 1000);
+
+        assert!(ImageUri::from_signed(SECRET, "not base64!").is_err());
+        assert!(ImageUri::from_signed(SECRET, &url[..url.len() / 2]).is_err());
+        assert!(ImageUri::from_signed(SECRET, "").is_err());
+    }
 }
@@ -1,9 +1,15 @@
+use std::{fmt, str::FromStr};
+
 use chrono::NaiveDateTime;
 
+use super::content_policy::ContentRestriction;
+
 #[derive(Debug, Clone)]
 pub struct Category {
     pub id: Option<i64>,
     pub name: String,
+    /// filter expression of a smart category, see [`CategoryRule`](super::category_rule::CategoryRule)
+    pub rule: Option<String>,
 }
 
 impl Default for Category {
@@ -11,6 +17,7 @@ impl Default for Category {
         Self {
             id: None,
             name: "Default".to_string(),
+            rule: None,
         }
     }
 }
@@ -23,4 +30,114 @@ pub struct LibraryUpdate {
     pub cover_url: String,
     pub chapter_title: String,
     pub uploaded: NaiveDateTime,
+    pub source_id: i64,
+}
+
+/// Column to sort library by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LibrarySortBy {
+    #[default]
+    Title,
+    LastRead,
+    LastUpdated,
+    DateAdded,
+    UnreadCount,
+}
+
+impl LibrarySortBy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LibrarySortBy::Title => "title",
+            LibrarySortBy::LastRead => "last_read",
+            LibrarySortBy::LastUpdated => "last_updated",
+            LibrarySortBy::DateAdded => "date_added",
+            LibrarySortBy::UnreadCount => "unread_count",
+        }
+    }
+}
+
+impl fmt::Display for LibrarySortBy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for LibrarySortBy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "title" => Ok(LibrarySortBy::Title),
+            "last_read" => Ok(LibrarySortBy::LastRead),
+            "last_updated" => Ok(LibrarySortBy::LastUpdated),
+            "date_added" => Ok(LibrarySortBy::DateAdded),
+            "unread_count" => Ok(LibrarySortBy::UnreadCount),
+            _ => Err(anyhow::anyhow!("unknown sort {s}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LibrarySort {
+    pub by: LibrarySortBy,
+    pub descending: bool,
+}
+
+/// Value of the sort column of a manga in library
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LibrarySortValue {
+    Integer(i64),
+    Text(String),
+}
+
+/// Where a manga is in sorted library, a page continues after it.
+/// Ties on sort value are broken by title then id
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LibraryPosition {
+    pub sort_value: LibrarySortValue,
+    pub title: String,
+    pub id: i64,
+}
+
+/// Library filters, unset fields match everything
+#[derive(Debug, Clone, Default)]
+pub struct LibraryFilter {
+    /// true for manga with unread chapters, false for fully read manga
+    pub unread: Option<bool>,
+    /// true for manga with at least one downloaded chapter
+    pub downloaded: Option<bool>,
+    /// true for manga linked to any tracker
+    pub tracked: Option<bool>,
+    pub status: Option<String>,
+    pub source_id: Option<i64>,
+    pub genre: Option<String>,
+    /// user's own tag
+    pub tag: Option<String>,
+    /// manga rated at least this much by user
+    pub min_rating: Option<i64>,
+    /// content policy of user
+    pub restriction: ContentRestriction,
+}
+
+/// Personal tag on a manga
+#[derive(Debug, Clone)]
+pub struct MangaTag {
+    pub manga_id: i64,
+    pub tag: String,
+}
+
+/// Tag of a user and number of manga tagged with it
+#[derive(Debug, Clone)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: i64,
+}
+
+/// Rating from 1 to 10 and private note of a user on a manga
+#[derive(Debug, Clone)]
+pub struct MangaNote {
+    pub manga_id: i64,
+    pub rating: Option<i64>,
+    pub note: Option<String>,
+    pub updated_at: NaiveDateTime,
 }
@@ -1,6 +1,8 @@
 use chrono::NaiveDateTime;
 use tanoshi_lib::prelude::Input;
 
+use super::content_policy::ContentRestriction;
+
 #[derive(Debug, Clone)]
 pub struct Manga {
     pub id: i64,
@@ -52,4 +54,56 @@ impl From<tanoshi_lib::models::MangaInfo> for Manga {
     }
 }
 
+/// Metadata a user set for a manga, unset fields keep what the source returns so
+/// refreshing manga from source doesn't undo them
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MangaOverride {
+    pub manga_id: i64,
+    pub title: Option<String>,
+    pub author: Option<Vec<String>>,
+    pub genre: Option<Vec<String>>,
+    pub status: Option<String>,
+    pub description: Option<String>,
+    /// uploaded cover image on disk
+    pub cover_path: Option<String>,
+}
+
+impl MangaOverride {
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none()
+            && self.author.is_none()
+            && self.genre.is_none()
+            && self.status.is_none()
+            && self.description.is_none()
+            && self.cover_path.is_none()
+    }
+}
+
+/// Filters for full-text search, unset fields match everything
+#[derive(Debug, Clone, Default)]
+pub struct MangaSearchFilter {
+    /// only search manga in user's library, otherwise search every manga cached by the server
+    pub library_only: bool,
+    pub category_id: Option<i64>,
+    pub source_id: Option<i64>,
+    pub status: Option<String>,
+    pub genre: Option<String>,
+    /// user's own tag
+    pub tag: Option<String>,
+    /// only manga with at least one chapter not read to completion
+    pub unread_only: bool,
+    /// only these manga, e.g. the ones matching rule of a smart category
+    pub manga_ids: Option<Vec<i64>>,
+    /// content policy of user
+    pub restriction: ContentRestriction,
+}
+
+/// Manga found by one source of a global search, `error` is set when the source failed or timed out
+#[derive(Debug, Clone)]
+pub struct SourceSearchResult {
+    pub source_id: i64,
+    pub manga: Vec<Manga>,
+    pub error: Option<String>,
+}
+
 pub type InputList = Vec<Input>;
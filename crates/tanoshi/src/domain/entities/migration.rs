@@ -0,0 +1,16 @@
+use super::manga::Manga;
+
+/// A manga from another source that may be the same title
+#[derive(Debug, Clone)]
+pub struct MigrationCandidate {
+    pub manga: Manga,
+    pub similarity: f64,
+}
+
+/// Number of records moved from old manga to the new one
+#[derive(Debug, Clone, Default)]
+pub struct MigrationResult {
+    pub categories: u64,
+    pub history: u64,
+    pub trackers: u64,
+}
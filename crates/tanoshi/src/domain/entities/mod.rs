@@ -1,9 +1,19 @@
+pub mod api_key;
+pub mod audit_log;
+pub mod category_rule;
 pub mod chapter;
+pub mod content_policy;
 pub mod download;
 pub mod history;
+pub mod identity;
 pub mod image;
 pub mod library;
 pub mod manga;
+pub mod migration;
+pub mod preference;
+pub mod session;
 pub mod source;
+pub mod statistics;
+pub mod totp;
 pub mod tracker;
 pub mod user;
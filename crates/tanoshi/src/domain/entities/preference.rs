@@ -0,0 +1,22 @@
+use chrono::NaiveDateTime;
+
+/// Client settings document of a user, e.g. reader or library settings
+#[derive(Debug, Clone)]
+pub struct Preference {
+    pub user_id: i64,
+    pub key: String,
+    /// None if it applies to every manga
+    pub manga_id: Option<i64>,
+    /// Version of the document layout, written by the client
+    pub schema_version: i64,
+    /// json document
+    pub value: String,
+    pub updated_at: NaiveDateTime,
+}
+
+/// Sent to subscribers when a preference is saved or deleted
+#[derive(Debug, Clone)]
+pub struct PreferenceChange {
+    pub preference: Preference,
+    pub deleted: bool,
+}
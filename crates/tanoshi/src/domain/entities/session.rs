@@ -0,0 +1,23 @@
+use chrono::NaiveDateTime;
+
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub id: i64,
+    pub user_id: i64,
+    pub user_agent: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub last_used_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub revoked_at: Option<NaiveDateTime>,
+}
+
+/// Short-lived access token and the refresh token used to renew it
+#[derive(Debug, Clone)]
+pub struct AuthToken {
+    pub access_token: String,
+    pub refresh_token: String,
+    /// unix timestamp when access token expires
+    pub expires_at: i64,
+    /// access token can only enroll two-factor authentication and has no refresh token
+    pub totp_enrollment: bool,
+}
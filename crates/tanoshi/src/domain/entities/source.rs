@@ -1,3 +1,5 @@
+use tanoshi_vm::extension::TrustPolicy;
+
 pub struct Source {
     pub id: i64,
     pub name: String,
@@ -6,7 +8,16 @@ pub struct Source {
     pub rustc_version: String,
     pub lib_version: String,
     pub icon: String,
+    pub nsfw: bool,
     pub has_update: bool,
+    /// Name of repository the source is installed or available from
+    pub repository: Option<String>,
+    /// Other repositories providing a source with the same id
+    pub conflicts: Vec<String>,
+    /// Hex encoded sha256 of extension file listed in repository index
+    pub sha256: Option<String>,
+    /// Signature of extension file listed in repository index
+    pub signature: Option<String>,
 }
 
 impl From<tanoshi_lib::models::SourceInfo> for Source {
@@ -19,7 +30,23 @@ impl From<tanoshi_lib::models::SourceInfo> for Source {
             rustc_version: "".to_string(),
             lib_version: "".to_string(),
             icon: s.icon.to_string(),
+            nsfw: s.nsfw,
             has_update: false,
+            repository: None,
+            conflicts: vec![],
+            sha256: None,
+            signature: None,
         }
     }
 }
+
+/// Repository containing `index.json` and extension files
+#[derive(Debug, Clone)]
+pub struct ExtensionRepository {
+    pub name: String,
+    /// Url or local directory
+    pub url: String,
+    /// Built in or defined in config, can't be removed at runtime
+    pub from_config: bool,
+    pub trust: TrustPolicy,
+}
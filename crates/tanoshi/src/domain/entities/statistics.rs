@@ -0,0 +1,99 @@
+use std::{fmt, str::FromStr};
+
+use chrono::{NaiveDate, NaiveDateTime};
+
+/// Bucket size of reading activity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ActivityPeriod {
+    #[default]
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+impl ActivityPeriod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ActivityPeriod::Day => "day",
+            ActivityPeriod::Week => "week",
+            ActivityPeriod::Month => "month",
+            ActivityPeriod::Year => "year",
+        }
+    }
+}
+
+impl fmt::Display for ActivityPeriod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ActivityPeriod {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "day" => Ok(ActivityPeriod::Day),
+            "week" => Ok(ActivityPeriod::Week),
+            "month" => Ok(ActivityPeriod::Month),
+            "year" => Ok(ActivityPeriod::Year),
+            _ => Err(anyhow::anyhow!("unknown period {s}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReadingActivity {
+    /// e.g. `2022-06-30`, `2022-W26`, `2022-06` or `2022`
+    pub period: String,
+    pub chapters: i64,
+    pub pages: i64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ReadingSummary {
+    pub chapters: i64,
+    pub pages: i64,
+    pub manga: i64,
+    /// manga with every chapter read to completion
+    pub completed_manga: i64,
+    pub days: i64,
+    pub sessions: i64,
+    pub average_chapters_per_session: f64,
+}
+
+/// Consecutive days with at least one chapter read
+#[derive(Debug, Clone, Default)]
+pub struct ReadingStreak {
+    /// streak ending today or yesterday, 0 if it is broken
+    pub current: i64,
+    pub longest: i64,
+    pub longest_start: Option<NaiveDate>,
+    pub longest_end: Option<NaiveDate>,
+}
+
+/// Chapters read for a genre or an author
+#[derive(Debug, Clone)]
+pub struct ReadingCount {
+    pub name: String,
+    pub chapters: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct SourceReadingCount {
+    pub source_id: i64,
+    pub chapters: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct MangaCompletion {
+    pub manga_id: i64,
+    pub title: String,
+    pub chapters_read: i64,
+    pub chapters_total: i64,
+    pub started_at: NaiveDateTime,
+    pub last_read_at: NaiveDateTime,
+    /// set when every chapter is read
+    pub completed_at: Option<NaiveDateTime>,
+}
@@ -0,0 +1,22 @@
+use chrono::NaiveDateTime;
+
+/// Time-based one-time password secret of a user
+#[derive(Debug, Clone)]
+pub struct UserTotp {
+    pub user_id: i64,
+    /// base32 encoded secret
+    pub secret: String,
+    /// false until the first code is confirmed
+    pub enabled: bool,
+    /// last accepted time step, codes at or before it are rejected
+    pub last_used_step: i64,
+    pub created_at: NaiveDateTime,
+}
+
+/// Secret to add to an authenticator app, returned once on enrollment
+#[derive(Debug, Clone)]
+pub struct TotpEnrollment {
+    pub secret: String,
+    /// otpauth:// uri, usually shown as qr code
+    pub uri: String,
+}
@@ -0,0 +1,37 @@
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use thiserror::Error;
+
+use crate::domain::entities::api_key::{ApiKey, ApiKeyScope};
+
+#[derive(Debug, Error)]
+pub enum ApiKeyRepositoryError {
+    #[error("api key not found")]
+    NotFound,
+    #[error("database error: {0}")]
+    DbError(#[from] sqlx::Error),
+}
+
+#[async_trait]
+pub trait ApiKeyRepository: Send + Sync {
+    async fn insert_api_key(
+        &self,
+        user_id: i64,
+        name: &str,
+        key_hash: &str,
+        prefix: &str,
+        scopes: &[ApiKeyScope],
+        expires_at: Option<NaiveDateTime>,
+    ) -> Result<ApiKey, ApiKeyRepositoryError>;
+
+    async fn get_api_key_by_hash(&self, key_hash: &str) -> Result<ApiKey, ApiKeyRepositoryError>;
+
+    async fn get_api_keys_by_user_id(
+        &self,
+        user_id: i64,
+    ) -> Result<Vec<ApiKey>, ApiKeyRepositoryError>;
+
+    async fn update_last_used_at(&self, id: i64) -> Result<(), ApiKeyRepositoryError>;
+
+    async fn delete_api_key(&self, user_id: i64, id: i64) -> Result<u64, ApiKeyRepositoryError>;
+}
@@ -0,0 +1,34 @@
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use thiserror::Error;
+
+use crate::domain::entities::audit_log::AuditLog;
+
+#[derive(Debug, Error)]
+pub enum AuditLogRepositoryError {
+    #[error("database error: {0}")]
+    DbError(#[from] sqlx::Error),
+}
+
+#[async_trait]
+pub trait AuditLogRepository: Send + Sync {
+    async fn insert_audit_log(
+        &self,
+        user_id: Option<i64>,
+        username: Option<&str>,
+        action: &str,
+        target: Option<&str>,
+        ip: Option<&str>,
+    ) -> Result<i64, AuditLogRepositoryError>;
+
+    /// Newest first, `before_id` is used to fetch the next page
+    async fn get_audit_logs(
+        &self,
+        user_id: Option<i64>,
+        action: Option<&str>,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+        before_id: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<AuditLog>, AuditLogRepositoryError>;
+}
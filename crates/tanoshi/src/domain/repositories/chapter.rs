@@ -2,7 +2,7 @@ use async_trait::async_trait;
 
 use thiserror::Error;
 
-use crate::domain::entities::chapter::Chapter;
+use crate::domain::entities::chapter::{Chapter, ScanlatorSetting};
 
 #[derive(Debug, Error)]
 pub enum ChapterRepositoryError {
@@ -45,4 +45,21 @@ pub trait ChapterRepository: Send + Sync {
         manga_id: i64,
         paths: &[String],
     ) -> Result<Vec<Chapter>, ChapterRepositoryError>;
+
+    async fn get_scanlators_by_manga_id(
+        &self,
+        manga_id: i64,
+    ) -> Result<Vec<String>, ChapterRepositoryError>;
+
+    async fn get_scanlator_setting(
+        &self,
+        manga_id: i64,
+    ) -> Result<ScanlatorSetting, ChapterRepositoryError>;
+
+    async fn set_scanlator_setting(
+        &self,
+        setting: &ScanlatorSetting,
+    ) -> Result<(), ChapterRepositoryError>;
+
+    async fn delete_scanlator_setting(&self, manga_id: i64) -> Result<(), ChapterRepositoryError>;
 }
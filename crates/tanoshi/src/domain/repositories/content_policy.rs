@@ -0,0 +1,30 @@
+use async_trait::async_trait;
+use thiserror::Error;
+
+use crate::domain::entities::content_policy::ContentPolicy;
+
+#[derive(Debug, Error)]
+pub enum ContentPolicyRepositoryError {
+    #[error("content policy not found")]
+    NotFound,
+    #[error("database error: {0}")]
+    DbError(#[from] sqlx::Error),
+}
+
+#[async_trait]
+pub trait ContentPolicyRepository: Send + Sync {
+    async fn get_content_policy(
+        &self,
+        user_id: i64,
+    ) -> Result<ContentPolicy, ContentPolicyRepositoryError>;
+
+    async fn upsert_content_policy(
+        &self,
+        policy: &ContentPolicy,
+    ) -> Result<(), ContentPolicyRepositoryError>;
+
+    async fn delete_content_policy(
+        &self,
+        user_id: i64,
+    ) -> Result<u64, ContentPolicyRepositoryError>;
+}
@@ -0,0 +1,31 @@
+use async_trait::async_trait;
+use tanoshi_lib::prelude::HttpProfile;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum HttpProfileRepositoryError {
+    #[error("database error: {0}")]
+    DbError(#[from] sqlx::Error),
+    #[error("serialize error: {0}")]
+    SerializeError(#[from] serde_json::Error),
+}
+
+#[async_trait]
+pub trait HttpProfileRepository: Send + Sync {
+    async fn get_http_profiles(
+        &self,
+    ) -> Result<Vec<(i64, HttpProfile)>, HttpProfileRepositoryError>;
+
+    async fn get_http_profile(
+        &self,
+        source_id: i64,
+    ) -> Result<Option<HttpProfile>, HttpProfileRepositoryError>;
+
+    async fn set_http_profile(
+        &self,
+        source_id: i64,
+        profile: &HttpProfile,
+    ) -> Result<(), HttpProfileRepositoryError>;
+
+    async fn delete_http_profile(&self, source_id: i64) -> Result<(), HttpProfileRepositoryError>;
+}
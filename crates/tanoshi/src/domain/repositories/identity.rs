@@ -0,0 +1,39 @@
+use async_trait::async_trait;
+use thiserror::Error;
+
+use crate::domain::entities::identity::UserIdentity;
+
+#[derive(Debug, Error)]
+pub enum IdentityRepositoryError {
+    #[error("identity not found")]
+    NotFound,
+    #[error("database error: {0}")]
+    DbError(#[from] sqlx::Error),
+}
+
+#[async_trait]
+pub trait IdentityRepository: Send + Sync {
+    async fn get_identity(
+        &self,
+        issuer: &str,
+        subject: &str,
+    ) -> Result<UserIdentity, IdentityRepositoryError>;
+
+    async fn get_identities_by_user_id(
+        &self,
+        user_id: i64,
+    ) -> Result<Vec<UserIdentity>, IdentityRepositoryError>;
+
+    async fn insert_identity(
+        &self,
+        user_id: i64,
+        issuer: &str,
+        subject: &str,
+    ) -> Result<i64, IdentityRepositoryError>;
+
+    async fn delete_identities_by_user_id(
+        &self,
+        user_id: i64,
+        issuer: &str,
+    ) -> Result<u64, IdentityRepositoryError>;
+}
@@ -1,15 +1,18 @@
 use std::path::Path;
 
 use async_trait::async_trait;
+use bytes::Bytes;
 
 use thiserror::Error;
 
-use crate::domain::entities::image::Image;
+use crate::domain::entities::image::{Image, ImageFileMetadata};
 
 #[derive(Debug, Error)]
 pub enum ImageRepositoryError {
     #[error("error request image: {0}")]
     RequestError(#[from] reqwest::Error),
+    #[error("host of {0} does not belong to the source")]
+    HostNotAllowed(String),
     #[error("other error: {0}")]
     Other(String),
 }
@@ -19,16 +22,39 @@ pub trait ImageRepository: Send + Sync {
     async fn fetch_image_from_url(
         &self,
         url: &str,
+        source_id: Option<i64>,
         referer: Option<&String>,
     ) -> Result<Image, ImageRepositoryError>;
-    async fn fetch_image_from_file<P>(&self, path: P) -> Result<Image, ImageRepositoryError>
+    async fn fetch_file_metadata<P>(
+        &self,
+        path: P,
+    ) -> Result<ImageFileMetadata, ImageRepositoryError>
+    where
+        P: AsRef<Path> + std::marker::Send;
+    /// Read `len` bytes of a file from `start` without reading the rest of it
+    async fn fetch_image_range_from_file<P>(
+        &self,
+        path: P,
+        start: u64,
+        len: u64,
+    ) -> Result<Bytes, ImageRepositoryError>
     where
         P: AsRef<Path> + std::marker::Send;
     async fn fetch_image_from_archive<P>(
         &self,
         archive: P,
         filename: &str,
-    ) -> Result<Image, ImageRepositoryError>
+    ) -> Result<Bytes, ImageRepositoryError>
+    where
+        P: AsRef<Path> + std::marker::Send;
+    async fn write_image_to_file<P>(
+        &self,
+        path: P,
+        data: &[u8],
+    ) -> Result<(), ImageRepositoryError>
+    where
+        P: AsRef<Path> + std::marker::Send;
+    async fn remove_image_file<P>(&self, path: P) -> Result<(), ImageRepositoryError>
     where
         P: AsRef<Path> + std::marker::Send;
 }
@@ -1,7 +1,7 @@
 use async_trait::async_trait;
 use thiserror::Error;
 
-use crate::domain::entities::image::Image;
+use crate::domain::entities::image::{Image, ImageCacheStats, ImageOrigin};
 
 #[derive(Debug, Error)]
 pub enum ImageCacheRepositoryError {
@@ -9,13 +9,32 @@ pub enum ImageCacheRepositoryError {
     FileError(#[from] std::io::Error),
     #[error("io error: {0}")]
     SerializeError(#[from] bincode::Error),
+    #[error("database error: {0}")]
+    DbError(#[from] sqlx::Error),
     #[error("other error: {0}")]
     Other(String),
 }
 
 #[async_trait]
-pub trait ImageCacheRepository {
-    async fn set(&self, key: &str, image: &Image) -> Result<(), ImageCacheRepositoryError>;
+pub trait ImageCacheRepository: Send + Sync {
+    async fn set(
+        &self,
+        key: &str,
+        origin: &ImageOrigin,
+        image: &Image,
+    ) -> Result<(), ImageCacheRepositoryError>;
 
     async fn get(&self, key: &str) -> Result<Image, ImageCacheRepositoryError>;
+
+    async fn contains(&self, key: &str) -> Result<bool, ImageCacheRepositoryError>;
+
+    async fn get_stats(&self) -> Result<ImageCacheStats, ImageCacheRepositoryError>;
+
+    async fn purge_by_manga_id(&self, manga_id: i64) -> Result<u64, ImageCacheRepositoryError>;
+
+    async fn purge_by_source_id(&self, source_id: i64) -> Result<u64, ImageCacheRepositoryError>;
+
+    /// Trim least recently used entries down to the size limit and remove
+    /// files that are no longer tracked by the index.
+    async fn evict(&self) -> Result<u64, ImageCacheRepositoryError>;
 }
@@ -6,7 +6,10 @@ use futures::stream::BoxStream;
 use thiserror::Error;
 
 use crate::domain::entities::{
-    library::{Category, LibraryUpdate},
+    library::{
+        Category, LibraryFilter, LibraryPosition, LibrarySort, LibraryUpdate, MangaNote, MangaTag,
+        TagCount,
+    },
     manga::Manga,
     user::User,
 };
@@ -15,6 +18,8 @@ use crate::domain::entities::{
 pub enum LibraryRepositoryError {
     #[error("database error: {0}")]
     DbError(#[from] sqlx::Error),
+    #[error("invalid category rule: {0}")]
+    InvalidRule(String),
 }
 
 #[async_trait]
@@ -30,16 +35,20 @@ pub trait LibraryRepository: Clone + Send + Sync {
         &self,
         user_id: i64,
         name: &str,
+        rule: Option<&str>,
     ) -> Result<Category, LibraryRepositoryError>;
 
-    async fn rename_category(
+    /// Rule is kept if not set, setting a rule removes manually added manga from category
+    async fn update_category(
         &self,
         id: i64,
         name: &str,
+        rule: Option<&str>,
     ) -> Result<Category, LibraryRepositoryError>;
 
     async fn delete_category(&self, id: i64) -> Result<(), LibraryRepositoryError>;
 
+    /// Counts of smart categories are evaluated from their rule
     async fn get_category_count(
         &self,
         user_id: i64,
@@ -75,6 +84,18 @@ pub trait LibraryRepository: Clone + Send + Sync {
         user_id: i64,
     ) -> Result<Vec<Manga>, LibraryRepositoryError>;
 
+    /// Returns manga after `after` with their position in sorted and filtered library,
+    /// manga of a smart category are the ones matching its rule
+    async fn get_manga_from_library_page(
+        &self,
+        user_id: i64,
+        category_id: Option<i64>,
+        sort: LibrarySort,
+        filter: &LibraryFilter,
+        after: Option<&LibraryPosition>,
+        first: i64,
+    ) -> Result<Vec<(LibraryPosition, Manga)>, LibraryRepositoryError>;
+
     async fn insert_manga_to_library(
         &self,
         user_id: i64,
@@ -116,4 +137,44 @@ pub trait LibraryRepository: Clone + Send + Sync {
         before_timestamp: i64,
         before_id: i64,
     ) -> Result<Vec<LibraryUpdate>, LibraryRepositoryError>;
+
+    /// Tags of user with number of manga tagged, most used first
+    async fn get_tags_by_user_id(
+        &self,
+        user_id: i64,
+    ) -> Result<Vec<TagCount>, LibraryRepositoryError>;
+
+    async fn get_manga_tags(
+        &self,
+        user_id: i64,
+        manga_ids: &[i64],
+    ) -> Result<Vec<MangaTag>, LibraryRepositoryError>;
+
+    /// Replace user's tags on manga
+    async fn set_manga_tags(
+        &self,
+        user_id: i64,
+        manga_id: i64,
+        tags: &[String],
+    ) -> Result<(), LibraryRepositoryError>;
+
+    async fn get_manga_notes(
+        &self,
+        user_id: i64,
+        manga_ids: &[i64],
+    ) -> Result<Vec<MangaNote>, LibraryRepositoryError>;
+
+    async fn set_manga_rating(
+        &self,
+        user_id: i64,
+        manga_id: i64,
+        rating: Option<i64>,
+    ) -> Result<(), LibraryRepositoryError>;
+
+    async fn set_manga_note(
+        &self,
+        user_id: i64,
+        manga_id: i64,
+        note: Option<&str>,
+    ) -> Result<(), LibraryRepositoryError>;
 }
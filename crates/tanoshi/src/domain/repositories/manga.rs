@@ -1,4 +1,4 @@
-use crate::domain::entities::manga::Manga;
+use crate::domain::entities::manga::{Manga, MangaOverride, MangaSearchFilter};
 use async_trait::async_trait;
 use thiserror::Error;
 
@@ -18,4 +18,28 @@ pub trait MangaRepository: Send + Sync {
         path: &str,
     ) -> Result<Manga, MangaRepositoryError>;
     async fn insert_manga(&self, manga: &mut Manga) -> Result<(), MangaRepositoryError>;
+    /// Search manga index with fts5 query syntax, best match first
+    async fn search_manga(
+        &self,
+        user_id: i64,
+        query: &str,
+        filter: &MangaSearchFilter,
+        limit: i64,
+    ) -> Result<Vec<Manga>, MangaRepositoryError>;
+    async fn get_manga_overrides(
+        &self,
+        user_id: i64,
+        manga_ids: &[i64],
+    ) -> Result<Vec<MangaOverride>, MangaRepositoryError>;
+    /// Replace every field of user's override of the manga
+    async fn set_manga_override(
+        &self,
+        user_id: i64,
+        manga_override: &MangaOverride,
+    ) -> Result<(), MangaRepositoryError>;
+    async fn delete_manga_override(
+        &self,
+        user_id: i64,
+        manga_id: i64,
+    ) -> Result<(), MangaRepositoryError>;
 }
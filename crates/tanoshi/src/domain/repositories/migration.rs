@@ -0,0 +1,22 @@
+use async_trait::async_trait;
+use thiserror::Error;
+
+use crate::domain::entities::migration::MigrationResult;
+
+#[derive(Debug, Error)]
+pub enum MigrationRepositoryError {
+    #[error("database error: {0}")]
+    DbError(#[from] sqlx::Error),
+}
+
+#[async_trait]
+pub trait MigrationRepository: Send + Sync {
+    /// Move library entry with its categories, history and tracker links of a user
+    /// from one manga to another, history is matched by chapter number
+    async fn migrate_manga(
+        &self,
+        user_id: i64,
+        from_manga_id: i64,
+        to_manga_id: i64,
+    ) -> Result<MigrationResult, MigrationRepositoryError>;
+}
@@ -0,0 +1,42 @@
+use async_trait::async_trait;
+use thiserror::Error;
+
+use crate::domain::entities::preference::Preference;
+
+#[derive(Debug, Error)]
+pub enum PreferenceRepositoryError {
+    #[error("preference not found")]
+    NotFound,
+    #[error("database error: {0}")]
+    DbError(#[from] sqlx::Error),
+}
+
+#[async_trait]
+pub trait PreferenceRepository: Send + Sync {
+    /// Global preferences if manga id is None, otherwise preferences of the manga
+    async fn get_preferences(
+        &self,
+        user_id: i64,
+        manga_id: Option<i64>,
+    ) -> Result<Vec<Preference>, PreferenceRepositoryError>;
+
+    async fn get_preference(
+        &self,
+        user_id: i64,
+        key: &str,
+        manga_id: Option<i64>,
+    ) -> Result<Preference, PreferenceRepositoryError>;
+
+    /// Returns false if the stored preference has a newer schema version
+    async fn upsert_preference(
+        &self,
+        preference: &Preference,
+    ) -> Result<bool, PreferenceRepositoryError>;
+
+    async fn delete_preference(
+        &self,
+        user_id: i64,
+        key: &str,
+        manga_id: Option<i64>,
+    ) -> Result<u64, PreferenceRepositoryError>;
+}
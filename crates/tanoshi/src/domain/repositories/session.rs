@@ -0,0 +1,55 @@
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use thiserror::Error;
+
+use crate::domain::entities::session::Session;
+
+#[derive(Debug, Error)]
+pub enum SessionRepositoryError {
+    #[error("session not found")]
+    NotFound,
+    #[error("database error: {0}")]
+    DbError(#[from] sqlx::Error),
+}
+
+#[async_trait]
+pub trait SessionRepository: Send + Sync {
+    async fn insert_session(
+        &self,
+        user_id: i64,
+        refresh_token_hash: &str,
+        user_agent: Option<&str>,
+        expires_at: NaiveDateTime,
+    ) -> Result<i64, SessionRepositoryError>;
+
+    async fn get_session_by_id(&self, id: i64) -> Result<Session, SessionRepositoryError>;
+
+    async fn get_session_by_refresh_token_hash(
+        &self,
+        refresh_token_hash: &str,
+    ) -> Result<Session, SessionRepositoryError>;
+
+    /// Returns sessions of a user which are not revoked or expired
+    async fn get_active_sessions_by_user_id(
+        &self,
+        user_id: i64,
+    ) -> Result<Vec<Session>, SessionRepositoryError>;
+
+    async fn rotate_refresh_token(
+        &self,
+        id: i64,
+        refresh_token_hash: &str,
+        expires_at: NaiveDateTime,
+    ) -> Result<(), SessionRepositoryError>;
+
+    async fn revoke_session(&self, user_id: i64, id: i64) -> Result<u64, SessionRepositoryError>;
+
+    async fn revoke_sessions_by_user_id(
+        &self,
+        user_id: i64,
+        except_id: Option<i64>,
+    ) -> Result<u64, SessionRepositoryError>;
+
+    /// Remove revoked and expired sessions
+    async fn delete_inactive_sessions(&self) -> Result<u64, SessionRepositoryError>;
+}
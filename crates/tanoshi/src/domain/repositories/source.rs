@@ -1,8 +1,10 @@
+use std::collections::HashMap;
+
 use async_trait::async_trait;
 
 use thiserror::Error;
 
-use crate::domain::entities::source::Source;
+use crate::domain::entities::source::{ExtensionRepository, Source};
 
 #[derive(Debug, Error)]
 pub enum SourceRepositoryError {
@@ -12,6 +14,10 @@ pub enum SourceRepositoryError {
     VersionError(#[from] tanoshi_lib::error::Error),
     #[error("request return error: {0}")]
     RequestError(#[from] reqwest::Error),
+    #[error("database error: {0}")]
+    DbError(#[from] sqlx::Error),
+    #[error("invalid index: {0}")]
+    IndexError(#[from] serde_json::Error),
     #[error("source not found")]
     NotFound,
     #[error("other error: {0}")]
@@ -22,16 +28,40 @@ pub enum SourceRepositoryError {
 pub trait SourceRepository: Send + Sync {
     async fn installed_sources(&self) -> Result<Vec<Source>, SourceRepositoryError>;
 
+    /// Sources listed in `index.json` of a repository
     async fn available_sources(
         &self,
-        repo_url: &str,
-        filter_installed: bool,
+        repository: &ExtensionRepository,
     ) -> Result<Vec<Source>, SourceRepositoryError>;
     async fn get_source_by_id(&self, id: i64) -> Result<Source, SourceRepositoryError>;
 
-    async fn install_source(&self, repo_url: &str, id: i64) -> Result<(), SourceRepositoryError>;
+    async fn install_source(
+        &self,
+        repository: &ExtensionRepository,
+        source: &Source,
+    ) -> Result<(), SourceRepositoryError>;
 
-    async fn update_source(&self, repo_url: &str, id: i64) -> Result<(), SourceRepositoryError>;
+    async fn update_source(
+        &self,
+        repository: &ExtensionRepository,
+        source: &Source,
+    ) -> Result<(), SourceRepositoryError>;
 
     async fn uninstall_source(&self, id: i64) -> Result<(), SourceRepositoryError>;
+
+    /// Repository each installed source came from, keyed by source id
+    async fn get_source_repositories(&self) -> Result<HashMap<i64, String>, SourceRepositoryError>;
+
+    /// Repositories added at runtime, without the ones from config
+    async fn get_extension_repositories(
+        &self,
+    ) -> Result<Vec<ExtensionRepository>, SourceRepositoryError>;
+
+    async fn insert_extension_repository(
+        &self,
+        name: &str,
+        url: &str,
+    ) -> Result<(), SourceRepositoryError>;
+
+    async fn delete_extension_repository(&self, name: &str) -> Result<(), SourceRepositoryError>;
 }
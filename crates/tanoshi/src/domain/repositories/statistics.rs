@@ -0,0 +1,77 @@
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use thiserror::Error;
+
+use crate::domain::entities::statistics::{
+    ActivityPeriod, MangaCompletion, ReadingActivity, ReadingCount, ReadingSummary,
+    SourceReadingCount,
+};
+
+#[derive(Debug, Error)]
+pub enum StatisticsRepositoryError {
+    #[error("database error: {0}")]
+    DbError(#[from] sqlx::Error),
+}
+
+/// Aggregates of `user_history`, every query is limited to chapters last read between `from` and `to`
+#[async_trait]
+pub trait StatisticsRepository: Send + Sync {
+    /// Oldest period first, periods are in local time shifted by `utc_offset_minutes`
+    async fn get_reading_activity(
+        &self,
+        user_id: i64,
+        period: ActivityPeriod,
+        utc_offset_minutes: i32,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+    ) -> Result<Vec<ReadingActivity>, StatisticsRepositoryError>;
+
+    /// Only chapters, pages and manga counts are filled
+    async fn get_reading_totals(
+        &self,
+        user_id: i64,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+    ) -> Result<ReadingSummary, StatisticsRepositoryError>;
+
+    /// Oldest first
+    async fn get_read_times(
+        &self,
+        user_id: i64,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+    ) -> Result<Vec<NaiveDateTime>, StatisticsRepositoryError>;
+
+    async fn get_top_genres(
+        &self,
+        user_id: i64,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+        limit: i64,
+    ) -> Result<Vec<ReadingCount>, StatisticsRepositoryError>;
+
+    async fn get_top_authors(
+        &self,
+        user_id: i64,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+        limit: i64,
+    ) -> Result<Vec<ReadingCount>, StatisticsRepositoryError>;
+
+    async fn get_top_sources(
+        &self,
+        user_id: i64,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+        limit: i64,
+    ) -> Result<Vec<SourceReadingCount>, StatisticsRepositoryError>;
+
+    /// Most recently read manga first
+    async fn get_manga_completion(
+        &self,
+        user_id: i64,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+        limit: i64,
+    ) -> Result<Vec<MangaCompletion>, StatisticsRepositoryError>;
+}
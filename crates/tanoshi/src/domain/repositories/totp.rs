@@ -0,0 +1,47 @@
+use async_trait::async_trait;
+use thiserror::Error;
+
+use crate::domain::entities::totp::UserTotp;
+
+#[derive(Debug, Error)]
+pub enum TotpRepositoryError {
+    #[error("totp not found")]
+    NotFound,
+    #[error("database error: {0}")]
+    DbError(#[from] sqlx::Error),
+}
+
+#[async_trait]
+pub trait TotpRepository: Send + Sync {
+    async fn get_totp(&self, user_id: i64) -> Result<UserTotp, TotpRepositoryError>;
+
+    /// Insert or replace secret, the replaced one is disabled until confirmed
+    async fn upsert_totp(&self, user_id: i64, secret: &str) -> Result<(), TotpRepositoryError>;
+
+    async fn enable_totp(&self, user_id: i64) -> Result<(), TotpRepositoryError>;
+
+    /// Returns false if the step is not after the last used step
+    async fn update_last_used_step(
+        &self,
+        user_id: i64,
+        step: i64,
+    ) -> Result<bool, TotpRepositoryError>;
+
+    /// Delete secret and recovery codes
+    async fn delete_totp(&self, user_id: i64) -> Result<u64, TotpRepositoryError>;
+
+    async fn replace_recovery_codes(
+        &self,
+        user_id: i64,
+        code_hashes: &[String],
+    ) -> Result<(), TotpRepositoryError>;
+
+    /// Mark recovery code as used, returns false if not found or already used
+    async fn use_recovery_code(
+        &self,
+        user_id: i64,
+        code_hash: &str,
+    ) -> Result<bool, TotpRepositoryError>;
+
+    async fn get_recovery_codes_count(&self, user_id: i64) -> Result<i64, TotpRepositoryError>;
+}
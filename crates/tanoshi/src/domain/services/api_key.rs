@@ -0,0 +1,304 @@
+use base64::{engine::general_purpose, Engine};
+use chrono::{NaiveDateTime, Utc};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::{
+    domain::{
+        entities::api_key::{ApiKey, ApiKeyScope},
+        repositories::{
+            api_key::{ApiKeyRepository, ApiKeyRepositoryError},
+            user::{UserRepository, UserRepositoryError},
+        },
+    },
+    infrastructure::auth::Claims,
+};
+
+/// Every api key starts with this, used to tell it apart from access token
+pub const API_KEY_PREFIX: &str = "tnsh_";
+
+#[derive(Debug, Error)]
+pub enum ApiKeyError {
+    #[error("invalid or expired api key")]
+    InvalidKey,
+    #[error("scope {0} requires admin")]
+    Forbidden(ApiKeyScope),
+    #[error("name cannot be empty")]
+    EmptyName,
+    #[error("repository error: {0}")]
+    RepositoryError(#[from] ApiKeyRepositoryError),
+    #[error("user repository error: {0}")]
+    UserRepositoryError(#[from] UserRepositoryError),
+}
+
+fn hash_key(key: &str) -> String {
+    format!("{:x}", Sha256::digest(key.as_bytes()))
+}
+
+pub fn is_api_key(token: &str) -> bool {
+    token.starts_with(API_KEY_PREFIX)
+}
+
+#[derive(Clone)]
+pub struct ApiKeyService<R, U>
+where
+    R: ApiKeyRepository,
+    U: UserRepository,
+{
+    repo: R,
+    user_repo: U,
+}
+
+impl<R, U> ApiKeyService<R, U>
+where
+    R: ApiKeyRepository,
+    U: UserRepository,
+{
+    pub fn new(repo: R, user_repo: U) -> Self {
+        Self { repo, user_repo }
+    }
+
+    /// Create a new api key, returns the key itself only this once as only its hash is stored
+    pub async fn create_api_key(
+        &self,
+        user_id: i64,
+        name: &str,
+        scopes: &[ApiKeyScope],
+        expires_at: Option<NaiveDateTime>,
+    ) -> Result<(ApiKey, String), ApiKeyError> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(ApiKeyError::EmptyName);
+        }
+
+        let user = self.user_repo.get_user_by_id(user_id).await?;
+        if let Some(scope) = scopes.iter().find(|scope| scope.requires_admin()) {
+            if !user.is_admin {
+                return Err(ApiKeyError::Forbidden(*scope));
+            }
+        }
+
+        let mut scopes = scopes.to_vec();
+        scopes.sort_unstable();
+        scopes.dedup();
+
+        let mut bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let key = format!(
+            "{API_KEY_PREFIX}{}",
+            general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+        );
+        let prefix = &key[..API_KEY_PREFIX.len() + 6];
+
+        let api_key = self
+            .repo
+            .insert_api_key(user.id, name, &hash_key(&key), prefix, &scopes, expires_at)
+            .await?;
+
+        Ok((api_key, key))
+    }
+
+    /// Check api key and returns claims limited to its scopes
+    pub async fn authenticate(&self, key: &str) -> Result<Claims, ApiKeyError> {
+        let api_key = self
+            .repo
+            .get_api_key_by_hash(&hash_key(key))
+            .await
+            .map_err(|e| match e {
+                ApiKeyRepositoryError::NotFound => ApiKeyError::InvalidKey,
+                e => e.into(),
+            })?;
+
+        if matches!(api_key.expires_at, Some(expires_at) if expires_at <= Utc::now().naive_utc()) {
+            return Err(ApiKeyError::InvalidKey);
+        }
+
+        let user = self.user_repo.get_user_by_id(api_key.user_id).await?;
+
+        if let Err(e) = self.repo.update_last_used_at(api_key.id).await {
+            debug!("failed to update api key last used: {e}");
+        }
+
+        // user may no longer be admin since the key is created
+        let scopes = api_key
+            .scopes
+            .into_iter()
+            .filter(|scope| user.is_admin || !scope.requires_admin())
+            .collect();
+
+        Ok(Claims {
+            sub: user.id,
+            username: user.username,
+            is_admin: user.is_admin,
+            sid: 0,
+            exp: api_key
+                .expires_at
+                .map(|expires_at| expires_at.timestamp() as usize)
+                .unwrap_or(usize::MAX),
+            scopes: Some(scopes),
+        })
+    }
+
+    pub async fn get_api_keys(&self, user_id: i64) -> Result<Vec<ApiKey>, ApiKeyError> {
+        Ok(self.repo.get_api_keys_by_user_id(user_id).await?)
+    }
+
+    pub async fn delete_api_key(&self, user_id: i64, id: i64) -> Result<u64, ApiKeyError> {
+        Ok(self.repo.delete_api_key(user_id, id).await?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Duration;
+    use sqlx::SqlitePool;
+
+    use super::*;
+    use crate::infrastructure::{
+        database::{establish_connection, Pool},
+        domain::repositories::{api_key::ApiKeyRepositoryImpl, user::UserRepositoryImpl},
+    };
+
+    type Service = ApiKeyService<ApiKeyRepositoryImpl, UserRepositoryImpl>;
+
+    /// User 1 is an admin, user 2 is not
+    async fn service(dir: &std::path::Path) -> (Service, Pool) {
+        let pool = establish_connection(&dir.join("tanoshi.db").display().to_string(), true)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO user(id, username, password, is_admin) VALUES (1, 'one', '', true), (2, 'two', '', false)",
+        )
+        .execute(&pool as &SqlitePool)
+        .await
+        .unwrap();
+
+        let svc = ApiKeyService::new(
+            ApiKeyRepositoryImpl::new(pool.clone()),
+            UserRepositoryImpl::new(pool.clone()),
+        );
+        (svc, pool)
+    }
+
+    #[test]
+    fn test_is_api_key() {
+        assert!(is_api_key("tnsh_abc"));
+        assert!(is_api_key(API_KEY_PREFIX));
+        assert!(!is_api_key("eyJhbGciOiJIUzI1NiJ9.e30.abc"));
+        assert!(!is_api_key("TNSH_abc"));
+        assert!(!is_api_key(" tnsh_abc"));
+        assert!(!is_api_key(""));
+    }
+
+    #[test]
+    fn test_hash_key() {
+        assert_eq!(
+            hash_key("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_ne!(hash_key("tnsh_abc"), hash_key("tnsh_abd"));
+    }
+
+    #[tokio::test]
+    async fn test_create_and_authenticate() {
+        let dir = tempfile::tempdir().unwrap();
+        let (svc, pool) = service(dir.path()).await;
+
+        let (api_key, key) = svc
+            .create_api_key(
+                2,
+                " reader ",
+                &[ApiKeyScope::LibraryRead, ApiKeyScope::LibraryRead],
+                None,
+            )
+            .await
+            .unwrap();
+        assert!(is_api_key(&key));
+        assert_eq!(api_key.name, "reader");
+        assert_eq!(api_key.prefix, key[..API_KEY_PREFIX.len() + 6]);
+        assert_eq!(api_key.scopes, vec![ApiKeyScope::LibraryRead]);
+
+        // only the hash is stored
+        let (key_hash,): (String,) = sqlx::query_as("SELECT key_hash FROM api_key")
+            .fetch_one(&pool as &SqlitePool)
+            .await
+            .unwrap();
+        assert_eq!(key_hash, hash_key(&key));
+        assert_ne!(key_hash, key);
+
+        let claims = svc.authenticate(&key).await.unwrap();
+        assert_eq!(claims.sub, 2);
+        assert_eq!(claims.sid, 0);
+        assert!(claims.is_api_key());
+        assert_eq!(claims.scopes, Some(vec![ApiKeyScope::LibraryRead]));
+
+        assert!(matches!(
+            svc.authenticate(&format!("{key}x")).await,
+            Err(ApiKeyError::InvalidKey)
+        ));
+        assert!(matches!(
+            svc.authenticate(&key_hash).await,
+            Err(ApiKeyError::InvalidKey)
+        ));
+
+        assert_eq!(svc.delete_api_key(1, api_key.id).await.unwrap(), 0);
+        assert_eq!(svc.delete_api_key(2, api_key.id).await.unwrap(), 1);
+        assert!(matches!(
+            svc.authenticate(&key).await,
+            Err(ApiKeyError::InvalidKey)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_rejects_invalid_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let (svc, _) = service(dir.path()).await;
+
+        assert!(matches!(
+            svc.create_api_key(2, " ", &[], None).await,
+            Err(ApiKeyError::EmptyName)
+        ));
+        assert!(matches!(
+            svc.create_api_key(2, "downloads", &[ApiKeyScope::DownloadsAdmin], None)
+                .await,
+            Err(ApiKeyError::Forbidden(ApiKeyScope::DownloadsAdmin))
+        ));
+
+        let expired = Utc::now().naive_utc() - Duration::seconds(1);
+        let (_, key) = svc
+            .create_api_key(2, "old", &[], Some(expired))
+            .await
+            .unwrap();
+        assert!(matches!(
+            svc.authenticate(&key).await,
+            Err(ApiKeyError::InvalidKey)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_admin_scope_dropped_when_no_longer_admin() {
+        let dir = tempfile::tempdir().unwrap();
+        let (svc, pool) = service(dir.path()).await;
+
+        let (_, key) = svc
+            .create_api_key(
+                1,
+                "downloads",
+                &[ApiKeyScope::DownloadsAdmin, ApiKeyScope::LibraryRead],
+                None,
+            )
+            .await
+            .unwrap();
+        let claims = svc.authenticate(&key).await.unwrap();
+        assert!(claims.has_scope(ApiKeyScope::DownloadsAdmin));
+
+        sqlx::query("UPDATE user SET is_admin = false WHERE id = 1")
+            .execute(&pool as &SqlitePool)
+            .await
+            .unwrap();
+        let claims = svc.authenticate(&key).await.unwrap();
+        assert!(!claims.has_scope(ApiKeyScope::DownloadsAdmin));
+        assert!(claims.has_scope(ApiKeyScope::LibraryRead));
+    }
+}
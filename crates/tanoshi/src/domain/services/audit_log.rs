@@ -0,0 +1,75 @@
+use std::net::IpAddr;
+
+use chrono::NaiveDateTime;
+use thiserror::Error;
+
+use crate::domain::{
+    entities::audit_log::{AuditAction, AuditLog},
+    repositories::audit_log::{AuditLogRepository, AuditLogRepositoryError},
+};
+
+const MAX_AUDIT_LOGS_LIMIT: i64 = 500;
+
+#[derive(Debug, Error)]
+pub enum AuditLogError {
+    #[error("repository error: {0}")]
+    RepositoryError(#[from] AuditLogRepositoryError),
+}
+
+#[derive(Clone)]
+pub struct AuditLogService<R>
+where
+    R: AuditLogRepository,
+{
+    repo: R,
+}
+
+impl<R> AuditLogService<R>
+where
+    R: AuditLogRepository,
+{
+    pub fn new(repo: R) -> Self {
+        Self { repo }
+    }
+
+    /// Append an entry, failing to write it is logged but does not fail the action itself
+    pub async fn record(
+        &self,
+        user_id: Option<i64>,
+        username: Option<&str>,
+        action: AuditAction,
+        target: Option<&str>,
+        ip: Option<IpAddr>,
+    ) {
+        let ip = ip.map(|ip| ip.to_string());
+        if let Err(e) = self
+            .repo
+            .insert_audit_log(user_id, username, action.as_str(), target, ip.as_deref())
+            .await
+        {
+            error!("failed to write audit log for {action}: {e}");
+        }
+    }
+
+    pub async fn get_audit_logs(
+        &self,
+        user_id: Option<i64>,
+        action: Option<&str>,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+        before_id: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<AuditLog>, AuditLogError> {
+        Ok(self
+            .repo
+            .get_audit_logs(
+                user_id,
+                action,
+                from,
+                to,
+                before_id,
+                limit.clamp(1, MAX_AUDIT_LOGS_LIMIT),
+            )
+            .await?)
+    }
+}
@@ -2,7 +2,7 @@ use std::path::PathBuf;
 
 use crate::{
     domain::{
-        entities::chapter::Chapter,
+        entities::chapter::{Chapter, ScanlatorSetting},
         repositories::chapter::{ChapterRepository, ChapterRepositoryError},
     },
     infrastructure::local,
@@ -13,10 +13,14 @@ use tanoshi_vm::prelude::ExtensionManager;
 use thiserror::Error;
 use tokio::task::JoinError;
 
+const MAX_SCANLATORS: usize = 100;
+
 #[derive(Debug, Error)]
 pub enum ChapterError {
     #[error("repository error: {0}")]
     RepositoryError(#[from] ChapterRepositoryError),
+    #[error("invalid scanlator setting: {0}")]
+    InvalidScanlatorSetting(String),
     #[error("other error: {0}")]
     Other(#[from] anyhow::Error),
 }
@@ -119,4 +123,69 @@ where
 
         Ok(())
     }
+
+    pub async fn get_scanlators(&self, manga_id: i64) -> Result<Vec<String>, ChapterError> {
+        Ok(self.repo.get_scanlators_by_manga_id(manga_id).await?)
+    }
+
+    pub async fn get_scanlator_setting(
+        &self,
+        manga_id: i64,
+    ) -> Result<ScanlatorSetting, ChapterError> {
+        Ok(self.repo.get_scanlator_setting(manga_id).await?)
+    }
+
+    pub async fn set_scanlator_setting(
+        &self,
+        manga_id: i64,
+        dedup: bool,
+        priority: Vec<String>,
+        blocklist: Vec<String>,
+    ) -> Result<ScanlatorSetting, ChapterError> {
+        let priority = normalize_scanlators(priority)?;
+        let blocklist = normalize_scanlators(blocklist)?;
+
+        if let Some(scanlator) = priority
+            .iter()
+            .find(|p| blocklist.iter().any(|b| b.eq_ignore_ascii_case(p)))
+        {
+            return Err(ChapterError::InvalidScanlatorSetting(format!(
+                "{scanlator} is both preferred and blocked"
+            )));
+        }
+
+        let setting = ScanlatorSetting {
+            manga_id,
+            dedup,
+            priority,
+            blocklist,
+        };
+
+        if !setting.dedup && setting.priority.is_empty() && setting.blocklist.is_empty() {
+            self.repo.delete_scanlator_setting(manga_id).await?;
+        } else {
+            self.repo.set_scanlator_setting(&setting).await?;
+        }
+
+        Ok(setting)
+    }
+}
+
+/// Trim and remove empty and duplicate scanlators, order is kept
+fn normalize_scanlators(scanlators: Vec<String>) -> Result<Vec<String>, ChapterError> {
+    let mut normalized: Vec<String> = vec![];
+    for scanlator in scanlators {
+        let scanlator = scanlator.trim();
+        if !scanlator.is_empty() && !normalized.iter().any(|s| s.eq_ignore_ascii_case(scanlator)) {
+            normalized.push(scanlator.to_string());
+        }
+    }
+
+    if normalized.len() > MAX_SCANLATORS {
+        return Err(ChapterError::InvalidScanlatorSetting(format!(
+            "at most {MAX_SCANLATORS} scanlators allowed"
+        )));
+    }
+
+    Ok(normalized)
 }
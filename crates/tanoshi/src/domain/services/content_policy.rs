@@ -0,0 +1,349 @@
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use crate::domain::{
+    entities::{
+        content_policy::{ContentPolicy, ContentRestriction},
+        manga::Manga,
+        source::Source,
+    },
+    repositories::{
+        content_policy::{ContentPolicyRepository, ContentPolicyRepositoryError},
+        source::{SourceRepository, SourceRepositoryError},
+    },
+};
+
+#[derive(Debug, Error)]
+pub enum ContentPolicyError {
+    #[error("source {0} is not allowed")]
+    SourceNotAllowed(i64),
+    #[error("manga is not allowed")]
+    MangaNotAllowed,
+    #[error("repository error: {0}")]
+    RepositoryError(#[from] ContentPolicyRepositoryError),
+    #[error("source error: {0}")]
+    SourceError(#[from] SourceRepositoryError),
+}
+
+#[derive(Clone)]
+pub struct ContentPolicyService<R, S>
+where
+    R: ContentPolicyRepository,
+    S: SourceRepository,
+{
+    repo: R,
+    source_repo: S,
+}
+
+impl<R, S> ContentPolicyService<R, S>
+where
+    R: ContentPolicyRepository,
+    S: SourceRepository,
+{
+    pub fn new(repo: R, source_repo: S) -> Self {
+        Self { repo, source_repo }
+    }
+
+    /// Users without a policy are unrestricted
+    pub async fn get_policy(&self, user_id: i64) -> Result<ContentPolicy, ContentPolicyError> {
+        match self.repo.get_content_policy(user_id).await {
+            Ok(policy) => Ok(policy),
+            Err(ContentPolicyRepositoryError::NotFound) => Ok(ContentPolicy {
+                user_id,
+                ..Default::default()
+            }),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub async fn set_policy(&self, policy: ContentPolicy) -> Result<(), ContentPolicyError> {
+        if policy.is_unrestricted() {
+            self.repo.delete_content_policy(policy.user_id).await?;
+        } else {
+            self.repo.upsert_content_policy(&policy).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn is_nsfw(&self, source_id: i64) -> bool {
+        // sources that are not installed can't be browsed anyway
+        self.source_repo
+            .get_source_by_id(source_id)
+            .await
+            .map(|source| source.nsfw)
+            .unwrap_or(false)
+    }
+
+    async fn is_source_allowed(&self, policy: &ContentPolicy, source_id: i64) -> bool {
+        if policy.is_unrestricted() {
+            return true;
+        }
+
+        policy.is_source_allowed(source_id, self.is_nsfw(source_id).await)
+    }
+
+    pub async fn check_source(
+        &self,
+        user_id: i64,
+        source_id: i64,
+    ) -> Result<(), ContentPolicyError> {
+        let policy = self.get_policy(user_id).await?;
+        if !self.is_source_allowed(&policy, source_id).await {
+            return Err(ContentPolicyError::SourceNotAllowed(source_id));
+        }
+
+        Ok(())
+    }
+
+    pub async fn check_manga(&self, user_id: i64, manga: &Manga) -> Result<(), ContentPolicyError> {
+        let policy = self.get_policy(user_id).await?;
+        if !self.is_source_allowed(&policy, manga.source_id).await {
+            return Err(ContentPolicyError::SourceNotAllowed(manga.source_id));
+        }
+        if !policy.is_genre_allowed(&manga.genre) {
+            return Err(ContentPolicyError::MangaNotAllowed);
+        }
+
+        Ok(())
+    }
+
+    /// Policy of a user for queries that filter manga themselves, so pages are not cut short
+    /// by filtering them afterwards
+    pub async fn get_restriction(
+        &self,
+        user_id: i64,
+    ) -> Result<ContentRestriction, ContentPolicyError> {
+        let policy = self.get_policy(user_id).await?;
+
+        let mut denied_source_ids = policy.denied_source_ids;
+        if policy.hide_nsfw {
+            // sources that are not installed are not nsfw, same as `is_nsfw`
+            denied_source_ids.extend(
+                self.source_repo
+                    .installed_sources()
+                    .await?
+                    .into_iter()
+                    .filter(|source| source.nsfw)
+                    .map(|source| source.id),
+            );
+        }
+
+        Ok(ContentRestriction {
+            allowed_source_ids: policy.allowed_source_ids,
+            denied_source_ids,
+            blocked_genres: policy.blocked_genres,
+        })
+    }
+
+    pub async fn filter_sources(
+        &self,
+        user_id: i64,
+        sources: Vec<Source>,
+    ) -> Result<Vec<Source>, ContentPolicyError> {
+        let policy = self.get_policy(user_id).await?;
+
+        Ok(sources
+            .into_iter()
+            .filter(|source| policy.is_source_allowed(source.id, source.nsfw))
+            .collect())
+    }
+
+    /// Remove manga from sources that are not allowed or with a blocked genre
+    pub async fn filter_manga(
+        &self,
+        user_id: i64,
+        manga: Vec<Manga>,
+    ) -> Result<Vec<Manga>, ContentPolicyError> {
+        let policy = self.get_policy(user_id).await?;
+        if policy.is_unrestricted() {
+            return Ok(manga);
+        }
+
+        let mut allowed_sources: HashMap<i64, bool> = HashMap::new();
+        let mut filtered = Vec::with_capacity(manga.len());
+        for m in manga {
+            if !policy.is_genre_allowed(&m.genre) {
+                continue;
+            }
+
+            let allowed = match allowed_sources.get(&m.source_id) {
+                Some(allowed) => *allowed,
+                None => {
+                    let allowed = self.is_source_allowed(&policy, m.source_id).await;
+                    allowed_sources.insert(m.source_id, allowed);
+                    allowed
+                }
+            };
+
+            if allowed {
+                filtered.push(m);
+            }
+        }
+
+        Ok(filtered)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use sqlx::SqlitePool;
+    use tanoshi_vm::prelude::ExtensionManager;
+
+    use super::*;
+    use crate::infrastructure::{
+        database::establish_connection,
+        domain::repositories::{
+            content_policy::ContentPolicyRepositoryImpl, source::SourceRepositoryImpl,
+        },
+    };
+
+    type Service = ContentPolicyService<ContentPolicyRepositoryImpl, SourceRepositoryImpl>;
+
+    /// No source is installed, so none of them is nsfw
+    async fn service(dir: &std::path::Path) -> Service {
+        let pool = establish_connection(&dir.join("tanoshi.db").display().to_string(), true)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO user(id, username, password) VALUES (1, 'one', ''), (2, 'two', '')",
+        )
+        .execute(&pool as &SqlitePool)
+        .await
+        .unwrap();
+
+        ContentPolicyService::new(
+            ContentPolicyRepositoryImpl::new(pool.clone()),
+            SourceRepositoryImpl::new(pool, ExtensionManager::new(dir)),
+        )
+    }
+
+    fn manga(id: i64, source_id: i64, genre: &[&str]) -> Manga {
+        Manga {
+            id,
+            source_id,
+            genre: genre.iter().map(|genre| genre.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_user_without_policy_is_unrestricted() {
+        let dir = tempfile::tempdir().unwrap();
+        let svc = service(dir.path()).await;
+
+        assert!(svc.get_policy(1).await.unwrap().is_unrestricted());
+        assert!(svc.check_source(1, 1).await.is_ok());
+        assert!(svc.check_manga(1, &manga(1, 1, &["Horror"])).await.is_ok());
+        assert_eq!(
+            svc.filter_manga(1, vec![manga(1, 1, &["Horror"])])
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_policy_restricts_sources_and_genres() {
+        let dir = tempfile::tempdir().unwrap();
+        let svc = service(dir.path()).await;
+
+        svc.set_policy(ContentPolicy {
+            user_id: 1,
+            allowed_source_ids: Some(vec![1, 2]),
+            denied_source_ids: vec![2],
+            blocked_genres: vec![" horror ".to_string()],
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        assert!(svc.check_source(1, 1).await.is_ok());
+        assert!(matches!(
+            svc.check_source(1, 2).await,
+            Err(ContentPolicyError::SourceNotAllowed(2))
+        ));
+        assert!(matches!(
+            svc.check_source(1, 3).await,
+            Err(ContentPolicyError::SourceNotAllowed(3))
+        ));
+
+        assert!(svc.check_manga(1, &manga(1, 1, &["Comedy"])).await.is_ok());
+        assert!(matches!(
+            svc.check_manga(1, &manga(1, 1, &["Comedy", "HORROR"]))
+                .await,
+            Err(ContentPolicyError::MangaNotAllowed)
+        ));
+
+        let allowed: Vec<i64> = svc
+            .filter_manga(
+                1,
+                vec![
+                    manga(1, 1, &["Comedy"]),
+                    manga(2, 1, &["Horror"]),
+                    manga(3, 2, &["Comedy"]),
+                    manga(4, 3, &[]),
+                ],
+            )
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|m| m.id)
+            .collect();
+        assert_eq!(allowed, vec![1]);
+
+        // policy of one user doesn't apply to another
+        assert!(svc.check_source(2, 2).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_get_restriction() {
+        let dir = tempfile::tempdir().unwrap();
+        let svc = service(dir.path()).await;
+
+        svc.set_policy(ContentPolicy {
+            user_id: 1,
+            hide_nsfw: true,
+            denied_source_ids: vec![2],
+            blocked_genres: vec!["Horror".to_string()],
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        let restriction = svc.get_restriction(1).await.unwrap();
+        assert_eq!(restriction.allowed_source_ids, None);
+        assert_eq!(restriction.denied_source_ids, vec![2]);
+        assert_eq!(restriction.blocked_genres, vec!["Horror".to_string()]);
+
+        let restriction = svc.get_restriction(2).await.unwrap();
+        assert!(restriction.denied_source_ids.is_empty());
+        assert!(restriction.blocked_genres.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_set_unrestricted_policy_removes_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let svc = service(dir.path()).await;
+
+        svc.set_policy(ContentPolicy {
+            user_id: 1,
+            denied_source_ids: vec![1],
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        assert!(svc.check_source(1, 1).await.is_err());
+
+        svc.set_policy(ContentPolicy {
+            user_id: 1,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        assert!(svc.check_source(1, 1).await.is_ok());
+        assert!(svc.get_policy(1).await.unwrap().is_unrestricted());
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+
+use crate::domain::repositories::http_profile::{
+    HttpProfileRepository, HttpProfileRepositoryError,
+};
+use tanoshi_lib::prelude::HttpProfile;
+use tanoshi_vm::prelude::ExtensionManager;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum HttpProfileError {
+    #[error("repository error: {0}")]
+    RepositoryError(#[from] HttpProfileRepositoryError),
+    #[error("other error: {0}")]
+    Other(#[from] anyhow::Error),
+}
+
+#[derive(Clone)]
+pub struct HttpProfileService<R>
+where
+    R: HttpProfileRepository,
+{
+    repo: R,
+    extension_manager: ExtensionManager,
+    default_profile: HttpProfile,
+    source_profiles: HashMap<i64, HttpProfile>,
+}
+
+impl<R> HttpProfileService<R>
+where
+    R: HttpProfileRepository,
+{
+    /// `default_profile` and `source_profiles` come from config, profiles stored in
+    /// database take precedence over them
+    pub fn new(
+        repo: R,
+        extension_manager: ExtensionManager,
+        default_profile: HttpProfile,
+        source_profiles: HashMap<i64, HttpProfile>,
+    ) -> Self {
+        Self {
+            repo,
+            extension_manager,
+            default_profile,
+            source_profiles,
+        }
+    }
+
+    fn merge(&self, source_id: i64, profile: Option<HttpProfile>) -> Option<HttpProfile> {
+        match (self.source_profiles.get(&source_id), profile) {
+            (Some(config), Some(profile)) => Some(config.merge(&profile)),
+            (Some(config), None) => Some(config.clone()),
+            (None, profile) => profile,
+        }
+    }
+
+    /// Apply default and every source profiles to extension manager
+    pub async fn apply_all(&self) -> Result<(), HttpProfileError> {
+        self.extension_manager
+            .set_default_http_profile(self.default_profile.clone())?;
+
+        let mut profiles: HashMap<i64, HttpProfile> =
+            self.repo.get_http_profiles().await?.into_iter().collect();
+        for source_id in self.source_profiles.keys() {
+            profiles.entry(*source_id).or_default();
+        }
+
+        for (source_id, profile) in profiles {
+            self.extension_manager
+                .set_http_profile(source_id, self.merge(source_id, Some(profile)))?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns profile stored for a source, without config and extension values
+    pub async fn get_source_http_profile(
+        &self,
+        source_id: i64,
+    ) -> Result<Option<HttpProfile>, HttpProfileError> {
+        Ok(self.repo.get_http_profile(source_id).await?)
+    }
+
+    /// Returns profile used by requests to a source
+    pub fn get_effective_http_profile(
+        &self,
+        source_id: i64,
+    ) -> Result<HttpProfile, HttpProfileError> {
+        Ok(self.extension_manager.http_profile(source_id)?)
+    }
+
+    pub async fn set_source_http_profile(
+        &self,
+        source_id: i64,
+        profile: HttpProfile,
+    ) -> Result<(), HttpProfileError> {
+        self.repo.set_http_profile(source_id, &profile).await?;
+        self.extension_manager
+            .set_http_profile(source_id, self.merge(source_id, Some(profile)))?;
+
+        Ok(())
+    }
+
+    pub async fn delete_source_http_profile(&self, source_id: i64) -> Result<(), HttpProfileError> {
+        self.repo.delete_http_profile(source_id).await?;
+        self.extension_manager
+            .set_http_profile(source_id, self.merge(source_id, None))?;
+
+        Ok(())
+    }
+}
@@ -1,17 +1,39 @@
 use crate::domain::{
-    entities::image::{Image, ImageUri},
+    entities::image::{
+        content_type_from_path, Image, ImageCacheStats, ImageContent, ImageOrigin, ImageUri,
+        ImageUrlClaims,
+    },
     repositories::{
         image::{ImageRepository, ImageRepositoryError},
         image_cache::{ImageCacheRepository, ImageCacheRepositoryError},
     },
 };
-use std::convert::TryFrom;
+use bytes::Bytes;
+use chrono::Utc;
+use sha2::{Digest, Sha256};
+use std::{
+    convert::TryFrom,
+    path::{Path, PathBuf},
+};
 use thiserror::Error;
 
+/// Largest cover image accepted for upload
+const MAX_COVER_SIZE: usize = 5 * 1024 * 1024;
+
 #[derive(Debug, Error)]
 pub enum ImageError {
     #[error("error request image")]
     RequestError,
+    #[error("invalid image url")]
+    InvalidUrl,
+    #[error("image url expired")]
+    Expired,
+    #[error("image url belongs to another user")]
+    UserMismatch,
+    #[error("image is not a png, jpeg, gif or webp")]
+    UnsupportedFormat,
+    #[error("image is larger than {0} bytes")]
+    TooLarge(usize),
     #[error("repository error: {0}")]
     RepositoryError(#[from] ImageRepositoryError),
     #[error("cache error: {0}")]
@@ -28,6 +50,10 @@ where
 {
     repo: R,
     cache_repo: C,
+    /// Image urls are valid for at least this many seconds and at most twice as long
+    url_ttl: i64,
+    /// Directory of covers uploaded by users
+    cover_path: PathBuf,
 }
 
 impl<C, R> ImageService<C, R>
@@ -35,46 +61,340 @@ where
     C: ImageCacheRepository,
     R: ImageRepository,
 {
-    pub fn new(repo: R, cache_repo: C) -> Self {
-        Self { repo, cache_repo }
+    pub fn new<P: AsRef<Path>>(repo: R, cache_repo: C, url_ttl: u64, cover_path: P) -> Self {
+        Self {
+            repo,
+            cache_repo,
+            url_ttl: (url_ttl as i64).max(1),
+            cover_path: PathBuf::new().join(cover_path),
+        }
+    }
+
+    /// Check signature and expiry of an image url, returns the decrypted uri, where it is used
+    /// and the user it was created for
+    pub fn verify_image_url(
+        &self,
+        secret: &str,
+        encrypted_url: &str,
+    ) -> Result<(ImageUri, ImageOrigin, i64), ImageError> {
+        let (uri, origin, claims) = ImageUri::from_signed(secret, encrypted_url).map_err(|e| {
+            debug!("rejected image url: {e}");
+            ImageError::InvalidUrl
+        })?;
+
+        if claims.expires_at < Utc::now().timestamp() {
+            return Err(ImageError::Expired);
+        }
+
+        Ok((uri, origin, claims.user_id))
     }
 
+    /// Fetch image of a signed url, if the requesting user is known it has to be the one
+    /// the url was created for. Local images are not read yet, see [`ImageService::read_image`].
     pub async fn fetch_image(
         &self,
         secret: &str,
         encrypted_url: &str,
         referer: Option<&String>,
-    ) -> Result<Image, ImageError> {
-        if let Ok(image) = self.cache_repo.get(encrypted_url).await {
-            return Ok(image);
+        user_id: Option<i64>,
+    ) -> Result<ImageContent, ImageError> {
+        let (uri, origin, url_user_id) = self.verify_image_url(secret, encrypted_url)?;
+        if user_id.map_or(false, |user_id| user_id != url_user_id) {
+            return Err(ImageError::UserMismatch);
         }
 
-        let uri = ImageUri::from_encrypted(secret, encrypted_url)
-            .map_err(|e| ImageError::Other(anyhow::anyhow!("{e}")))?;
-
         let image = match uri {
             ImageUri::Remote(url) => {
-                let image = self.repo.fetch_image_from_url(&url, referer).await?;
-                if let Err(e) = self.cache_repo.set(encrypted_url, &image).await {
-                    error!("error cache image {encrypted_url}: {e}");
+                let key = cache_key(&url);
+                if let Ok(image) = self.cache_repo.get(&key).await {
+                    return Ok(ImageContent::Memory(image));
                 }
 
-                image
-            }
-            ImageUri::File(path) => self.repo.fetch_image_from_file(&path).await?,
-            ImageUri::Archive(archive, filename) => {
-                self.repo
-                    .fetch_image_from_archive(&archive, &filename)
-                    .await?
+                let image = self
+                    .repo
+                    .fetch_image_from_url(&url, origin.source_id, referer)
+                    .await?;
+                if let Err(e) = self.cache_repo.set(&key, &origin, &image).await {
+                    error!("error cache image {url}: {e}");
+                }
+
+                ImageContent::Memory(image)
             }
+            ImageUri::File(path) => ImageContent::File {
+                metadata: self.repo.fetch_file_metadata(&path).await?,
+                path: path.into(),
+            },
+            ImageUri::Archive(archive, filename) => ImageContent::Archive {
+                metadata: self.repo.fetch_file_metadata(&archive).await?,
+                archive: archive.into(),
+                filename,
+            },
         };
 
         Ok(image)
     }
 
-    pub fn encrypt_image_url(&self, secret: &str, url: &str) -> Result<String, ImageError> {
+    /// Extract an image from its archive, the size of an image in an archive is only
+    /// known once it is extracted. Other images are returned as they are.
+    pub async fn extract_image(&self, content: ImageContent) -> Result<ImageContent, ImageError> {
+        match content {
+            ImageContent::Archive {
+                archive,
+                filename,
+                metadata,
+            } => {
+                let data = self
+                    .repo
+                    .fetch_image_from_archive(&archive, &filename)
+                    .await?;
+
+                Ok(ImageContent::Memory(Image {
+                    content_type: content_type_from_path(&filename),
+                    data,
+                    etag: metadata.etag(Some(&filename)),
+                    last_modified: metadata.last_modified,
+                }))
+            }
+            content => Ok(content),
+        }
+    }
+
+    /// Read `len` bytes of an image from `start`, only the requested part of a file is read
+    pub async fn read_image(
+        &self,
+        content: &ImageContent,
+        start: u64,
+        len: u64,
+    ) -> Result<Bytes, ImageError> {
+        match content {
+            ImageContent::Memory(image) => {
+                let start = (start as usize).min(image.data.len());
+                let end = start.saturating_add(len as usize).min(image.data.len());
+                Ok(image.data.slice(start..end))
+            }
+            ImageContent::File { path, .. } => Ok(self
+                .repo
+                .fetch_image_range_from_file(path, start, len)
+                .await?),
+            ImageContent::Archive { .. } => {
+                Err(anyhow::anyhow!("image has to be extracted from archive first").into())
+            }
+        }
+    }
+
+    /// Fetch a remote image into the cache ahead of a request, does nothing
+    /// if the image is already cached.
+    pub async fn prefetch_image(
+        &self,
+        url: &str,
+        origin: ImageOrigin,
+        referer: Option<&String>,
+    ) -> Result<(), ImageError> {
+        if !url.starts_with("http") {
+            return Ok(());
+        }
+
+        let key = cache_key(url);
+        if self.cache_repo.contains(&key).await? {
+            return Ok(());
+        }
+
+        let image = self
+            .repo
+            .fetch_image_from_url(url, origin.source_id, referer)
+            .await?;
+        self.cache_repo.set(&key, &origin, &image).await?;
+
+        Ok(())
+    }
+
+    /// Create an url for the image that only works for the user until it expires
+    pub fn encrypt_image_url(
+        &self,
+        secret: &str,
+        url: &str,
+        origin: ImageOrigin,
+        user_id: i64,
+    ) -> Result<String, ImageError> {
         let image_uri = ImageUri::try_from(url)?;
 
-        Ok(image_uri.into_encrypted(secret)?)
+        // round expiry up so the url stays the same for a while and can be cached by browsers
+        let now = Utc::now().timestamp();
+        let expires_at = (now / self.url_ttl + 2) * self.url_ttl;
+
+        Ok(image_uri.into_signed(
+            secret,
+            origin,
+            ImageUrlClaims {
+                user_id,
+                expires_at,
+            },
+        )?)
+    }
+
+    /// Store a cover uploaded by user for a manga, returns path of the stored image
+    pub async fn save_cover(
+        &self,
+        user_id: i64,
+        manga_id: i64,
+        data: &[u8],
+    ) -> Result<String, ImageError> {
+        if data.len() > MAX_COVER_SIZE {
+            return Err(ImageError::TooLarge(MAX_COVER_SIZE));
+        }
+        let extension = image_extension(data).ok_or(ImageError::UnsupportedFormat)?;
+
+        // every upload gets a new name so signed urls, and browser cache, of the old cover go stale
+        let path = self.cover_path.join(user_id.to_string()).join(format!(
+            "{manga_id}-{}.{extension}",
+            Utc::now().timestamp_millis()
+        ));
+        self.repo.write_image_to_file(&path, data).await?;
+
+        Ok(path.display().to_string())
+    }
+
+    /// Remove a cover stored by `save_cover`, paths outside of cover directory are left alone
+    pub async fn remove_cover(&self, path: &str) -> Result<(), ImageError> {
+        let path = Path::new(path);
+        if !path.starts_with(&self.cover_path) {
+            return Ok(());
+        }
+
+        Ok(self.repo.remove_image_file(path).await?)
+    }
+
+    pub async fn get_cache_stats(&self) -> Result<ImageCacheStats, ImageError> {
+        Ok(self.cache_repo.get_stats().await?)
+    }
+
+    pub async fn purge_cache_by_manga_id(&self, manga_id: i64) -> Result<u64, ImageError> {
+        Ok(self.cache_repo.purge_by_manga_id(manga_id).await?)
+    }
+
+    pub async fn purge_cache_by_source_id(&self, source_id: i64) -> Result<u64, ImageError> {
+        Ok(self.cache_repo.purge_by_source_id(source_id).await?)
+    }
+}
+
+/// Extension of an image by its magic bytes, the uploaded file name can't be trusted
+fn image_extension(data: &[u8]) -> Option<&'static str> {
+    match data {
+        [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, ..] => Some("png"),
+        [0xFF, 0xD8, 0xFF, ..] => Some("jpg"),
+        [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => Some("gif"),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some("webp"),
+        _ => None,
+    }
+}
+
+fn cache_key(url: &str) -> String {
+    format!("{:x}", Sha256::digest(url.as_bytes()))
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use tanoshi_vm::prelude::ExtensionManager;
+
+    use super::*;
+    use crate::infrastructure::{
+        database::establish_connection,
+        domain::repositories::{image::ImageRepositoryImpl, image_cache::ImageCacheRepositoryImpl},
+    };
+
+    const SECRET: &str = "secret";
+
+    /// No source is installed, so only a cached image can be served
+    async fn service(dir: &Path) -> ImageService<ImageCacheRepositoryImpl, ImageRepositoryImpl> {
+        let pool = establish_connection(&dir.join("tanoshi.db").display().to_string(), true)
+            .await
+            .unwrap();
+
+        let cache_path = dir.join("cache");
+        std::fs::create_dir_all(&cache_path).unwrap();
+
+        ImageService::new(
+            ImageRepositoryImpl::new(ExtensionManager::new(dir), HashMap::new()),
+            ImageCacheRepositoryImpl::new(pool, &cache_path, 1024 * 1024),
+            60,
+            dir.join("covers"),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_verify_image_url() {
+        let dir = tempfile::tempdir().unwrap();
+        let svc = service(dir.path()).await;
+        let url = "https://example.com/page.png";
+        let origin = ImageOrigin::page(1, 1);
+
+        let encrypted_url = svc.encrypt_image_url(SECRET, url, origin, 1).unwrap();
+        let (uri, _, user_id) = svc.verify_image_url(SECRET, &encrypted_url).unwrap();
+        assert_eq!(uri.to_string(), url);
+        assert_eq!(user_id, 1);
+
+        assert!(matches!(
+            svc.verify_image_url("other secret", &encrypted_url),
+            Err(ImageError::InvalidUrl)
+        ));
+
+        let expired_url = ImageUri::Remote(url.to_string())
+            .into_signed(
+                SECRET,
+                origin,
+                ImageUrlClaims {
+                    user_id: 1,
+                    expires_at: Utc::now().timestamp() - 1,
+                },
+            )
+            .unwrap();
+        assert!(matches!(
+            svc.verify_image_url(SECRET, &expired_url),
+            Err(ImageError::Expired)
+        ));
+
+        assert!(matches!(
+            svc.fetch_image(SECRET, &encrypted_url, None, Some(2)).await,
+            Err(ImageError::UserMismatch)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_image_from_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let svc = service(dir.path()).await;
+
+        let url = "https://example.com/page.png";
+        let origin = ImageOrigin::cover(1, 1);
+        let encrypted_url = svc.encrypt_image_url(SECRET, url, origin, 1).unwrap();
+
+        assert!(matches!(
+            svc.fetch_image(SECRET, &encrypted_url, None, Some(1)).await,
+            Err(ImageError::RepositoryError(
+                ImageRepositoryError::HostNotAllowed(_)
+            ))
+        ));
+
+        let image = Image::new("image/png".to_string(), Bytes::from_static(b"png"), None);
+        svc.cache_repo
+            .set(&cache_key(url), &origin, &image)
+            .await
+            .unwrap();
+
+        match svc.fetch_image(SECRET, &encrypted_url, None, Some(1)).await {
+            Ok(ImageContent::Memory(cached)) => {
+                assert_eq!(cached.data, image.data);
+                assert_eq!(cached.content_type, image.content_type);
+                assert_eq!(cached.etag, image.etag);
+            }
+            _ => panic!("image is not served from cache"),
+        }
+
+        assert!(matches!(
+            svc.fetch_image(SECRET, &encrypted_url, None, Some(2)).await,
+            Err(ImageError::UserMismatch)
+        ));
     }
 }
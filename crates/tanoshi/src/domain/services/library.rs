@@ -1,6 +1,7 @@
 use crate::domain::{
     entities::{
-        library::{Category, LibraryUpdate},
+        category_rule::CategoryRule,
+        library::{Category, LibraryFilter, LibraryPosition, LibrarySort, LibraryUpdate, TagCount},
         manga::Manga,
     },
     repositories::library::{LibraryRepository, LibraryRepositoryError},
@@ -8,10 +9,24 @@ use crate::domain::{
 
 use thiserror::Error;
 
+const MAX_TAGS: usize = 50;
+const MAX_TAG_LENGTH: usize = 50;
+const MAX_NOTE_LENGTH: usize = 10_000;
+
 #[derive(Debug, Error)]
 pub enum LibraryError {
     #[error("repository error: {0}")]
     RepositoryError(#[from] LibraryRepositoryError),
+    #[error("invalid category rule: {0}")]
+    InvalidRule(String),
+    #[error("manga can't be added to smart category {0}")]
+    SmartCategory(String),
+    #[error("invalid tags: {0}")]
+    InvalidTags(String),
+    #[error("rating {0} is not between 1 and 10")]
+    InvalidRating(i64),
+    #[error("note is longer than {0} characters")]
+    NoteTooLong(usize),
 }
 
 pub struct LibraryService<R>
@@ -46,31 +61,48 @@ where
         let category = if let Some(id) = id {
             self.repo.get_category_by_id(id).await?
         } else {
-            Category {
-                id: None,
-                name: "Default".to_string(),
-            }
+            Category::default()
         };
 
         Ok(category)
     }
 
+    /// Creates a smart category if `rule` is set
     pub async fn create_category(
         &self,
         user_id: i64,
         name: &str,
+        rule: Option<&str>,
     ) -> Result<Category, LibraryError> {
-        let category = self.repo.create_category(user_id, name).await?;
+        let rule = Self::validate_rule(rule)?;
+        let category = self.repo.create_category(user_id, name, rule).await?;
 
         Ok(category)
     }
 
-    pub async fn rename_category(&self, id: i64, name: &str) -> Result<Category, LibraryError> {
-        let category = self.repo.rename_category(id, name).await?;
+    /// Rule is kept if not set, setting one turns a category into a smart category
+    pub async fn update_category(
+        &self,
+        id: i64,
+        name: &str,
+        rule: Option<&str>,
+    ) -> Result<Category, LibraryError> {
+        let rule = Self::validate_rule(rule)?;
+        let category = self.repo.update_category(id, name, rule).await?;
 
         Ok(category)
     }
 
+    fn validate_rule(rule: Option<&str>) -> Result<Option<&str>, LibraryError> {
+        let rule = rule.map(str::trim).filter(|rule| !rule.is_empty());
+        if let Some(rule) = rule {
+            rule.parse::<CategoryRule>()
+                .map_err(|e| LibraryError::InvalidRule(e.to_string()))?;
+        }
+
+        Ok(rule)
+    }
+
     pub async fn delete_category(&self, id: i64) -> Result<(), LibraryError> {
         self.repo.delete_category(id).await?;
 
@@ -90,12 +122,37 @@ where
         Ok(manga)
     }
 
+    /// Returns manga after `after`, paired with their position to be used as cursor
+    pub async fn get_manga_from_library_page(
+        &self,
+        user_id: i64,
+        category_id: Option<i64>,
+        sort: LibrarySort,
+        filter: &LibraryFilter,
+        after: Option<&LibraryPosition>,
+        first: i64,
+    ) -> Result<Vec<(LibraryPosition, Manga)>, LibraryError> {
+        let manga = self
+            .repo
+            .get_manga_from_library_page(user_id, category_id, sort, filter, after, first)
+            .await?;
+
+        Ok(manga)
+    }
+
     pub async fn insert_manga_to_library(
         &self,
         user_id: i64,
         manga_id: i64,
         category_ids: Vec<i64>,
     ) -> Result<(), LibraryError> {
+        for category_id in category_ids.iter() {
+            let category = self.repo.get_category_by_id(*category_id).await?;
+            if category.rule.is_some() {
+                return Err(LibraryError::SmartCategory(category.name));
+            }
+        }
+
         self.repo
             .insert_manga_to_library(user_id, manga_id, &category_ids)
             .await?;
@@ -115,6 +172,84 @@ where
         Ok(())
     }
 
+    pub async fn get_tags(&self, user_id: i64) -> Result<Vec<TagCount>, LibraryError> {
+        Ok(self.repo.get_tags_by_user_id(user_id).await?)
+    }
+
+    /// Replace user's tags on manga, tags are trimmed and compared case insensitively.
+    /// Returns the tags that were saved.
+    pub async fn set_manga_tags(
+        &self,
+        user_id: i64,
+        manga_id: i64,
+        tags: Vec<String>,
+    ) -> Result<Vec<String>, LibraryError> {
+        let mut saved: Vec<String> = vec![];
+        for tag in tags {
+            let tag = tag.trim();
+            if tag.is_empty() || saved.iter().any(|saved| saved.eq_ignore_ascii_case(tag)) {
+                continue;
+            }
+            if tag.chars().count() > MAX_TAG_LENGTH {
+                return Err(LibraryError::InvalidTags(format!(
+                    "{tag} is longer than {MAX_TAG_LENGTH} characters"
+                )));
+            }
+            saved.push(tag.to_string());
+        }
+        if saved.len() > MAX_TAGS {
+            return Err(LibraryError::InvalidTags(format!(
+                "manga can have at most {MAX_TAGS} tags"
+            )));
+        }
+
+        self.repo.set_manga_tags(user_id, manga_id, &saved).await?;
+
+        Ok(saved)
+    }
+
+    /// Rating from 1 to 10, none to remove it
+    pub async fn set_manga_rating(
+        &self,
+        user_id: i64,
+        manga_id: i64,
+        rating: Option<i64>,
+    ) -> Result<(), LibraryError> {
+        if let Some(rating) = rating.filter(|rating| !(1..=10).contains(rating)) {
+            return Err(LibraryError::InvalidRating(rating));
+        }
+
+        self.repo
+            .set_manga_rating(user_id, manga_id, rating)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Blank note removes it
+    pub async fn set_manga_note(
+        &self,
+        user_id: i64,
+        manga_id: i64,
+        note: Option<String>,
+    ) -> Result<Option<String>, LibraryError> {
+        let note = note
+            .map(|note| note.trim().to_string())
+            .filter(|note| !note.is_empty());
+        if note
+            .as_ref()
+            .map_or(false, |note| note.chars().count() > MAX_NOTE_LENGTH)
+        {
+            return Err(LibraryError::NoteTooLong(MAX_NOTE_LENGTH));
+        }
+
+        self.repo
+            .set_manga_note(user_id, manga_id, note.as_deref())
+            .await?;
+
+        Ok(note)
+    }
+
     pub async fn get_library_recent_updates(
         &self,
         user_id: i64,
@@ -0,0 +1,201 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use thiserror::Error;
+
+use crate::infrastructure::config::LoginThrottleConfig;
+
+#[derive(Debug, Error)]
+pub enum LoginThrottleError {
+    #[error("too many failed login attempts, try again in {0} seconds")]
+    Locked(u64),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum ThrottleKey {
+    Username(String),
+    Ip(IpAddr),
+}
+
+#[derive(Debug, Default)]
+struct Attempts {
+    failures: Vec<Instant>,
+    locked_until: Option<Instant>,
+}
+
+/// Count failed logins per username and per address in memory, locking them temporarily
+/// once there are too many in the window
+#[derive(Clone)]
+pub struct LoginThrottleService {
+    config: LoginThrottleConfig,
+    attempts: Arc<Mutex<HashMap<ThrottleKey, Attempts>>>,
+}
+
+impl LoginThrottleService {
+    pub fn new(config: LoginThrottleConfig) -> Self {
+        Self {
+            config,
+            attempts: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn keys(username: &str, ip: Option<IpAddr>) -> Vec<ThrottleKey> {
+        let mut keys = vec![ThrottleKey::Username(username.to_lowercase())];
+        if let Some(ip) = ip {
+            keys.push(ThrottleKey::Ip(ip));
+        }
+        keys
+    }
+
+    fn max_attempts(&self, key: &ThrottleKey) -> u32 {
+        match key {
+            ThrottleKey::Username(_) => self.config.max_attempts_per_username,
+            ThrottleKey::Ip(_) => self.config.max_attempts_per_ip,
+        }
+    }
+
+    /// Returns error if either username or address is locked
+    pub fn check(&self, username: &str, ip: Option<IpAddr>) -> Result<(), LoginThrottleError> {
+        let now = Instant::now();
+        let attempts = self.attempts.lock().unwrap();
+
+        let remaining = Self::keys(username, ip)
+            .iter()
+            .filter_map(|key| attempts.get(key)?.locked_until)
+            .filter(|locked_until| *locked_until > now)
+            .map(|locked_until| locked_until - now)
+            .max();
+
+        match remaining {
+            Some(remaining) => Err(LoginThrottleError::Locked(remaining.as_secs() + 1)),
+            None => Ok(()),
+        }
+    }
+
+    /// Record a failed login, returns true if it caused a lockout
+    pub fn record_failure(&self, username: &str, ip: Option<IpAddr>) -> bool {
+        let now = Instant::now();
+        let window = Duration::from_secs(self.config.window);
+        let lockout = Duration::from_secs(self.config.lockout);
+
+        let mut attempts = self.attempts.lock().unwrap();
+
+        // forget entries that no longer count so the map doesn't grow forever
+        attempts.retain(|_, entry| {
+            entry.failures.retain(|at| now.duration_since(*at) < window);
+            !entry.failures.is_empty() || entry.locked_until.map_or(false, |until| until > now)
+        });
+
+        let mut locked = false;
+        for key in Self::keys(username, ip) {
+            let max_attempts = self.max_attempts(&key) as usize;
+            let entry = attempts.entry(key).or_default();
+            entry.failures.push(now);
+            if max_attempts > 0 && entry.failures.len() >= max_attempts {
+                entry.failures.clear();
+                entry.locked_until = Some(now + lockout);
+                locked = true;
+            }
+        }
+
+        locked
+    }
+
+    /// Successful login clears failures of the username, not of the address
+    pub fn record_success(&self, username: &str) {
+        self.attempts
+            .lock()
+            .unwrap()
+            .remove(&ThrottleKey::Username(username.to_lowercase()));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn service(max_attempts_per_username: u32, max_attempts_per_ip: u32) -> LoginThrottleService {
+        LoginThrottleService::new(LoginThrottleConfig {
+            max_attempts_per_username,
+            max_attempts_per_ip,
+            window: 60,
+            lockout: 60,
+        })
+    }
+
+    fn ip(addr: &str) -> Option<IpAddr> {
+        Some(addr.parse().unwrap())
+    }
+
+    #[test]
+    fn test_locks_username() {
+        let svc = service(3, 10);
+
+        assert!(!svc.record_failure("one", ip("10.0.0.1")));
+        assert!(!svc.record_failure("One", ip("10.0.0.2")));
+        assert!(svc.check("one", None).is_ok());
+        assert!(svc.record_failure("ONE", ip("10.0.0.3")));
+
+        assert!(matches!(
+            svc.check("one", ip("10.0.0.4")),
+            Err(LoginThrottleError::Locked(secs)) if secs > 0 && secs <= 61
+        ));
+        assert!(svc.check("two", ip("10.0.0.1")).is_ok());
+    }
+
+    #[test]
+    fn test_locks_address() {
+        let svc = service(10, 3);
+
+        assert!(!svc.record_failure("one", ip("10.0.0.1")));
+        assert!(!svc.record_failure("two", ip("10.0.0.1")));
+        assert!(svc.record_failure("three", ip("10.0.0.1")));
+
+        assert!(svc.check("four", ip("10.0.0.1")).is_err());
+        assert!(svc.check("four", ip("10.0.0.2")).is_ok());
+        assert!(svc.check("four", None).is_ok());
+    }
+
+    #[test]
+    fn test_success_clears_username_only() {
+        let svc = service(2, 3);
+
+        svc.record_failure("one", ip("10.0.0.1"));
+        svc.record_success("One");
+        assert!(!svc.record_failure("one", ip("10.0.0.1")));
+
+        // the address keeps its failures
+        assert!(svc.record_failure("two", ip("10.0.0.1")));
+        assert!(svc.check("one", ip("10.0.0.1")).is_err());
+    }
+
+    #[test]
+    fn test_zero_disables_limit() {
+        let svc = service(0, 0);
+
+        for _ in 0..20 {
+            assert!(!svc.record_failure("one", ip("10.0.0.1")));
+        }
+        assert!(svc.check("one", ip("10.0.0.1")).is_ok());
+    }
+
+    #[test]
+    fn test_failures_outside_window_are_forgotten() {
+        let svc = LoginThrottleService::new(LoginThrottleConfig {
+            max_attempts_per_username: 2,
+            max_attempts_per_ip: 2,
+            window: 0,
+            lockout: 0,
+        });
+
+        // failures outside the window are forgotten before counting
+        assert!(!svc.record_failure("one", ip("10.0.0.1")));
+        assert!(!svc.record_failure("one", ip("10.0.0.1")));
+        assert!(svc.check("one", ip("10.0.0.1")).is_ok());
+        assert!(svc.attempts.lock().unwrap().len() <= 2);
+    }
+}
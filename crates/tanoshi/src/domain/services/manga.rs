@@ -1,15 +1,28 @@
+use std::time::Duration;
+
 use anyhow::anyhow;
+use futures::{Stream, StreamExt};
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use tanoshi_lib::prelude::ResolvedUrl;
 use tanoshi_vm::prelude::ExtensionManager;
 use thiserror::Error;
 
 use crate::domain::{
-    entities::manga::{InputList, Manga},
+    entities::manga::{InputList, Manga, MangaOverride, MangaSearchFilter, SourceSearchResult},
     repositories::manga::{MangaRepository, MangaRepositoryError},
 };
 
+/// Number of sources searched at the same time by global search
+const GLOBAL_SEARCH_CONCURRENCY: usize = 8;
+
 #[derive(Debug, Error)]
 pub enum MangaError {
+    #[error("search query is empty")]
+    EmptyQuery,
+    #[error("invalid url: {0}")]
+    InvalidUrl(String),
+    #[error("no installed source recognizes {0}")]
+    UnresolvedUrl(String),
     #[error("other error: {0}")]
     Other(#[from] anyhow::Error),
 }
@@ -22,6 +35,7 @@ impl From<MangaRepositoryError> for MangaError {
     }
 }
 
+#[derive(Clone)]
 pub struct MangaService<R>
 where
     R: MangaRepository,
@@ -88,6 +102,51 @@ where
         Ok(fetched_manga)
     }
 
+    /// Search first page of every source, results are yielded as each source finishes
+    pub fn global_search(
+        &self,
+        source_ids: Vec<i64>,
+        query: &str,
+        timeout: Duration,
+    ) -> Result<impl Stream<Item = SourceSearchResult> + Send + 'static, MangaError> {
+        let query = query.trim().to_string();
+        if query.is_empty() {
+            return Err(MangaError::EmptyQuery);
+        }
+
+        let sources = self.sources.clone();
+        let stream = futures::stream::iter(source_ids)
+            .map(move |source_id| {
+                let sources = sources.clone();
+                let query = query.clone();
+                async move {
+                    let res = tokio::time::timeout(
+                        timeout,
+                        sources.search_manga(source_id, 1, Some(query), None),
+                    )
+                    .await;
+
+                    let (manga, error) = match res {
+                        Ok(Ok(manga)) => (manga.into_iter().map(Manga::from).collect(), None),
+                        Ok(Err(e)) => (vec![], Some(e.to_string())),
+                        Err(_) => (
+                            vec![],
+                            Some(format!("timed out after {} seconds", timeout.as_secs())),
+                        ),
+                    };
+
+                    SourceSearchResult {
+                        source_id,
+                        manga,
+                        error,
+                    }
+                }
+            })
+            .buffer_unordered(GLOBAL_SEARCH_CONCURRENCY);
+
+        Ok(stream)
+    }
+
     pub async fn fetch_manga_by_source_path(
         &self,
         source_id: i64,
@@ -110,6 +169,62 @@ where
         Ok(manga)
     }
 
+    /// Find installed source by host of the url and let it resolve the url into manga or chapter path
+    pub async fn resolve_url(&self, url: &str) -> Result<(i64, ResolvedUrl), MangaError> {
+        let host = reqwest::Url::parse(url.trim())
+            .ok()
+            .and_then(|url| url.host_str().map(strip_www))
+            .ok_or_else(|| MangaError::InvalidUrl(url.to_string()))?;
+
+        let sources = self.sources.list().await?.into_iter().filter(|source| {
+            reqwest::Url::parse(&source.url)
+                .ok()
+                .and_then(|url| url.host_str().map(strip_www))
+                .map_or(false, |source_host| {
+                    host == source_host || host.ends_with(&format!(".{source_host}"))
+                })
+        });
+
+        for source in sources {
+            match self
+                .sources
+                .resolve_url(source.id, url.trim().to_string())
+                .await
+            {
+                Ok(Some(resolved)) => return Ok((source.id, resolved)),
+                Ok(None) => {}
+                Err(e) => debug!("source {} failed to resolve {url}: {e}", source.id),
+            }
+        }
+
+        Err(MangaError::UnresolvedUrl(url.to_string()))
+    }
+
+    /// Search manga cached by the server, every word in query is matched as a prefix
+    pub async fn search_manga(
+        &self,
+        user_id: i64,
+        query: &str,
+        filter: &MangaSearchFilter,
+        limit: i64,
+    ) -> Result<Vec<Manga>, MangaError> {
+        let terms: Vec<String> = query
+            .split_whitespace()
+            .filter(|term| term.chars().any(char::is_alphanumeric))
+            .map(|term| format!("\"{}\"*", term.replace('"', "\"\"")))
+            .collect();
+        if terms.is_empty() {
+            return Err(MangaError::EmptyQuery);
+        }
+
+        let manga = self
+            .repo
+            .search_manga(user_id, &terms.join(" "), filter, limit.clamp(1, 100))
+            .await?;
+
+        Ok(manga)
+    }
+
     pub async fn fetch_manga_by_id(&self, id: i64, refresh: bool) -> Result<Manga, MangaError> {
         let mut manga = self.repo.get_manga_by_id(id).await?;
         if refresh {
@@ -125,4 +240,127 @@ where
 
         Ok(manga)
     }
+
+    pub async fn fetch_manga_by_ids(&self, ids: &[i64]) -> Result<Vec<Manga>, MangaError> {
+        Ok(self.repo.get_manga_by_ids(ids).await?)
+    }
+
+    pub async fn get_manga_override(
+        &self,
+        user_id: i64,
+        manga_id: i64,
+    ) -> Result<Option<MangaOverride>, MangaError> {
+        let manga_override = self
+            .repo
+            .get_manga_overrides(user_id, &[manga_id])
+            .await?
+            .into_iter()
+            .next();
+
+        Ok(manga_override)
+    }
+
+    /// Override metadata of a manga for user, blank fields go back to what source returns.
+    /// Uploaded cover is kept.
+    pub async fn set_manga_override(
+        &self,
+        user_id: i64,
+        manga_override: MangaOverride,
+    ) -> Result<Option<MangaOverride>, MangaError> {
+        self.repo.get_manga_by_id(manga_override.manga_id).await?;
+
+        let text = |value: Option<String>| {
+            value
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        let list = |values: Option<Vec<String>>| {
+            values
+                .map(|values| {
+                    values
+                        .into_iter()
+                        .filter_map(|value| text(Some(value)))
+                        .collect::<Vec<_>>()
+                })
+                .filter(|values| !values.is_empty())
+        };
+
+        let cover_path = self
+            .get_manga_override(user_id, manga_override.manga_id)
+            .await?
+            .and_then(|current| current.cover_path);
+        let manga_override = MangaOverride {
+            manga_id: manga_override.manga_id,
+            title: text(manga_override.title),
+            author: list(manga_override.author),
+            genre: list(manga_override.genre),
+            status: text(manga_override.status),
+            description: text(manga_override.description),
+            cover_path,
+        };
+
+        self.save_manga_override(user_id, manga_override).await
+    }
+
+    /// Replace uploaded cover of a manga, returns path of the previous cover so it can be removed
+    pub async fn set_manga_cover(
+        &self,
+        user_id: i64,
+        manga_id: i64,
+        cover_path: Option<String>,
+    ) -> Result<Option<String>, MangaError> {
+        self.repo.get_manga_by_id(manga_id).await?;
+
+        let mut manga_override =
+            self.get_manga_override(user_id, manga_id)
+                .await?
+                .unwrap_or(MangaOverride {
+                    manga_id,
+                    ..Default::default()
+                });
+        let previous_cover_path = std::mem::replace(&mut manga_override.cover_path, cover_path);
+
+        self.save_manga_override(user_id, manga_override).await?;
+
+        Ok(previous_cover_path)
+    }
+
+    /// Remove every override of a manga, returns path of the uploaded cover so it can be removed
+    pub async fn delete_manga_override(
+        &self,
+        user_id: i64,
+        manga_id: i64,
+    ) -> Result<Option<String>, MangaError> {
+        let cover_path = self
+            .get_manga_override(user_id, manga_id)
+            .await?
+            .and_then(|manga_override| manga_override.cover_path);
+
+        self.repo.delete_manga_override(user_id, manga_id).await?;
+
+        Ok(cover_path)
+    }
+
+    async fn save_manga_override(
+        &self,
+        user_id: i64,
+        manga_override: MangaOverride,
+    ) -> Result<Option<MangaOverride>, MangaError> {
+        if manga_override.is_empty() {
+            self.repo
+                .delete_manga_override(user_id, manga_override.manga_id)
+                .await?;
+            return Ok(None);
+        }
+
+        self.repo
+            .set_manga_override(user_id, &manga_override)
+            .await?;
+
+        Ok(Some(manga_override))
+    }
+}
+
+fn strip_www(host: &str) -> String {
+    host.trim_start_matches("www.").to_lowercase()
 }
@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+
+use futures::future::join_all;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use tanoshi_vm::prelude::ExtensionManager;
+use thiserror::Error;
+
+use crate::domain::{
+    entities::{
+        chapter::Chapter,
+        manga::Manga,
+        migration::{MigrationCandidate, MigrationResult},
+    },
+    repositories::{
+        chapter::{ChapterRepository, ChapterRepositoryError},
+        library::{LibraryRepository, LibraryRepositoryError},
+        manga::{MangaRepository, MangaRepositoryError},
+        migration::{MigrationRepository, MigrationRepositoryError},
+    },
+};
+
+#[derive(Debug, Error)]
+pub enum MigrationError {
+    #[error("repository error: {0}")]
+    RepositoryError(#[from] MigrationRepositoryError),
+    #[error("library repository error: {0}")]
+    LibraryRepositoryError(#[from] LibraryRepositoryError),
+    #[error("manga repository error: {0}")]
+    MangaRepositoryError(#[from] MangaRepositoryError),
+    #[error("chapter repository error: {0}")]
+    ChapterRepositoryError(#[from] ChapterRepositoryError),
+    #[error("cannot migrate manga to itself")]
+    SameManga,
+    #[error("other error: {0}")]
+    Other(#[from] anyhow::Error),
+}
+
+/// Lowercase a title and strip everything but letters and digits
+fn normalize_title(title: &str) -> String {
+    title
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Similarity of two titles between 0 and 1
+pub fn title_similarity(a: &str, b: &str) -> f64 {
+    strsim::normalized_levenshtein(&normalize_title(a), &normalize_title(b))
+}
+
+pub struct MigrationService<L, M, C, R>
+where
+    L: LibraryRepository,
+    M: MangaRepository,
+    C: ChapterRepository,
+    R: MigrationRepository,
+{
+    library_repo: L,
+    manga_repo: M,
+    chapter_repo: C,
+    repo: R,
+    extension_manager: ExtensionManager,
+}
+
+impl<L, M, C, R> MigrationService<L, M, C, R>
+where
+    L: LibraryRepository,
+    M: MangaRepository,
+    C: ChapterRepository,
+    R: MigrationRepository,
+{
+    pub fn new(
+        library_repo: L,
+        manga_repo: M,
+        chapter_repo: C,
+        repo: R,
+        extension_manager: ExtensionManager,
+    ) -> Self {
+        Self {
+            library_repo,
+            manga_repo,
+            chapter_repo,
+            repo,
+            extension_manager,
+        }
+    }
+
+    /// Group manga in user's library which titles are at least `threshold` similar
+    pub async fn find_duplicates(
+        &self,
+        user_id: i64,
+        threshold: f64,
+    ) -> Result<Vec<Vec<Manga>>, MigrationError> {
+        let manga = self.library_repo.get_manga_from_library(user_id).await?;
+        let titles: Vec<String> = manga.iter().map(|m| normalize_title(&m.title)).collect();
+
+        // union find over every pair of similar titles
+        let mut parents: Vec<usize> = (0..manga.len()).collect();
+        fn find(parents: &mut [usize], i: usize) -> usize {
+            let mut root = i;
+            while parents[root] != root {
+                root = parents[root];
+            }
+            parents[i] = root;
+            root
+        }
+
+        for i in 0..titles.len() {
+            for j in (i + 1)..titles.len() {
+                if strsim::normalized_levenshtein(&titles[i], &titles[j]) >= threshold {
+                    let (a, b) = (find(&mut parents, i), find(&mut parents, j));
+                    parents[b] = a;
+                }
+            }
+        }
+
+        let mut groups: HashMap<usize, Vec<Manga>> = HashMap::new();
+        for (i, m) in manga.into_iter().enumerate() {
+            let root = find(&mut parents, i);
+            groups.entry(root).or_default().push(m);
+        }
+
+        let mut groups: Vec<Vec<Manga>> = groups
+            .into_values()
+            .filter(|group| group.len() > 1)
+            .collect();
+        groups.sort_by(|a, b| a[0].title.cmp(&b[0].title));
+
+        Ok(groups)
+    }
+
+    /// Search `source_ids`, except the source of the manga, for manga with similar title
+    pub async fn search_migration_candidates(
+        &self,
+        manga_id: i64,
+        source_ids: &[i64],
+        threshold: f64,
+    ) -> Result<Vec<MigrationCandidate>, MigrationError> {
+        let manga = self.manga_repo.get_manga_by_id(manga_id).await?;
+
+        let source_ids: Vec<i64> = source_ids
+            .iter()
+            .copied()
+            .filter(|source_id| *source_id != manga.source_id)
+            .collect();
+
+        let results = join_all(source_ids.into_iter().map(|source_id| {
+            let title = manga.title.clone();
+            async move {
+                self.extension_manager
+                    .search_manga(source_id, 1, Some(title), None)
+                    .await
+                    .map_err(|e| (source_id, e))
+            }
+        }))
+        .await;
+
+        let mut candidates: Vec<MigrationCandidate> = results
+            .into_iter()
+            .filter_map(|res| match res {
+                Ok(res) => Some(res),
+                Err((source_id, e)) => {
+                    debug!("failed to search source {source_id}: {e}");
+                    None
+                }
+            })
+            .flatten()
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .map(|m| {
+                let m = Manga::from(m);
+                MigrationCandidate {
+                    similarity: title_similarity(&manga.title, &m.title),
+                    manga: m,
+                }
+            })
+            .filter(|candidate| candidate.similarity >= threshold)
+            .collect();
+
+        candidates.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+
+        Ok(candidates)
+    }
+
+    /// Move categories, history and tracker links of a user to manga at `path` of `source_id`
+    pub async fn migrate_manga(
+        &self,
+        user_id: i64,
+        manga_id: i64,
+        source_id: i64,
+        path: &str,
+    ) -> Result<(Manga, MigrationResult), MigrationError> {
+        let target = match self
+            .manga_repo
+            .get_manga_by_source_path(source_id, path)
+            .await
+        {
+            Ok(manga) => manga,
+            Err(_) => {
+                let mut manga: Manga = self
+                    .extension_manager
+                    .get_manga_detail(source_id, path.to_string())
+                    .await?
+                    .into();
+                self.manga_repo.insert_manga(&mut manga).await?;
+                manga
+            }
+        };
+
+        if target.id == manga_id {
+            return Err(MigrationError::SameManga);
+        }
+
+        // chapters are needed to match history by chapter number
+        let chapters: Vec<Chapter> = self
+            .extension_manager
+            .get_chapters(target.source_id, target.path.clone())
+            .await?
+            .into_par_iter()
+            .map(|c| {
+                let mut c: Chapter = c.into();
+                c.manga_id = target.id;
+                c
+            })
+            .collect();
+
+        if !chapters.is_empty() {
+            self.chapter_repo.insert_chapters(&chapters).await?;
+        }
+
+        let result = self
+            .repo
+            .migrate_manga(user_id, manga_id, target.id)
+            .await?;
+
+        Ok((target, result))
+    }
+}
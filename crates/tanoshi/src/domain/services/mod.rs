@@ -1,9 +1,21 @@
+pub mod api_key;
+pub mod audit_log;
 pub mod chapter;
+pub mod content_policy;
 pub mod download;
 pub mod history;
+pub mod http_profile;
 pub mod image;
 pub mod library;
+pub mod login_throttle;
 pub mod manga;
+pub mod migration;
+pub mod oidc;
+pub mod preference;
+pub mod proxy_auth;
+pub mod session;
 pub mod source;
+pub mod statistics;
+pub mod totp;
 pub mod tracker;
 pub mod user;
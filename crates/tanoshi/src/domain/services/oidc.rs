@@ -0,0 +1,187 @@
+use thiserror::Error;
+
+use crate::{
+    domain::{
+        entities::{identity::UserIdentity, user::User},
+        repositories::{
+            identity::{IdentityRepository, IdentityRepositoryError},
+            user::{UserRepository, UserRepositoryError},
+        },
+    },
+    infrastructure::oidc::{OidcClient, OidcSession, OidcUserInfo},
+};
+
+#[derive(Debug, Error)]
+pub enum OidcError {
+    #[error("single sign-on is not configured")]
+    NotConfigured,
+    #[error("invalid state")]
+    InvalidState,
+    #[error("no user linked to this account")]
+    NotLinked,
+    #[error("account already linked to another user")]
+    AlreadyLinked,
+    #[error("no username in userinfo")]
+    NoUsername,
+    #[error("username {0} already taken")]
+    UsernameTaken(String),
+    #[error("repository error: {0}")]
+    RepositoryError(#[from] IdentityRepositoryError),
+    #[error("user repository error: {0}")]
+    UserRepositoryError(#[from] UserRepositoryError),
+    #[error("other error: {0}")]
+    Other(#[from] anyhow::Error),
+}
+
+#[derive(Clone)]
+pub struct OidcService<I, U>
+where
+    I: IdentityRepository,
+    U: UserRepository,
+{
+    client: Option<OidcClient>,
+    repo: I,
+    user_repo: U,
+}
+
+impl<I, U> OidcService<I, U>
+where
+    I: IdentityRepository,
+    U: UserRepository,
+{
+    pub fn new(client: Option<OidcClient>, repo: I, user_repo: U) -> Self {
+        Self {
+            client,
+            repo,
+            user_repo,
+        }
+    }
+
+    fn client(&self) -> Result<&OidcClient, OidcError> {
+        self.client.as_ref().ok_or(OidcError::NotConfigured)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.client.is_some()
+    }
+
+    pub fn is_password_login_disabled(&self) -> bool {
+        self.client
+            .as_ref()
+            .map(|client| client.config().disable_password_login)
+            .unwrap_or(false)
+    }
+
+    pub fn login_start(&self) -> Result<OidcSession, OidcError> {
+        Ok(self.client()?.get_authorize_url())
+    }
+
+    async fn exchange_code(&self, code: String, state: String) -> Result<OidcUserInfo, OidcError> {
+        let client = self.client()?;
+        let login = client.take_pending(&state).ok_or(OidcError::InvalidState)?;
+
+        Ok(client.exchange_code(code, login).await?)
+    }
+
+    /// Finish login, returns the user linked to the account which may be newly created
+    pub async fn login_end(&self, code: String, state: String) -> Result<User, OidcError> {
+        let info = self.exchange_code(code, state).await?;
+
+        let mut user = match self
+            .repo
+            .get_identity(self.client()?.issuer(), &info.subject)
+            .await
+        {
+            Ok(identity) => self.user_repo.get_user_by_id(identity.user_id).await?,
+            Err(IdentityRepositoryError::NotFound) => self.provision_user(&info).await?,
+            Err(e) => return Err(e.into()),
+        };
+
+        if let Some(is_admin) = info.is_admin {
+            if user.is_admin != is_admin {
+                self.user_repo
+                    .update_user_is_admin(user.id, is_admin)
+                    .await?;
+                user.is_admin = is_admin;
+            }
+        }
+
+        Ok(user)
+    }
+
+    /// Link account on first login to an existing user with the same username or create a new user
+    async fn provision_user(&self, info: &OidcUserInfo) -> Result<User, OidcError> {
+        let client = self.client()?;
+        let username = info.username.clone().ok_or(OidcError::NoUsername)?;
+
+        let user = match self.user_repo.get_user_by_username(username.clone()).await {
+            Ok(user) if client.config().link_by_username => user,
+            Ok(_) => return Err(OidcError::UsernameTaken(username)),
+            Err(UserRepositoryError::NotFound)
+            | Err(UserRepositoryError::DbError(sqlx::Error::RowNotFound)) => {
+                if !client.config().auto_create_user {
+                    return Err(OidcError::NotLinked);
+                }
+
+                // first user is admin, same as registering without single sign-on
+                let is_admin = match info.is_admin {
+                    Some(is_admin) => is_admin,
+                    None => self.user_repo.get_users_count().await? == 0,
+                };
+
+                let id = self
+                    .user_repo
+                    .insert_user(User {
+                        username,
+                        is_admin,
+                        ..Default::default()
+                    })
+                    .await?;
+                info!("created user {id} from single sign-on");
+
+                self.user_repo.get_user_by_id(id).await?
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        self.repo
+            .insert_identity(user.id, client.issuer(), &info.subject)
+            .await?;
+
+        Ok(user)
+    }
+
+    /// Link account to a logged in user
+    pub async fn link_account(
+        &self,
+        user_id: i64,
+        code: String,
+        state: String,
+    ) -> Result<(), OidcError> {
+        let info = self.exchange_code(code, state).await?;
+        let issuer = self.client()?.issuer();
+
+        match self.repo.get_identity(issuer, &info.subject).await {
+            Ok(identity) if identity.user_id == user_id => Ok(()),
+            Ok(_) => Err(OidcError::AlreadyLinked),
+            Err(IdentityRepositoryError::NotFound) => {
+                self.repo
+                    .insert_identity(user_id, issuer, &info.subject)
+                    .await?;
+                Ok(())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub async fn unlink_account(&self, user_id: i64) -> Result<u64, OidcError> {
+        Ok(self
+            .repo
+            .delete_identities_by_user_id(user_id, self.client()?.issuer())
+            .await?)
+    }
+
+    pub async fn get_identities(&self, user_id: i64) -> Result<Vec<UserIdentity>, OidcError> {
+        Ok(self.repo.get_identities_by_user_id(user_id).await?)
+    }
+}
@@ -0,0 +1,146 @@
+use chrono::Utc;
+use thiserror::Error;
+use tokio::sync::broadcast;
+
+use crate::domain::{
+    entities::preference::{Preference, PreferenceChange},
+    repositories::preference::{PreferenceRepository, PreferenceRepositoryError},
+};
+
+const MAX_KEY_LENGTH: usize = 64;
+const MAX_VALUE_LENGTH: usize = 64 * 1024;
+
+#[derive(Debug, Error)]
+pub enum PreferenceError {
+    #[error("invalid preference key, only lowercase letters, digits, '_' and '-' are allowed")]
+    InvalidKey,
+    #[error("preference value must be a json object: {0}")]
+    InvalidValue(String),
+    #[error("preference value is larger than {MAX_VALUE_LENGTH} bytes")]
+    ValueTooLarge,
+    #[error("preference was saved with a newer schema version, update the client")]
+    OutdatedSchema,
+    #[error("repository error: {0}")]
+    RepositoryError(#[from] PreferenceRepositoryError),
+}
+
+#[derive(Clone)]
+pub struct PreferenceService<R>
+where
+    R: PreferenceRepository,
+{
+    repo: R,
+    tx: broadcast::Sender<PreferenceChange>,
+}
+
+impl<R> PreferenceService<R>
+where
+    R: PreferenceRepository,
+{
+    pub fn new(repo: R) -> Self {
+        let (tx, _) = broadcast::channel(64);
+        Self { repo, tx }
+    }
+
+    fn validate_key(key: &str) -> Result<(), PreferenceError> {
+        let valid = !key.is_empty()
+            && key.len() <= MAX_KEY_LENGTH
+            && key
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-');
+
+        if valid {
+            Ok(())
+        } else {
+            Err(PreferenceError::InvalidKey)
+        }
+    }
+
+    fn notify(&self, preference: Preference, deleted: bool) {
+        // error only means nobody is subscribed
+        let _ = self.tx.send(PreferenceChange {
+            preference,
+            deleted,
+        });
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<PreferenceChange> {
+        self.tx.subscribe()
+    }
+
+    pub async fn get_preferences(
+        &self,
+        user_id: i64,
+        manga_id: Option<i64>,
+    ) -> Result<Vec<Preference>, PreferenceError> {
+        Ok(self.repo.get_preferences(user_id, manga_id).await?)
+    }
+
+    pub async fn get_preference(
+        &self,
+        user_id: i64,
+        key: &str,
+        manga_id: Option<i64>,
+    ) -> Result<Option<Preference>, PreferenceError> {
+        match self.repo.get_preference(user_id, key, manga_id).await {
+            Ok(preference) => Ok(Some(preference)),
+            Err(PreferenceRepositoryError::NotFound) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Replace the whole document, a document saved with a newer schema version is not overwritten
+    pub async fn set_preference(
+        &self,
+        user_id: i64,
+        key: &str,
+        manga_id: Option<i64>,
+        schema_version: i64,
+        value: &str,
+    ) -> Result<Preference, PreferenceError> {
+        Self::validate_key(key)?;
+
+        if value.len() > MAX_VALUE_LENGTH {
+            return Err(PreferenceError::ValueTooLarge);
+        }
+        match serde_json::from_str::<serde_json::Value>(value) {
+            Ok(serde_json::Value::Object(_)) => {}
+            Ok(_) => return Err(PreferenceError::InvalidValue("not an object".to_string())),
+            Err(e) => return Err(PreferenceError::InvalidValue(e.to_string())),
+        }
+
+        let preference = Preference {
+            user_id,
+            key: key.to_string(),
+            manga_id: manga_id.filter(|manga_id| *manga_id > 0),
+            schema_version,
+            value: value.to_string(),
+            updated_at: Utc::now().naive_utc(),
+        };
+
+        if !self.repo.upsert_preference(&preference).await? {
+            return Err(PreferenceError::OutdatedSchema);
+        }
+
+        self.notify(preference.clone(), false);
+
+        Ok(preference)
+    }
+
+    pub async fn delete_preference(
+        &self,
+        user_id: i64,
+        key: &str,
+        manga_id: Option<i64>,
+    ) -> Result<bool, PreferenceError> {
+        let preference = match self.get_preference(user_id, key, manga_id).await? {
+            Some(preference) => preference,
+            None => return Ok(false),
+        };
+
+        self.repo.delete_preference(user_id, key, manga_id).await?;
+        self.notify(preference, true);
+
+        Ok(true)
+    }
+}
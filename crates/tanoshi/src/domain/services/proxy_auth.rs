@@ -0,0 +1,107 @@
+use std::net::IpAddr;
+
+use thiserror::Error;
+
+use crate::{
+    domain::{
+        entities::user::User,
+        repositories::user::{UserRepository, UserRepositoryError},
+    },
+    infrastructure::{auth::Claims, config::ProxyAuthConfig},
+};
+
+#[derive(Debug, Error)]
+pub enum ProxyAuthError {
+    #[error("proxy auth is not configured")]
+    NotConfigured,
+    #[error("user {0} not found")]
+    UserNotFound(String),
+    #[error("user repository error: {0}")]
+    UserRepositoryError(#[from] UserRepositoryError),
+}
+
+#[derive(Clone)]
+pub struct ProxyAuthService<U>
+where
+    U: UserRepository,
+{
+    config: Option<ProxyAuthConfig>,
+    user_repo: U,
+}
+
+impl<U> ProxyAuthService<U>
+where
+    U: UserRepository,
+{
+    pub fn new(config: Option<ProxyAuthConfig>, user_repo: U) -> Self {
+        Self { config, user_repo }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.is_some()
+    }
+
+    pub fn is_trusted_proxy(&self, addr: IpAddr) -> bool {
+        // ipv4 connections may be reported as ipv4-mapped ipv6 addresses
+        let addr = match addr {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(addr),
+            addr => addr,
+        };
+
+        self.config
+            .as_ref()
+            .map(|config| config.trusted_proxies.iter().any(|net| net.contains(&addr)))
+            .unwrap_or(false)
+    }
+
+    /// Returns the header to read username from if the request comes from a trusted proxy
+    pub fn trusted_header(&self, addr: IpAddr) -> Option<&str> {
+        self.config
+            .as_ref()
+            .filter(|_| self.is_trusted_proxy(addr))
+            .map(|config| config.header.as_str())
+    }
+
+    /// Returns claims for the username set by the proxy, creating the user if allowed
+    pub async fn authenticate(&self, username: &str) -> Result<Claims, ProxyAuthError> {
+        let config = self.config.as_ref().ok_or(ProxyAuthError::NotConfigured)?;
+
+        let user = match self
+            .user_repo
+            .get_user_by_username(username.to_string())
+            .await
+        {
+            Ok(user) => user,
+            Err(UserRepositoryError::NotFound)
+            | Err(UserRepositoryError::DbError(sqlx::Error::RowNotFound)) => {
+                if !config.auto_create_user {
+                    return Err(ProxyAuthError::UserNotFound(username.to_string()));
+                }
+
+                // first user is admin, same as registering normally
+                let is_admin = self.user_repo.get_users_count().await? == 0;
+                let id = self
+                    .user_repo
+                    .insert_user(User {
+                        username: username.to_string(),
+                        is_admin,
+                        ..Default::default()
+                    })
+                    .await?;
+                info!("created user {id} from proxy auth");
+
+                self.user_repo.get_user_by_id(id).await?
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Claims {
+            sub: user.id,
+            username: user.username,
+            is_admin: user.is_admin,
+            sid: 0,
+            exp: usize::MAX,
+            scopes: None,
+        })
+    }
+}
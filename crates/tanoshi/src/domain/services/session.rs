@@ -0,0 +1,360 @@
+use base64::{engine::general_purpose, Engine};
+use chrono::{Duration, Utc};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::{
+    domain::{
+        entities::{
+            session::{AuthToken, Session},
+            user::User,
+        },
+        repositories::{
+            session::{SessionRepository, SessionRepositoryError},
+            user::{UserRepository, UserRepositoryError},
+        },
+    },
+    infrastructure::auth::{self, Claims, TotpEnrollmentClaims},
+};
+
+/// Lifetime of a two-factor enrollment token in seconds
+const TOTP_ENROLLMENT_TOKEN_TTL: i64 = 10 * 60;
+
+#[derive(Debug, Error)]
+pub enum SessionError {
+    #[error("session expired or revoked")]
+    InvalidSession,
+    #[error("repository error: {0}")]
+    RepositoryError(#[from] SessionRepositoryError),
+    #[error("user repository error: {0}")]
+    UserRepositoryError(#[from] UserRepositoryError),
+    #[error("other error: {0}")]
+    Other(#[from] anyhow::Error),
+}
+
+fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+fn generate_token() -> String {
+    let mut token = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut token);
+    general_purpose::URL_SAFE_NO_PAD.encode(token)
+}
+
+#[derive(Clone)]
+pub struct SessionService<S, U>
+where
+    S: SessionRepository,
+    U: UserRepository,
+{
+    repo: S,
+    user_repo: U,
+    secret: String,
+    access_token_ttl: i64,
+    refresh_token_ttl: i64,
+}
+
+impl<S, U> SessionService<S, U>
+where
+    S: SessionRepository,
+    U: UserRepository,
+{
+    /// `access_token_ttl` and `refresh_token_ttl` are in seconds
+    pub fn new(
+        repo: S,
+        user_repo: U,
+        secret: &str,
+        access_token_ttl: u64,
+        refresh_token_ttl: u64,
+    ) -> Self {
+        Self {
+            repo,
+            user_repo,
+            secret: secret.to_string(),
+            access_token_ttl: access_token_ttl as i64,
+            refresh_token_ttl: refresh_token_ttl as i64,
+        }
+    }
+
+    fn issue_access_token(&self, user: &User, session_id: i64) -> Result<AuthToken, SessionError> {
+        let expires_at = Utc::now().timestamp() + self.access_token_ttl;
+        let claims = Claims {
+            sub: user.id,
+            username: user.username.clone(),
+            is_admin: user.is_admin,
+            sid: session_id,
+            exp: expires_at as usize,
+            scopes: None,
+        };
+
+        Ok(AuthToken {
+            access_token: auth::encode_jwt(&self.secret, &claims)?,
+            refresh_token: String::new(),
+            expires_at,
+            totp_enrollment: false,
+        })
+    }
+
+    /// Token for a user that has to enroll two-factor authentication before getting a session
+    pub fn create_totp_enrollment_token(&self, user: &User) -> Result<AuthToken, SessionError> {
+        let expires_at = Utc::now().timestamp() + TOTP_ENROLLMENT_TOKEN_TTL;
+        let claims = TotpEnrollmentClaims {
+            sub: user.id,
+            exp: expires_at as usize,
+            totp_enrollment: true,
+        };
+
+        Ok(AuthToken {
+            access_token: auth::encode_jwt(&self.secret, &claims)?,
+            refresh_token: String::new(),
+            expires_at,
+            totp_enrollment: true,
+        })
+    }
+
+    /// Start a new session for an authenticated user
+    pub async fn create_session(
+        &self,
+        user: &User,
+        user_agent: Option<&str>,
+    ) -> Result<AuthToken, SessionError> {
+        if let Err(e) = self.repo.delete_inactive_sessions().await {
+            debug!("failed to delete inactive sessions: {e}");
+        }
+
+        let refresh_token = generate_token();
+        let expires_at = Utc::now().naive_utc() + Duration::seconds(self.refresh_token_ttl);
+        let session_id = self
+            .repo
+            .insert_session(user.id, &hash_token(&refresh_token), user_agent, expires_at)
+            .await?;
+
+        Ok(AuthToken {
+            refresh_token,
+            ..self.issue_access_token(user, session_id)?
+        })
+    }
+
+    /// Exchange a refresh token for a new access token, the refresh token is rotated
+    pub async fn refresh_session(&self, refresh_token: &str) -> Result<AuthToken, SessionError> {
+        let session = self
+            .repo
+            .get_session_by_refresh_token_hash(&hash_token(refresh_token))
+            .await
+            .map_err(|e| match e {
+                SessionRepositoryError::NotFound => SessionError::InvalidSession,
+                e => e.into(),
+            })?;
+
+        if !Self::is_active(&session) {
+            return Err(SessionError::InvalidSession);
+        }
+
+        let user = self.user_repo.get_user_by_id(session.user_id).await?;
+
+        let refresh_token = generate_token();
+        let expires_at = Utc::now().naive_utc() + Duration::seconds(self.refresh_token_ttl);
+        self.repo
+            .rotate_refresh_token(session.id, &hash_token(&refresh_token), expires_at)
+            .await?;
+
+        Ok(AuthToken {
+            refresh_token,
+            ..self.issue_access_token(&user, session.id)?
+        })
+    }
+
+    fn is_active(session: &Session) -> bool {
+        session.revoked_at.is_none() && session.expires_at > Utc::now().naive_utc()
+    }
+
+    /// Check that session of an access token is still active
+    pub async fn validate(&self, claims: &Claims) -> Result<(), SessionError> {
+        let session = self
+            .repo
+            .get_session_by_id(claims.sid)
+            .await
+            .map_err(|_| SessionError::InvalidSession)?;
+
+        if session.user_id != claims.sub || !Self::is_active(&session) {
+            return Err(SessionError::InvalidSession);
+        }
+
+        Ok(())
+    }
+
+    /// Decode an access token and check its session, returns claims if valid
+    pub async fn authenticate(&self, token: &str) -> Result<Claims, SessionError> {
+        let claims: Claims = auth::decode_jwt(&self.secret, token)?;
+        self.validate(&claims).await?;
+
+        Ok(claims)
+    }
+
+    /// Decode a token from [`Self::create_totp_enrollment_token`]
+    pub fn authenticate_totp_enrollment(
+        &self,
+        token: &str,
+    ) -> Result<TotpEnrollmentClaims, SessionError> {
+        Ok(auth::decode_jwt(&self.secret, token)?)
+    }
+
+    pub async fn get_sessions(&self, user_id: i64) -> Result<Vec<Session>, SessionError> {
+        Ok(self.repo.get_active_sessions_by_user_id(user_id).await?)
+    }
+
+    pub async fn revoke_session(&self, user_id: i64, id: i64) -> Result<u64, SessionError> {
+        Ok(self.repo.revoke_session(user_id, id).await?)
+    }
+
+    /// Revoke every session of a user, except `except_id` if given
+    pub async fn revoke_all_sessions(
+        &self,
+        user_id: i64,
+        except_id: Option<i64>,
+    ) -> Result<u64, SessionError> {
+        Ok(self
+            .repo
+            .revoke_sessions_by_user_id(user_id, except_id)
+            .await?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use sqlx::SqlitePool;
+
+    use super::*;
+    use crate::infrastructure::{
+        database::establish_connection,
+        domain::repositories::{session::SessionRepositoryImpl, user::UserRepositoryImpl},
+    };
+
+    type Service = SessionService<SessionRepositoryImpl, UserRepositoryImpl>;
+
+    async fn service(dir: &std::path::Path) -> Service {
+        let pool = establish_connection(&dir.join("tanoshi.db").display().to_string(), true)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO user(id, username, password) VALUES (1, 'one', '')")
+            .execute(&pool as &SqlitePool)
+            .await
+            .unwrap();
+
+        SessionService::new(
+            SessionRepositoryImpl::new(pool.clone()),
+            UserRepositoryImpl::new(pool),
+            "secret",
+            60,
+            60,
+        )
+    }
+
+    fn user() -> User {
+        User {
+            id: 1,
+            username: "one".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_refresh_rotates_refresh_token() {
+        let dir = tempfile::tempdir().unwrap();
+        let svc = service(dir.path()).await;
+
+        let token = svc.create_session(&user(), None).await.unwrap();
+        let claims = svc.authenticate(&token.access_token).await.unwrap();
+        assert_eq!(claims.sub, 1);
+
+        let refreshed = svc.refresh_session(&token.refresh_token).await.unwrap();
+        assert_ne!(refreshed.refresh_token, token.refresh_token);
+        assert_eq!(
+            svc.authenticate(&refreshed.access_token).await.unwrap().sid,
+            claims.sid
+        );
+
+        // the old refresh token is spent
+        assert!(matches!(
+            svc.refresh_session(&token.refresh_token).await,
+            Err(SessionError::InvalidSession)
+        ));
+        assert!(svc.refresh_session(&refreshed.refresh_token).await.is_ok());
+        assert!(matches!(
+            svc.refresh_session("unknown").await,
+            Err(SessionError::InvalidSession)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_revoked_session_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let svc = service(dir.path()).await;
+
+        let token = svc.create_session(&user(), None).await.unwrap();
+        let claims = svc.authenticate(&token.access_token).await.unwrap();
+
+        // only the owner can revoke a session
+        assert_eq!(svc.revoke_session(2, claims.sid).await.unwrap(), 0);
+        assert!(svc.validate(&claims).await.is_ok());
+
+        assert_eq!(svc.revoke_session(1, claims.sid).await.unwrap(), 1);
+        assert!(matches!(
+            svc.validate(&claims).await,
+            Err(SessionError::InvalidSession)
+        ));
+        assert!(svc.authenticate(&token.access_token).await.is_err());
+        assert!(matches!(
+            svc.refresh_session(&token.refresh_token).await,
+            Err(SessionError::InvalidSession)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_revoke_all_sessions_keeps_excepted() {
+        let dir = tempfile::tempdir().unwrap();
+        let svc = service(dir.path()).await;
+
+        let current = svc.create_session(&user(), None).await.unwrap();
+        let other = svc.create_session(&user(), None).await.unwrap();
+        let current_claims = svc.authenticate(&current.access_token).await.unwrap();
+
+        svc.revoke_all_sessions(1, Some(current_claims.sid))
+            .await
+            .unwrap();
+        assert!(svc.authenticate(&current.access_token).await.is_ok());
+        assert!(svc.authenticate(&other.access_token).await.is_err());
+        assert_eq!(svc.get_sessions(1).await.unwrap().len(), 1);
+
+        svc.revoke_all_sessions(1, None).await.unwrap();
+        assert!(svc.authenticate(&current.access_token).await.is_err());
+        assert!(svc.get_sessions(1).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_expired_access_token_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let svc = service(dir.path()).await;
+
+        let token = svc.create_session(&user(), None).await.unwrap();
+        let mut claims = svc.authenticate(&token.access_token).await.unwrap();
+        claims.exp = (Utc::now().timestamp() - 3600) as usize;
+        let expired = auth::encode_jwt("secret", &claims).unwrap();
+
+        assert!(svc.authenticate(&expired).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_token_signed_with_other_secret_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let svc = service(dir.path()).await;
+
+        let token = svc.create_session(&user(), None).await.unwrap();
+        let claims = svc.authenticate(&token.access_token).await.unwrap();
+        let forged = auth::encode_jwt("other", &claims).unwrap();
+
+        assert!(svc.authenticate(&forged).await.is_err());
+    }
+}
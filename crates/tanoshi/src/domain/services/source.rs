@@ -1,11 +1,12 @@
-use std::{collections::HashMap, str::FromStr};
+use std::{collections::HashMap, path::Path, str::FromStr};
 
 use crate::domain::{
-    entities::source::Source,
+    entities::source::{ExtensionRepository, Source},
     repositories::source::{SourceRepository, SourceRepositoryError},
 };
 
 use tanoshi_lib::prelude::Version;
+use tanoshi_vm::extension::TrustPolicy;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -14,6 +15,8 @@ pub enum SourceError {
     ExtensionError(#[from] SourceRepositoryError),
     #[error("invalid version error: {0}")]
     LibError(#[from] tanoshi_lib::error::Error),
+    #[error("invalid extension repository: {0}")]
+    InvalidRepository(String),
     #[error("other error: {0}")]
     Other(#[from] anyhow::Error),
 }
@@ -24,34 +27,162 @@ where
     R: SourceRepository,
 {
     repo: R,
+    repositories: Vec<ExtensionRepository>,
+    default_trust: TrustPolicy,
 }
 
 impl<R> SourceService<R>
 where
     R: SourceRepository,
 {
-    pub fn new(repo: R) -> Self {
-        Self { repo }
+    /// `repositories` come from config and are searched before the ones added at runtime,
+    /// which use `default_trust`
+    pub fn new(
+        repo: R,
+        repositories: Vec<ExtensionRepository>,
+        default_trust: TrustPolicy,
+    ) -> Self {
+        Self {
+            repo,
+            repositories,
+            default_trust,
+        }
+    }
+
+    pub async fn get_extension_repositories(
+        &self,
+    ) -> Result<Vec<ExtensionRepository>, SourceError> {
+        let mut repositories = self.repositories.clone();
+        repositories.extend(
+            self.repo
+                .get_extension_repositories()
+                .await?
+                .into_iter()
+                .map(|repository| ExtensionRepository {
+                    trust: self.default_trust.clone(),
+                    ..repository
+                }),
+        );
+
+        Ok(repositories)
+    }
+
+    pub async fn add_extension_repository(
+        &self,
+        name: &str,
+        url: &str,
+    ) -> Result<ExtensionRepository, SourceError> {
+        let name = name.trim();
+        let url = url.trim().trim_end_matches('/');
+
+        if name.is_empty() {
+            return Err(SourceError::InvalidRepository(
+                "name can't be empty".to_string(),
+            ));
+        }
+
+        if !(url.starts_with("http://")
+            || url.starts_with("https://")
+            || url.starts_with("file://")
+            || Path::new(url).is_absolute())
+        {
+            return Err(SourceError::InvalidRepository(format!(
+                "{url} is not an url or absolute path"
+            )));
+        }
+
+        let repositories = self.get_extension_repositories().await?;
+        if repositories
+            .iter()
+            .any(|repository| repository.name.eq_ignore_ascii_case(name))
+        {
+            return Err(SourceError::InvalidRepository(format!(
+                "{name} already exists"
+            )));
+        }
+        if let Some(repository) = repositories.iter().find(|repository| repository.url == url) {
+            return Err(SourceError::InvalidRepository(format!(
+                "{url} already added as {}",
+                repository.name
+            )));
+        }
+
+        self.repo.insert_extension_repository(name, url).await?;
+
+        Ok(ExtensionRepository {
+            name: name.to_string(),
+            url: url.to_string(),
+            from_config: false,
+            trust: self.default_trust.clone(),
+        })
+    }
+
+    pub async fn remove_extension_repository(&self, name: &str) -> Result<(), SourceError> {
+        if self
+            .repositories
+            .iter()
+            .any(|repository| repository.name == name)
+        {
+            return Err(SourceError::InvalidRepository(format!(
+                "{name} is defined in config"
+            )));
+        }
+
+        self.repo.delete_extension_repository(name).await?;
+
+        Ok(())
+    }
+
+    /// Index of every repository in order, a repository failed to fetch is skipped
+    async fn fetch_indexes(&self) -> Result<Vec<(ExtensionRepository, Source)>, SourceError> {
+        let mut indexes = vec![];
+        for repository in self.get_extension_repositories().await? {
+            match self.repo.available_sources(&repository).await {
+                Ok(sources) => indexes.extend(
+                    sources
+                        .into_iter()
+                        .map(|source| (repository.clone(), source)),
+                ),
+                Err(e) => {
+                    warn!("failed to fetch index of {}: {e}", repository.name);
+                }
+            }
+        }
+
+        Ok(indexes)
+    }
+
+    /// Returns source from `repository`, or from the first repository providing it when
+    /// `repository` is not known. Another repository providing the same id may ship a
+    /// different plugin, so there is no fallback from a known repository
+    fn find_index<'a>(
+        indexes: &'a [(ExtensionRepository, Source)],
+        id: i64,
+        repository: Option<&str>,
+    ) -> Option<&'a (ExtensionRepository, Source)> {
+        indexes.iter().find(|(found, source)| {
+            source.id == id && repository.map_or(true, |name| found.name == name)
+        })
     }
 
     pub async fn get_installed_sources(
         &self,
-        repo_url: &str,
         check_update: bool,
     ) -> Result<Vec<Source>, SourceError> {
         let mut sources = self.repo.installed_sources().await?;
+        let source_repositories = self.repo.get_source_repositories().await?;
+
+        for source in sources.iter_mut() {
+            source.repository = source_repositories.get(&source.id).cloned();
+        }
 
         if check_update {
-            let available_sources: HashMap<i64, Source> = self
-                .repo
-                .available_sources(repo_url, false)
-                .await?
-                .into_iter()
-                .map(|s| (s.id, s))
-                .collect();
+            let indexes = self.fetch_indexes().await?;
 
             for source in sources.iter_mut() {
-                if let Some(available_source) = available_sources.get(&source.id) {
+                if let Some((_, available_source)) =
+                    Self::find_index(&indexes, source.id, source.repository.as_deref())
+                {
                     let available_version = Version::from_str(&available_source.version)?;
                     let installed_version = Version::from_str(&source.version)?;
 
@@ -67,8 +198,34 @@ where
         Ok(sources)
     }
 
-    pub async fn get_available_sources(&self, repo_url: &str) -> Result<Vec<Source>, SourceError> {
-        let sources = self.repo.available_sources(repo_url, true).await?;
+    /// Sources not installed yet from every repository, a source id provided by multiple
+    /// repositories is listed once with the other repositories in `conflicts`
+    pub async fn get_available_sources(&self) -> Result<Vec<Source>, SourceError> {
+        let installed: Vec<i64> = self
+            .repo
+            .installed_sources()
+            .await?
+            .iter()
+            .map(|source| source.id)
+            .collect();
+
+        let mut sources: Vec<Source> = vec![];
+        let mut positions: HashMap<i64, usize> = HashMap::new();
+        for (repository, source) in self.fetch_indexes().await? {
+            if installed.contains(&source.id) {
+                continue;
+            }
+
+            if let Some(position) = positions.get(&source.id) {
+                let conflicts = &mut sources[*position].conflicts;
+                if !conflicts.contains(&repository.name) {
+                    conflicts.push(repository.name);
+                }
+            } else {
+                positions.insert(source.id, sources.len());
+                sources.push(source);
+            }
+        }
 
         Ok(sources)
     }
@@ -79,14 +236,35 @@ where
         Ok(source)
     }
 
-    pub async fn install_source(&self, repo_url: &str, id: i64) -> Result<(), SourceError> {
-        self.repo.install_source(repo_url, id).await?;
+    /// Install from `repository`, or from the first repository providing the source
+    pub async fn install_source(
+        &self,
+        id: i64,
+        repository: Option<&str>,
+    ) -> Result<(), SourceError> {
+        let indexes = self.fetch_indexes().await?;
+        let (repository, source) =
+            Self::find_index(&indexes, id, repository).ok_or(SourceRepositoryError::NotFound)?;
+
+        self.repo.install_source(repository, source).await?;
 
         Ok(())
     }
 
-    pub async fn update_source(&self, repo_url: &str, id: i64) -> Result<(), SourceError> {
-        self.repo.update_source(repo_url, id).await?;
+    /// Update from the repository the source was installed from, not found if that repository
+    /// no longer provides the source
+    pub async fn update_source(&self, id: i64) -> Result<(), SourceError> {
+        let source_repositories = self.repo.get_source_repositories().await?;
+
+        let indexes = self.fetch_indexes().await?;
+        let (repository, source) = Self::find_index(
+            &indexes,
+            id,
+            source_repositories.get(&id).map(String::as_str),
+        )
+        .ok_or(SourceRepositoryError::NotFound)?;
+
+        self.repo.update_source(repository, source).await?;
 
         Ok(())
     }
@@ -97,3 +275,89 @@ where
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use tanoshi_vm::prelude::ExtensionManager;
+
+    use super::*;
+    use crate::infrastructure::{
+        database::establish_connection, domain::repositories::source::SourceRepositoryImpl,
+    };
+
+    fn write_index(dir: &Path, ids: &[i64]) {
+        let index: Vec<serde_json::Value> = ids
+            .iter()
+            .map(|id| {
+                serde_json::json!({
+                    "id": id,
+                    "name": format!("source{id}"),
+                    "url": "https://example.com",
+                    "version": "0.1.0",
+                    "rustc_version": tanoshi_lib::RUSTC_VERSION,
+                    "lib_version": tanoshi_lib::LIB_VERSION,
+                    "icon": "",
+                })
+            })
+            .collect();
+        std::fs::write(dir.join("index.json"), serde_json::to_vec(&index).unwrap()).unwrap();
+    }
+
+    fn repository(name: &str, dir: &Path) -> ExtensionRepository {
+        ExtensionRepository {
+            name: name.to_string(),
+            url: format!("file://{}", dir.display()),
+            from_config: true,
+            trust: TrustPolicy::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_update_source_only_from_recorded_repository() {
+        let one = tempfile::tempdir().unwrap();
+        let two = tempfile::tempdir().unwrap();
+        let data = tempfile::tempdir().unwrap();
+
+        // both repositories provide source 1, only the first one provides source 2
+        write_index(one.path(), &[1, 2]);
+        write_index(two.path(), &[1]);
+
+        let pool =
+            establish_connection(&data.path().join("tanoshi.db").display().to_string(), true)
+                .await
+                .unwrap();
+        sqlx::query(
+            "INSERT INTO source_repository(source_id, repository) VALUES (1, 'two'), (2, 'two')",
+        )
+        .execute(&pool as &sqlx::SqlitePool)
+        .await
+        .unwrap();
+
+        let svc = SourceService::new(
+            SourceRepositoryImpl::new(pool, ExtensionManager::new(data.path())),
+            vec![repository("one", one.path()), repository("two", two.path())],
+            TrustPolicy::default(),
+        );
+
+        let indexes = svc.fetch_indexes().await.unwrap();
+        let find = |id, repository| {
+            SourceService::<SourceRepositoryImpl>::find_index(&indexes, id, repository)
+                .map(|(repository, _)| repository.name.as_str())
+        };
+        assert_eq!(find(1, Some("two")), Some("two"));
+        assert_eq!(find(1, None), Some("one"));
+        assert_eq!(find(2, Some("two")), None);
+        assert_eq!(find(2, None), Some("one"));
+
+        let res = svc.update_source(2).await;
+        assert!(
+            matches!(
+                res,
+                Err(SourceError::ExtensionError(SourceRepositoryError::NotFound))
+            ),
+            "{res:?}"
+        );
+    }
+}
@@ -0,0 +1,191 @@
+use chrono::{Duration, NaiveDate, NaiveDateTime, Utc};
+use thiserror::Error;
+
+use crate::domain::{
+    entities::statistics::{
+        ActivityPeriod, MangaCompletion, ReadingActivity, ReadingCount, ReadingStreak,
+        ReadingSummary, SourceReadingCount,
+    },
+    repositories::statistics::{StatisticsRepository, StatisticsRepositoryError},
+};
+
+/// Reads further apart than this start a new session
+const SESSION_GAP_MINUTES: i64 = 30;
+
+const MAX_LIMIT: i64 = 100;
+
+#[derive(Debug, Error)]
+pub enum StatisticsError {
+    #[error("repository error: {0}")]
+    RepositoryError(#[from] StatisticsRepositoryError),
+}
+
+/// Statistics are derived from reading history, which only keeps the last time a chapter was read,
+/// so rereading a chapter moves it instead of counting it twice
+#[derive(Clone)]
+pub struct StatisticsService<R>
+where
+    R: StatisticsRepository,
+{
+    repo: R,
+}
+
+impl<R> StatisticsService<R>
+where
+    R: StatisticsRepository,
+{
+    pub fn new(repo: R) -> Self {
+        Self { repo }
+    }
+
+    pub async fn get_reading_activity(
+        &self,
+        user_id: i64,
+        period: ActivityPeriod,
+        utc_offset_minutes: i32,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+    ) -> Result<Vec<ReadingActivity>, StatisticsError> {
+        Ok(self
+            .repo
+            .get_reading_activity(user_id, period, utc_offset_minutes, from, to)
+            .await?)
+    }
+
+    pub async fn get_reading_summary(
+        &self,
+        user_id: i64,
+        utc_offset_minutes: i32,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+    ) -> Result<ReadingSummary, StatisticsError> {
+        let mut summary = self.repo.get_reading_totals(user_id, from, to).await?;
+        let times = self.repo.get_read_times(user_id, from, to).await?;
+
+        summary.sessions = Self::count_sessions(&times);
+        summary.days = Self::read_days(&times, utc_offset_minutes).len() as i64;
+        if summary.sessions > 0 {
+            summary.average_chapters_per_session =
+                summary.chapters as f64 / summary.sessions as f64;
+        }
+
+        Ok(summary)
+    }
+
+    pub async fn get_reading_streak(
+        &self,
+        user_id: i64,
+        utc_offset_minutes: i32,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+    ) -> Result<ReadingStreak, StatisticsError> {
+        let times = self.repo.get_read_times(user_id, from, to).await?;
+        let days = Self::read_days(&times, utc_offset_minutes);
+
+        let mut streak = ReadingStreak::default();
+        let mut start = None;
+        let mut length = 0;
+        let mut prev: Option<NaiveDate> = None;
+        for day in days.iter().copied() {
+            if prev.and_then(|prev| prev.succ_opt()) == Some(day) {
+                length += 1;
+            } else {
+                start = Some(day);
+                length = 1;
+            }
+
+            if length > streak.longest {
+                streak.longest = length;
+                streak.longest_start = start;
+                streak.longest_end = Some(day);
+            }
+
+            prev = Some(day);
+        }
+
+        let today = (Utc::now().naive_utc() + Duration::minutes(utc_offset_minutes as i64)).date();
+        if let Some(last) = prev {
+            if last == today || last.succ_opt() == Some(today) {
+                streak.current = length;
+            }
+        }
+
+        Ok(streak)
+    }
+
+    pub async fn get_top_genres(
+        &self,
+        user_id: i64,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+        limit: i64,
+    ) -> Result<Vec<ReadingCount>, StatisticsError> {
+        Ok(self
+            .repo
+            .get_top_genres(user_id, from, to, limit.clamp(1, MAX_LIMIT))
+            .await?)
+    }
+
+    pub async fn get_top_authors(
+        &self,
+        user_id: i64,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+        limit: i64,
+    ) -> Result<Vec<ReadingCount>, StatisticsError> {
+        Ok(self
+            .repo
+            .get_top_authors(user_id, from, to, limit.clamp(1, MAX_LIMIT))
+            .await?)
+    }
+
+    pub async fn get_top_sources(
+        &self,
+        user_id: i64,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+        limit: i64,
+    ) -> Result<Vec<SourceReadingCount>, StatisticsError> {
+        Ok(self
+            .repo
+            .get_top_sources(user_id, from, to, limit.clamp(1, MAX_LIMIT))
+            .await?)
+    }
+
+    pub async fn get_manga_completion(
+        &self,
+        user_id: i64,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+        limit: i64,
+    ) -> Result<Vec<MangaCompletion>, StatisticsError> {
+        Ok(self
+            .repo
+            .get_manga_completion(user_id, from, to, limit.clamp(1, MAX_LIMIT))
+            .await?)
+    }
+
+    /// `times` must be sorted
+    fn count_sessions(times: &[NaiveDateTime]) -> i64 {
+        let gap = Duration::minutes(SESSION_GAP_MINUTES);
+        let mut sessions = 0;
+        let mut prev: Option<NaiveDateTime> = None;
+        for time in times {
+            if prev.map(|prev| *time - prev > gap).unwrap_or(true) {
+                sessions += 1;
+            }
+            prev = Some(*time);
+        }
+
+        sessions
+    }
+
+    /// Distinct local days, sorted if `times` is sorted
+    fn read_days(times: &[NaiveDateTime], utc_offset_minutes: i32) -> Vec<NaiveDate> {
+        let offset = Duration::minutes(utc_offset_minutes as i64);
+        let mut days: Vec<NaiveDate> = times.iter().map(|time| (*time + offset).date()).collect();
+        days.dedup();
+
+        days
+    }
+}
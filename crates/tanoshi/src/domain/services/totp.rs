@@ -0,0 +1,422 @@
+use chrono::Utc;
+use rand::{seq::SliceRandom, RngCore};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use totp_rs::{Algorithm, Secret, TOTP};
+
+use crate::domain::{
+    entities::{totp::TotpEnrollment, user::User},
+    repositories::{
+        totp::{TotpRepository, TotpRepositoryError},
+        user::{UserRepository, UserRepositoryError},
+    },
+};
+
+const TOTP_ISSUER: &str = "Tanoshi";
+const TOTP_DIGITS: usize = 6;
+const TOTP_STEP: u64 = 30;
+/// Number of steps before and after current time a code is still accepted
+const TOTP_SKEW: i64 = 1;
+
+const RECOVERY_CODES_COUNT: usize = 10;
+const RECOVERY_CODE_LENGTH: usize = 10;
+/// Without characters that are easily confused with each other
+const RECOVERY_CODE_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+#[derive(Debug, Error)]
+pub enum TotpError {
+    #[error("two-factor authentication is not enabled")]
+    NotEnabled,
+    #[error("two-factor authentication is already enabled")]
+    AlreadyEnabled,
+    #[error("invalid two-factor code")]
+    InvalidCode,
+    #[error("repository error: {0}")]
+    RepositoryError(#[from] TotpRepositoryError),
+    #[error("user repository error: {0}")]
+    UserRepositoryError(#[from] UserRepositoryError),
+    #[error("other error: {0}")]
+    Other(#[from] anyhow::Error),
+}
+
+/// Recovery codes are normalized before hashing so dashes and case don't matter
+fn hash_recovery_code(code: &str) -> String {
+    let code = code.replace('-', "").to_lowercase();
+    format!("{:x}", Sha256::digest(code.as_bytes()))
+}
+
+fn generate_recovery_code() -> String {
+    let mut rng = rand::thread_rng();
+    let code: String = (0..RECOVERY_CODE_LENGTH)
+        .filter_map(|_| RECOVERY_CODE_ALPHABET.choose(&mut rng))
+        .map(|c| *c as char)
+        .collect();
+    let (left, right) = code.split_at(RECOVERY_CODE_LENGTH / 2);
+    format!("{left}-{right}")
+}
+
+#[derive(Clone)]
+pub struct TotpService<R, U>
+where
+    R: TotpRepository,
+    U: UserRepository,
+{
+    repo: R,
+    user_repo: U,
+    require_for_admins: bool,
+}
+
+impl<R, U> TotpService<R, U>
+where
+    R: TotpRepository,
+    U: UserRepository,
+{
+    pub fn new(repo: R, user_repo: U, require_for_admins: bool) -> Self {
+        Self {
+            repo,
+            user_repo,
+            require_for_admins,
+        }
+    }
+
+    fn totp(secret: Vec<u8>, username: &str) -> Result<TOTP, TotpError> {
+        // account name can't contain colon as it separates issuer in the uri
+        TOTP::new(
+            Algorithm::SHA1,
+            TOTP_DIGITS,
+            TOTP_SKEW as u8,
+            TOTP_STEP,
+            secret,
+            Some(TOTP_ISSUER.to_string()),
+            username.replace(':', "_"),
+        )
+        .map_err(|e| anyhow::anyhow!("{e}").into())
+    }
+
+    /// Returns the matching time step if code is valid and newer than the last used one
+    fn verify_code(totp: &TOTP, code: &str, last_used_step: i64) -> Option<i64> {
+        let step = Utc::now().timestamp() / TOTP_STEP as i64;
+        (step - TOTP_SKEW..=step + TOTP_SKEW)
+            .filter(|step| *step > last_used_step)
+            .find(|step| totp.generate(*step as u64 * TOTP_STEP) == code)
+    }
+
+    pub async fn is_enabled(&self, user_id: i64) -> Result<bool, TotpError> {
+        match self.repo.get_totp(user_id).await {
+            Ok(totp) => Ok(totp.enabled),
+            Err(TotpRepositoryError::NotFound) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// User has to enroll two-factor authentication before getting a session
+    pub async fn is_enrollment_required(&self, user: &User) -> Result<bool, TotpError> {
+        Ok(self.require_for_admins && user.is_admin && !self.is_enabled(user.id).await?)
+    }
+
+    /// Generate a new secret, it is not required on login until confirmed
+    pub async fn enroll(&self, user_id: i64) -> Result<TotpEnrollment, TotpError> {
+        if self.is_enabled(user_id).await? {
+            return Err(TotpError::AlreadyEnabled);
+        }
+
+        let user = self.user_repo.get_user_by_id(user_id).await?;
+
+        let mut secret = vec![0u8; 20];
+        rand::thread_rng().fill_bytes(&mut secret);
+        let totp = Self::totp(secret, &user.username)?;
+
+        let secret = totp.get_secret_base32();
+        self.repo.upsert_totp(user_id, &secret).await?;
+
+        Ok(TotpEnrollment {
+            secret,
+            uri: totp.get_url(),
+        })
+    }
+
+    /// Enable two-factor authentication with a code from the enrolled secret, returns recovery codes
+    pub async fn confirm(&self, user_id: i64, code: &str) -> Result<Vec<String>, TotpError> {
+        let user_totp = match self.repo.get_totp(user_id).await {
+            Ok(user_totp) if user_totp.enabled => return Err(TotpError::AlreadyEnabled),
+            Ok(user_totp) => user_totp,
+            Err(TotpRepositoryError::NotFound) => return Err(TotpError::NotEnabled),
+            Err(e) => return Err(e.into()),
+        };
+
+        self.verify_totp(user_id, &user_totp.secret, code, user_totp.last_used_step)
+            .await?;
+        self.repo.enable_totp(user_id).await?;
+
+        self.generate_recovery_codes(user_id).await
+    }
+
+    async fn verify_totp(
+        &self,
+        user_id: i64,
+        secret: &str,
+        code: &str,
+        last_used_step: i64,
+    ) -> Result<(), TotpError> {
+        let user = self.user_repo.get_user_by_id(user_id).await?;
+        let secret = Secret::Encoded(secret.to_string())
+            .to_bytes()
+            .map_err(|e| anyhow::anyhow!("{e:?}"))?;
+        let totp = Self::totp(secret, &user.username)?;
+
+        let step = Self::verify_code(&totp, code, last_used_step).ok_or(TotpError::InvalidCode)?;
+
+        // a code can only be used once, even when sent twice at the same time
+        if !self.repo.update_last_used_step(user_id, step).await? {
+            return Err(TotpError::InvalidCode);
+        }
+
+        Ok(())
+    }
+
+    /// Verify a code from authenticator app or an unused recovery code
+    pub async fn verify(&self, user_id: i64, code: &str) -> Result<(), TotpError> {
+        let user_totp = match self.repo.get_totp(user_id).await {
+            Ok(user_totp) if user_totp.enabled => user_totp,
+            Ok(_) | Err(TotpRepositoryError::NotFound) => return Err(TotpError::NotEnabled),
+            Err(e) => return Err(e.into()),
+        };
+
+        let code: String = code.chars().filter(|c| !c.is_whitespace()).collect();
+        if code.len() == TOTP_DIGITS && code.chars().all(|c| c.is_ascii_digit()) {
+            return self
+                .verify_totp(user_id, &user_totp.secret, &code, user_totp.last_used_step)
+                .await;
+        }
+
+        if self
+            .repo
+            .use_recovery_code(user_id, &hash_recovery_code(&code))
+            .await?
+        {
+            info!("user {user_id} used a recovery code");
+            return Ok(());
+        }
+
+        Err(TotpError::InvalidCode)
+    }
+
+    /// Replace all recovery codes, returns the new codes only this once as only their hashes are stored
+    pub async fn generate_recovery_codes(&self, user_id: i64) -> Result<Vec<String>, TotpError> {
+        let codes: Vec<String> = (0..RECOVERY_CODES_COUNT)
+            .map(|_| generate_recovery_code())
+            .collect();
+        let hashes: Vec<String> = codes.iter().map(|code| hash_recovery_code(code)).collect();
+
+        self.repo.replace_recovery_codes(user_id, &hashes).await?;
+
+        Ok(codes)
+    }
+
+    pub async fn get_recovery_codes_count(&self, user_id: i64) -> Result<i64, TotpError> {
+        Ok(self.repo.get_recovery_codes_count(user_id).await?)
+    }
+
+    /// Remove secret and recovery codes, used to disable or when admin resets it
+    pub async fn reset(&self, user_id: i64) -> Result<u64, TotpError> {
+        Ok(self.repo.delete_totp(user_id).await?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use sqlx::SqlitePool;
+
+    use super::*;
+    use crate::infrastructure::{
+        database::establish_connection,
+        domain::repositories::{totp::TotpRepositoryImpl, user::UserRepositoryImpl},
+    };
+
+    type Service = TotpService<TotpRepositoryImpl, UserRepositoryImpl>;
+
+    /// User 1 is an admin, user 2 is not
+    async fn service(dir: &std::path::Path) -> Service {
+        let pool = establish_connection(&dir.join("tanoshi.db").display().to_string(), true)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO user(id, username, password, is_admin) VALUES (1, 'one', '', true), (2, 'two', '', false)",
+        )
+        .execute(&pool as &SqlitePool)
+        .await
+        .unwrap();
+
+        TotpService::new(
+            TotpRepositoryImpl::new(pool.clone()),
+            UserRepositoryImpl::new(pool),
+            true,
+        )
+    }
+
+    /// Code of the authenticator app for the time step `offset` steps from now
+    fn code(secret: &str, offset: i64) -> String {
+        let secret = Secret::Encoded(secret.to_string()).to_bytes().unwrap();
+        let totp = Service::totp(secret, "one").unwrap();
+        let step = Utc::now().timestamp() / TOTP_STEP as i64 + offset;
+        totp.generate(step as u64 * TOTP_STEP)
+    }
+
+    #[test]
+    fn test_recovery_code() {
+        let code = generate_recovery_code();
+        assert_eq!(code.len(), RECOVERY_CODE_LENGTH + 1);
+        assert_eq!(code.chars().nth(RECOVERY_CODE_LENGTH / 2), Some('-'));
+        assert!(code
+            .bytes()
+            .filter(|c| *c != b'-')
+            .all(|c| RECOVERY_CODE_ALPHABET.contains(&c)));
+
+        assert_eq!(
+            hash_recovery_code("abcde-fghjk"),
+            hash_recovery_code("ABCDEFGHJK")
+        );
+        assert_ne!(
+            hash_recovery_code("abcde-fghjk"),
+            hash_recovery_code("abcde-fghjm")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_enroll_and_confirm() {
+        let dir = tempfile::tempdir().unwrap();
+        let svc = service(dir.path()).await;
+
+        assert!(matches!(
+            svc.confirm(1, "000000").await,
+            Err(TotpError::NotEnabled)
+        ));
+
+        let enrollment = svc.enroll(1).await.unwrap();
+        assert!(enrollment.uri.starts_with("otpauth://totp/Tanoshi:one?"));
+        assert!(!svc.is_enabled(1).await.unwrap());
+        // not required on login until confirmed
+        assert!(matches!(
+            svc.verify(1, &code(&enrollment.secret, 0)).await,
+            Err(TotpError::NotEnabled)
+        ));
+
+        assert!(matches!(
+            svc.confirm(1, &code(&enrollment.secret, 5)).await,
+            Err(TotpError::InvalidCode)
+        ));
+        let recovery_codes = svc.confirm(1, &code(&enrollment.secret, 0)).await.unwrap();
+        assert_eq!(recovery_codes.len(), RECOVERY_CODES_COUNT);
+        assert!(svc.is_enabled(1).await.unwrap());
+        assert_eq!(
+            svc.get_recovery_codes_count(1).await.unwrap(),
+            RECOVERY_CODES_COUNT as i64
+        );
+
+        assert!(matches!(
+            svc.enroll(1).await,
+            Err(TotpError::AlreadyEnabled)
+        ));
+        assert!(matches!(
+            svc.confirm(1, &code(&enrollment.secret, 1)).await,
+            Err(TotpError::AlreadyEnabled)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_verify_code_only_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let svc = service(dir.path()).await;
+
+        let enrollment = svc.enroll(1).await.unwrap();
+        svc.confirm(1, &code(&enrollment.secret, -1)).await.unwrap();
+
+        // the code used to confirm and older ones are spent
+        assert!(matches!(
+            svc.verify(1, &code(&enrollment.secret, -1)).await,
+            Err(TotpError::InvalidCode)
+        ));
+
+        let current = code(&enrollment.secret, 0);
+        let spaced = format!("{} {}", &current[..3], &current[3..]);
+        assert!(svc.verify(1, &spaced).await.is_ok());
+        assert!(matches!(
+            svc.verify(1, &current).await,
+            Err(TotpError::InvalidCode)
+        ));
+
+        assert!(svc.verify(1, &code(&enrollment.secret, 1)).await.is_ok());
+        assert!(matches!(
+            svc.verify(1, &code(&enrollment.secret, 2)).await,
+            Err(TotpError::InvalidCode)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_verify_recovery_code_only_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let svc = service(dir.path()).await;
+
+        let enrollment = svc.enroll(1).await.unwrap();
+        let recovery_codes = svc.confirm(1, &code(&enrollment.secret, 0)).await.unwrap();
+
+        let recovery_code = recovery_codes[0].to_uppercase().replace('-', "");
+        assert!(svc.verify(1, &recovery_code).await.is_ok());
+        assert_eq!(
+            svc.get_recovery_codes_count(1).await.unwrap(),
+            RECOVERY_CODES_COUNT as i64 - 1
+        );
+        assert!(matches!(
+            svc.verify(1, &recovery_codes[0]).await,
+            Err(TotpError::InvalidCode)
+        ));
+        assert!(matches!(
+            svc.verify(1, "abcde-fghjk").await,
+            Err(TotpError::InvalidCode)
+        ));
+
+        // regenerating replaces the unused codes
+        let new_codes = svc.generate_recovery_codes(1).await.unwrap();
+        assert!(matches!(
+            svc.verify(1, &recovery_codes[1]).await,
+            Err(TotpError::InvalidCode)
+        ));
+        assert!(svc.verify(1, &new_codes[0]).await.is_ok());
+
+        // recovery codes belong to their user
+        assert!(matches!(
+            svc.verify(2, &new_codes[1]).await,
+            Err(TotpError::NotEnabled)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_reset_and_enrollment_required() {
+        let dir = tempfile::tempdir().unwrap();
+        let svc = service(dir.path()).await;
+        let admin = User {
+            id: 1,
+            is_admin: true,
+            ..Default::default()
+        };
+        let user = User {
+            id: 2,
+            ..Default::default()
+        };
+
+        assert!(svc.is_enrollment_required(&admin).await.unwrap());
+        assert!(!svc.is_enrollment_required(&user).await.unwrap());
+
+        let enrollment = svc.enroll(1).await.unwrap();
+        let recovery_codes = svc.confirm(1, &code(&enrollment.secret, 0)).await.unwrap();
+        assert!(!svc.is_enrollment_required(&admin).await.unwrap());
+
+        assert_eq!(svc.reset(1).await.unwrap(), 1);
+        assert!(!svc.is_enabled(1).await.unwrap());
+        assert!(svc.is_enrollment_required(&admin).await.unwrap());
+        assert!(matches!(
+            svc.verify(1, &recovery_codes[0]).await,
+            Err(TotpError::NotEnabled)
+        ));
+    }
+}
@@ -74,6 +74,11 @@ where
     pub async fn verify_password(&self, username: &str, password: &str) -> Result<(), UserError> {
         let user = self.repo.get_user_by_username(username.to_owned()).await?;
 
+        // user created from single sign-on has no password
+        if user.password.is_empty() {
+            return Err(UserError::WrongPassword);
+        }
+
         if !argon2::verify_encoded(&user.password, password.as_bytes())
             .map_err(|e| UserError::Other(format!("{e}")))?
         {
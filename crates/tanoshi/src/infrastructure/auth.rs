@@ -1,17 +1,47 @@
 use anyhow::Result;
 use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation};
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::domain::entities::api_key::ApiKeyScope;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: i64,
     pub username: String,
     pub is_admin: bool,
+    /// id of the session this token is issued for, 0 for api key and proxy auth
+    pub sid: i64,
+    pub exp: usize,
+    /// set when authenticated with an api key, request is limited to these scopes
+    #[serde(skip)]
+    pub scopes: Option<Vec<ApiKeyScope>>,
+}
+
+impl Claims {
+    pub fn is_api_key(&self) -> bool {
+        self.scopes.is_some()
+    }
+
+    /// Login sessions have every scope, api keys only the ones granted
+    pub fn has_scope(&self, scope: ApiKeyScope) -> bool {
+        self.scopes
+            .as_ref()
+            .map(|scopes| scopes.contains(&scope))
+            .unwrap_or(true)
+    }
+}
+
+/// Claims of a token that can only enroll two-factor authentication, it has no session
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TotpEnrollmentClaims {
+    pub sub: i64,
     pub exp: usize,
+    /// only here so an access token is never decoded as enrollment token
+    pub totp_enrollment: bool,
 }
 
-pub fn decode_jwt(secret: &str, token: &str) -> Result<Claims> {
-    Ok(jsonwebtoken::decode::<Claims>(
+pub fn decode_jwt<T: DeserializeOwned>(secret: &str, token: &str) -> Result<T> {
+    Ok(jsonwebtoken::decode::<T>(
         token,
         &DecodingKey::from_secret(secret.as_bytes()),
         &Validation::default(),
@@ -19,7 +49,7 @@ pub fn decode_jwt(secret: &str, token: &str) -> Result<Claims> {
     .claims)
 }
 
-pub fn encode_jwt(secret: &str, claims: &Claims) -> Result<String> {
+pub fn encode_jwt<T: Serialize>(secret: &str, claims: &T) -> Result<String> {
     Ok(jsonwebtoken::encode(
         &Header::default(),
         claims,
@@ -1,8 +1,15 @@
+use ipnet::IpNet;
 use rand::distributions::Alphanumeric;
 use rand::{thread_rng, Rng};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
 use std::path::Path;
 use std::{iter, path::PathBuf};
+use tanoshi_lib::prelude::HttpProfile;
+use tanoshi_vm::extension::TrustPolicy;
+
+use crate::domain::entities::source::ExtensionRepository;
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct TelegramConfig {
@@ -32,6 +39,137 @@ pub struct AniListConfig {
     pub client_secret: String,
 }
 
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct OidcConfig {
+    /// Issuer url, `/.well-known/openid-configuration` is fetched from here
+    pub issuer_url: String,
+    pub client_id: String,
+    #[serde(default)]
+    pub client_secret: Option<String>,
+    #[serde(default = "default_oidc_scopes")]
+    pub scopes: Vec<String>,
+    /// Userinfo claim used as username for new user
+    #[serde(default = "default_oidc_username_claim")]
+    pub username_claim: String,
+    /// Userinfo claim checked against `admin_values`, e.g. groups
+    #[serde(default = "default_oidc_admin_claim")]
+    pub admin_claim: String,
+    /// User is admin if admin claim contains one of these, admin is not synced if empty
+    #[serde(default)]
+    pub admin_values: Vec<String>,
+    /// Create user on first login
+    #[serde(default = "default_oidc_auto_create_user")]
+    pub auto_create_user: bool,
+    /// Link first login to existing user with the same username
+    #[serde(default)]
+    pub link_by_username: bool,
+    #[serde(default)]
+    pub disable_password_login: bool,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ProxyAuthConfig {
+    /// Header set by the proxy containing the username
+    #[serde(default = "default_proxy_auth_header")]
+    pub header: String,
+    /// Header is only accepted from these networks, e.g. 172.16.0.0/12
+    pub trusted_proxies: Vec<IpNet>,
+    /// Create user if it does not exist
+    #[serde(default)]
+    pub auto_create_user: bool,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct LoginThrottleConfig {
+    /// Failed logins for a username before it is locked
+    #[serde(default = "default_login_throttle_max_attempts_per_username")]
+    pub max_attempts_per_username: u32,
+    /// Failed logins from an address before it is locked
+    #[serde(default = "default_login_throttle_max_attempts_per_ip")]
+    pub max_attempts_per_ip: u32,
+    /// Failed logins older than this many seconds are not counted
+    #[serde(default = "default_login_throttle_window")]
+    pub window: u64,
+    /// How long in seconds a username or address is locked
+    #[serde(default = "default_login_throttle_lockout")]
+    pub lockout: u64,
+}
+
+impl Default for LoginThrottleConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts_per_username: default_login_throttle_max_attempts_per_username(),
+            max_attempts_per_ip: default_login_throttle_max_attempts_per_ip(),
+            window: default_login_throttle_window(),
+            lockout: default_login_throttle_lockout(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct PrefetchConfig {
+    #[serde(default = "default_prefetch_enabled")]
+    pub enabled: bool,
+    /// Maximum concurrent page fetches per user
+    #[serde(default = "default_prefetch_concurrency")]
+    pub concurrency: usize,
+    /// Number of pages of the next chapter to prefetch
+    #[serde(default = "default_prefetch_next_chapter_pages")]
+    pub next_chapter_pages: usize,
+}
+
+impl Default for PrefetchConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_prefetch_enabled(),
+            concurrency: default_prefetch_concurrency(),
+            next_chapter_pages: default_prefetch_next_chapter_pages(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct HttpConfig {
+    /// Proxy url used for every source, `http`, `https` and `socks5` scheme are supported
+    #[serde(default)]
+    pub proxy: Option<String>,
+    #[serde(default)]
+    pub user_agent: Option<String>,
+    /// Headers sent to every source, merged with headers from extension
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Profile for a source keyed by source id, takes precedence over global settings
+    #[serde(default)]
+    pub sources: HashMap<i64, HttpProfile>,
+    /// Hosts a source may load images from besides its own domain, e.g. a cdn, keyed by source id
+    #[serde(default)]
+    pub image_hosts: HashMap<i64, Vec<String>>,
+}
+
+impl HttpConfig {
+    pub fn default_profile(&self) -> HttpProfile {
+        HttpProfile {
+            proxy: self.proxy.clone(),
+            user_agent: self.user_agent.clone(),
+            headers: self.headers.clone(),
+            cookie_jar: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ExtensionRepositoryConfig {
+    pub name: String,
+    /// Url or local directory containing `index.json`, e.g. `file:///srv/extensions`
+    pub url: String,
+    /// Keys trusted to sign extensions of this repository besides `extension_trusted_keys`
+    #[serde(default)]
+    pub trusted_keys: Vec<String>,
+    /// Overrides `allow_unsigned_extensions` for this repository
+    #[serde(default)]
+    pub allow_unsigned: Option<bool>,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct LocalFolder {
     pub name: String,
@@ -51,6 +189,16 @@ pub struct Config {
     path: PathBuf,
     #[serde(skip, default = "default_extension_repository")]
     pub extension_repository: String,
+    /// Repositories searched after the official one, sources with the same id are
+    /// installed from the first repository unless specified
+    #[serde(default)]
+    pub extension_repositories: Vec<ExtensionRepositoryConfig>,
+    /// Base64 encoded ed25519 public keys trusted to sign extensions of every repository
+    #[serde(default)]
+    pub extension_trusted_keys: Vec<String>,
+    /// Install extensions without a valid signature of a trusted key, checksum is verified regardless
+    #[serde(default)]
+    pub allow_unsigned_extensions: bool,
     #[serde(default)]
     pub base_url: Option<String>,
     #[serde(default = "default_port")]
@@ -61,6 +209,15 @@ pub struct Config {
     pub create_database: bool,
     #[serde(default = "default_secret")]
     pub secret: String,
+    /// Lifetime of access token in seconds
+    #[serde(default = "default_access_token_ttl")]
+    pub access_token_ttl: u64,
+    /// Lifetime of refresh token in seconds, renewed on every refresh
+    #[serde(default = "default_refresh_token_ttl")]
+    pub refresh_token_ttl: u64,
+    /// Minimum lifetime of image urls in seconds, an url is valid for up to twice as long
+    #[serde(default = "default_image_url_ttl")]
+    pub image_url_ttl: u64,
     #[serde(default = "default_update_interval")]
     pub update_interval: u64,
     #[serde(default)]
@@ -73,6 +230,16 @@ pub struct Config {
     pub download_path: String,
     #[serde(default = "default_cache_path")]
     pub cache_path: String,
+    /// Directory of covers uploaded to override the ones from source
+    #[serde(default = "default_cover_path")]
+    pub cover_path: String,
+    /// Maximum size of the image cache in bytes, covers of library manga are not counted
+    #[serde(default = "default_cache_max_size")]
+    pub cache_max_size: u64,
+    #[serde(default)]
+    pub prefetch: PrefetchConfig,
+    #[serde(default)]
+    pub http: HttpConfig,
     #[serde(default)]
     pub enable_playground: bool,
     pub telegram: Option<TelegramConfig>,
@@ -80,6 +247,19 @@ pub struct Config {
     pub gotify: Option<GotifyConfig>,
     pub myanimelist: Option<MyAnimeListConfig>,
     pub anilist: Option<AniListConfig>,
+    #[serde(default)]
+    pub oidc: Option<OidcConfig>,
+    #[serde(default)]
+    pub proxy_auth: Option<ProxyAuthConfig>,
+    /// Reverse proxies whose `X-Forwarded-For` is used as client address, e.g. 172.16.0.0/12
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpNet>,
+    #[serde(default)]
+    pub login_throttle: LoginThrottleConfig,
+    /// Admins without two-factor authentication have to enroll it on password login before
+    /// getting a session
+    #[serde(default = "default_require_totp_for_admins")]
+    pub require_totp_for_admins: bool,
 }
 
 impl Default for Config {
@@ -87,23 +267,38 @@ impl Default for Config {
         Self {
             path: tanoshi_home().join("config.yml"),
             extension_repository: default_extension_repository(),
+            extension_repositories: vec![],
+            extension_trusted_keys: vec![],
+            allow_unsigned_extensions: false,
             base_url: None,
             port: default_port(),
             database_path: default_database_path(),
             create_database: default_create_database(),
             secret: default_secret(),
+            access_token_ttl: default_access_token_ttl(),
+            refresh_token_ttl: default_refresh_token_ttl(),
+            image_url_ttl: default_image_url_ttl(),
             update_interval: default_update_interval(),
             auto_download_chapters: false,
             plugin_path: default_plugin_path(),
             local_path: default_local_folders(),
             download_path: default_download_path(),
             cache_path: default_cache_path(),
+            cover_path: default_cover_path(),
+            cache_max_size: default_cache_max_size(),
+            prefetch: PrefetchConfig::default(),
+            http: HttpConfig::default(),
             enable_playground: false,
             telegram: None,
             pushover: None,
             gotify: None,
             myanimelist: None,
             anilist: None,
+            oidc: None,
+            proxy_auth: None,
+            trusted_proxies: vec![],
+            login_throttle: LoginThrottleConfig::default(),
+            require_totp_for_admins: default_require_totp_for_admins(),
         }
     }
 }
@@ -139,6 +334,18 @@ fn default_secret() -> String {
     String::from_utf8(chars).unwrap()
 }
 
+fn default_access_token_ttl() -> u64 {
+    15 * 60
+}
+
+fn default_refresh_token_ttl() -> u64 {
+    31 * 24 * 60 * 60
+}
+
+fn default_image_url_ttl() -> u64 {
+    24 * 60 * 60
+}
+
 fn default_database_path() -> String {
     let path = tanoshi_home();
     if !path.exists() {
@@ -151,6 +358,10 @@ fn default_create_database() -> bool {
     true
 }
 
+fn default_require_totp_for_admins() -> bool {
+    true
+}
+
 fn default_plugin_path() -> String {
     let path = tanoshi_home().join("plugins");
     if !path.exists() {
@@ -187,6 +398,70 @@ fn default_cache_path() -> String {
     path.display().to_string()
 }
 
+fn default_cover_path() -> String {
+    let path = tanoshi_home().join("covers");
+    if !path.exists() {
+        let _ = std::fs::create_dir_all(&path);
+    }
+    path.display().to_string()
+}
+
+fn default_cache_max_size() -> u64 {
+    1024 * 1024 * 1024
+}
+
+fn default_oidc_scopes() -> Vec<String> {
+    vec![
+        "openid".to_string(),
+        "profile".to_string(),
+        "email".to_string(),
+    ]
+}
+
+fn default_oidc_username_claim() -> String {
+    "preferred_username".to_string()
+}
+
+fn default_oidc_admin_claim() -> String {
+    "groups".to_string()
+}
+
+fn default_oidc_auto_create_user() -> bool {
+    true
+}
+
+fn default_proxy_auth_header() -> String {
+    "Remote-User".to_string()
+}
+
+fn default_login_throttle_max_attempts_per_username() -> u32 {
+    5
+}
+
+fn default_login_throttle_max_attempts_per_ip() -> u32 {
+    20
+}
+
+fn default_login_throttle_window() -> u64 {
+    900
+}
+
+fn default_login_throttle_lockout() -> u64 {
+    900
+}
+
+fn default_prefetch_enabled() -> bool {
+    true
+}
+
+fn default_prefetch_concurrency() -> usize {
+    2
+}
+
+fn default_prefetch_next_chapter_pages() -> usize {
+    3
+}
+
 impl Config {
     pub fn open<P: AsRef<Path>>(path: Option<P>) -> Result<Config, anyhow::Error> {
         let config_path = match path {
@@ -213,6 +488,54 @@ impl Config {
         }
     }
 
+    /// `X-Forwarded-For` of requests from this address can be used as client address
+    pub fn is_trusted_proxy(&self, addr: IpAddr) -> bool {
+        // ipv4 connections may be reported as ipv4-mapped ipv6 addresses
+        let addr = match addr {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(addr),
+            addr => addr,
+        };
+
+        self.trusted_proxies.iter().any(|net| net.contains(&addr))
+    }
+
+    /// Trust policy of repositories without their own settings
+    pub fn extension_trust_policy(&self) -> TrustPolicy {
+        TrustPolicy {
+            trusted_keys: self.extension_trusted_keys.clone(),
+            allow_unsigned: self.allow_unsigned_extensions,
+        }
+    }
+
+    /// Official repository followed by the ones in config
+    pub fn extension_repository_list(&self) -> Vec<ExtensionRepository> {
+        let official = ExtensionRepository {
+            name: "official".to_string(),
+            url: self.extension_repository.clone(),
+            from_config: true,
+            trust: self.extension_trust_policy(),
+        };
+
+        iter::once(official)
+            .chain(self.extension_repositories.iter().map(|repository| {
+                let mut trust = self.extension_trust_policy();
+                trust
+                    .trusted_keys
+                    .extend(repository.trusted_keys.iter().cloned());
+                if let Some(allow_unsigned) = repository.allow_unsigned {
+                    trust.allow_unsigned = allow_unsigned;
+                }
+
+                ExtensionRepository {
+                    name: repository.name.clone(),
+                    url: repository.url.trim_end_matches('/').to_string(),
+                    from_config: true,
+                    trust,
+                }
+            }))
+            .collect()
+    }
+
     pub fn save(&self) -> Result<(), anyhow::Error> {
         std::fs::write(&self.path, serde_yaml::to_string(&self)?)?;
 
@@ -0,0 +1,140 @@
+use async_trait::async_trait;
+use chrono::{NaiveDateTime, Utc};
+use itertools::Itertools;
+use sqlx::{sqlite::SqliteRow, Row, SqlitePool};
+
+use crate::{
+    domain::{
+        entities::api_key::{ApiKey, ApiKeyScope},
+        repositories::api_key::{ApiKeyRepository, ApiKeyRepositoryError},
+    },
+    infrastructure::database::Pool,
+};
+
+#[derive(Clone)]
+pub struct ApiKeyRepositoryImpl {
+    pool: Pool,
+}
+
+impl ApiKeyRepositoryImpl {
+    pub fn new<P: Into<Pool>>(pool: P) -> Self {
+        Self { pool: pool.into() }
+    }
+
+    fn from_row(row: SqliteRow) -> ApiKey {
+        let scopes: String = row.get(4);
+        ApiKey {
+            id: row.get(0),
+            user_id: row.get(1),
+            name: row.get(2),
+            prefix: row.get(3),
+            // unknown scopes from older versions are dropped
+            scopes: scopes
+                .split_whitespace()
+                .filter_map(|scope| scope.parse().ok())
+                .collect(),
+            created_at: row.get(5),
+            last_used_at: row.get(6),
+            expires_at: row.get(7),
+        }
+    }
+}
+
+#[async_trait]
+impl ApiKeyRepository for ApiKeyRepositoryImpl {
+    async fn insert_api_key(
+        &self,
+        user_id: i64,
+        name: &str,
+        key_hash: &str,
+        prefix: &str,
+        scopes: &[ApiKeyScope],
+        expires_at: Option<NaiveDateTime>,
+    ) -> Result<ApiKey, ApiKeyRepositoryError> {
+        let created_at = Utc::now().naive_utc();
+        let id = sqlx::query(
+            r#"INSERT INTO api_key(
+                user_id,
+                name,
+                key_hash,
+                prefix,
+                scopes,
+                created_at,
+                expires_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?)"#,
+        )
+        .bind(user_id)
+        .bind(name)
+        .bind(key_hash)
+        .bind(prefix)
+        .bind(scopes.iter().map(|scope| scope.as_str()).join(" "))
+        .bind(created_at)
+        .bind(expires_at)
+        .execute(&self.pool as &SqlitePool)
+        .await?
+        .last_insert_rowid();
+
+        Ok(ApiKey {
+            id,
+            user_id,
+            name: name.to_string(),
+            prefix: prefix.to_string(),
+            scopes: scopes.to_vec(),
+            created_at,
+            last_used_at: None,
+            expires_at,
+        })
+    }
+
+    async fn get_api_key_by_hash(&self, key_hash: &str) -> Result<ApiKey, ApiKeyRepositoryError> {
+        sqlx::query(
+            r#"SELECT id, user_id, name, prefix, scopes, created_at, last_used_at, expires_at
+            FROM api_key WHERE key_hash = ?"#,
+        )
+        .bind(key_hash)
+        .fetch_optional(&self.pool as &SqlitePool)
+        .await?
+        .map(Self::from_row)
+        .ok_or(ApiKeyRepositoryError::NotFound)
+    }
+
+    async fn get_api_keys_by_user_id(
+        &self,
+        user_id: i64,
+    ) -> Result<Vec<ApiKey>, ApiKeyRepositoryError> {
+        let keys = sqlx::query(
+            r#"SELECT id, user_id, name, prefix, scopes, created_at, last_used_at, expires_at
+            FROM api_key WHERE user_id = ?
+            ORDER BY created_at DESC"#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool as &SqlitePool)
+        .await?
+        .into_iter()
+        .map(Self::from_row)
+        .collect();
+
+        Ok(keys)
+    }
+
+    async fn update_last_used_at(&self, id: i64) -> Result<(), ApiKeyRepositoryError> {
+        sqlx::query(r#"UPDATE api_key SET last_used_at = ? WHERE id = ?"#)
+            .bind(Utc::now().naive_utc())
+            .bind(id)
+            .execute(&self.pool as &SqlitePool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn delete_api_key(&self, user_id: i64, id: i64) -> Result<u64, ApiKeyRepositoryError> {
+        let rows_affected = sqlx::query(r#"DELETE FROM api_key WHERE user_id = ? AND id = ?"#)
+            .bind(user_id)
+            .bind(id)
+            .execute(&self.pool as &SqlitePool)
+            .await?
+            .rows_affected();
+
+        Ok(rows_affected)
+    }
+}
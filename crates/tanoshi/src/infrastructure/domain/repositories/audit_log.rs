@@ -0,0 +1,102 @@
+use async_trait::async_trait;
+use chrono::{NaiveDateTime, Utc};
+use sqlx::{sqlite::SqliteRow, Row, SqlitePool};
+
+use crate::{
+    domain::{
+        entities::audit_log::AuditLog,
+        repositories::audit_log::{AuditLogRepository, AuditLogRepositoryError},
+    },
+    infrastructure::database::Pool,
+};
+
+#[derive(Clone)]
+pub struct AuditLogRepositoryImpl {
+    pool: Pool,
+}
+
+impl AuditLogRepositoryImpl {
+    pub fn new<P: Into<Pool>>(pool: P) -> Self {
+        Self { pool: pool.into() }
+    }
+
+    fn from_row(row: SqliteRow) -> AuditLog {
+        AuditLog {
+            id: row.get(0),
+            user_id: row.get(1),
+            username: row.get(2),
+            action: row.get(3),
+            target: row.get(4),
+            ip: row.get(5),
+            created_at: row.get(6),
+        }
+    }
+}
+
+#[async_trait]
+impl AuditLogRepository for AuditLogRepositoryImpl {
+    async fn insert_audit_log(
+        &self,
+        user_id: Option<i64>,
+        username: Option<&str>,
+        action: &str,
+        target: Option<&str>,
+        ip: Option<&str>,
+    ) -> Result<i64, AuditLogRepositoryError> {
+        let id = sqlx::query(
+            r#"INSERT INTO audit_log(user_id, username, action, target, ip, created_at)
+            VALUES (?, ?, ?, ?, ?, ?)"#,
+        )
+        .bind(user_id)
+        .bind(username)
+        .bind(action)
+        .bind(target)
+        .bind(ip)
+        .bind(Utc::now().naive_utc())
+        .execute(&self.pool as &SqlitePool)
+        .await?
+        .last_insert_rowid();
+
+        Ok(id)
+    }
+
+    async fn get_audit_logs(
+        &self,
+        user_id: Option<i64>,
+        action: Option<&str>,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+        before_id: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<AuditLog>, AuditLogRepositoryError> {
+        let logs = sqlx::query(
+            r#"SELECT id, user_id, username, action, target, ip, created_at
+            FROM audit_log
+            WHERE (? IS NULL OR user_id = ?)
+            AND (? IS NULL OR action = ?)
+            AND (? IS NULL OR created_at >= ?)
+            AND (? IS NULL OR created_at < ?)
+            AND (? IS NULL OR id < ?)
+            ORDER BY id DESC
+            LIMIT ?"#,
+        )
+        .bind(user_id)
+        .bind(user_id)
+        .bind(action)
+        .bind(action)
+        .bind(from)
+        .bind(from)
+        .bind(to)
+        .bind(to)
+        .bind(before_id)
+        .bind(before_id)
+        .bind(limit)
+        .fetch_all(&self.pool as &SqlitePool)
+        .await?
+        .into_iter()
+        .map(Self::from_row)
+        .collect();
+
+        Ok(logs)
+    }
+}
@@ -5,7 +5,7 @@ use sqlx::{Row, SqlitePool};
 
 use crate::{
     domain::{
-        entities::chapter::Chapter,
+        entities::chapter::{Chapter, ScanlatorSetting},
         repositories::chapter::{ChapterRepository, ChapterRepositoryError},
     },
     infrastructure::database::Pool,
@@ -75,8 +75,8 @@ impl ChapterRepository for ChapterRepositoryImpl {
         let row = sqlx::query(
             r#"SELECT 
                         chapter.*,
-                        (SELECT c.id FROM chapter c WHERE c.manga_id = chapter.manga_id AND c.number > chapter.number ORDER BY c.number ASC LIMIT 1) next,
-                        (SELECT c.id FROM chapter c WHERE c.manga_id = chapter.manga_id AND c.number < chapter.number ORDER BY c.number DESC LIMIT 1) prev
+                        (SELECT c.id FROM visible_chapter c WHERE c.manga_id = chapter.manga_id AND c.number > chapter.number ORDER BY c.number ASC LIMIT 1) next,
+                        (SELECT c.id FROM visible_chapter c WHERE c.manga_id = chapter.manga_id AND c.number < chapter.number ORDER BY c.number DESC LIMIT 1) prev
                     FROM chapter WHERE id = ?"#,
         )
         .bind(id)
@@ -107,8 +107,8 @@ impl ChapterRepository for ChapterRepositoryImpl {
         let row = sqlx::query(
             r#"SELECT 
                         chapter.*,
-                        (SELECT c.id FROM chapter c WHERE c.manga_id = chapter.manga_id AND c.number > chapter.number ORDER BY c.number ASC LIMIT 1) next,
-                        (SELECT c.id FROM chapter c WHERE c.manga_id = chapter.manga_id AND c.number < chapter.number ORDER BY c.number DESC LIMIT 1) prev
+                        (SELECT c.id FROM visible_chapter c WHERE c.manga_id = chapter.manga_id AND c.number > chapter.number ORDER BY c.number ASC LIMIT 1) next,
+                        (SELECT c.id FROM visible_chapter c WHERE c.manga_id = chapter.manga_id AND c.number < chapter.number ORDER BY c.number DESC LIMIT 1) prev
                     FROM chapter WHERE source_id = ? AND path = ?"#,
         )
         .bind(source_id)
@@ -148,9 +148,9 @@ impl ChapterRepository for ChapterRepositoryImpl {
         let query_str = format!(
             r#"SELECT
                         chapter.*,
-                        (SELECT c.id FROM chapter c WHERE c.manga_id = chapter.manga_id AND c.number > chapter.number ORDER BY c.number ASC LIMIT 1) next,
-                        (SELECT c.id FROM chapter c WHERE c.manga_id = chapter.manga_id AND c.number < chapter.number ORDER BY c.number DESC LIMIT 1) prev
-                    FROM chapter WHERE manga_id = ? ORDER BY {order_by} {order} {limit}"#,
+                        (SELECT c.id FROM visible_chapter c WHERE c.manga_id = chapter.manga_id AND c.number > chapter.number ORDER BY c.number ASC LIMIT 1) next,
+                        (SELECT c.id FROM visible_chapter c WHERE c.manga_id = chapter.manga_id AND c.number < chapter.number ORDER BY c.number DESC LIMIT 1) prev
+                    FROM visible_chapter chapter WHERE manga_id = ? ORDER BY {order_by} {order} {limit}"#,
         );
         let chapters = sqlx::query(&query_str)
             .bind(manga_id)
@@ -256,4 +256,80 @@ impl ChapterRepository for ChapterRepositoryImpl {
 
         Ok(chapters)
     }
+
+    async fn get_scanlators_by_manga_id(
+        &self,
+        manga_id: i64,
+    ) -> Result<Vec<String>, ChapterRepositoryError> {
+        let scanlators = sqlx::query(
+            r#"SELECT DISTINCT scanlator FROM chapter
+            WHERE manga_id = ? AND IFNULL(scanlator, '') <> ''
+            ORDER BY scanlator"#,
+        )
+        .bind(manga_id)
+        .fetch_all(&self.pool as &SqlitePool)
+        .await?
+        .iter()
+        .map(|row| row.get(0))
+        .collect();
+
+        Ok(scanlators)
+    }
+
+    async fn get_scanlator_setting(
+        &self,
+        manga_id: i64,
+    ) -> Result<ScanlatorSetting, ChapterRepositoryError> {
+        let setting = sqlx::query(
+            "SELECT manga_id, dedup, priority, blocklist FROM manga_scanlator_setting WHERE manga_id = ?",
+        )
+        .bind(manga_id)
+        .fetch_optional(&self.pool as &SqlitePool)
+        .await?
+        .map(|row| ScanlatorSetting {
+            manga_id: row.get(0),
+            dedup: row.get(1),
+            priority: serde_json::from_str(row.get::<String, _>(2).as_str()).unwrap_or_default(),
+            blocklist: serde_json::from_str(row.get::<String, _>(3).as_str()).unwrap_or_default(),
+        })
+        .unwrap_or_else(|| ScanlatorSetting {
+            manga_id,
+            ..Default::default()
+        });
+
+        Ok(setting)
+    }
+
+    async fn set_scanlator_setting(
+        &self,
+        setting: &ScanlatorSetting,
+    ) -> Result<(), ChapterRepositoryError> {
+        sqlx::query(
+            r#"INSERT INTO manga_scanlator_setting(manga_id, dedup, priority, blocklist, updated_at)
+            VALUES (?, ?, ?, ?, ?)
+            ON CONFLICT(manga_id) DO UPDATE SET
+                dedup = excluded.dedup,
+                priority = excluded.priority,
+                blocklist = excluded.blocklist,
+                updated_at = excluded.updated_at"#,
+        )
+        .bind(setting.manga_id)
+        .bind(setting.dedup)
+        .bind(serde_json::to_string(&setting.priority).unwrap_or_else(|_| "[]".to_string()))
+        .bind(serde_json::to_string(&setting.blocklist).unwrap_or_else(|_| "[]".to_string()))
+        .bind(Utc::now().naive_utc())
+        .execute(&self.pool as &SqlitePool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn delete_scanlator_setting(&self, manga_id: i64) -> Result<(), ChapterRepositoryError> {
+        sqlx::query("DELETE FROM manga_scanlator_setting WHERE manga_id = ?")
+            .bind(manga_id)
+            .execute(&self.pool as &SqlitePool)
+            .await?;
+
+        Ok(())
+    }
 }
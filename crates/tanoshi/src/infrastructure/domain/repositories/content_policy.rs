@@ -0,0 +1,139 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use sqlx::{
+    query::Query,
+    sqlite::{SqliteArguments, SqliteRow},
+    Row, Sqlite, SqlitePool,
+};
+
+use crate::{
+    domain::{
+        entities::content_policy::{ContentPolicy, ContentRestriction},
+        repositories::content_policy::{ContentPolicyRepository, ContentPolicyRepositoryError},
+    },
+    infrastructure::database::Pool,
+};
+
+/// Condition on source id and genre columns of a query for manga allowed by a restriction,
+/// values are bound by [`bind_restriction`] in the same order
+pub fn restriction_condition(source_id: &str, genre: &str) -> String {
+    format!(
+        r#"(? IS NULL OR {source_id} IN (SELECT value FROM json_each(?)))
+        AND {source_id} NOT IN (SELECT value FROM json_each(?))
+        AND NOT EXISTS (
+            SELECT 1 FROM json_each(CASE WHEN json_valid({genre}) THEN {genre} ELSE '[]' END) AS manga_genre
+            JOIN json_each(?) AS blocked_genre
+            ON LOWER(TRIM(manga_genre.value)) = LOWER(TRIM(blocked_genre.value))
+        )"#
+    )
+}
+
+pub fn bind_restriction<'q>(
+    query: Query<'q, Sqlite, SqliteArguments<'q>>,
+    restriction: &ContentRestriction,
+) -> Query<'q, Sqlite, SqliteArguments<'q>> {
+    let allowed_source_ids = restriction
+        .allowed_source_ids
+        .as_ref()
+        .map(|ids| serde_json::json!(ids).to_string());
+
+    query
+        .bind(allowed_source_ids.clone())
+        .bind(allowed_source_ids)
+        .bind(serde_json::json!(restriction.denied_source_ids).to_string())
+        .bind(serde_json::json!(restriction.blocked_genres).to_string())
+}
+
+#[derive(Clone)]
+pub struct ContentPolicyRepositoryImpl {
+    pool: Pool,
+}
+
+impl ContentPolicyRepositoryImpl {
+    pub fn new<P: Into<Pool>>(pool: P) -> Self {
+        Self { pool: pool.into() }
+    }
+
+    fn from_row(row: SqliteRow) -> ContentPolicy {
+        ContentPolicy {
+            user_id: row.get(0),
+            hide_nsfw: row.get(1),
+            allowed_source_ids: row
+                .get::<Option<String>, _>(2)
+                .and_then(|ids| serde_json::from_str(&ids).ok()),
+            denied_source_ids: serde_json::from_str(row.get::<String, _>(3).as_str())
+                .unwrap_or_default(),
+            blocked_genres: serde_json::from_str(row.get::<String, _>(4).as_str())
+                .unwrap_or_default(),
+            updated_at: row.get(5),
+        }
+    }
+}
+
+#[async_trait]
+impl ContentPolicyRepository for ContentPolicyRepositoryImpl {
+    async fn get_content_policy(
+        &self,
+        user_id: i64,
+    ) -> Result<ContentPolicy, ContentPolicyRepositoryError> {
+        sqlx::query(
+            r#"SELECT user_id, hide_nsfw, allowed_source_ids, denied_source_ids, blocked_genres, updated_at
+            FROM user_content_policy WHERE user_id = ?"#,
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool as &SqlitePool)
+        .await?
+        .map(Self::from_row)
+        .ok_or(ContentPolicyRepositoryError::NotFound)
+    }
+
+    async fn upsert_content_policy(
+        &self,
+        policy: &ContentPolicy,
+    ) -> Result<(), ContentPolicyRepositoryError> {
+        sqlx::query(
+            r#"INSERT INTO user_content_policy(
+                user_id,
+                hide_nsfw,
+                allowed_source_ids,
+                denied_source_ids,
+                blocked_genres,
+                updated_at
+            ) VALUES (?, ?, ?, ?, ?, ?)
+            ON CONFLICT(user_id) DO UPDATE SET
+            hide_nsfw = excluded.hide_nsfw,
+            allowed_source_ids = excluded.allowed_source_ids,
+            denied_source_ids = excluded.denied_source_ids,
+            blocked_genres = excluded.blocked_genres,
+            updated_at = excluded.updated_at"#,
+        )
+        .bind(policy.user_id)
+        .bind(policy.hide_nsfw)
+        .bind(
+            policy
+                .allowed_source_ids
+                .as_ref()
+                .and_then(|ids| serde_json::to_string(ids).ok()),
+        )
+        .bind(serde_json::to_string(&policy.denied_source_ids).unwrap_or_else(|_| "[]".to_string()))
+        .bind(serde_json::to_string(&policy.blocked_genres).unwrap_or_else(|_| "[]".to_string()))
+        .bind(Utc::now().naive_utc())
+        .execute(&self.pool as &SqlitePool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn delete_content_policy(
+        &self,
+        user_id: i64,
+    ) -> Result<u64, ContentPolicyRepositoryError> {
+        let rows_affected = sqlx::query(r#"DELETE FROM user_content_policy WHERE user_id = ?"#)
+            .bind(user_id)
+            .execute(&self.pool as &SqlitePool)
+            .await?
+            .rows_affected();
+
+        Ok(rows_affected)
+    }
+}
@@ -43,7 +43,8 @@ impl HistoryRepository for HistoryRepositoryImpl {
             chapter.title,
             MAX(user_history.read_at) AS read_at,
             user_history.last_page,
-            user_history.is_complete
+            user_history.is_complete,
+            manga.source_id
         FROM user_history
         JOIN chapter ON 
             user_history.user_id = ? AND
@@ -72,6 +73,7 @@ impl HistoryRepository for HistoryRepositoryImpl {
             read_at: row.get(5),
             last_page_read: row.get(6),
             is_complete: row.get(7),
+            source_id: row.get(8),
         })
         .collect();
 
@@ -96,7 +98,8 @@ impl HistoryRepository for HistoryRepositoryImpl {
                 chapter.title,
                 MAX(user_history.read_at) AS read_at,
                 user_history.last_page,
-                user_history.is_complete
+                user_history.is_complete,
+                manga.source_id
             FROM user_history
             JOIN chapter ON 
                 user_history.user_id = ? AND
@@ -125,6 +128,7 @@ impl HistoryRepository for HistoryRepositoryImpl {
             read_at: row.get(5),
             last_page_read: row.get(6),
             is_complete: row.get(7),
+            source_id: row.get(8),
         })
         .collect();
 
@@ -147,7 +151,8 @@ impl HistoryRepository for HistoryRepositoryImpl {
             chapter.title,
             MAX(user_history.read_at) AS read_at,
             user_history.last_page,
-            user_history.is_complete
+            user_history.is_complete,
+            manga.source_id
         FROM user_history
         JOIN chapter ON 
             user_history.user_id = ? AND
@@ -174,6 +179,7 @@ impl HistoryRepository for HistoryRepositoryImpl {
             read_at: row.get(5),
             last_page_read: row.get(6),
             is_complete: row.get(7),
+            source_id: row.get(8),
         })
         .collect();
 
@@ -194,7 +200,8 @@ impl HistoryRepository for HistoryRepositoryImpl {
                     chapter.title,
                     user_history.read_at,
                     user_history.last_page,
-                    user_history.is_complete
+                    user_history.is_complete,
+                    manga.source_id
                 FROM user_history
                 JOIN chapter ON 
                     chapter.id = user_history.chapter_id AND
@@ -225,6 +232,7 @@ impl HistoryRepository for HistoryRepositoryImpl {
                 read_at: row.get(5),
                 last_page_read: row.get(6),
                 is_complete: row.get(7),
+                source_id: row.get(8),
             })
             .collect();
 
@@ -245,7 +253,8 @@ impl HistoryRepository for HistoryRepositoryImpl {
                     chapter.title,
                     user_history.read_at,
                     user_history.last_page,
-                    user_history.is_complete
+                    user_history.is_complete,
+                    manga.source_id
                 FROM user_history
                 JOIN chapter ON 
                     chapter.id = user_history.chapter_id
@@ -273,6 +282,7 @@ impl HistoryRepository for HistoryRepositoryImpl {
                 read_at: row.get(5),
                 last_page_read: row.get(6),
                 is_complete: row.get(7),
+                source_id: row.get(8),
             })
             .collect();
 
@@ -379,7 +389,7 @@ impl HistoryRepository for HistoryRepositoryImpl {
         let query_str = format!(
             r#"SELECT manga_id, COUNT(1) FROM (
                 SELECT manga_id, IFNULL(user_history.is_complete, false) AS is_complete 
-                FROM chapter c 
+                FROM visible_chapter c 
                 LEFT JOIN user_history ON 
                     user_history.user_id = ? AND 
                     user_history.chapter_id = c.id 
@@ -431,7 +441,7 @@ impl HistoryRepository for HistoryRepositoryImpl {
                 SELECT
                     id
                 FROM
-                    chapter
+                    visible_chapter chapter
                     LEFT JOIN user_history ON user_history.chapter_id = chapter.id
                     AND user_history.user_id = ?
                 WHERE
@@ -449,7 +459,7 @@ impl HistoryRepository for HistoryRepositoryImpl {
                                 SELECT
                                     id
                                 FROM
-                                    chapter
+                                    visible_chapter chapter
                                     LEFT JOIN user_history ON user_history.chapter_id = chapter.id
                                     AND user_history.user_id = ?
                                 WHERE
@@ -0,0 +1,94 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use sqlx::{sqlite::SqliteRow, Row, SqlitePool};
+use tanoshi_lib::prelude::HttpProfile;
+
+use crate::{
+    domain::repositories::http_profile::{HttpProfileRepository, HttpProfileRepositoryError},
+    infrastructure::database::Pool,
+};
+
+#[derive(Clone)]
+pub struct HttpProfileRepositoryImpl {
+    pool: Pool,
+}
+
+impl HttpProfileRepositoryImpl {
+    pub fn new<P: Into<Pool>>(pool: P) -> Self {
+        Self { pool: pool.into() }
+    }
+
+    fn from_row(row: &SqliteRow) -> Result<HttpProfile, HttpProfileRepositoryError> {
+        Ok(HttpProfile {
+            proxy: row.get(1),
+            user_agent: row.get(2),
+            headers: serde_json::from_str(row.get(3))?,
+            cookie_jar: None,
+        })
+    }
+}
+
+#[async_trait]
+impl HttpProfileRepository for HttpProfileRepositoryImpl {
+    async fn get_http_profiles(
+        &self,
+    ) -> Result<Vec<(i64, HttpProfile)>, HttpProfileRepositoryError> {
+        let rows =
+            sqlx::query(r#"SELECT source_id, proxy, user_agent, headers FROM source_http_profile"#)
+                .fetch_all(&self.pool as &SqlitePool)
+                .await?;
+
+        rows.iter()
+            .map(|row| Ok((row.get(0), Self::from_row(row)?)))
+            .collect()
+    }
+
+    async fn get_http_profile(
+        &self,
+        source_id: i64,
+    ) -> Result<Option<HttpProfile>, HttpProfileRepositoryError> {
+        let row = sqlx::query(
+            r#"SELECT source_id, proxy, user_agent, headers FROM source_http_profile
+            WHERE source_id = ?"#,
+        )
+        .bind(source_id)
+        .fetch_optional(&self.pool as &SqlitePool)
+        .await?;
+
+        row.as_ref().map(Self::from_row).transpose()
+    }
+
+    async fn set_http_profile(
+        &self,
+        source_id: i64,
+        profile: &HttpProfile,
+    ) -> Result<(), HttpProfileRepositoryError> {
+        sqlx::query(
+            r#"INSERT INTO source_http_profile(source_id, proxy, user_agent, headers, updated_at)
+            VALUES (?, ?, ?, ?, ?)
+            ON CONFLICT(source_id) DO UPDATE SET
+                proxy = excluded.proxy,
+                user_agent = excluded.user_agent,
+                headers = excluded.headers,
+                updated_at = excluded.updated_at"#,
+        )
+        .bind(source_id)
+        .bind(&profile.proxy)
+        .bind(&profile.user_agent)
+        .bind(serde_json::to_string(&profile.headers)?)
+        .bind(Utc::now().naive_utc())
+        .execute(&self.pool as &SqlitePool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn delete_http_profile(&self, source_id: i64) -> Result<(), HttpProfileRepositoryError> {
+        sqlx::query(r#"DELETE FROM source_http_profile WHERE source_id = ?"#)
+            .bind(source_id)
+            .execute(&self.pool as &SqlitePool)
+            .await?;
+
+        Ok(())
+    }
+}
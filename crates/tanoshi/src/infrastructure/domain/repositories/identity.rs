@@ -0,0 +1,106 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use sqlx::{sqlite::SqliteRow, Row, SqlitePool};
+
+use crate::{
+    domain::{
+        entities::identity::UserIdentity,
+        repositories::identity::{IdentityRepository, IdentityRepositoryError},
+    },
+    infrastructure::database::Pool,
+};
+
+#[derive(Clone)]
+pub struct IdentityRepositoryImpl {
+    pool: Pool,
+}
+
+impl IdentityRepositoryImpl {
+    pub fn new<P: Into<Pool>>(pool: P) -> Self {
+        Self { pool: pool.into() }
+    }
+
+    fn from_row(row: SqliteRow) -> UserIdentity {
+        UserIdentity {
+            id: row.get(0),
+            user_id: row.get(1),
+            issuer: row.get(2),
+            subject: row.get(3),
+            created_at: row.get(4),
+        }
+    }
+}
+
+#[async_trait]
+impl IdentityRepository for IdentityRepositoryImpl {
+    async fn get_identity(
+        &self,
+        issuer: &str,
+        subject: &str,
+    ) -> Result<UserIdentity, IdentityRepositoryError> {
+        sqlx::query(
+            r#"SELECT id, user_id, issuer, subject, created_at
+            FROM user_identity WHERE issuer = ? AND subject = ?"#,
+        )
+        .bind(issuer)
+        .bind(subject)
+        .fetch_optional(&self.pool as &SqlitePool)
+        .await?
+        .map(Self::from_row)
+        .ok_or(IdentityRepositoryError::NotFound)
+    }
+
+    async fn get_identities_by_user_id(
+        &self,
+        user_id: i64,
+    ) -> Result<Vec<UserIdentity>, IdentityRepositoryError> {
+        let identities = sqlx::query(
+            r#"SELECT id, user_id, issuer, subject, created_at
+            FROM user_identity WHERE user_id = ?"#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool as &SqlitePool)
+        .await?
+        .into_iter()
+        .map(Self::from_row)
+        .collect();
+
+        Ok(identities)
+    }
+
+    async fn insert_identity(
+        &self,
+        user_id: i64,
+        issuer: &str,
+        subject: &str,
+    ) -> Result<i64, IdentityRepositoryError> {
+        let id = sqlx::query(
+            r#"INSERT INTO user_identity(user_id, issuer, subject, created_at) VALUES (?, ?, ?, ?)"#,
+        )
+        .bind(user_id)
+        .bind(issuer)
+        .bind(subject)
+        .bind(Utc::now().naive_utc())
+        .execute(&self.pool as &SqlitePool)
+        .await?
+        .last_insert_rowid();
+
+        Ok(id)
+    }
+
+    async fn delete_identities_by_user_id(
+        &self,
+        user_id: i64,
+        issuer: &str,
+    ) -> Result<u64, IdentityRepositoryError> {
+        let rows_affected =
+            sqlx::query(r#"DELETE FROM user_identity WHERE user_id = ? AND issuer = ?"#)
+                .bind(user_id)
+                .bind(issuer)
+                .execute(&self.pool as &SqlitePool)
+                .await?
+                .rows_affected();
+
+        Ok(rows_affected)
+    }
+}
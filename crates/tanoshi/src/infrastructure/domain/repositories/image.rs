@@ -1,22 +1,153 @@
-use std::path::Path;
+use std::{
+    collections::HashMap,
+    io::SeekFrom,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
 
 use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::{DateTime, NaiveDateTime, Utc};
 
 use http::{HeaderMap, HeaderValue};
+use reqwest::redirect::Policy;
+use tanoshi_lib::prelude::HttpProfile;
+use tanoshi_vm::extension::{http::client_builder, ExtensionManager};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
 
 use crate::domain::{
-    entities::image::Image,
+    entities::image::{Image, ImageFileMetadata},
     repositories::image::{ImageRepository, ImageRepositoryError},
 };
 
-#[derive(Default, Clone)]
+/// Common second level labels under country code domains, e.g. `co` in `co.jp`
+const COUNTRY_SECOND_LEVEL_LABELS: &[&str] =
+    &["ac", "co", "com", "edu", "gov", "ne", "net", "or", "org"];
+
+/// Domain of a host without subdomains, e.g. `example.com` for `img.example.com` and
+/// `example.co.jp` for `img.example.co.jp`
+fn base_domain(host: &str) -> String {
+    if host.starts_with('[') || host.parse::<IpAddr>().is_ok() {
+        return host.to_string();
+    }
+
+    let labels: Vec<&str> = host.trim_end_matches('.').split('.').collect();
+    let take = match labels.as_slice() {
+        [.., _, second_level, top_level]
+            if top_level.len() == 2 && COUNTRY_SECOND_LEVEL_LABELS.contains(second_level) =>
+        {
+            3
+        }
+        _ => 2,
+    };
+
+    labels[labels.len().saturating_sub(take)..].join(".")
+}
+
+fn is_same_or_subdomain(host: &str, domain: &str) -> bool {
+    host.eq_ignore_ascii_case(domain)
+        || host
+            .to_lowercase()
+            .ends_with(&format!(".{}", domain.to_lowercase()))
+}
+
+/// Redirects followed for an image before giving up, same as reqwest default
+const MAX_REDIRECTS: usize = 10;
+
+/// Images can only be loaded from the domain of the source or hosts configured for it
+fn is_source_host(
+    ext: &ExtensionManager,
+    image_hosts: &HashMap<i64, Vec<String>>,
+    source_id: i64,
+    url: &reqwest::Url,
+) -> bool {
+    let host = match url.host_str() {
+        Some(host) => host,
+        None => return false,
+    };
+
+    if let Some(hosts) = image_hosts.get(&source_id) {
+        if hosts
+            .iter()
+            .any(|allowed| is_same_or_subdomain(host, allowed))
+        {
+            return true;
+        }
+    }
+
+    ext.get_source_info(source_id)
+        .ok()
+        .and_then(|info| reqwest::Url::parse(&info.url).ok())
+        .and_then(|url| url.host_str().map(base_domain))
+        .map_or(false, |domain| is_same_or_subdomain(host, &domain))
+}
+
+#[derive(Clone)]
 pub struct ImageRepositoryImpl {
-    client: reqwest::Client,
+    ext: ExtensionManager,
+    image_hosts: Arc<HashMap<i64, Vec<String>>>,
+    /// clients of sources and the http profile they are built from, a client is built
+    /// again when the profile changes
+    clients: Arc<RwLock<HashMap<i64, (HttpProfile, reqwest::Client)>>>,
 }
 
 impl ImageRepositoryImpl {
-    pub fn new() -> Self {
-        Self::default()
+    pub fn new(ext: ExtensionManager, image_hosts: HashMap<i64, Vec<String>>) -> Self {
+        Self {
+            ext,
+            image_hosts: Arc::new(image_hosts),
+            clients: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Every redirect has to stay on hosts of the source too
+    fn redirect_policy(&self, source_id: i64) -> Policy {
+        let ext = self.ext.clone();
+        let image_hosts = self.image_hosts.clone();
+        Policy::custom(move |attempt| {
+            if attempt.previous().len() >= MAX_REDIRECTS {
+                attempt.error("too many redirects")
+            } else if is_source_host(&ext, &image_hosts, source_id, attempt.url()) {
+                attempt.follow()
+            } else {
+                let url = attempt.url().to_string();
+                attempt.error(ImageRepositoryError::HostNotAllowed(url))
+            }
+        })
+    }
+
+    /// Use client with http profile of the source if available
+    fn client(&self, source_id: i64) -> Result<reqwest::Client, ImageRepositoryError> {
+        let profile = match self.ext.http_profile(source_id) {
+            Ok(profile) => profile,
+            Err(e) => {
+                debug!("no http profile for source {source_id}: {e}");
+                HttpProfile::default()
+            }
+        };
+
+        if let Some((client_profile, client)) = self
+            .clients
+            .read()
+            .map_err(|e| ImageRepositoryError::Other(e.to_string()))?
+            .get(&source_id)
+        {
+            if *client_profile == profile {
+                return Ok(client.clone());
+            }
+        }
+
+        let client = client_builder(&profile)
+            .map_err(|e| ImageRepositoryError::Other(e.to_string()))?
+            .redirect(self.redirect_policy(source_id))
+            .build()?;
+        self.clients
+            .write()
+            .map_err(|e| ImageRepositoryError::Other(e.to_string()))?
+            .insert(source_id, (profile, client.clone()));
+
+        Ok(client)
     }
 }
 
@@ -25,6 +156,7 @@ impl ImageRepository for ImageRepositoryImpl {
     async fn fetch_image_from_url(
         &self,
         url: &str,
+        source_id: Option<i64>,
         referer: Option<&String>,
     ) -> Result<Image, ImageRepositoryError> {
         debug!("get image from {}", url);
@@ -34,13 +166,40 @@ impl ImageRepository for ImageRepositoryImpl {
             ));
         }
 
+        let source_id = match (source_id, reqwest::Url::parse(url)) {
+            (Some(source_id), Ok(parsed))
+                if is_source_host(&self.ext, &self.image_hosts, source_id, &parsed) =>
+            {
+                source_id
+            }
+            _ => {
+                return Err(ImageRepositoryError::HostNotAllowed(url.to_string()));
+            }
+        };
+
         let mut headers = HeaderMap::new();
 
         if let Some(referer) = referer.and_then(|r| r.parse::<HeaderValue>().ok()) {
             headers.insert("Referer", referer);
         }
 
-        let source_res = self.client.get(url).headers(headers).send().await?;
+        let source_res = self
+            .client(source_id)?
+            .get(url)
+            .headers(headers)
+            .send()
+            .await
+            .map_err(|e| {
+                // redirect to another host is rejected by the redirect policy
+                match std::error::Error::source(&e)
+                    .and_then(|source| source.downcast_ref::<ImageRepositoryError>())
+                {
+                    Some(ImageRepositoryError::HostNotAllowed(url)) => {
+                        ImageRepositoryError::HostNotAllowed(url.clone())
+                    }
+                    _ => e.into(),
+                }
+            })?;
 
         let content_type = source_res
             .headers()
@@ -52,55 +211,167 @@ impl ImageRepository for ImageRepositoryImpl {
 
         let data = source_res.bytes().await?;
 
-        Ok(Image { content_type, data })
+        Ok(Image::new(content_type, data, Some(Utc::now().naive_utc())))
     }
 
-    async fn fetch_image_from_file<P>(&self, path: P) -> Result<Image, ImageRepositoryError>
+    async fn fetch_file_metadata<P>(
+        &self,
+        path: P,
+    ) -> Result<ImageFileMetadata, ImageRepositoryError>
     where
         P: AsRef<Path> + std::marker::Send,
     {
-        let content_type = mime_guess::from_path(&path)
-            .first_or_octet_stream()
-            .to_string();
-        let data = tokio::fs::read(path)
+        let metadata = tokio::fs::metadata(path)
             .await
             .map_err(|e| ImageRepositoryError::Other(format!("{e}")))?;
 
-        Ok(Image {
-            content_type,
-            data: data.into(),
+        Ok(ImageFileMetadata {
+            len: metadata.len(),
+            last_modified: modified_at(&metadata),
         })
     }
 
+    async fn fetch_image_range_from_file<P>(
+        &self,
+        path: P,
+        start: u64,
+        len: u64,
+    ) -> Result<Bytes, ImageRepositoryError>
+    where
+        P: AsRef<Path> + std::marker::Send,
+    {
+        let mut file = tokio::fs::File::open(PathBuf::from(path.as_ref()))
+            .await
+            .map_err(|e| ImageRepositoryError::Other(format!("{e}")))?;
+        file.seek(SeekFrom::Start(start))
+            .await
+            .map_err(|e| ImageRepositoryError::Other(format!("{e}")))?;
+
+        let mut data = Vec::with_capacity(len as usize);
+        file.take(len)
+            .read_to_end(&mut data)
+            .await
+            .map_err(|e| ImageRepositoryError::Other(format!("{e}")))?;
+
+        Ok(data.into())
+    }
+
     async fn fetch_image_from_archive<P>(
         &self,
         archive: P,
         filename: &str,
-    ) -> Result<Image, ImageRepositoryError>
+    ) -> Result<Bytes, ImageRepositoryError>
     where
         P: AsRef<Path> + std::marker::Send,
     {
         let filename = filename.to_owned();
-        let content_type = mime_guess::from_path(&filename)
-            .first_or_octet_stream()
-            .to_string();
 
         let source = std::fs::File::open(archive)
             .map_err(|e| ImageRepositoryError::Other(format!("{e}")))?;
-        let (content_type, data) =
-            tokio::task::spawn_blocking(move || -> Result<(String, Vec<u8>), anyhow::Error> {
-                let mut buf: Vec<u8> = vec![];
-                compress_tools::uncompress_archive_file(source, &mut buf, &filename)?;
+        let data = tokio::task::spawn_blocking(move || -> Result<Vec<u8>, anyhow::Error> {
+            let mut buf: Vec<u8> = vec![];
+            compress_tools::uncompress_archive_file(source, &mut buf, &filename)?;
+
+            Ok(buf)
+        })
+        .await
+        .map_err(|e| ImageRepositoryError::Other(format!("{e}")))?
+        .map_err(|e| ImageRepositoryError::Other(format!("{e}")))?;
+
+        Ok(data.into())
+    }
+
+    async fn write_image_to_file<P>(&self, path: P, data: &[u8]) -> Result<(), ImageRepositoryError>
+    where
+        P: AsRef<Path> + std::marker::Send,
+    {
+        if let Some(parent) = path.as_ref().parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| ImageRepositoryError::Other(format!("{e}")))?;
+        }
 
-                Ok((content_type, buf))
-            })
+        tokio::fs::write(path, data)
             .await
-            .map_err(|e| ImageRepositoryError::Other(format!("{e}")))?
-            .map_err(|e| ImageRepositoryError::Other(format!("{e}")))?;
+            .map_err(|e| ImageRepositoryError::Other(format!("{e}")))
+    }
 
-        Ok(Image {
-            content_type,
-            data: data.into(),
-        })
+    async fn remove_image_file<P>(&self, path: P) -> Result<(), ImageRepositoryError>
+    where
+        P: AsRef<Path> + std::marker::Send,
+    {
+        match tokio::fs::remove_file(path).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(ImageRepositoryError::Other(format!("{e}")))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn modified_at(metadata: &std::fs::Metadata) -> Option<NaiveDateTime> {
+    metadata
+        .modified()
+        .ok()
+        .map(|modified| DateTime::<Utc>::from(modified).naive_utc())
+}
+
+#[cfg(test)]
+mod test {
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_fetch_image_checks_redirect_host() {
+        let server = MockServer::start().await;
+        let port = server.address().port();
+        Mock::given(method("GET"))
+            .and(path("/image"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw("png", "image/png"))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/same-host"))
+            .respond_with(ResponseTemplate::new(302).insert_header(
+                "Location",
+                format!("http://127.0.0.1:{port}/image").as_str(),
+            ))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/other-host"))
+            .respond_with(ResponseTemplate::new(302).insert_header(
+                "Location",
+                format!("http://localhost:{port}/image").as_str(),
+            ))
+            .mount(&server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let repo = ImageRepositoryImpl::new(
+            ExtensionManager::new(dir.path()),
+            HashMap::from([(1, vec!["127.0.0.1".to_string()])]),
+        );
+
+        let image = repo
+            .fetch_image_from_url(&format!("{}/same-host", server.uri()), Some(1), None)
+            .await
+            .unwrap();
+        assert_eq!(image.data, Bytes::from_static(b"png"));
+
+        assert!(matches!(
+            repo.fetch_image_from_url(&format!("{}/other-host", server.uri()), Some(1), None)
+                .await,
+            Err(ImageRepositoryError::HostNotAllowed(url)) if url.starts_with("http://localhost")
+        ));
+        assert!(matches!(
+            repo.fetch_image_from_url(&format!("{}/image", server.uri()), Some(2), None)
+                .await,
+            Err(ImageRepositoryError::HostNotAllowed(_))
+        ));
     }
 }
@@ -1,43 +1,340 @@
 use async_trait::async_trait;
-use std::path::{Path, PathBuf};
+use chrono::Utc;
+use sqlx::{Row, SqlitePool};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc,
+    },
+};
+use tokio_stream::StreamExt;
 
-use crate::domain::{
-    entities::image::Image,
-    repositories::image_cache::{ImageCacheRepository, ImageCacheRepositoryError},
+use crate::{
+    domain::{
+        entities::image::{Image, ImageCacheStats, ImageOrigin},
+        repositories::image_cache::{ImageCacheRepository, ImageCacheRepositoryError},
+    },
+    infrastructure::database::Pool,
 };
 
+/// Covers of manga in any user's library are kept out of the LRU and only
+/// leave the cache once the manga is removed from every library.
+const LIBRARY_COVER: &str = r#"(image_cache.is_cover AND EXISTS (
+    SELECT 1 FROM user_library WHERE user_library.manga_id = image_cache.manga_id
+))"#;
+
+/// Marks the running size as not loaded from the index yet
+const SIZE_UNKNOWN: i64 = -1;
+
 #[derive(Clone)]
 pub struct ImageCacheRepositoryImpl {
+    pool: Pool,
     path: PathBuf,
+    max_size: i64,
+    /// Running total of cached bytes, so inserts don't have to sum the index.
+    /// It may overestimate when covers are added to a library, in which case
+    /// eviction recomputes it.
+    size: Arc<AtomicI64>,
 }
 
 impl ImageCacheRepositoryImpl {
-    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+    pub fn new<P: Into<Pool>, Q: AsRef<Path>>(pool: P, path: Q, max_size: u64) -> Self {
         Self {
+            pool: pool.into(),
             path: PathBuf::new().join(path),
+            max_size: max_size as i64,
+            size: Arc::new(AtomicI64::new(SIZE_UNKNOWN)),
+        }
+    }
+
+    async fn evictable_size(&self) -> Result<i64, ImageCacheRepositoryError> {
+        let size: i64 = sqlx::query(&format!(
+            r#"SELECT IFNULL(SUM(size), 0) FROM image_cache WHERE NOT {LIBRARY_COVER}"#
+        ))
+        .fetch_one(&self.pool as &SqlitePool)
+        .await?
+        .get(0);
+
+        Ok(size)
+    }
+
+    /// Add the size difference of an insert to the running total, returns the new total
+    async fn add_size(&self, delta: i64) -> Result<i64, ImageCacheRepositoryError> {
+        if self.size.load(Ordering::Relaxed) == SIZE_UNKNOWN {
+            // the insert is already in the index, so don't count it twice
+            let size = self.evictable_size().await?;
+            let _ = self.size.compare_exchange(
+                SIZE_UNKNOWN,
+                size,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            );
+            return Ok(self.size.load(Ordering::Relaxed));
+        }
+
+        Ok(self.size.fetch_add(delta, Ordering::Relaxed) + delta)
+    }
+
+    async fn record_access(&self, hit: bool) -> Result<(), ImageCacheRepositoryError> {
+        let query_str = if hit {
+            r#"UPDATE image_cache_stats SET hits = hits + 1 WHERE id = 1"#
+        } else {
+            r#"UPDATE image_cache_stats SET misses = misses + 1 WHERE id = 1"#
+        };
+
+        sqlx::query(query_str)
+            .execute(&self.pool as &SqlitePool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn remove_files(&self, keys: &[String]) {
+        for key in keys {
+            if let Err(e) = tokio::fs::remove_file(self.path.join(key)).await {
+                debug!("error remove cached image {key}: {e}");
+            }
+        }
+    }
+
+    async fn delete_keys(&self, keys: &[String]) -> Result<(), ImageCacheRepositoryError> {
+        for chunk in keys.chunks(500) {
+            let query_str = format!(
+                r#"DELETE FROM image_cache WHERE key IN ({})"#,
+                vec!["?"; chunk.len()].join(",")
+            );
+
+            let mut query = sqlx::query(&query_str);
+            for key in chunk {
+                query = query.bind(key);
+            }
+
+            query.execute(&self.pool as &SqlitePool).await?;
+        }
+
+        self.remove_files(keys).await;
+
+        Ok(())
+    }
+
+    async fn evict_lru(&self) -> Result<u64, ImageCacheRepositoryError> {
+        let size = self.evictable_size().await?;
+
+        if size <= self.max_size {
+            self.size.store(size, Ordering::Relaxed);
+            return Ok(0);
+        }
+
+        let query_str = format!(
+            r#"SELECT key, size FROM image_cache
+            WHERE NOT {LIBRARY_COVER}
+            ORDER BY last_accessed_at ASC"#
+        );
+        let mut stream = sqlx::query(&query_str).fetch(&self.pool as &SqlitePool);
+
+        let mut excess = size - self.max_size;
+        let mut removed_size = 0;
+        let mut keys: Vec<String> = vec![];
+        while excess > 0 {
+            match stream.try_next().await? {
+                Some(row) => {
+                    let entry_size = row.get::<i64, _>(1);
+                    keys.push(row.get(0));
+                    excess -= entry_size;
+                    removed_size += entry_size;
+                }
+                None => break,
+            }
         }
+        drop(stream);
+
+        self.delete_keys(&keys).await?;
+        self.size.store(size - removed_size, Ordering::Relaxed);
+
+        Ok(keys.len() as u64)
     }
 }
 
 #[async_trait]
 impl ImageCacheRepository for ImageCacheRepositoryImpl {
-    async fn set(&self, key: &str, image: &Image) -> Result<(), ImageCacheRepositoryError> {
+    async fn set(
+        &self,
+        key: &str,
+        origin: &ImageOrigin,
+        image: &Image,
+    ) -> Result<(), ImageCacheRepositoryError> {
         let path = self.path.join(key);
 
         let encoded = bincode::serialize(&image)?;
 
         tokio::fs::write(&path, &encoded).await?;
 
+        let previous_size: i64 = sqlx::query(r#"SELECT size FROM image_cache WHERE key = ?"#)
+            .bind(key)
+            .fetch_optional(&self.pool as &SqlitePool)
+            .await?
+            .map(|row| row.get(0))
+            .unwrap_or(0);
+
+        let now = Utc::now().naive_utc();
+        sqlx::query(
+            r#"INSERT INTO image_cache(key, source_id, manga_id, is_cover, size, created_at, last_accessed_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(key) DO UPDATE SET
+                source_id = IFNULL(excluded.source_id, source_id),
+                manga_id = IFNULL(excluded.manga_id, manga_id),
+                is_cover = excluded.is_cover OR is_cover,
+                size = excluded.size,
+                last_accessed_at = excluded.last_accessed_at"#,
+        )
+        .bind(key)
+        .bind(origin.source_id)
+        .bind(origin.manga_id)
+        .bind(origin.is_cover)
+        .bind(encoded.len() as i64)
+        .bind(now)
+        .bind(now)
+        .execute(&self.pool as &SqlitePool)
+        .await?;
+
+        if self.add_size(encoded.len() as i64 - previous_size).await? > self.max_size {
+            self.evict_lru().await?;
+        }
+
         Ok(())
     }
 
     async fn get(&self, key: &str) -> Result<Image, ImageCacheRepositoryError> {
         let path = self.path.join(key);
 
-        let encoded = tokio::fs::read(path).await?;
+        let encoded = match tokio::fs::read(path).await {
+            Ok(encoded) => encoded,
+            Err(e) => {
+                self.record_access(false).await?;
+                return Err(e.into());
+            }
+        };
 
-        let decoded = bincode::deserialize(&encoded)?;
+        let decoded = match bincode::deserialize(&encoded) {
+            Ok(decoded) => decoded,
+            Err(e) => {
+                self.record_access(false).await?;
+                return Err(e.into());
+            }
+        };
+
+        self.record_access(true).await?;
+
+        sqlx::query(
+            r#"UPDATE image_cache SET hits = hits + 1, last_accessed_at = ? WHERE key = ?"#,
+        )
+        .bind(Utc::now().naive_utc())
+        .bind(key)
+        .execute(&self.pool as &SqlitePool)
+        .await?;
 
         Ok(decoded)
     }
+
+    async fn contains(&self, key: &str) -> Result<bool, ImageCacheRepositoryError> {
+        let row = sqlx::query(r#"SELECT EXISTS(SELECT 1 FROM image_cache WHERE key = ?)"#)
+            .bind(key)
+            .fetch_one(&self.pool as &SqlitePool)
+            .await?;
+
+        Ok(row.get(0))
+    }
+
+    async fn get_stats(&self) -> Result<ImageCacheStats, ImageCacheRepositoryError> {
+        let row = sqlx::query(&format!(
+            r#"SELECT
+                COUNT(1),
+                IFNULL(SUM(size), 0),
+                IFNULL(SUM(CASE WHEN {LIBRARY_COVER} THEN 1 ELSE 0 END), 0),
+                IFNULL(SUM(CASE WHEN {LIBRARY_COVER} THEN size ELSE 0 END), 0),
+                (SELECT hits FROM image_cache_stats WHERE id = 1),
+                (SELECT misses FROM image_cache_stats WHERE id = 1)
+            FROM image_cache"#
+        ))
+        .fetch_one(&self.pool as &SqlitePool)
+        .await?;
+
+        Ok(ImageCacheStats {
+            entries: row.get(0),
+            bytes: row.get(1),
+            max_bytes: self.max_size,
+            cover_entries: row.get(2),
+            cover_bytes: row.get(3),
+            hits: row.get(4),
+            misses: row.get(5),
+        })
+    }
+
+    async fn purge_by_manga_id(&self, manga_id: i64) -> Result<u64, ImageCacheRepositoryError> {
+        let keys: Vec<String> =
+            sqlx::query(r#"DELETE FROM image_cache WHERE manga_id = ? RETURNING key"#)
+                .bind(manga_id)
+                .fetch_all(&self.pool as &SqlitePool)
+                .await?
+                .into_iter()
+                .map(|row| row.get(0))
+                .collect();
+
+        self.remove_files(&keys).await;
+        self.size.store(SIZE_UNKNOWN, Ordering::Relaxed);
+
+        Ok(keys.len() as u64)
+    }
+
+    async fn purge_by_source_id(&self, source_id: i64) -> Result<u64, ImageCacheRepositoryError> {
+        let keys: Vec<String> = sqlx::query(
+            r#"DELETE FROM image_cache
+            WHERE source_id = ?
+            OR manga_id IN (SELECT id FROM manga WHERE source_id = ?)
+            RETURNING key"#,
+        )
+        .bind(source_id)
+        .bind(source_id)
+        .fetch_all(&self.pool as &SqlitePool)
+        .await?
+        .into_iter()
+        .map(|row| row.get(0))
+        .collect();
+
+        self.remove_files(&keys).await;
+        self.size.store(SIZE_UNKNOWN, Ordering::Relaxed);
+
+        Ok(keys.len() as u64)
+    }
+
+    async fn evict(&self) -> Result<u64, ImageCacheRepositoryError> {
+        let mut removed = self.evict_lru().await?;
+
+        let keys: HashSet<String> = sqlx::query(r#"SELECT key FROM image_cache"#)
+            .fetch_all(&self.pool as &SqlitePool)
+            .await?
+            .into_iter()
+            .map(|row| row.get(0))
+            .collect();
+
+        let mut read_dir = tokio::fs::read_dir(&self.path).await?;
+        while let Some(entry) = read_dir.next_entry().await? {
+            if !entry.file_type().await?.is_file() {
+                continue;
+            }
+
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !keys.contains(&name) {
+                if let Err(e) = tokio::fs::remove_file(entry.path()).await {
+                    debug!("error remove untracked image {name}: {e}");
+                    continue;
+                }
+                removed += 1;
+            }
+        }
+
+        Ok(removed)
+    }
 }
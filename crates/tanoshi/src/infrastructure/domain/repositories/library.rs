@@ -1,20 +1,28 @@
 use std::collections::HashMap;
 
 use async_trait::async_trait;
+use chrono::Utc;
 use futures::{stream::BoxStream, StreamExt};
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
-use sqlx::{Row, SqlitePool};
+use sqlx::{query::Query, sqlite::SqliteArguments, Row, Sqlite, SqlitePool, Transaction};
 
 use crate::{
     domain::{
         entities::{
-            library::{Category, LibraryUpdate},
+            category_rule::{CategoryRule, RuleField, RuleOperator, RuleValue, MAX_RULE_DEPTH},
+            library::{
+                Category, LibraryFilter, LibraryPosition, LibrarySort, LibrarySortBy,
+                LibrarySortValue, LibraryUpdate, MangaNote, MangaTag, TagCount,
+            },
             manga::Manga,
             user::User,
         },
         repositories::library::{LibraryRepository, LibraryRepositoryError},
     },
-    infrastructure::database::Pool,
+    infrastructure::{
+        database::Pool,
+        domain::repositories::content_policy::{bind_restriction, restriction_condition},
+    },
 };
 
 #[derive(Clone)]
@@ -26,6 +34,208 @@ impl LibraryRepositoryImpl {
     pub fn new<P: Into<Pool>>(pool: P) -> Self {
         Self { pool: pool.into() }
     }
+
+    async fn get_category_rule(
+        &self,
+        id: i64,
+    ) -> Result<Option<CategoryRule>, LibraryRepositoryError> {
+        let rule: Option<String> = sqlx::query("SELECT rule FROM user_category WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool as &SqlitePool)
+            .await?
+            .and_then(|row| row.get(0));
+
+        rule.map(|rule| parse_rule(&rule)).transpose()
+    }
+}
+
+fn parse_rule(rule: &str) -> Result<CategoryRule, LibraryRepositoryError> {
+    rule.parse()
+        .map_err(|e: anyhow::Error| LibraryRepositoryError::InvalidRule(e.to_string()))
+}
+
+/// Manga in user library with the columns used to sort, filter and evaluate category rules,
+/// metadata overridden by the user takes precedence.
+/// Binds user id, then whatever `category_clause` needs.
+fn library_cte(category_clause: &str) -> String {
+    format!(
+        r#"library AS (
+            SELECT
+                manga.id,
+                manga.source_id,
+                IFNULL(manga_override.title, manga.title) AS title,
+                IFNULL(manga_override.author, manga.author) AS author,
+                IFNULL(manga_override.genre, manga.genre) AS genre,
+                IFNULL(manga_override.status, manga.status) AS status,
+                IFNULL(manga_override.description, manga.description) AS description,
+                manga.path,
+                IFNULL(manga_override.cover_path, manga.cover_url) AS cover_url,
+                manga.date_added,
+                user_library.date_added AS library_date_added,
+                (
+                    SELECT MAX(user_history.read_at) FROM user_history
+                    JOIN chapter ON chapter.id = user_history.chapter_id
+                    WHERE user_history.user_id = user_library.user_id AND chapter.manga_id = manga.id
+                ) AS last_read_at,
+                (SELECT MAX(chapter.uploaded) FROM visible_chapter chapter WHERE chapter.manga_id = manga.id) AS last_uploaded_at,
+                (
+                    SELECT COUNT(1) FROM visible_chapter chapter
+                    LEFT JOIN user_history ON user_history.user_id = user_library.user_id AND user_history.chapter_id = chapter.id
+                    WHERE chapter.manga_id = manga.id AND IFNULL(user_history.is_complete, false) = false
+                ) AS unread_count,
+                (
+                    SELECT COUNT(1) FROM chapter
+                    WHERE chapter.manga_id = manga.id AND chapter.downloaded_path IS NOT NULL
+                ) AS downloaded_count,
+                EXISTS (
+                    SELECT 1 FROM tracker_manga
+                    WHERE tracker_manga.user_id = user_library.user_id AND tracker_manga.manga_id = manga.id
+                ) AS tracked,
+                (
+                    SELECT rating FROM user_manga_note
+                    WHERE user_manga_note.user_id = user_library.user_id AND user_manga_note.manga_id = manga.id
+                ) AS rating,
+                (
+                    SELECT json_group_array(tag) FROM user_manga_tag
+                    WHERE user_manga_tag.user_id = user_library.user_id AND user_manga_tag.manga_id = manga.id
+                ) AS tags
+            FROM manga
+            INNER JOIN user_library ON user_library.user_id = ? AND manga.id = user_library.manga_id
+            LEFT JOIN manga_override ON manga_override.user_id = user_library.user_id AND manga_override.manga_id = manga.id
+            {category_clause}
+        )"#
+    )
+}
+
+/// Translate a rule to a condition on `library` columns, values to bind are pushed to `binds` in order.
+/// `depth` is the level of `rule` starting from 1, rules nested deeper than parser allows are rejected
+fn rule_to_sql(
+    rule: &CategoryRule,
+    binds: &mut Vec<RuleValue>,
+    depth: usize,
+) -> Result<String, LibraryRepositoryError> {
+    if depth > MAX_RULE_DEPTH {
+        return Err(LibraryRepositoryError::InvalidRule(format!(
+            "rule is nested deeper than {MAX_RULE_DEPTH} levels"
+        )));
+    }
+
+    let condition = match rule {
+        CategoryRule::And(lhs, rhs) => {
+            format!(
+                "({} AND {})",
+                rule_to_sql(lhs, binds, depth + 1)?,
+                rule_to_sql(rhs, binds, depth + 1)?
+            )
+        }
+        CategoryRule::Or(lhs, rhs) => {
+            format!(
+                "({} OR {})",
+                rule_to_sql(lhs, binds, depth + 1)?,
+                rule_to_sql(rhs, binds, depth + 1)?
+            )
+        }
+        CategoryRule::Not(rule) => format!("NOT ({})", rule_to_sql(rule, binds, depth + 1)?),
+        CategoryRule::Compare {
+            field,
+            operator,
+            value,
+        } => {
+            binds.push(value.clone());
+            let column = match field {
+                RuleField::Unread => "unread_count",
+                RuleField::Downloaded => "downloaded_count",
+                RuleField::Tracked => "tracked",
+                RuleField::Status => "LOWER(status)",
+                RuleField::Source => "source_id",
+                RuleField::Title => "LOWER(title)",
+                RuleField::DaysSinceRead => {
+                    "CAST(julianday('now') - julianday(last_read_at) AS INTEGER)"
+                }
+                RuleField::DaysSinceUpdate => {
+                    "CAST(julianday('now') - julianday(last_uploaded_at) AS INTEGER)"
+                }
+                RuleField::DaysSinceAdded => {
+                    "CAST(julianday('now') - julianday(IFNULL(library_date_added, date_added)) AS INTEGER)"
+                }
+                RuleField::Rating => "rating",
+                RuleField::Genre | RuleField::Author | RuleField::Tag => {
+                    let column = match field {
+                        RuleField::Tag => "tags",
+                        _ => field.as_str(),
+                    };
+                    let negate = if *operator == RuleOperator::Ne {
+                        "NOT "
+                    } else {
+                        ""
+                    };
+                    return Ok(format!(
+                        r#"{negate}EXISTS (
+                            SELECT 1 FROM json_each(CASE WHEN json_valid({column}) THEN {column} ELSE '[]' END)
+                            WHERE LOWER(value) = LOWER(?)
+                        )"#
+                    ));
+                }
+            };
+
+            match value {
+                RuleValue::Text(_) => format!("{column} {} LOWER(?)", operator.as_str()),
+                _ => format!("{column} {} ?", operator.as_str()),
+            }
+        }
+        CategoryRule::In { field, values } => {
+            let conditions: Vec<String> = values
+                .iter()
+                .map(|value| {
+                    rule_to_sql(
+                        &CategoryRule::Compare {
+                            field: *field,
+                            operator: RuleOperator::Eq,
+                            value: value.clone(),
+                        },
+                        binds,
+                        depth,
+                    )
+                })
+                .collect::<Result<_, _>>()?;
+            format!("({})", conditions.join(" OR "))
+        }
+    };
+
+    Ok(condition)
+}
+
+/// Rows without rating and note are not kept
+async fn delete_empty_note(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    manga_id: i64,
+) -> Result<(), LibraryRepositoryError> {
+    sqlx::query(
+        r#"DELETE FROM user_manga_note
+        WHERE user_id = ? AND manga_id = ? AND rating IS NULL AND note IS NULL"#,
+    )
+    .bind(user_id)
+    .bind(manga_id)
+    .execute(tx)
+    .await?;
+
+    Ok(())
+}
+
+fn bind_rule_values<'q>(
+    mut query: Query<'q, Sqlite, SqliteArguments<'q>>,
+    values: Vec<RuleValue>,
+) -> Query<'q, Sqlite, SqliteArguments<'q>> {
+    for value in values {
+        query = match value {
+            RuleValue::Number(n) => query.bind(n),
+            RuleValue::Text(s) => query.bind(s),
+            RuleValue::Boolean(b) => query.bind(b),
+        };
+    }
+
+    query
 }
 
 #[async_trait]
@@ -37,7 +247,8 @@ impl LibraryRepository for LibraryRepositoryImpl {
         let categories = sqlx::query(
             r#"SELECT
                 id,
-                name
+                name,
+                rule
             FROM user_category
             WHERE user_id = ?
             ORDER BY name"#,
@@ -49,6 +260,7 @@ impl LibraryRepository for LibraryRepositoryImpl {
         .map(|row| Category {
             id: row.get(0),
             name: row.get(1),
+            rule: row.get(2),
         })
         .collect();
 
@@ -59,7 +271,8 @@ impl LibraryRepository for LibraryRepositoryImpl {
         let row = sqlx::query(
             r#"SELECT
                     id,
-                    name
+                    name,
+                    rule
                 FROM user_category
                 WHERE id = ?"#,
         )
@@ -70,6 +283,7 @@ impl LibraryRepository for LibraryRepositoryImpl {
         Ok(Category {
             id: row.get(0),
             name: row.get(1),
+            rule: row.get(2),
         })
     }
 
@@ -77,35 +291,54 @@ impl LibraryRepository for LibraryRepositoryImpl {
         &self,
         user_id: i64,
         name: &str,
+        rule: Option<&str>,
     ) -> Result<Category, LibraryRepositoryError> {
         let row = sqlx::query(
-            "INSERT INTO user_category (user_id, name) VALUES (?, ?) RETURNING id, name",
+            "INSERT INTO user_category (user_id, name, rule) VALUES (?, ?, ?) RETURNING id, name, rule",
         )
         .bind(user_id)
         .bind(name)
+        .bind(rule)
         .fetch_one(&self.pool as &SqlitePool)
         .await?;
 
         Ok(Category {
             id: row.get(0),
             name: row.get(1),
+            rule: row.get(2),
         })
     }
 
-    async fn rename_category(
+    async fn update_category(
         &self,
         id: i64,
         name: &str,
+        rule: Option<&str>,
     ) -> Result<Category, LibraryRepositoryError> {
-        let row = sqlx::query("UPDATE user_category SET name = ? WHERE id = ? RETURNING id, name")
-            .bind(name)
-            .bind(id)
-            .fetch_one(&self.pool as &SqlitePool)
-            .await?;
+        let mut tx = self.pool.begin().await?;
+
+        let row = sqlx::query(
+            "UPDATE user_category SET name = ?, rule = IFNULL(?, rule) WHERE id = ? RETURNING id, name, rule",
+        )
+        .bind(name)
+        .bind(rule)
+        .bind(id)
+        .fetch_one(&mut tx)
+        .await?;
+
+        if rule.is_some() {
+            sqlx::query("DELETE FROM library_category WHERE category_id = ?")
+                .bind(id)
+                .execute(&mut tx)
+                .await?;
+        }
+
+        tx.commit().await?;
 
         Ok(Category {
             id: row.get(0),
             name: row.get(1),
+            rule: row.get(2),
         })
     }
 
@@ -122,7 +355,7 @@ impl LibraryRepository for LibraryRepositoryImpl {
         &self,
         user_id: i64,
     ) -> Result<HashMap<Option<i64>, i64>, LibraryRepositoryError> {
-        let data = sqlx::query(
+        let mut data: HashMap<Option<i64>, i64> = sqlx::query(
             "SELECT user_category.id, COUNT(1) FROM manga
         INNER JOIN user_library ON user_library.user_id = ? AND manga.id = user_library.manga_id
         LEFT JOIN library_category ON user_library.id = library_category.library_id
@@ -136,6 +369,32 @@ impl LibraryRepository for LibraryRepositoryImpl {
         .map(|row| (row.get(0), row.get(1)))
         .collect();
 
+        let rules: Vec<(i64, String)> = sqlx::query(
+            "SELECT id, rule FROM user_category WHERE user_id = ? AND rule IS NOT NULL",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool as &SqlitePool)
+        .await?
+        .into_iter()
+        .map(|row| (row.get(0), row.get(1)))
+        .collect();
+
+        for (category_id, rule) in rules {
+            let mut binds = vec![];
+            let condition = rule_to_sql(&parse_rule(&rule)?, &mut binds, 1)?;
+            let query_str = format!(
+                "WITH {} SELECT COUNT(1) FROM library WHERE {condition}",
+                library_cte("")
+            );
+
+            let count = bind_rule_values(sqlx::query(&query_str).bind(user_id), binds)
+                .fetch_one(&self.pool as &SqlitePool)
+                .await?
+                .get(0);
+
+            data.insert(Some(category_id), count);
+        }
+
         Ok(data)
     }
 
@@ -298,6 +557,26 @@ impl LibraryRepository for LibraryRepositoryImpl {
         user_id: i64,
         category_id: Option<i64>,
     ) -> Result<Vec<Manga>, LibraryRepositoryError> {
+        if let Some(id) = category_id {
+            if self.get_category_rule(id).await?.is_some() {
+                let manga = self
+                    .get_manga_from_library_page(
+                        user_id,
+                        category_id,
+                        LibrarySort::default(),
+                        &LibraryFilter::default(),
+                        None,
+                        -1,
+                    )
+                    .await?
+                    .into_iter()
+                    .map(|(_, manga)| manga)
+                    .collect();
+
+                return Ok(manga);
+            }
+        }
+
         let manga = sqlx::query(
             r#"SELECT manga.*, library_category.category_id FROM manga
             INNER JOIN user_library ON user_library.user_id = ? AND manga.id = user_library.manga_id
@@ -328,6 +607,154 @@ impl LibraryRepository for LibraryRepositoryImpl {
         Ok(manga)
     }
 
+    async fn get_manga_from_library_page(
+        &self,
+        user_id: i64,
+        category_id: Option<i64>,
+        sort: LibrarySort,
+        filter: &LibraryFilter,
+        after: Option<&LibraryPosition>,
+        first: i64,
+    ) -> Result<Vec<(LibraryPosition, Manga)>, LibraryRepositoryError> {
+        // nullable columns sort as empty string, same place as NULL in both directions,
+        // so they can be compared in keyset condition
+        let sort_column = match sort.by {
+            LibrarySortBy::Title => "title COLLATE NOCASE",
+            LibrarySortBy::LastRead => "IFNULL(last_read_at, '')",
+            LibrarySortBy::LastUpdated => "IFNULL(last_uploaded_at, '')",
+            LibrarySortBy::DateAdded => "IFNULL(library_date_added, date_added)",
+            LibrarySortBy::UnreadCount => "unread_count",
+        };
+        let (order, cmp) = if sort.descending {
+            ("DESC", "<")
+        } else {
+            ("ASC", ">")
+        };
+
+        let rule = match category_id {
+            Some(id) => self.get_category_rule(id).await?,
+            None => None,
+        };
+
+        let mut rule_binds = vec![];
+        let (category_clause, rule_condition) = match &rule {
+            Some(rule) => (String::new(), rule_to_sql(rule, &mut rule_binds, 1)?),
+            None => (
+                r#"LEFT JOIN library_category ON user_library.id = library_category.library_id
+                WHERE library_category.category_id IS ?"#
+                    .to_string(),
+                "1".to_string(),
+            ),
+        };
+
+        // ties are broken by title then id so a page continues right after the last manga
+        // of previous one, without numbering every manga before it
+        let after_condition = if after.is_some() {
+            format!(
+                r#"({sort_column} {cmp} ? OR ({sort_column} = ? AND (
+                    title COLLATE NOCASE > ? OR (title COLLATE NOCASE = ? AND id > ?)
+                )))"#
+            )
+        } else {
+            "1".to_string()
+        };
+
+        let query_str = format!(
+            r#"WITH {}
+            SELECT *, {sort_column} AS sort_value FROM library
+            WHERE {rule_condition}
+            AND (? IS NULL OR (unread_count > 0) = ?)
+            AND (? IS NULL OR (downloaded_count > 0) = ?)
+            AND (? IS NULL OR tracked = ?)
+            AND (? IS NULL OR LOWER(status) = LOWER(?))
+            AND (? IS NULL OR source_id = ?)
+            AND (? IS NULL OR EXISTS (
+                SELECT 1 FROM json_each(CASE WHEN json_valid(genre) THEN genre ELSE '[]' END)
+                WHERE LOWER(value) = LOWER(?)
+            ))
+            AND (? IS NULL OR EXISTS (
+                SELECT 1 FROM json_each(tags) WHERE LOWER(value) = LOWER(?)
+            ))
+            AND (? IS NULL OR rating >= ?)
+            AND {}
+            AND {after_condition}
+            ORDER BY {sort_column} {order}, title COLLATE NOCASE, id
+            LIMIT ?"#,
+            library_cte(&category_clause),
+            restriction_condition("source_id", "genre")
+        );
+
+        let mut query = sqlx::query(&query_str).bind(user_id);
+        if rule.is_none() {
+            query = query.bind(category_id);
+        }
+
+        let mut query = bind_rule_values(query, rule_binds)
+            .bind(filter.unread)
+            .bind(filter.unread)
+            .bind(filter.downloaded)
+            .bind(filter.downloaded)
+            .bind(filter.tracked)
+            .bind(filter.tracked)
+            .bind(&filter.status)
+            .bind(&filter.status)
+            .bind(filter.source_id)
+            .bind(filter.source_id)
+            .bind(&filter.genre)
+            .bind(&filter.genre)
+            .bind(&filter.tag)
+            .bind(&filter.tag)
+            .bind(filter.min_rating)
+            .bind(filter.min_rating);
+        query = bind_restriction(query, &filter.restriction);
+        if let Some(after) = after {
+            query = match &after.sort_value {
+                LibrarySortValue::Integer(value) => query.bind(*value).bind(*value),
+                LibrarySortValue::Text(value) => query.bind(value.clone()).bind(value.clone()),
+            }
+            .bind(after.title.clone())
+            .bind(after.title.clone())
+            .bind(after.id);
+        }
+
+        let manga = query
+            .bind(first)
+            .fetch_all(&self.pool as &SqlitePool)
+            .await?
+            .into_par_iter()
+            .map(|row| {
+                let manga = Manga {
+                    id: row.get(0),
+                    source_id: row.get(1),
+                    title: row.get(2),
+                    author: serde_json::from_str(row.get::<String, _>(3).as_str())
+                        .unwrap_or_default(),
+                    genre: serde_json::from_str(row.get::<String, _>(4).as_str())
+                        .unwrap_or_default(),
+                    status: row.get(5),
+                    description: row.get(6),
+                    path: row.get(7),
+                    cover_url: row.get(8),
+                    date_added: row.get(9),
+                    last_uploaded_at: row.get("last_uploaded_at"),
+                };
+                let sort_value = match sort.by {
+                    LibrarySortBy::UnreadCount => LibrarySortValue::Integer(row.get("sort_value")),
+                    _ => LibrarySortValue::Text(row.get("sort_value")),
+                };
+                let position = LibraryPosition {
+                    sort_value,
+                    title: manga.title.clone(),
+                    id: manga.id,
+                };
+
+                (position, manga)
+            })
+            .collect();
+
+        Ok(manga)
+    }
+
     async fn insert_manga_to_library(
         &self,
         user_id: i64,
@@ -336,12 +763,14 @@ impl LibraryRepository for LibraryRepositoryImpl {
     ) -> Result<(), LibraryRepositoryError> {
         let mut tx = self.pool.begin().await?;
 
-        let library_id = sqlx::query("INSERT INTO user_library(user_id, manga_id) VALUES (?, ?)")
-            .bind(user_id)
-            .bind(manga_id)
-            .execute(&mut tx)
-            .await
-            .map(|res| res.last_insert_rowid())?;
+        let library_id =
+            sqlx::query("INSERT INTO user_library(user_id, manga_id, date_added) VALUES (?, ?, ?)")
+                .bind(user_id)
+                .bind(manga_id)
+                .bind(Utc::now().naive_utc())
+                .execute(&mut tx)
+                .await
+                .map(|res| res.last_insert_rowid())?;
 
         if !category_ids.is_empty() {
             let query_str = format!(
@@ -392,8 +821,9 @@ impl LibraryRepository for LibraryRepositoryImpl {
             manga.title,
             manga.cover_url,
             chapter.title,
-            chapter.uploaded
-        FROM chapter
+            chapter.uploaded,
+            manga.source_id
+        FROM visible_chapter chapter
         JOIN manga ON manga.id = chapter.manga_id
         JOIN user_library ON
             user_library.manga_id = manga.id
@@ -420,6 +850,7 @@ impl LibraryRepository for LibraryRepositoryImpl {
             cover_url: row.get(3),
             chapter_title: row.get(4),
             uploaded: row.get(5),
+            source_id: row.get(6),
         })
         .collect();
 
@@ -445,8 +876,9 @@ impl LibraryRepository for LibraryRepositoryImpl {
                 manga.cover_url,
                 chapter.title,
                 chapter.uploaded,
-                chapter.number
-            FROM chapter
+                chapter.number,
+                manga.source_id
+            FROM visible_chapter chapter
             JOIN manga ON manga.id = chapter.manga_id
             JOIN user_library ON
                 user_library.manga_id = manga.id
@@ -474,6 +906,7 @@ impl LibraryRepository for LibraryRepositoryImpl {
             cover_url: row.get(3),
             chapter_title: row.get(4),
             uploaded: row.get(5),
+            source_id: row.get(7),
         })
         .collect();
 
@@ -496,8 +929,9 @@ impl LibraryRepository for LibraryRepositoryImpl {
             manga.title,
             manga.cover_url,
             chapter.title,
-            chapter.uploaded
-        FROM chapter
+            chapter.uploaded,
+            manga.source_id
+        FROM visible_chapter chapter
         JOIN manga ON manga.id = chapter.manga_id
         JOIN user_library ON
             user_library.manga_id = manga.id
@@ -522,9 +956,271 @@ impl LibraryRepository for LibraryRepositoryImpl {
             cover_url: row.get(3),
             chapter_title: row.get(4),
             uploaded: row.get(5),
+            source_id: row.get(6),
         })
         .collect();
 
         Ok(chapters)
     }
+
+    async fn get_tags_by_user_id(
+        &self,
+        user_id: i64,
+    ) -> Result<Vec<TagCount>, LibraryRepositoryError> {
+        let tags = sqlx::query(
+            r#"SELECT tag, COUNT(1) FROM user_manga_tag
+            WHERE user_id = ?
+            GROUP BY tag
+            ORDER BY COUNT(1) DESC, tag"#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool as &SqlitePool)
+        .await?
+        .into_par_iter()
+        .map(|row| TagCount {
+            tag: row.get(0),
+            count: row.get(1),
+        })
+        .collect();
+
+        Ok(tags)
+    }
+
+    async fn get_manga_tags(
+        &self,
+        user_id: i64,
+        manga_ids: &[i64],
+    ) -> Result<Vec<MangaTag>, LibraryRepositoryError> {
+        let query_str = format!(
+            r#"SELECT manga_id, tag FROM user_manga_tag
+            WHERE user_id = ? AND manga_id IN ({})
+            ORDER BY manga_id, tag"#,
+            vec!["?"; manga_ids.len()].join(",")
+        );
+        let mut query = sqlx::query(&query_str).bind(user_id);
+        for id in manga_ids {
+            query = query.bind(id);
+        }
+
+        let tags = query
+            .fetch_all(&self.pool as &SqlitePool)
+            .await?
+            .into_iter()
+            .map(|row| MangaTag {
+                manga_id: row.get(0),
+                tag: row.get(1),
+            })
+            .collect();
+
+        Ok(tags)
+    }
+
+    async fn set_manga_tags(
+        &self,
+        user_id: i64,
+        manga_id: i64,
+        tags: &[String],
+    ) -> Result<(), LibraryRepositoryError> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM user_manga_tag WHERE user_id = ? AND manga_id = ?")
+            .bind(user_id)
+            .bind(manga_id)
+            .execute(&mut tx)
+            .await?;
+
+        for tag in tags {
+            sqlx::query(
+                "INSERT OR IGNORE INTO user_manga_tag(user_id, manga_id, tag) VALUES (?, ?, ?)",
+            )
+            .bind(user_id)
+            .bind(manga_id)
+            .bind(tag)
+            .execute(&mut tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn get_manga_notes(
+        &self,
+        user_id: i64,
+        manga_ids: &[i64],
+    ) -> Result<Vec<MangaNote>, LibraryRepositoryError> {
+        let query_str = format!(
+            r#"SELECT manga_id, rating, note, updated_at FROM user_manga_note
+            WHERE user_id = ? AND manga_id IN ({})"#,
+            vec!["?"; manga_ids.len()].join(",")
+        );
+        let mut query = sqlx::query(&query_str).bind(user_id);
+        for id in manga_ids {
+            query = query.bind(id);
+        }
+
+        let notes = query
+            .fetch_all(&self.pool as &SqlitePool)
+            .await?
+            .into_iter()
+            .map(|row| MangaNote {
+                manga_id: row.get(0),
+                rating: row.get(1),
+                note: row.get(2),
+                updated_at: row.get(3),
+            })
+            .collect();
+
+        Ok(notes)
+    }
+
+    async fn set_manga_rating(
+        &self,
+        user_id: i64,
+        manga_id: i64,
+        rating: Option<i64>,
+    ) -> Result<(), LibraryRepositoryError> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"INSERT INTO user_manga_note(user_id, manga_id, rating, updated_at) VALUES (?, ?, ?, ?)
+            ON CONFLICT(user_id, manga_id)
+            DO UPDATE SET rating = excluded.rating, updated_at = excluded.updated_at"#,
+        )
+        .bind(user_id)
+        .bind(manga_id)
+        .bind(rating)
+        .bind(Utc::now().naive_utc())
+        .execute(&mut tx)
+        .await?;
+
+        delete_empty_note(&mut tx, user_id, manga_id).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn set_manga_note(
+        &self,
+        user_id: i64,
+        manga_id: i64,
+        note: Option<&str>,
+    ) -> Result<(), LibraryRepositoryError> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"INSERT INTO user_manga_note(user_id, manga_id, note, updated_at) VALUES (?, ?, ?, ?)
+            ON CONFLICT(user_id, manga_id)
+            DO UPDATE SET note = excluded.note, updated_at = excluded.updated_at"#,
+        )
+        .bind(user_id)
+        .bind(manga_id)
+        .bind(note)
+        .bind(Utc::now().naive_utc())
+        .execute(&mut tx)
+        .await?;
+
+        delete_empty_note(&mut tx, user_id, manga_id).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        domain::entities::content_policy::ContentRestriction,
+        infrastructure::database::establish_connection,
+    };
+
+    #[tokio::test]
+    async fn test_library_page_applies_content_restriction() {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = establish_connection(&dir.path().join("tanoshi.db").display().to_string(), true)
+            .await
+            .unwrap();
+
+        sqlx::query("INSERT INTO user(id, username, password) VALUES (1, 'user', '')")
+            .execute(&pool as &SqlitePool)
+            .await
+            .unwrap();
+        // odd manga are from source 1, even from source 2, manga 5 has a blocked genre
+        for id in 1..=7_i64 {
+            let genre = if id == 5 {
+                r#"["Horror"]"#
+            } else {
+                r#"["Comedy"]"#
+            };
+            sqlx::query(
+                r#"INSERT INTO manga(id, source_id, title, author, genre, path, cover_url, date_added)
+                VALUES (?, ?, ?, '[]', ?, ?, '', CURRENT_TIMESTAMP)"#,
+            )
+            .bind(id)
+            .bind(2 - id % 2)
+            .bind(format!("Manga {id}"))
+            .bind(genre)
+            .bind(format!("/manga/{id}"))
+            .execute(&pool as &SqlitePool)
+            .await
+            .unwrap();
+            sqlx::query("INSERT INTO user_library(user_id, manga_id) VALUES (1, ?)")
+                .bind(id)
+                .execute(&pool as &SqlitePool)
+                .await
+                .unwrap();
+        }
+
+        let repo = LibraryRepositoryImpl::new(pool);
+        let filter = LibraryFilter {
+            restriction: ContentRestriction {
+                allowed_source_ids: None,
+                denied_source_ids: vec![2],
+                blocked_genres: vec![" horror".to_string()],
+            },
+            ..Default::default()
+        };
+
+        let page = repo
+            .get_manga_from_library_page(1, None, LibrarySort::default(), &filter, None, 2)
+            .await
+            .unwrap();
+        assert_eq!(
+            page.iter().map(|(_, m)| m.id).collect::<Vec<_>>(),
+            vec![1, 3]
+        );
+
+        let page = repo
+            .get_manga_from_library_page(
+                1,
+                None,
+                LibrarySort::default(),
+                &filter,
+                page.last().map(|(position, _)| position),
+                2,
+            )
+            .await
+            .unwrap();
+        assert_eq!(page.iter().map(|(_, m)| m.id).collect::<Vec<_>>(), vec![7]);
+
+        let filter = LibraryFilter {
+            restriction: ContentRestriction {
+                allowed_source_ids: Some(vec![2]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let page = repo
+            .get_manga_from_library_page(1, None, LibrarySort::default(), &filter, None, 10)
+            .await
+            .unwrap();
+        assert_eq!(
+            page.iter().map(|(_, m)| m.id).collect::<Vec<_>>(),
+            vec![2, 4, 6]
+        );
+    }
 }
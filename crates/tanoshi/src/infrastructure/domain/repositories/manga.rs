@@ -1,9 +1,12 @@
 use crate::{
     domain::{
-        entities::manga::Manga,
+        entities::manga::{Manga, MangaOverride, MangaSearchFilter},
         repositories::manga::{MangaRepository, MangaRepositoryError},
     },
-    infrastructure::database::Pool,
+    infrastructure::{
+        database::Pool,
+        domain::repositories::content_policy::{bind_restriction, restriction_condition},
+    },
 };
 use async_trait::async_trait;
 use chrono::Utc;
@@ -144,4 +147,287 @@ impl MangaRepository for MangaRepositoryImpl {
 
         Ok(())
     }
+
+    async fn search_manga(
+        &self,
+        user_id: i64,
+        query: &str,
+        filter: &MangaSearchFilter,
+        limit: i64,
+    ) -> Result<Vec<Manga>, MangaRepositoryError> {
+        let query_str = format!(
+            r#"WITH matched AS (
+                SELECT rowid AS manga_id, bm25(manga_fts, 10.0, 4.0, 2.0, 1.0) AS rank
+                FROM manga_fts WHERE manga_fts MATCH ?
+                UNION ALL
+                SELECT manga_id, bm25(manga_override_fts, 0.0, 0.0, 10.0, 4.0) AS rank
+                FROM manga_override_fts WHERE manga_override_fts MATCH ? AND user_id = ?
+            )
+            SELECT manga.* FROM (
+                SELECT manga_id, MIN(rank) AS rank FROM matched GROUP BY manga_id
+            ) AS matched
+            JOIN manga ON manga.id = matched.manga_id
+            LEFT JOIN user_library ON user_library.user_id = ? AND user_library.manga_id = manga.id
+            WHERE (? = false OR user_library.id IS NOT NULL)
+            AND (? IS NULL OR EXISTS (
+                SELECT 1 FROM library_category
+                WHERE library_category.library_id = user_library.id AND library_category.category_id = ?
+            ))
+            AND (? IS NULL OR manga.source_id = ?)
+            AND (? IS NULL OR LOWER(manga.status) = LOWER(?))
+            AND (? IS NULL OR EXISTS (
+                SELECT 1 FROM json_each(CASE WHEN json_valid(manga.genre) THEN manga.genre ELSE '[]' END)
+                WHERE LOWER(value) = LOWER(?)
+            ))
+            AND (? IS NULL OR EXISTS (
+                SELECT 1 FROM user_manga_tag
+                WHERE user_manga_tag.user_id = ? AND user_manga_tag.manga_id = manga.id AND user_manga_tag.tag = ?
+            ))
+            AND (? = false OR EXISTS (
+                SELECT 1 FROM chapter
+                LEFT JOIN user_history ON user_history.user_id = ? AND user_history.chapter_id = chapter.id
+                WHERE chapter.manga_id = manga.id AND IFNULL(user_history.is_complete, false) = false
+            ))
+            AND (? IS NULL OR manga.id IN (SELECT value FROM json_each(?)))
+            AND {}
+            ORDER BY matched.rank
+            LIMIT ?"#,
+            restriction_condition("manga.source_id", "manga.genre")
+        );
+
+        let manga_ids = filter
+            .manga_ids
+            .as_ref()
+            .map(|ids| serde_json::json!(ids).to_string());
+        let query = sqlx::query(&query_str)
+            .bind(query)
+            .bind(query)
+            .bind(user_id)
+            .bind(user_id)
+            .bind(filter.library_only)
+            .bind(filter.category_id)
+            .bind(filter.category_id)
+            .bind(filter.source_id)
+            .bind(filter.source_id)
+            .bind(&filter.status)
+            .bind(&filter.status)
+            .bind(&filter.genre)
+            .bind(&filter.genre)
+            .bind(&filter.tag)
+            .bind(user_id)
+            .bind(&filter.tag)
+            .bind(filter.unread_only)
+            .bind(user_id)
+            .bind(manga_ids.clone())
+            .bind(manga_ids);
+
+        let manga = bind_restriction(query, &filter.restriction)
+            .bind(limit)
+            .fetch_all(&self.pool as &SqlitePool)
+            .await?
+            .iter()
+            .map(|row| Manga {
+                id: row.get(0),
+                source_id: row.get(1),
+                title: row.get(2),
+                author: serde_json::from_str(row.get::<String, _>(3).as_str()).unwrap_or_default(),
+                genre: serde_json::from_str(row.get::<String, _>(4).as_str()).unwrap_or_default(),
+                status: row.get(5),
+                description: row.get(6),
+                path: row.get(7),
+                cover_url: row.get(8),
+                date_added: row.get(9),
+                last_uploaded_at: None,
+            })
+            .collect();
+
+        Ok(manga)
+    }
+
+    async fn get_manga_overrides(
+        &self,
+        user_id: i64,
+        manga_ids: &[i64],
+    ) -> Result<Vec<MangaOverride>, MangaRepositoryError> {
+        let query_str = format!(
+            r#"SELECT manga_id, title, author, genre, status, description, cover_path
+            FROM manga_override WHERE user_id = ? AND manga_id IN ({})"#,
+            vec!["?"; manga_ids.len()].join(",")
+        );
+        let mut query = sqlx::query(&query_str).bind(user_id);
+        for id in manga_ids {
+            query = query.bind(id);
+        }
+        let overrides = query
+            .fetch_all(&self.pool as &SqlitePool)
+            .await?
+            .iter()
+            .map(|row| MangaOverride {
+                manga_id: row.get(0),
+                title: row.get(1),
+                author: row
+                    .get::<Option<String>, _>(2)
+                    .and_then(|author| serde_json::from_str(&author).ok()),
+                genre: row
+                    .get::<Option<String>, _>(3)
+                    .and_then(|genre| serde_json::from_str(&genre).ok()),
+                status: row.get(4),
+                description: row.get(5),
+                cover_path: row.get(6),
+            })
+            .collect();
+
+        Ok(overrides)
+    }
+
+    async fn set_manga_override(
+        &self,
+        user_id: i64,
+        manga_override: &MangaOverride,
+    ) -> Result<(), MangaRepositoryError> {
+        sqlx::query(
+            r#"
+            INSERT INTO manga_override(
+                user_id,
+                manga_id,
+                title,
+                author,
+                genre,
+                status,
+                description,
+                cover_path,
+                updated_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(user_id, manga_id)
+            DO UPDATE SET
+                title=excluded.title,
+                author=excluded.author,
+                genre=excluded.genre,
+                status=excluded.status,
+                description=excluded.description,
+                cover_path=excluded.cover_path,
+                updated_at=excluded.updated_at
+        "#,
+        )
+        .bind(user_id)
+        .bind(manga_override.manga_id)
+        .bind(&manga_override.title)
+        .bind(
+            manga_override
+                .author
+                .as_ref()
+                .and_then(|author| serde_json::to_string(author).ok()),
+        )
+        .bind(
+            manga_override
+                .genre
+                .as_ref()
+                .and_then(|genre| serde_json::to_string(genre).ok()),
+        )
+        .bind(&manga_override.status)
+        .bind(&manga_override.description)
+        .bind(&manga_override.cover_path)
+        .bind(Utc::now().naive_utc())
+        .execute(&self.pool as &SqlitePool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn delete_manga_override(
+        &self,
+        user_id: i64,
+        manga_id: i64,
+    ) -> Result<(), MangaRepositoryError> {
+        sqlx::query("DELETE FROM manga_override WHERE user_id = ? AND manga_id = ?")
+            .bind(user_id)
+            .bind(manga_id)
+            .execute(&self.pool as &SqlitePool)
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        domain::entities::content_policy::ContentRestriction,
+        infrastructure::database::establish_connection,
+    };
+
+    async fn search(repo: &MangaRepositoryImpl, user_id: i64, query: &str) -> Vec<i64> {
+        search_with(repo, user_id, query, &MangaSearchFilter::default()).await
+    }
+
+    async fn search_with(
+        repo: &MangaRepositoryImpl,
+        user_id: i64,
+        query: &str,
+        filter: &MangaSearchFilter,
+    ) -> Vec<i64> {
+        repo.search_manga(user_id, query, filter, 10)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|manga| manga.id)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_search_manga_only_matches_own_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = establish_connection(&dir.path().join("tanoshi.db").display().to_string(), true)
+            .await
+            .unwrap();
+
+        sqlx::query(
+            "INSERT INTO user(id, username, password) VALUES (1, 'one', ''), (2, 'two', '')",
+        )
+        .execute(&pool as &SqlitePool)
+        .await
+        .unwrap();
+        sqlx::query(
+            r#"INSERT INTO manga(id, source_id, title, author, genre, path, cover_url, date_added)
+            VALUES
+                (1, 1, 'Space Adventures', '["Jane Doe"]', '["Comedy"]', '/1', '', CURRENT_TIMESTAMP),
+                (2, 2, 'Haunted House', '["John Doe"]', '["Horror"]', '/2', '', CURRENT_TIMESTAMP)"#,
+        )
+        .execute(&pool as &SqlitePool)
+        .await
+        .unwrap();
+
+        let repo = MangaRepositoryImpl::new(pool);
+        assert_eq!(search(&repo, 1, r#""space"*"#).await, vec![1]);
+        assert_eq!(search(&repo, 1, r#""doe"*"#).await.len(), 2);
+
+        let mut manga_override = MangaOverride {
+            manga_id: 1,
+            title: Some("Secret Name".to_string()),
+            ..Default::default()
+        };
+        repo.set_manga_override(2, &manga_override).await.unwrap();
+        assert_eq!(search(&repo, 2, r#""secret"*"#).await, vec![1]);
+        assert!(search(&repo, 1, r#""secret"*"#).await.is_empty());
+
+        // an upsert replaces the indexed override
+        manga_override.title = Some("Other Name".to_string());
+        repo.set_manga_override(2, &manga_override).await.unwrap();
+        assert!(search(&repo, 2, r#""secret"*"#).await.is_empty());
+        assert_eq!(search(&repo, 2, r#""other"*"#).await, vec![1]);
+
+        repo.delete_manga_override(2, 1).await.unwrap();
+        assert!(search(&repo, 2, r#""other"*"#).await.is_empty());
+        assert_eq!(search(&repo, 2, r#""space"*"#).await, vec![1]);
+
+        let filter = MangaSearchFilter {
+            restriction: ContentRestriction {
+                blocked_genres: vec!["horror".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(search_with(&repo, 1, r#""doe"*"#, &filter).await, vec![1]);
+    }
 }
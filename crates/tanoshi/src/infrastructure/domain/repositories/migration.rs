@@ -0,0 +1,131 @@
+use async_trait::async_trait;
+use sqlx::Row;
+
+use crate::{
+    domain::{
+        entities::migration::MigrationResult,
+        repositories::migration::{MigrationRepository, MigrationRepositoryError},
+    },
+    infrastructure::database::Pool,
+};
+
+#[derive(Clone)]
+pub struct MigrationRepositoryImpl {
+    pool: Pool,
+}
+
+impl MigrationRepositoryImpl {
+    pub fn new<P: Into<Pool>>(pool: P) -> Self {
+        Self { pool: pool.into() }
+    }
+}
+
+#[async_trait]
+impl MigrationRepository for MigrationRepositoryImpl {
+    async fn migrate_manga(
+        &self,
+        user_id: i64,
+        from_manga_id: i64,
+        to_manga_id: i64,
+    ) -> Result<MigrationResult, MigrationRepositoryError> {
+        let mut result = MigrationResult::default();
+
+        let mut tx = self.pool.begin().await?;
+
+        let library_id: Option<i64> =
+            sqlx::query(r#"SELECT id FROM user_library WHERE user_id = ? AND manga_id = ?"#)
+                .bind(user_id)
+                .bind(from_manga_id)
+                .fetch_optional(&mut tx)
+                .await?
+                .map(|row| row.get(0));
+
+        if let Some(library_id) = library_id {
+            sqlx::query(
+                r#"INSERT INTO user_library(user_id, manga_id, date_added)
+                SELECT ?, ?, date_added FROM user_library WHERE id = ?
+                ON CONFLICT(user_id, manga_id) DO NOTHING"#,
+            )
+            .bind(user_id)
+            .bind(to_manga_id)
+            .bind(library_id)
+            .execute(&mut tx)
+            .await?;
+
+            result.categories = sqlx::query(
+                r#"INSERT OR IGNORE INTO library_category(library_id, category_id)
+                SELECT
+                    (SELECT id FROM user_library WHERE user_id = ? AND manga_id = ?),
+                    category_id
+                FROM library_category
+                WHERE library_id = ?"#,
+            )
+            .bind(user_id)
+            .bind(to_manga_id)
+            .bind(library_id)
+            .execute(&mut tx)
+            .await?
+            .rows_affected();
+
+            sqlx::query(r#"DELETE FROM library_category WHERE library_id = ?"#)
+                .bind(library_id)
+                .execute(&mut tx)
+                .await?;
+
+            sqlx::query(r#"DELETE FROM user_library WHERE id = ?"#)
+                .bind(library_id)
+                .execute(&mut tx)
+                .await?;
+        }
+
+        result.history = sqlx::query(
+            r#"INSERT INTO user_history(user_id, chapter_id, last_page, read_at, is_complete)
+            SELECT h.user_id, nc.id, h.last_page, h.read_at, h.is_complete
+            FROM user_history h
+            JOIN chapter oc ON oc.id = h.chapter_id
+            JOIN chapter nc ON nc.manga_id = ? AND nc.number = oc.number
+            WHERE h.user_id = ? AND oc.manga_id = ?
+            ON CONFLICT(user_id, chapter_id) DO UPDATE SET
+                last_page = excluded.last_page,
+                read_at = excluded.read_at,
+                is_complete = excluded.is_complete
+            WHERE excluded.read_at > user_history.read_at"#,
+        )
+        .bind(to_manga_id)
+        .bind(user_id)
+        .bind(from_manga_id)
+        .execute(&mut tx)
+        .await?
+        .rows_affected();
+
+        sqlx::query(
+            r#"DELETE FROM user_history
+            WHERE user_id = ? AND chapter_id IN (SELECT id FROM chapter WHERE manga_id = ?)"#,
+        )
+        .bind(user_id)
+        .bind(from_manga_id)
+        .execute(&mut tx)
+        .await?;
+
+        result.trackers = sqlx::query(
+            r#"UPDATE OR IGNORE tracker_manga SET manga_id = ? WHERE user_id = ? AND manga_id = ?"#,
+        )
+        .bind(to_manga_id)
+        .bind(user_id)
+        .bind(from_manga_id)
+        .execute(&mut tx)
+        .await?
+        .rows_affected();
+
+        // links already present on the new manga take precedence
+        sqlx::query(r#"DELETE FROM tracker_manga WHERE user_id = ? AND manga_id = ?"#)
+            .bind(user_id)
+            .bind(from_manga_id)
+            .execute(&mut tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(result)
+    }
+}
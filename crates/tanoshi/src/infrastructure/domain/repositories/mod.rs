@@ -1,10 +1,20 @@
+pub mod api_key;
+pub mod audit_log;
 pub mod chapter;
+pub mod content_policy;
 pub mod download;
 pub mod history;
+pub mod http_profile;
+pub mod identity;
 pub mod image;
 pub mod image_cache;
 pub mod library;
 pub mod manga;
+pub mod migration;
+pub mod preference;
+pub mod session;
 pub mod source;
+pub mod statistics;
+pub mod totp;
 pub mod tracker;
 pub mod user;
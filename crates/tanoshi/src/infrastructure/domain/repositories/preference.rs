@@ -0,0 +1,124 @@
+use async_trait::async_trait;
+use sqlx::{sqlite::SqliteRow, Row, SqlitePool};
+
+use crate::{
+    domain::{
+        entities::preference::Preference,
+        repositories::preference::{PreferenceRepository, PreferenceRepositoryError},
+    },
+    infrastructure::database::Pool,
+};
+
+#[derive(Clone)]
+pub struct PreferenceRepositoryImpl {
+    pool: Pool,
+}
+
+impl PreferenceRepositoryImpl {
+    pub fn new<P: Into<Pool>>(pool: P) -> Self {
+        Self { pool: pool.into() }
+    }
+
+    fn from_row(row: SqliteRow) -> Preference {
+        let manga_id: i64 = row.get(2);
+
+        Preference {
+            user_id: row.get(0),
+            key: row.get(1),
+            manga_id: (manga_id > 0).then_some(manga_id),
+            schema_version: row.get(3),
+            value: row.get(4),
+            updated_at: row.get(5),
+        }
+    }
+}
+
+#[async_trait]
+impl PreferenceRepository for PreferenceRepositoryImpl {
+    async fn get_preferences(
+        &self,
+        user_id: i64,
+        manga_id: Option<i64>,
+    ) -> Result<Vec<Preference>, PreferenceRepositoryError> {
+        let preferences = sqlx::query(
+            r#"SELECT user_id, key, manga_id, schema_version, value, updated_at
+            FROM user_preference
+            WHERE user_id = ? AND manga_id = ?
+            ORDER BY key"#,
+        )
+        .bind(user_id)
+        .bind(manga_id.unwrap_or(0))
+        .fetch_all(&self.pool as &SqlitePool)
+        .await?
+        .into_iter()
+        .map(Self::from_row)
+        .collect();
+
+        Ok(preferences)
+    }
+
+    async fn get_preference(
+        &self,
+        user_id: i64,
+        key: &str,
+        manga_id: Option<i64>,
+    ) -> Result<Preference, PreferenceRepositoryError> {
+        sqlx::query(
+            r#"SELECT user_id, key, manga_id, schema_version, value, updated_at
+            FROM user_preference
+            WHERE user_id = ? AND key = ? AND manga_id = ?"#,
+        )
+        .bind(user_id)
+        .bind(key)
+        .bind(manga_id.unwrap_or(0))
+        .fetch_optional(&self.pool as &SqlitePool)
+        .await?
+        .map(Self::from_row)
+        .ok_or(PreferenceRepositoryError::NotFound)
+    }
+
+    async fn upsert_preference(
+        &self,
+        preference: &Preference,
+    ) -> Result<bool, PreferenceRepositoryError> {
+        let rows_affected = sqlx::query(
+            r#"INSERT INTO user_preference(user_id, key, manga_id, schema_version, value, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            ON CONFLICT(user_id, key, manga_id) DO UPDATE SET
+            schema_version = excluded.schema_version,
+            value = excluded.value,
+            updated_at = excluded.updated_at
+            WHERE excluded.schema_version >= user_preference.schema_version"#,
+        )
+        .bind(preference.user_id)
+        .bind(&preference.key)
+        .bind(preference.manga_id.unwrap_or(0))
+        .bind(preference.schema_version)
+        .bind(&preference.value)
+        .bind(preference.updated_at)
+        .execute(&self.pool as &SqlitePool)
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected > 0)
+    }
+
+    async fn delete_preference(
+        &self,
+        user_id: i64,
+        key: &str,
+        manga_id: Option<i64>,
+    ) -> Result<u64, PreferenceRepositoryError> {
+        let rows_affected = sqlx::query(
+            r#"DELETE FROM user_preference WHERE user_id = ? AND key = ? AND manga_id = ?"#,
+        )
+        .bind(user_id)
+        .bind(key)
+        .bind(manga_id.unwrap_or(0))
+        .execute(&self.pool as &SqlitePool)
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected)
+    }
+}
@@ -0,0 +1,183 @@
+use async_trait::async_trait;
+use chrono::{NaiveDateTime, Utc};
+use sqlx::{sqlite::SqliteRow, Row, SqlitePool};
+
+use crate::{
+    domain::{
+        entities::session::Session,
+        repositories::session::{SessionRepository, SessionRepositoryError},
+    },
+    infrastructure::database::Pool,
+};
+
+#[derive(Clone)]
+pub struct SessionRepositoryImpl {
+    pool: Pool,
+}
+
+impl SessionRepositoryImpl {
+    pub fn new<P: Into<Pool>>(pool: P) -> Self {
+        Self { pool: pool.into() }
+    }
+
+    fn from_row(row: SqliteRow) -> Session {
+        Session {
+            id: row.get(0),
+            user_id: row.get(1),
+            user_agent: row.get(2),
+            created_at: row.get(3),
+            last_used_at: row.get(4),
+            expires_at: row.get(5),
+            revoked_at: row.get(6),
+        }
+    }
+}
+
+#[async_trait]
+impl SessionRepository for SessionRepositoryImpl {
+    async fn insert_session(
+        &self,
+        user_id: i64,
+        refresh_token_hash: &str,
+        user_agent: Option<&str>,
+        expires_at: NaiveDateTime,
+    ) -> Result<i64, SessionRepositoryError> {
+        let now = Utc::now().naive_utc();
+        let id = sqlx::query(
+            r#"INSERT INTO user_session(
+                user_id,
+                refresh_token_hash,
+                user_agent,
+                created_at,
+                last_used_at,
+                expires_at
+            ) VALUES (?, ?, ?, ?, ?, ?)"#,
+        )
+        .bind(user_id)
+        .bind(refresh_token_hash)
+        .bind(user_agent)
+        .bind(now)
+        .bind(now)
+        .bind(expires_at)
+        .execute(&self.pool as &SqlitePool)
+        .await?
+        .last_insert_rowid();
+
+        Ok(id)
+    }
+
+    async fn get_session_by_id(&self, id: i64) -> Result<Session, SessionRepositoryError> {
+        sqlx::query(
+            r#"SELECT id, user_id, user_agent, created_at, last_used_at, expires_at, revoked_at
+            FROM user_session WHERE id = ?"#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool as &SqlitePool)
+        .await?
+        .map(Self::from_row)
+        .ok_or(SessionRepositoryError::NotFound)
+    }
+
+    async fn get_session_by_refresh_token_hash(
+        &self,
+        refresh_token_hash: &str,
+    ) -> Result<Session, SessionRepositoryError> {
+        sqlx::query(
+            r#"SELECT id, user_id, user_agent, created_at, last_used_at, expires_at, revoked_at
+            FROM user_session WHERE refresh_token_hash = ?"#,
+        )
+        .bind(refresh_token_hash)
+        .fetch_optional(&self.pool as &SqlitePool)
+        .await?
+        .map(Self::from_row)
+        .ok_or(SessionRepositoryError::NotFound)
+    }
+
+    async fn get_active_sessions_by_user_id(
+        &self,
+        user_id: i64,
+    ) -> Result<Vec<Session>, SessionRepositoryError> {
+        let sessions = sqlx::query(
+            r#"SELECT id, user_id, user_agent, created_at, last_used_at, expires_at, revoked_at
+            FROM user_session
+            WHERE user_id = ? AND revoked_at IS NULL AND expires_at > ?
+            ORDER BY last_used_at DESC"#,
+        )
+        .bind(user_id)
+        .bind(Utc::now().naive_utc())
+        .fetch_all(&self.pool as &SqlitePool)
+        .await?
+        .into_iter()
+        .map(Self::from_row)
+        .collect();
+
+        Ok(sessions)
+    }
+
+    async fn rotate_refresh_token(
+        &self,
+        id: i64,
+        refresh_token_hash: &str,
+        expires_at: NaiveDateTime,
+    ) -> Result<(), SessionRepositoryError> {
+        sqlx::query(
+            r#"UPDATE user_session
+            SET refresh_token_hash = ?, expires_at = ?, last_used_at = ?
+            WHERE id = ?"#,
+        )
+        .bind(refresh_token_hash)
+        .bind(expires_at)
+        .bind(Utc::now().naive_utc())
+        .bind(id)
+        .execute(&self.pool as &SqlitePool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn revoke_session(&self, user_id: i64, id: i64) -> Result<u64, SessionRepositoryError> {
+        let rows_affected = sqlx::query(
+            r#"UPDATE user_session SET revoked_at = ?
+            WHERE user_id = ? AND id = ? AND revoked_at IS NULL"#,
+        )
+        .bind(Utc::now().naive_utc())
+        .bind(user_id)
+        .bind(id)
+        .execute(&self.pool as &SqlitePool)
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected)
+    }
+
+    async fn revoke_sessions_by_user_id(
+        &self,
+        user_id: i64,
+        except_id: Option<i64>,
+    ) -> Result<u64, SessionRepositoryError> {
+        let rows_affected = sqlx::query(
+            r#"UPDATE user_session SET revoked_at = ?
+            WHERE user_id = ? AND id IS NOT ? AND revoked_at IS NULL"#,
+        )
+        .bind(Utc::now().naive_utc())
+        .bind(user_id)
+        .bind(except_id)
+        .execute(&self.pool as &SqlitePool)
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected)
+    }
+
+    async fn delete_inactive_sessions(&self) -> Result<u64, SessionRepositoryError> {
+        let rows_affected = sqlx::query(
+            r#"DELETE FROM user_session WHERE revoked_at IS NOT NULL OR expires_at <= ?"#,
+        )
+        .bind(Utc::now().naive_utc())
+        .execute(&self.pool as &SqlitePool)
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected)
+    }
+}
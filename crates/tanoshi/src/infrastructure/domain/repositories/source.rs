@@ -1,13 +1,21 @@
-use std::str::FromStr;
+use std::{collections::HashMap, str::FromStr};
 
 use async_trait::async_trait;
+use chrono::Utc;
 use serde::Deserialize;
+use sqlx::{Row, SqlitePool};
 use tanoshi_lib::prelude::Version;
-use tanoshi_vm::prelude::ExtensionManager;
+use tanoshi_vm::{
+    extension::{fetch_repository_file, PluginIndex, TrustPolicy},
+    prelude::ExtensionManager,
+};
 
-use crate::domain::{
-    entities::source::Source,
-    repositories::source::{SourceRepository, SourceRepositoryError},
+use crate::{
+    domain::{
+        entities::source::{ExtensionRepository, Source},
+        repositories::source::{SourceRepository, SourceRepositoryError},
+    },
+    infrastructure::database::Pool,
 };
 
 #[derive(Deserialize)]
@@ -19,19 +27,70 @@ pub struct SourceDto {
     pub rustc_version: String,
     pub lib_version: String,
     pub icon: String,
+    #[serde(default)]
+    pub nsfw: bool,
+    #[serde(default)]
+    pub sha256: Option<String>,
+    #[serde(default)]
+    pub signature: Option<String>,
 }
 
 #[derive(Clone)]
 pub struct SourceRepositoryImpl {
+    pool: Pool,
     extension_manager: ExtensionManager,
 }
 
 impl SourceRepositoryImpl {
-    pub fn new(ext: ExtensionManager) -> Self {
+    pub fn new<P: Into<Pool>>(pool: P, ext: ExtensionManager) -> Self {
         Self {
+            pool: pool.into(),
             extension_manager: ext,
         }
     }
+
+    fn plugin_index(source: &Source) -> PluginIndex {
+        PluginIndex {
+            id: source.id,
+            name: source.name.clone(),
+            version: source.version.clone(),
+            sha256: source.sha256.clone(),
+            signature: source.signature.clone(),
+        }
+    }
+
+    fn check_compatibility(source: &Source) -> Result<(), SourceRepositoryError> {
+        if source.rustc_version != tanoshi_lib::RUSTC_VERSION
+            || source.lib_version != tanoshi_lib::LIB_VERSION
+        {
+            return Err(SourceRepositoryError::Other(
+                "Incompatible version, update tanoshi server".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    async fn set_source_repository(
+        &self,
+        source_id: i64,
+        repository: &str,
+    ) -> Result<(), SourceRepositoryError> {
+        sqlx::query(
+            r#"INSERT INTO source_repository(source_id, repository, updated_at)
+            VALUES (?, ?, ?)
+            ON CONFLICT(source_id) DO UPDATE SET
+                repository = excluded.repository,
+                updated_at = excluded.updated_at"#,
+        )
+        .bind(source_id)
+        .bind(repository)
+        .bind(Utc::now().naive_utc())
+        .execute(&self.pool as &SqlitePool)
+        .await?;
+
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -52,21 +111,14 @@ impl SourceRepository for SourceRepositoryImpl {
 
     async fn available_sources(
         &self,
-        repo_url: &str,
-        filter_installed: bool,
+        repository: &ExtensionRepository,
     ) -> Result<Vec<Source>, SourceRepositoryError> {
-        let source_indexes: Vec<SourceDto> = reqwest::get(&format!("{repo_url}/index.json"))
-            .await?
-            .json()
-            .await?;
-
-        let mut sources: Vec<Source> = vec![];
-        for index in source_indexes {
-            if filter_installed && self.extension_manager.exists(index.id).await? {
-                continue;
-            }
+        let contents = fetch_repository_file(&repository.url, "index.json").await?;
+        let source_indexes: Vec<SourceDto> = serde_json::from_slice(&contents)?;
 
-            sources.push(Source {
+        let sources = source_indexes
+            .into_iter()
+            .map(|index| Source {
                 id: index.id,
                 name: index.name,
                 url: index.url,
@@ -74,9 +126,14 @@ impl SourceRepository for SourceRepositoryImpl {
                 rustc_version: index.rustc_version,
                 lib_version: index.lib_version,
                 icon: index.icon,
+                nsfw: index.nsfw,
                 has_update: false,
-            });
-        }
+                repository: Some(repository.name.clone()),
+                conflicts: vec![],
+                sha256: index.sha256,
+                signature: index.signature,
+            })
+            .collect();
 
         Ok(sources)
     }
@@ -86,65 +143,55 @@ impl SourceRepository for SourceRepositoryImpl {
         Ok(source.into())
     }
 
-    async fn install_source(&self, repo_url: &str, id: i64) -> Result<(), SourceRepositoryError> {
-        if self.extension_manager.exists(id).await? {
+    async fn install_source(
+        &self,
+        repository: &ExtensionRepository,
+        source: &Source,
+    ) -> Result<(), SourceRepositoryError> {
+        if self.extension_manager.exists(source.id).await? {
             return Err(SourceRepositoryError::Other(
                 "source installed, use updateSource to update".to_string(),
             ));
         }
 
-        let source_indexes: Vec<SourceDto> = reqwest::get(format!("{repo_url}/index.json"))
-            .await?
-            .json()
-            .await?;
-
-        let source = source_indexes
-            .iter()
-            .find(|index| index.id == id)
-            .ok_or(SourceRepositoryError::NotFound)?;
-
-        if source.rustc_version != tanoshi_lib::RUSTC_VERSION
-            || source.lib_version != tanoshi_lib::LIB_VERSION
-        {
-            return Err(SourceRepositoryError::Other(
-                "Incompatible version, update tanoshi server".to_string(),
-            ));
-        }
+        Self::check_compatibility(source)?;
 
         self.extension_manager
-            .install(repo_url, &source.name)
+            .install(
+                &repository.url,
+                &Self::plugin_index(source),
+                &repository.trust,
+            )
+            .await?;
+
+        self.set_source_repository(source.id, &repository.name)
             .await?;
 
         Ok(())
     }
 
-    async fn update_source(&self, repo_url: &str, id: i64) -> Result<(), SourceRepositoryError> {
-        let installed_source = self.extension_manager.get_source_info(id)?;
-
-        let source_indexes: Vec<SourceDto> = reqwest::get(format!("{repo_url}/index.json"))
-            .await?
-            .json()
-            .await?;
-        let source = source_indexes
-            .iter()
-            .find(|index| index.id == id)
-            .ok_or(SourceRepositoryError::NotFound)?;
+    async fn update_source(
+        &self,
+        repository: &ExtensionRepository,
+        source: &Source,
+    ) -> Result<(), SourceRepositoryError> {
+        let installed_source = self.extension_manager.get_source_info(source.id)?;
 
         if Version::from_str(installed_source.version)? == Version::from_str(&source.version)? {
             return Err(SourceRepositoryError::Other("No new version".to_string()));
         }
 
-        if source.rustc_version != tanoshi_lib::RUSTC_VERSION
-            || source.lib_version != tanoshi_lib::LIB_VERSION
-        {
-            return Err(SourceRepositoryError::Other(
-                "Incompatible version, update tanoshi server".to_string(),
-            ));
-        }
+        Self::check_compatibility(source)?;
 
-        self.extension_manager.remove(id).await?;
         self.extension_manager
-            .install(repo_url, &source.name)
+            .update(
+                &repository.url,
+                &Self::plugin_index(source),
+                &repository.trust,
+            )
+            .await?;
+
+        self.set_source_repository(source.id, &repository.name)
             .await?;
 
         Ok(())
@@ -153,6 +200,68 @@ impl SourceRepository for SourceRepositoryImpl {
     async fn uninstall_source(&self, id: i64) -> Result<(), SourceRepositoryError> {
         self.extension_manager.remove(id).await?;
 
+        sqlx::query(r#"DELETE FROM source_repository WHERE source_id = ?"#)
+            .bind(id)
+            .execute(&self.pool as &SqlitePool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_source_repositories(&self) -> Result<HashMap<i64, String>, SourceRepositoryError> {
+        let rows = sqlx::query(r#"SELECT source_id, repository FROM source_repository"#)
+            .fetch_all(&self.pool as &SqlitePool)
+            .await?;
+
+        Ok(rows.iter().map(|row| (row.get(0), row.get(1))).collect())
+    }
+
+    async fn get_extension_repositories(
+        &self,
+    ) -> Result<Vec<ExtensionRepository>, SourceRepositoryError> {
+        let rows =
+            sqlx::query(r#"SELECT name, url FROM extension_repository ORDER BY created_at, name"#)
+                .fetch_all(&self.pool as &SqlitePool)
+                .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| ExtensionRepository {
+                name: row.get(0),
+                url: row.get(1),
+                from_config: false,
+                trust: TrustPolicy::default(),
+            })
+            .collect())
+    }
+
+    async fn insert_extension_repository(
+        &self,
+        name: &str,
+        url: &str,
+    ) -> Result<(), SourceRepositoryError> {
+        sqlx::query(r#"INSERT INTO extension_repository(name, url, created_at) VALUES (?, ?, ?)"#)
+            .bind(name)
+            .bind(url)
+            .bind(Utc::now().naive_utc())
+            .execute(&self.pool as &SqlitePool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn delete_extension_repository(&self, name: &str) -> Result<(), SourceRepositoryError> {
+        let res = sqlx::query(r#"DELETE FROM extension_repository WHERE name = ?"#)
+            .bind(name)
+            .execute(&self.pool as &SqlitePool)
+            .await?;
+
+        if res.rows_affected() == 0 {
+            return Err(SourceRepositoryError::Other(format!(
+                "no extension repository named {name}"
+            )));
+        }
+
         Ok(())
     }
 }
@@ -0,0 +1,317 @@
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use sqlx::{Row, SqlitePool};
+
+use crate::{
+    domain::{
+        entities::statistics::{
+            ActivityPeriod, MangaCompletion, ReadingActivity, ReadingCount, ReadingSummary,
+            SourceReadingCount,
+        },
+        repositories::statistics::{StatisticsRepository, StatisticsRepositoryError},
+    },
+    infrastructure::database::Pool,
+};
+
+#[derive(Clone)]
+pub struct StatisticsRepositoryImpl {
+    pool: Pool,
+}
+
+impl StatisticsRepositoryImpl {
+    pub fn new<P: Into<Pool>>(pool: P) -> Self {
+        Self { pool: pool.into() }
+    }
+
+    /// Count chapters read for each value of a json array column of manga
+    async fn get_top_json_values(
+        &self,
+        column: &str,
+        user_id: i64,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+        limit: i64,
+    ) -> Result<Vec<ReadingCount>, StatisticsRepositoryError> {
+        let query_str = format!(
+            r#"SELECT value, COUNT(1) AS chapters
+            FROM user_history
+            JOIN chapter ON chapter.id = user_history.chapter_id
+            JOIN manga ON manga.id = chapter.manga_id,
+            json_each(CASE WHEN json_valid(manga.{column}) THEN manga.{column} ELSE '[]' END)
+            WHERE user_history.user_id = ?
+            AND (? IS NULL OR user_history.read_at >= ?)
+            AND (? IS NULL OR user_history.read_at < ?)
+            GROUP BY LOWER(value)
+            ORDER BY chapters DESC
+            LIMIT ?"#
+        );
+
+        let counts = sqlx::query(&query_str)
+            .bind(user_id)
+            .bind(from)
+            .bind(from)
+            .bind(to)
+            .bind(to)
+            .bind(limit)
+            .fetch_all(&self.pool as &SqlitePool)
+            .await?
+            .into_iter()
+            .map(|row| ReadingCount {
+                name: row.get(0),
+                chapters: row.get(1),
+            })
+            .collect();
+
+        Ok(counts)
+    }
+}
+
+#[async_trait]
+impl StatisticsRepository for StatisticsRepositoryImpl {
+    async fn get_reading_activity(
+        &self,
+        user_id: i64,
+        period: ActivityPeriod,
+        utc_offset_minutes: i32,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+    ) -> Result<Vec<ReadingActivity>, StatisticsRepositoryError> {
+        let format = match period {
+            ActivityPeriod::Day => "%Y-%m-%d",
+            ActivityPeriod::Week => "%Y-W%W",
+            ActivityPeriod::Month => "%Y-%m",
+            ActivityPeriod::Year => "%Y",
+        };
+
+        let activity = sqlx::query(
+            r#"SELECT strftime(?, read_at, ?) AS period, COUNT(1), SUM(last_page + 1)
+            FROM user_history
+            WHERE user_id = ?
+            AND (? IS NULL OR read_at >= ?)
+            AND (? IS NULL OR read_at < ?)
+            GROUP BY period
+            ORDER BY period"#,
+        )
+        .bind(format)
+        .bind(format!("{utc_offset_minutes:+} minutes"))
+        .bind(user_id)
+        .bind(from)
+        .bind(from)
+        .bind(to)
+        .bind(to)
+        .fetch_all(&self.pool as &SqlitePool)
+        .await?
+        .into_iter()
+        .map(|row| ReadingActivity {
+            period: row.get(0),
+            chapters: row.get(1),
+            pages: row.get(2),
+        })
+        .collect();
+
+        Ok(activity)
+    }
+
+    async fn get_reading_totals(
+        &self,
+        user_id: i64,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+    ) -> Result<ReadingSummary, StatisticsRepositoryError> {
+        let row = sqlx::query(
+            r#"SELECT
+                COUNT(1),
+                IFNULL(SUM(user_history.last_page + 1), 0),
+                COUNT(DISTINCT chapter.manga_id)
+            FROM user_history
+            JOIN chapter ON chapter.id = user_history.chapter_id
+            WHERE user_history.user_id = ?
+            AND (? IS NULL OR user_history.read_at >= ?)
+            AND (? IS NULL OR user_history.read_at < ?)"#,
+        )
+        .bind(user_id)
+        .bind(from)
+        .bind(from)
+        .bind(to)
+        .bind(to)
+        .fetch_one(&self.pool as &SqlitePool)
+        .await?;
+
+        let completed_manga = sqlx::query(
+            r#"SELECT COUNT(1) FROM (
+                SELECT chapter.manga_id FROM chapter
+                LEFT JOIN user_history ON user_history.user_id = ? AND user_history.chapter_id = chapter.id
+                WHERE chapter.manga_id IN (
+                    SELECT c.manga_id FROM user_history h
+                    JOIN chapter c ON c.id = h.chapter_id
+                    WHERE h.user_id = ?
+                    AND (? IS NULL OR h.read_at >= ?)
+                    AND (? IS NULL OR h.read_at < ?)
+                )
+                GROUP BY chapter.manga_id
+                HAVING SUM(IFNULL(user_history.is_complete, false)) = COUNT(1)
+            )"#,
+        )
+        .bind(user_id)
+        .bind(user_id)
+        .bind(from)
+        .bind(from)
+        .bind(to)
+        .bind(to)
+        .fetch_one(&self.pool as &SqlitePool)
+        .await?
+        .get(0);
+
+        Ok(ReadingSummary {
+            chapters: row.get(0),
+            pages: row.get(1),
+            manga: row.get(2),
+            completed_manga,
+            ..Default::default()
+        })
+    }
+
+    async fn get_read_times(
+        &self,
+        user_id: i64,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+    ) -> Result<Vec<NaiveDateTime>, StatisticsRepositoryError> {
+        let times = sqlx::query(
+            r#"SELECT read_at FROM user_history
+            WHERE user_id = ?
+            AND (? IS NULL OR read_at >= ?)
+            AND (? IS NULL OR read_at < ?)
+            ORDER BY read_at"#,
+        )
+        .bind(user_id)
+        .bind(from)
+        .bind(from)
+        .bind(to)
+        .bind(to)
+        .fetch_all(&self.pool as &SqlitePool)
+        .await?
+        .into_iter()
+        .map(|row| row.get(0))
+        .collect();
+
+        Ok(times)
+    }
+
+    async fn get_top_genres(
+        &self,
+        user_id: i64,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+        limit: i64,
+    ) -> Result<Vec<ReadingCount>, StatisticsRepositoryError> {
+        self.get_top_json_values("genre", user_id, from, to, limit)
+            .await
+    }
+
+    async fn get_top_authors(
+        &self,
+        user_id: i64,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+        limit: i64,
+    ) -> Result<Vec<ReadingCount>, StatisticsRepositoryError> {
+        self.get_top_json_values("author", user_id, from, to, limit)
+            .await
+    }
+
+    async fn get_top_sources(
+        &self,
+        user_id: i64,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+        limit: i64,
+    ) -> Result<Vec<SourceReadingCount>, StatisticsRepositoryError> {
+        let counts = sqlx::query(
+            r#"SELECT manga.source_id, COUNT(1) AS chapters
+            FROM user_history
+            JOIN chapter ON chapter.id = user_history.chapter_id
+            JOIN manga ON manga.id = chapter.manga_id
+            WHERE user_history.user_id = ?
+            AND (? IS NULL OR user_history.read_at >= ?)
+            AND (? IS NULL OR user_history.read_at < ?)
+            GROUP BY manga.source_id
+            ORDER BY chapters DESC
+            LIMIT ?"#,
+        )
+        .bind(user_id)
+        .bind(from)
+        .bind(from)
+        .bind(to)
+        .bind(to)
+        .bind(limit)
+        .fetch_all(&self.pool as &SqlitePool)
+        .await?
+        .into_iter()
+        .map(|row| SourceReadingCount {
+            source_id: row.get(0),
+            chapters: row.get(1),
+        })
+        .collect();
+
+        Ok(counts)
+    }
+
+    async fn get_manga_completion(
+        &self,
+        user_id: i64,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+        limit: i64,
+    ) -> Result<Vec<MangaCompletion>, StatisticsRepositoryError> {
+        let completion = sqlx::query(
+            r#"SELECT
+                manga.id,
+                manga.title,
+                SUM(IFNULL(user_history.is_complete, false)),
+                COUNT(chapter.id),
+                MIN(user_history.read_at),
+                MAX(user_history.read_at) AS last_read_at
+            FROM manga
+            JOIN chapter ON chapter.manga_id = manga.id
+            LEFT JOIN user_history ON user_history.user_id = ? AND user_history.chapter_id = chapter.id
+            WHERE manga.id IN (
+                SELECT c.manga_id FROM user_history h
+                JOIN chapter c ON c.id = h.chapter_id
+                WHERE h.user_id = ?
+                AND (? IS NULL OR h.read_at >= ?)
+                AND (? IS NULL OR h.read_at < ?)
+            )
+            GROUP BY manga.id
+            ORDER BY last_read_at DESC
+            LIMIT ?"#,
+        )
+        .bind(user_id)
+        .bind(user_id)
+        .bind(from)
+        .bind(from)
+        .bind(to)
+        .bind(to)
+        .bind(limit)
+        .fetch_all(&self.pool as &SqlitePool)
+        .await?
+        .into_iter()
+        .map(|row| {
+            let chapters_read: i64 = row.get(2);
+            let chapters_total: i64 = row.get(3);
+            let last_read_at: NaiveDateTime = row.get(5);
+            MangaCompletion {
+                manga_id: row.get(0),
+                title: row.get(1),
+                chapters_read,
+                chapters_total,
+                started_at: row.get(4),
+                last_read_at,
+                completed_at: (chapters_read == chapters_total).then_some(last_read_at),
+            }
+        })
+        .collect();
+
+        Ok(completion)
+    }
+}
@@ -0,0 +1,173 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use sqlx::{sqlite::SqliteRow, Row, SqlitePool};
+
+use crate::{
+    domain::{
+        entities::totp::UserTotp,
+        repositories::totp::{TotpRepository, TotpRepositoryError},
+    },
+    infrastructure::database::Pool,
+};
+
+#[derive(Clone)]
+pub struct TotpRepositoryImpl {
+    pool: Pool,
+}
+
+impl TotpRepositoryImpl {
+    pub fn new<P: Into<Pool>>(pool: P) -> Self {
+        Self { pool: pool.into() }
+    }
+
+    fn from_row(row: SqliteRow) -> UserTotp {
+        UserTotp {
+            user_id: row.get(0),
+            secret: row.get(1),
+            enabled: row.get(2),
+            last_used_step: row.get(3),
+            created_at: row.get(4),
+        }
+    }
+}
+
+#[async_trait]
+impl TotpRepository for TotpRepositoryImpl {
+    async fn get_totp(&self, user_id: i64) -> Result<UserTotp, TotpRepositoryError> {
+        sqlx::query(
+            r#"SELECT user_id, secret, enabled, last_used_step, created_at
+            FROM user_totp WHERE user_id = ?"#,
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool as &SqlitePool)
+        .await?
+        .map(Self::from_row)
+        .ok_or(TotpRepositoryError::NotFound)
+    }
+
+    async fn upsert_totp(&self, user_id: i64, secret: &str) -> Result<(), TotpRepositoryError> {
+        sqlx::query(
+            r#"INSERT INTO user_totp(user_id, secret, enabled, last_used_step, created_at)
+            VALUES (?, ?, false, 0, ?)
+            ON CONFLICT(user_id) DO UPDATE SET
+            secret = excluded.secret,
+            enabled = false,
+            last_used_step = 0,
+            created_at = excluded.created_at"#,
+        )
+        .bind(user_id)
+        .bind(secret)
+        .bind(Utc::now().naive_utc())
+        .execute(&self.pool as &SqlitePool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn enable_totp(&self, user_id: i64) -> Result<(), TotpRepositoryError> {
+        sqlx::query(r#"UPDATE user_totp SET enabled = true WHERE user_id = ?"#)
+            .bind(user_id)
+            .execute(&self.pool as &SqlitePool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn update_last_used_step(
+        &self,
+        user_id: i64,
+        step: i64,
+    ) -> Result<bool, TotpRepositoryError> {
+        let rows_affected = sqlx::query(
+            r#"UPDATE user_totp SET last_used_step = ? WHERE user_id = ? AND last_used_step < ?"#,
+        )
+        .bind(step)
+        .bind(user_id)
+        .bind(step)
+        .execute(&self.pool as &SqlitePool)
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected > 0)
+    }
+
+    async fn delete_totp(&self, user_id: i64) -> Result<u64, TotpRepositoryError> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(r#"DELETE FROM user_recovery_code WHERE user_id = ?"#)
+            .bind(user_id)
+            .execute(&mut tx)
+            .await?;
+
+        let rows_affected = sqlx::query(r#"DELETE FROM user_totp WHERE user_id = ?"#)
+            .bind(user_id)
+            .execute(&mut tx)
+            .await?
+            .rows_affected();
+
+        tx.commit().await?;
+
+        Ok(rows_affected)
+    }
+
+    async fn replace_recovery_codes(
+        &self,
+        user_id: i64,
+        code_hashes: &[String],
+    ) -> Result<(), TotpRepositoryError> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(r#"DELETE FROM user_recovery_code WHERE user_id = ?"#)
+            .bind(user_id)
+            .execute(&mut tx)
+            .await?;
+
+        if !code_hashes.is_empty() {
+            let query_str = format!(
+                "INSERT INTO user_recovery_code(user_id, code_hash) VALUES {}",
+                vec!["(?,?)".to_string(); code_hashes.len()].join(",")
+            );
+
+            let mut query = sqlx::query(&query_str);
+            for code_hash in code_hashes {
+                query = query.bind(user_id).bind(code_hash);
+            }
+            query.execute(&mut tx).await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn use_recovery_code(
+        &self,
+        user_id: i64,
+        code_hash: &str,
+    ) -> Result<bool, TotpRepositoryError> {
+        let rows_affected = sqlx::query(
+            r#"UPDATE user_recovery_code SET used_at = ?
+            WHERE user_id = ? AND code_hash = ? AND used_at IS NULL"#,
+        )
+        .bind(Utc::now().naive_utc())
+        .bind(user_id)
+        .bind(code_hash)
+        .execute(&self.pool as &SqlitePool)
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected > 0)
+    }
+
+    async fn get_recovery_codes_count(&self, user_id: i64) -> Result<i64, TotpRepositoryError> {
+        let count = sqlx::query(
+            r#"SELECT COUNT(1) FROM user_recovery_code WHERE user_id = ? AND used_at IS NULL"#,
+        )
+        .bind(user_id)
+        .fetch_one(&self.pool as &SqlitePool)
+        .await?
+        .get(0);
+
+        Ok(count)
+    }
+}
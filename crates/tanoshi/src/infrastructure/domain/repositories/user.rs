@@ -47,7 +47,6 @@ impl UserRepository for UserRepositoryImpl {
         Ok(row_id)
     }
 
-    #[allow(dead_code)]
     async fn update_user_is_admin(
         &self,
         id: i64,
@@ -4,3 +4,4 @@ pub mod database;
 pub mod domain;
 pub mod local;
 pub mod notification;
+pub mod oidc;
@@ -0,0 +1,440 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose, Engine};
+use oauth2::{
+    basic::{
+        BasicErrorResponse, BasicRevocationErrorResponse, BasicTokenIntrospectionResponse,
+        BasicTokenType,
+    },
+    reqwest::async_http_client,
+    AuthUrl, AuthorizationCode, Client, ClientId, ClientSecret, CsrfToken, ExtraTokenFields,
+    PkceCodeChallenge, PkceCodeVerifier, RedirectUrl, Scope, StandardRevocableToken,
+    StandardTokenResponse, TokenResponse, TokenUrl,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::config::OidcConfig;
+
+/// How long a started login can be finished
+const PENDING_LOGIN_TTL: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IdTokenFields {
+    id_token: Option<String>,
+}
+
+impl ExtraTokenFields for IdTokenFields {}
+
+type OAuthClient = Client<
+    BasicErrorResponse,
+    StandardTokenResponse<IdTokenFields, BasicTokenType>,
+    BasicTokenType,
+    BasicTokenIntrospectionResponse,
+    StandardRevocableToken,
+    BasicRevocationErrorResponse,
+>;
+
+#[derive(Debug, Deserialize)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    userinfo_endpoint: String,
+}
+
+pub struct OidcSession {
+    pub authorize_url: String,
+    pub csrf_state: String,
+}
+
+/// Login started by [`OidcClient::get_authorize_url`], kept until the provider redirects back
+#[derive(Debug, Clone)]
+pub struct PendingLogin {
+    pkce_code_verifier: String,
+    nonce: String,
+    expires_at: Instant,
+}
+
+/// Claims of the logged in user taken from userinfo endpoint
+#[derive(Debug, Clone)]
+pub struct OidcUserInfo {
+    pub subject: String,
+    pub username: Option<String>,
+    /// `None` if admin mapping is not configured
+    pub is_admin: Option<bool>,
+}
+
+#[derive(Debug, Clone)]
+pub struct OidcClient {
+    config: OidcConfig,
+    issuer: String,
+    oauth_client: OAuthClient,
+    userinfo_url: String,
+    http_client: reqwest::Client,
+    /// Pending logins keyed by csrf state
+    pending: Arc<Mutex<HashMap<String, PendingLogin>>>,
+}
+
+impl OidcClient {
+    /// Fetch provider metadata from issuer, redirect url is `{base_url}/login/oidc/redirect`
+    pub async fn discover(base_url: &str, config: OidcConfig) -> Result<Self> {
+        let http_client = reqwest::Client::new();
+
+        let issuer_url = config.issuer_url.trim_end_matches('/');
+        let metadata: ProviderMetadata = http_client
+            .get(format!("{issuer_url}/.well-known/openid-configuration"))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        if metadata.issuer.trim_end_matches('/') != issuer_url {
+            return Err(anyhow!(
+                "issuer mismatch, expected {issuer_url} got {}",
+                metadata.issuer
+            ));
+        }
+
+        let oauth_client = OAuthClient::new(
+            ClientId::new(config.client_id.clone()),
+            config.client_secret.clone().map(ClientSecret::new),
+            AuthUrl::new(metadata.authorization_endpoint)?,
+            Some(TokenUrl::new(metadata.token_endpoint)?),
+        )
+        .set_redirect_uri(RedirectUrl::new(format!(
+            "{}/login/oidc/redirect",
+            base_url.trim_end_matches('/')
+        ))?);
+
+        Ok(Self {
+            config,
+            issuer: metadata.issuer,
+            oauth_client,
+            userinfo_url: metadata.userinfo_endpoint,
+            http_client,
+            pending: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    pub fn issuer(&self) -> &str {
+        &self.issuer
+    }
+
+    pub fn config(&self) -> &OidcConfig {
+        &self.config
+    }
+
+    /// Start a login, verifier and nonce stay on the server until [`Self::take_pending`]
+    pub fn get_authorize_url(&self) -> OidcSession {
+        let (pkce_code_challenge, pkce_code_verifier) = PkceCodeChallenge::new_random_sha256();
+        let nonce = CsrfToken::new_random().secret().to_owned();
+        let (authorize_url, csrf_state) = self
+            .oauth_client
+            .authorize_url(CsrfToken::new_random)
+            .add_scopes(self.config.scopes.iter().cloned().map(Scope::new))
+            .add_extra_param("nonce", nonce.clone())
+            .set_pkce_challenge(pkce_code_challenge)
+            .url();
+
+        let now = Instant::now();
+        let mut pending = self.pending.lock().unwrap();
+        // drop logins that were never finished so the map doesn't grow forever
+        pending.retain(|_, login| login.expires_at > now);
+        pending.insert(
+            csrf_state.secret().to_owned(),
+            PendingLogin {
+                pkce_code_verifier: pkce_code_verifier.secret().to_owned(),
+                nonce,
+                expires_at: now + PENDING_LOGIN_TTL,
+            },
+        );
+
+        OidcSession {
+            authorize_url: authorize_url.to_string(),
+            csrf_state: csrf_state.secret().to_owned(),
+        }
+    }
+
+    /// Remove pending login of the state returned by provider, each login can only be finished once
+    pub fn take_pending(&self, state: &str) -> Option<PendingLogin> {
+        self.pending
+            .lock()
+            .unwrap()
+            .remove(state)
+            .filter(|login| login.expires_at > Instant::now())
+    }
+
+    pub async fn exchange_code(&self, code: String, login: PendingLogin) -> Result<OidcUserInfo> {
+        let token = self
+            .oauth_client
+            .exchange_code(AuthorizationCode::new(code))
+            .set_pkce_verifier(PkceCodeVerifier::new(login.pkce_code_verifier))
+            .request_async(async_http_client)
+            .await
+            .map_err(|e| anyhow!("{e}"))?;
+
+        let id_token = token
+            .extra_fields()
+            .id_token
+            .as_deref()
+            .ok_or_else(|| anyhow!("token response has no id_token"))?;
+        self.verify_id_token(id_token, &login.nonce)?;
+
+        let claims: Map<String, Value> = self
+            .http_client
+            .get(&self.userinfo_url)
+            .bearer_auth(token.access_token().secret())
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let subject = claims
+            .get("sub")
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow!("userinfo has no sub"))?
+            .to_string();
+
+        let username = claims
+            .get(&self.config.username_claim)
+            .and_then(Value::as_str)
+            .map(|username| username.to_string());
+
+        let admin_values = &self.config.admin_values;
+        let is_admin =
+            (!admin_values.is_empty()).then(|| match claims.get(&self.config.admin_claim) {
+                Some(Value::Array(values)) => values
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .any(|value| admin_values.iter().any(|admin| admin == value)),
+                Some(Value::String(value)) => admin_values.contains(value),
+                Some(Value::Bool(value)) => admin_values.contains(&value.to_string()),
+                _ => false,
+            });
+
+        Ok(OidcUserInfo {
+            subject,
+            username,
+            is_admin,
+        })
+    }
+
+    /// Check issuer, audience and nonce of id token. It comes straight from the token endpoint
+    /// so its signature is not checked, the same as userinfo.
+    fn verify_id_token(&self, id_token: &str, nonce: &str) -> Result<()> {
+        let payload = id_token
+            .split('.')
+            .nth(1)
+            .ok_or_else(|| anyhow!("malformed id_token"))?;
+        let claims: Map<String, Value> =
+            serde_json::from_slice(&general_purpose::URL_SAFE_NO_PAD.decode(payload)?)?;
+
+        if claims.get("iss").and_then(Value::as_str) != Some(self.issuer.as_str()) {
+            return Err(anyhow!("id_token issuer mismatch"));
+        }
+
+        let client_id = &self.config.client_id;
+        let audience_ok = match claims.get("aud") {
+            Some(Value::String(aud)) => aud == client_id,
+            Some(Value::Array(aud)) => aud.iter().any(|aud| aud.as_str() == Some(client_id)),
+            _ => false,
+        };
+        if !audience_ok {
+            return Err(anyhow!("id_token audience mismatch"));
+        }
+
+        if claims.get("nonce").and_then(Value::as_str) != Some(nonce) {
+            return Err(anyhow!("id_token nonce mismatch"));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use wiremock::{
+        matchers::{body_string_contains, header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+
+    const CLIENT_ID: &str = "tanoshi";
+
+    fn config(issuer_url: &str) -> OidcConfig {
+        OidcConfig {
+            issuer_url: issuer_url.to_string(),
+            client_id: CLIENT_ID.to_string(),
+            client_secret: Some("secret".to_string()),
+            scopes: vec!["openid".to_string(), "profile".to_string()],
+            username_claim: "preferred_username".to_string(),
+            admin_claim: "groups".to_string(),
+            admin_values: vec!["admins".to_string()],
+            auto_create_user: true,
+            link_by_username: false,
+            disable_password_login: false,
+        }
+    }
+
+    fn id_token(claims: Value) -> String {
+        let encode = |value: Value| general_purpose::URL_SAFE_NO_PAD.encode(value.to_string());
+        format!(
+            "{}.{}.signature",
+            encode(json!({ "alg": "RS256", "typ": "JWT" })),
+            encode(claims)
+        )
+    }
+
+    async fn provider() -> (MockServer, OidcClient) {
+        let server = MockServer::start().await;
+        let issuer = server.uri();
+
+        Mock::given(method("GET"))
+            .and(path("/.well-known/openid-configuration"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "issuer": issuer,
+                "authorization_endpoint": format!("{issuer}/authorize"),
+                "token_endpoint": format!("{issuer}/token"),
+                "userinfo_endpoint": format!("{issuer}/userinfo"),
+            })))
+            .mount(&server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/userinfo"))
+            .and(header("authorization", "Bearer access-token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "sub": "subject",
+                "preferred_username": "alice",
+                "groups": ["users", "admins"],
+            })))
+            .mount(&server)
+            .await;
+
+        let client = OidcClient::discover("http://localhost:3030", config(&issuer))
+            .await
+            .unwrap();
+
+        (server, client)
+    }
+
+    /// Respond to code exchange with an id token containing `claims`
+    async fn mount_token(server: &MockServer, claims: Value) {
+        Mock::given(method("POST"))
+            .and(path("/token"))
+            .and(body_string_contains("code=code"))
+            .and(body_string_contains("code_verifier="))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "access_token": "access-token",
+                "token_type": "Bearer",
+                "id_token": id_token(claims),
+            })))
+            .mount(server)
+            .await;
+    }
+
+    fn query_param(url: &str, name: &str) -> String {
+        reqwest::Url::parse(url)
+            .unwrap()
+            .query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_positive_login() {
+        let (server, client) = provider().await;
+
+        let session = client.get_authorize_url();
+        assert_eq!(
+            query_param(&session.authorize_url, "state"),
+            session.csrf_state
+        );
+        let nonce = query_param(&session.authorize_url, "nonce");
+
+        mount_token(
+            &server,
+            json!({ "iss": server.uri(), "aud": CLIENT_ID, "sub": "subject", "nonce": nonce }),
+        )
+        .await;
+
+        let login = client.take_pending(&session.csrf_state).unwrap();
+        let info = client
+            .exchange_code("code".to_string(), login)
+            .await
+            .unwrap();
+
+        assert_eq!(info.subject, "subject");
+        assert_eq!(info.username.as_deref(), Some("alice"));
+        assert_eq!(info.is_admin, Some(true));
+    }
+
+    #[tokio::test]
+    async fn test_negative_unknown_state() {
+        let (_server, client) = provider().await;
+
+        client.get_authorize_url();
+
+        assert!(client.take_pending("forged").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_negative_state_used_twice() {
+        let (_server, client) = provider().await;
+
+        let session = client.get_authorize_url();
+
+        assert!(client.take_pending(&session.csrf_state).is_some());
+        assert!(client.take_pending(&session.csrf_state).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_negative_nonce_mismatch() {
+        let (server, client) = provider().await;
+
+        let session = client.get_authorize_url();
+        mount_token(
+            &server,
+            json!({ "iss": server.uri(), "aud": CLIENT_ID, "sub": "subject", "nonce": "replayed" }),
+        )
+        .await;
+
+        let login = client.take_pending(&session.csrf_state).unwrap();
+
+        assert!(client
+            .exchange_code("code".to_string(), login)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_negative_audience_mismatch() {
+        let (server, client) = provider().await;
+
+        let session = client.get_authorize_url();
+        let nonce = query_param(&session.authorize_url, "nonce");
+        mount_token(
+            &server,
+            json!({ "iss": server.uri(), "aud": "other-client", "sub": "subject", "nonce": nonce }),
+        )
+        .await;
+
+        let login = client.take_pending(&session.csrf_state).unwrap();
+
+        assert!(client
+            .exchange_code("code".to_string(), login)
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,142 @@
+use std::net::{IpAddr, SocketAddr};
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, Extension, FromRequestParts},
+    http::request::Parts,
+    RequestPartsExt,
+};
+
+use crate::infrastructure::config::Config;
+
+/// Address of the client, `X-Forwarded-For` is only used when the request comes from one of
+/// `trusted_proxies`
+#[derive(Debug, Clone, Copy)]
+pub struct ClientAddr(pub IpAddr);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ClientAddr
+where
+    S: Send + Sync,
+{
+    type Rejection = ();
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let addr = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip())
+            .ok_or(())?;
+
+        let trusted = parts
+            .extract::<Extension<Config>>()
+            .await
+            .map(|Extension(config)| config.is_trusted_proxy(addr))
+            .unwrap_or(false);
+        if !trusted {
+            return Ok(ClientAddr(addr));
+        }
+
+        // last entry is the one appended by the trusted proxy
+        let forwarded = parts
+            .headers
+            .get("x-forwarded-for")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.rsplit(',').next())
+            .and_then(|value| value.trim().parse().ok());
+
+        Ok(ClientAddr(forwarded.unwrap_or(addr)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use axum::http::Request;
+
+    use super::*;
+
+    async fn client_addr(
+        peer: &str,
+        forwarded_for: Option<&str>,
+        trusted_proxies: &[&str],
+    ) -> Result<ClientAddr, ()> {
+        let mut builder = Request::builder();
+        if let Some(forwarded_for) = forwarded_for {
+            builder = builder.header("x-forwarded-for", forwarded_for);
+        }
+        let mut config = Config::default();
+        config.trusted_proxies = trusted_proxies
+            .iter()
+            .map(|net| net.parse().unwrap())
+            .collect();
+        let (mut parts, _) = builder
+            .extension(ConnectInfo(SocketAddr::new(peer.parse().unwrap(), 8080)))
+            .extension(config)
+            .body(())
+            .unwrap()
+            .into_parts();
+
+        ClientAddr::from_request_parts(&mut parts, &()).await
+    }
+
+    fn ip(addr: &str) -> IpAddr {
+        addr.parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_forwarded_for_from_trusted_proxy() {
+        let addr = client_addr("10.0.0.1", Some("1.1.1.1, 2.2.2.2"), &["10.0.0.0/8"])
+            .await
+            .unwrap();
+        assert_eq!(addr.0, ip("2.2.2.2"));
+
+        let addr = client_addr("10.0.0.1", Some("2001:db8::1"), &["10.0.0.0/8"])
+            .await
+            .unwrap();
+        assert_eq!(addr.0, ip("2001:db8::1"));
+
+        // ipv4 peer reported as ipv4-mapped ipv6 address
+        let addr = client_addr("::ffff:10.0.0.1", Some("2.2.2.2"), &["10.0.0.0/8"])
+            .await
+            .unwrap();
+        assert_eq!(addr.0, ip("2.2.2.2"));
+    }
+
+    #[tokio::test]
+    async fn test_forwarded_for_from_untrusted_peer_is_ignored() {
+        let addr = client_addr("192.168.1.1", Some("2.2.2.2"), &["10.0.0.0/8"])
+            .await
+            .unwrap();
+        assert_eq!(addr.0, ip("192.168.1.1"));
+
+        let addr = client_addr("10.0.0.1", Some("2.2.2.2"), &[]).await.unwrap();
+        assert_eq!(addr.0, ip("10.0.0.1"));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_forwarded_for_falls_back_to_peer() {
+        let addr = client_addr("10.0.0.1", Some("unknown"), &["10.0.0.0/8"])
+            .await
+            .unwrap();
+        assert_eq!(addr.0, ip("10.0.0.1"));
+
+        // a spoofed first entry doesn't help when the last one is invalid
+        let addr = client_addr("10.0.0.1", Some("2.2.2.2, "), &["10.0.0.0/8"])
+            .await
+            .unwrap();
+        assert_eq!(addr.0, ip("10.0.0.1"));
+
+        let addr = client_addr("10.0.0.1", None, &["10.0.0.0/8"])
+            .await
+            .unwrap();
+        assert_eq!(addr.0, ip("10.0.0.1"));
+    }
+
+    #[tokio::test]
+    async fn test_without_peer_address() {
+        let (mut parts, _) = Request::builder().body(()).unwrap().into_parts();
+        assert!(ClientAddr::from_request_parts(&mut parts, &())
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,116 @@
+use super::guard::SessionGuard;
+use crate::{
+    domain::{entities::api_key::ApiKeyScope, services::api_key::ApiKeyService},
+    infrastructure::{
+        auth::Claims,
+        domain::repositories::{api_key::ApiKeyRepositoryImpl, user::UserRepositoryImpl},
+    },
+};
+use async_graphql::{Context, Object, Result, SimpleObject};
+use chrono::NaiveDateTime;
+
+#[derive(Debug, SimpleObject)]
+pub struct ApiKey {
+    pub id: i64,
+    pub name: String,
+    /// first characters of the key
+    pub prefix: String,
+    pub scopes: Vec<String>,
+    pub created_at: NaiveDateTime,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+impl From<crate::domain::entities::api_key::ApiKey> for ApiKey {
+    fn from(key: crate::domain::entities::api_key::ApiKey) -> Self {
+        Self {
+            id: key.id,
+            name: key.name,
+            prefix: key.prefix,
+            scopes: key.scopes.iter().map(|scope| scope.to_string()).collect(),
+            created_at: key.created_at,
+            last_used_at: key.last_used_at,
+            expires_at: key.expires_at,
+        }
+    }
+}
+
+#[derive(Debug, SimpleObject)]
+pub struct CreatedApiKey {
+    pub api_key: ApiKey,
+    /// the key itself, only shown once
+    pub key: String,
+}
+
+#[derive(Default)]
+pub struct ApiKeyRoot;
+
+#[Object]
+impl ApiKeyRoot {
+    #[graphql(guard = "SessionGuard::new()")]
+    async fn api_keys(&self, ctx: &Context<'_>) -> Result<Vec<ApiKey>> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+
+        let keys = ctx
+            .data::<ApiKeyService<ApiKeyRepositoryImpl, UserRepositoryImpl>>()?
+            .get_api_keys(claims.sub)
+            .await?
+            .into_iter()
+            .map(ApiKey::from)
+            .collect();
+
+        Ok(keys)
+    }
+}
+
+#[derive(Default)]
+pub struct ApiKeyMutationRoot;
+
+#[Object]
+impl ApiKeyMutationRoot {
+    #[graphql(guard = "SessionGuard::new()")]
+    async fn create_api_key(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "name")] name: String,
+        #[graphql(desc = "library:read, history:write or downloads:admin")] scopes: Vec<String>,
+        #[graphql(desc = "expiry, never expire if empty")] expires_at: Option<NaiveDateTime>,
+    ) -> Result<CreatedApiKey> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+
+        let scopes = scopes
+            .iter()
+            .map(|scope| scope.parse::<ApiKeyScope>())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let (api_key, key) = ctx
+            .data::<ApiKeyService<ApiKeyRepositoryImpl, UserRepositoryImpl>>()?
+            .create_api_key(claims.sub, &name, &scopes, expires_at)
+            .await?;
+
+        Ok(CreatedApiKey {
+            api_key: api_key.into(),
+            key,
+        })
+    }
+
+    #[graphql(guard = "SessionGuard::new()")]
+    async fn delete_api_key(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "api key id")] id: i64,
+    ) -> Result<u64> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+
+        Ok(ctx
+            .data::<ApiKeyService<ApiKeyRepositoryImpl, UserRepositoryImpl>>()?
+            .delete_api_key(claims.sub, id)
+            .await?)
+    }
+}
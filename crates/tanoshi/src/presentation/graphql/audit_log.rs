@@ -0,0 +1,79 @@
+use super::guard::AdminGuard;
+use crate::{
+    domain::{entities::audit_log::AuditAction, services::audit_log::AuditLogService},
+    infrastructure::{auth::Claims, domain::repositories::audit_log::AuditLogRepositoryImpl},
+    presentation::client_addr::ClientAddr,
+};
+use async_graphql::{Context, Object, Result, SimpleObject};
+use chrono::NaiveDateTime;
+
+#[derive(Debug, SimpleObject)]
+pub struct AuditLog {
+    pub id: i64,
+    pub user_id: Option<i64>,
+    pub username: Option<String>,
+    pub action: String,
+    pub target: Option<String>,
+    pub ip: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+impl From<crate::domain::entities::audit_log::AuditLog> for AuditLog {
+    fn from(log: crate::domain::entities::audit_log::AuditLog) -> Self {
+        Self {
+            id: log.id,
+            user_id: log.user_id,
+            username: log.username,
+            action: log.action,
+            target: log.target,
+            ip: log.ip,
+            created_at: log.created_at,
+        }
+    }
+}
+
+/// Record an action performed by the logged in user
+pub async fn record_audit(ctx: &Context<'_>, action: AuditAction, target: Option<String>) {
+    let audit_log_svc = match ctx.data::<AuditLogService<AuditLogRepositoryImpl>>() {
+        Ok(svc) => svc,
+        Err(_) => return,
+    };
+
+    let claims = ctx.data_opt::<Claims>();
+    let ip = ctx.data_opt::<ClientAddr>().map(|ClientAddr(addr)| *addr);
+
+    audit_log_svc
+        .record(
+            claims.map(|claims| claims.sub),
+            claims.map(|claims| claims.username.as_str()),
+            action,
+            target.as_deref(),
+            ip,
+        )
+        .await;
+}
+
+#[derive(Default)]
+pub struct AuditLogRoot;
+
+#[Object]
+impl AuditLogRoot {
+    #[graphql(guard = "AdminGuard::new()")]
+    async fn audit_logs(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "filter by user id")] user_id: Option<i64>,
+        #[graphql(desc = "filter by action, e.g. login_failed")] action: Option<String>,
+        #[graphql(desc = "only entries at or after")] from: Option<NaiveDateTime>,
+        #[graphql(desc = "only entries before")] to: Option<NaiveDateTime>,
+        #[graphql(desc = "only entries older than this id, for next page")] before_id: Option<i64>,
+        #[graphql(desc = "max entries", default = 50)] limit: i64,
+    ) -> Result<Vec<AuditLog>> {
+        let logs = ctx
+            .data::<AuditLogService<AuditLogRepositoryImpl>>()?
+            .get_audit_logs(user_id, action.as_deref(), from, to, before_id, limit)
+            .await?;
+
+        Ok(logs.into_iter().map(|log| log.into()).collect())
+    }
+}
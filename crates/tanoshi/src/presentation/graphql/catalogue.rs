@@ -1,14 +1,52 @@
-use super::{chapter::Chapter, common::InputList, guard::AdminGuard, manga::Manga};
+use super::{
+    audit_log::record_audit,
+    chapter::Chapter,
+    common::InputList,
+    guard::{AdminGuard, SessionGuard},
+    manga::Manga,
+    source::Source,
+};
 
 use crate::{
-    domain::services::{chapter::ChapterService, manga::MangaService},
-    infrastructure::domain::repositories::{
-        chapter::ChapterRepositoryImpl, manga::MangaRepositoryImpl,
+    domain::{
+        entities::audit_log::AuditAction,
+        services::{
+            chapter::ChapterService, content_policy::ContentPolicyService, manga::MangaService,
+            source::SourceService,
+        },
+    },
+    infrastructure::{
+        auth::Claims,
+        domain::repositories::{
+            chapter::ChapterRepositoryImpl, content_policy::ContentPolicyRepositoryImpl,
+            manga::MangaRepositoryImpl, source::SourceRepositoryImpl,
+        },
     },
 };
 
-use async_graphql::{Context, Object, Result};
+use async_graphql::{Context, Object, Result, SimpleObject, Subscription};
+use futures::{Stream, StreamExt};
 use rayon::prelude::*;
+use std::{collections::HashMap, time::Duration};
+use tanoshi_lib::prelude::ResolvedUrl as SourcePath;
+
+/// Search result of one source
+#[derive(SimpleObject)]
+pub struct GlobalSearchResult {
+    pub source: Source,
+    /// first page of result, `isFavorite` marks manga already in library
+    pub manga: Vec<Manga>,
+    /// set when source failed or didn't respond in time
+    pub error: Option<String>,
+}
+
+/// Manga or chapter a url points to
+#[derive(SimpleObject)]
+pub struct ResolvedUrl {
+    pub manga: Manga,
+    /// set when url points to a chapter
+    pub chapter: Option<Chapter>,
+}
 
 #[derive(Default)]
 pub struct CatalogueRoot;
@@ -21,9 +59,23 @@ impl CatalogueRoot {
         #[graphql(desc = "source id")] source_id: i64,
         #[graphql(desc = "page")] page: i64,
     ) -> Result<Vec<Manga>> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+
+        let content_policy_svc =
+            ctx.data::<ContentPolicyService<ContentPolicyRepositoryImpl, SourceRepositoryImpl>>()?;
+        content_policy_svc
+            .check_source(claims.sub, source_id)
+            .await?;
+
         let fetched_manga = ctx
             .data::<MangaService<MangaRepositoryImpl>>()?
             .fetch_source_popular_manga(source_id, page)
+            .await?;
+
+        let fetched_manga = content_policy_svc
+            .filter_manga(claims.sub, fetched_manga)
             .await?
             .into_par_iter()
             .map(Manga::from)
@@ -37,9 +89,23 @@ impl CatalogueRoot {
         #[graphql(desc = "source id")] source_id: i64,
         #[graphql(desc = "page")] page: i64,
     ) -> Result<Vec<Manga>> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+
+        let content_policy_svc =
+            ctx.data::<ContentPolicyService<ContentPolicyRepositoryImpl, SourceRepositoryImpl>>()?;
+        content_policy_svc
+            .check_source(claims.sub, source_id)
+            .await?;
+
         let fetched_manga = ctx
             .data::<MangaService<MangaRepositoryImpl>>()?
             .fetch_source_latest_manga(source_id, page)
+            .await?;
+
+        let fetched_manga = content_policy_svc
+            .filter_manga(claims.sub, fetched_manga)
             .await?
             .into_par_iter()
             .map(Manga::from)
@@ -56,9 +122,23 @@ impl CatalogueRoot {
         #[graphql(desc = "query")] query: Option<String>,
         #[graphql(desc = "filters")] filters: Option<InputList>,
     ) -> Result<Vec<Manga>> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+
+        let content_policy_svc =
+            ctx.data::<ContentPolicyService<ContentPolicyRepositoryImpl, SourceRepositoryImpl>>()?;
+        content_policy_svc
+            .check_source(claims.sub, source_id)
+            .await?;
+
         let fetched_manga = ctx
             .data::<MangaService<MangaRepositoryImpl>>()?
             .fetch_source_manga(source_id, page, query, filters.map(|filters| filters.0))
+            .await?;
+
+        let fetched_manga = content_policy_svc
+            .filter_manga(claims.sub, fetched_manga)
             .await?
             .into_par_iter()
             .map(Manga::from)
@@ -73,25 +153,96 @@ impl CatalogueRoot {
         #[graphql(desc = "source id")] source_id: i64,
         #[graphql(desc = "path to manga in source")] path: String,
     ) -> Result<Manga> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+
         let manga = ctx
             .data::<MangaService<MangaRepositoryImpl>>()?
             .fetch_manga_by_source_path(source_id, &path)
             .await?;
 
+        ctx.data::<ContentPolicyService<ContentPolicyRepositoryImpl, SourceRepositoryImpl>>()?
+            .check_manga(claims.sub, &manga)
+            .await?;
+
         Ok(manga.into())
     }
 
+    async fn resolve_url(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "url of manga or chapter in source website")] url: String,
+    ) -> Result<ResolvedUrl> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+
+        let (source_id, path) = ctx
+            .data::<MangaService<MangaRepositoryImpl>>()?
+            .resolve_url(&url)
+            .await?;
+
+        let (manga_path, chapter_path) = match path {
+            SourcePath::Manga { path } => (path, None),
+            SourcePath::Chapter { manga_path, path } => (manga_path, Some(path)),
+        };
+
+        let manga = ctx
+            .data::<MangaService<MangaRepositoryImpl>>()?
+            .fetch_manga_by_source_path(source_id, &manga_path)
+            .await?;
+
+        ctx.data::<ContentPolicyService<ContentPolicyRepositoryImpl, SourceRepositoryImpl>>()?
+            .check_manga(claims.sub, &manga)
+            .await?;
+
+        let chapter = match chapter_path {
+            Some(chapter_path) => {
+                let chapter_svc = ctx.data::<ChapterService<ChapterRepositoryImpl>>()?;
+                let mut chapter = None;
+                // stored chapters may be outdated, fetch from source if not found
+                for refresh in [false, true] {
+                    chapter = chapter_svc
+                        .fetch_chapters_by_manga_id(source_id, &manga.path, manga.id, refresh)
+                        .await?
+                        .into_iter()
+                        .find(|chapter| chapter.path == chapter_path);
+                    if chapter.is_some() {
+                        break;
+                    }
+                }
+
+                Some(chapter.ok_or("chapter not found in manga")?.into())
+            }
+            None => None,
+        };
+
+        Ok(ResolvedUrl {
+            manga: manga.into(),
+            chapter,
+        })
+    }
+
     async fn manga(
         &self,
         ctx: &Context<'_>,
         #[graphql(desc = "manga id")] id: i64,
         #[graphql(desc = "refresh data from source", default = false)] refresh: bool,
     ) -> Result<Manga> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+
         let manga = ctx
             .data::<MangaService<MangaRepositoryImpl>>()?
             .fetch_manga_by_id(id, refresh)
             .await?;
 
+        ctx.data::<ContentPolicyService<ContentPolicyRepositoryImpl, SourceRepositoryImpl>>()?
+            .check_manga(claims.sub, &manga)
+            .await?;
+
         Ok(manga.into())
     }
 
@@ -100,13 +251,25 @@ impl CatalogueRoot {
         ctx: &Context<'_>,
         #[graphql(desc = "chapter id")] id: i64,
     ) -> Result<Chapter> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+
         let chapter = ctx
             .data::<ChapterService<ChapterRepositoryImpl>>()?
             .fetch_chapter_by_id(id)
-            .await?
-            .into();
+            .await?;
+
+        let manga = ctx
+            .data::<MangaService<MangaRepositoryImpl>>()?
+            .fetch_manga_by_id(chapter.manga_id, false)
+            .await?;
+
+        ctx.data::<ContentPolicyService<ContentPolicyRepositoryImpl, SourceRepositoryImpl>>()?
+            .check_manga(claims.sub, &manga)
+            .await?;
 
-        Ok(chapter)
+        Ok(chapter.into())
     }
 }
 
@@ -125,6 +288,78 @@ impl CatalogueMutationRoot {
             .delete_chapter(id)
             .await?;
 
+        record_audit(ctx, AuditAction::RemoveChapter, Some(id.to_string())).await;
+
         Ok(true)
     }
 }
+
+#[derive(Default)]
+pub struct CatalogueSubscriptionRoot;
+
+#[Subscription]
+impl CatalogueSubscriptionRoot {
+    /// Search installed sources at once, each source is sent as soon as it responds
+    #[graphql(guard = "SessionGuard::new()")]
+    async fn global_search(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "query")] query: String,
+        #[graphql(desc = "source ids, default to all")] source_ids: Option<Vec<i64>>,
+        #[graphql(desc = "seconds to wait for each source", default = 15)] timeout: i64,
+    ) -> Result<impl Stream<Item = GlobalSearchResult>> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+
+        let sources = ctx
+            .data::<SourceService<SourceRepositoryImpl>>()?
+            .get_installed_sources(false)
+            .await?;
+
+        let content_policy_svc =
+            ctx.data::<ContentPolicyService<ContentPolicyRepositoryImpl, SourceRepositoryImpl>>()?;
+        let policy = content_policy_svc.get_policy(claims.sub).await?;
+        let sources: HashMap<i64, Source> = content_policy_svc
+            .filter_sources(claims.sub, sources)
+            .await?
+            .into_iter()
+            .filter(|source| {
+                source_ids
+                    .as_ref()
+                    .map(|ids| ids.contains(&source.id))
+                    .unwrap_or(true)
+            })
+            .map(|source| (source.id, Source::from(source)))
+            .collect();
+
+        let mut source_ids: Vec<i64> = sources.keys().copied().collect();
+        source_ids.sort_unstable();
+
+        let stream = ctx
+            .data::<MangaService<MangaRepositoryImpl>>()?
+            .global_search(
+                source_ids,
+                &query,
+                Duration::from_secs(timeout.clamp(1, 60) as u64),
+            )?
+            .filter_map(move |result| {
+                let source = sources.get(&result.source_id).cloned();
+                let policy = policy.clone();
+                async move {
+                    Some(GlobalSearchResult {
+                        source: source?,
+                        manga: result
+                            .manga
+                            .into_iter()
+                            .filter(|manga| policy.is_genre_allowed(&manga.genre))
+                            .map(Manga::from)
+                            .collect(),
+                        error: result.error,
+                    })
+                }
+            });
+
+        Ok(stream)
+    }
+}
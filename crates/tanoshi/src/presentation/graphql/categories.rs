@@ -1,5 +1,6 @@
+use super::guard::{ScopeGuard, SessionGuard};
 use crate::{
-    domain::services::library::LibraryService,
+    domain::{entities::api_key::ApiKeyScope, services::library::LibraryService},
     infrastructure::{auth::Claims, domain::repositories::library::LibraryRepositoryImpl},
     presentation::graphql::{loader::UserCategoryId, schema::DatabaseLoader},
 };
@@ -10,6 +11,7 @@ use rayon::iter::{IntoParallelIterator, ParallelIterator};
 pub struct Category {
     id: Option<i64>,
     name: String,
+    rule: Option<String>,
 }
 
 impl Default for Category {
@@ -17,6 +19,7 @@ impl Default for Category {
         Self {
             id: None,
             name: "Default".to_string(),
+            rule: None,
         }
     }
 }
@@ -25,6 +28,7 @@ impl From<crate::domain::entities::library::Category> for Category {
         Self {
             id: val.id,
             name: val.name,
+            rule: val.rule,
         }
     }
 }
@@ -39,6 +43,11 @@ impl Category {
         self.name.clone()
     }
 
+    /// Filter expression of a smart category, null for categories managed by hand
+    async fn rule(&self) -> Option<String> {
+        self.rule.clone()
+    }
+
     async fn count(&self, ctx: &Context<'_>) -> Result<i64> {
         let claims = ctx
             .data::<Claims>()
@@ -57,6 +66,7 @@ pub struct CategoryRoot;
 
 #[Object]
 impl CategoryRoot {
+    #[graphql(guard = "ScopeGuard::new(ApiKeyScope::LibraryRead)")]
     async fn get_categories(&self, ctx: &Context<'_>) -> Result<Vec<Category>> {
         let claims = ctx
             .data::<Claims>()
@@ -73,6 +83,7 @@ impl CategoryRoot {
         Ok(categories)
     }
 
+    #[graphql(guard = "ScopeGuard::new(ApiKeyScope::LibraryRead)")]
     async fn get_category(&self, ctx: &Context<'_>, id: Option<i64>) -> Result<Category> {
         let _ = ctx
             .data::<Claims>()
@@ -93,10 +104,15 @@ pub struct CategoryMutationRoot;
 
 #[Object]
 impl CategoryMutationRoot {
+    #[graphql(guard = "SessionGuard::new()")]
     async fn create_category(
         &self,
         ctx: &Context<'_>,
         #[graphql(desc = "category name")] name: String,
+        #[graphql(
+            desc = "filter expression for a smart category, e.g. `unread > 0 AND status = \"Ongoing\"` or `days_since_read >= 60`"
+        )]
+        rule: Option<String>,
     ) -> Result<Category> {
         let claims = ctx
             .data::<Claims>()
@@ -104,18 +120,22 @@ impl CategoryMutationRoot {
 
         let category = ctx
             .data::<LibraryService<LibraryRepositoryImpl>>()?
-            .create_category(claims.sub, &name)
+            .create_category(claims.sub, &name, rule.as_deref())
             .await?
             .into();
 
         Ok(category)
     }
 
+    #[graphql(guard = "SessionGuard::new()")]
     async fn update_category(
         &self,
         ctx: &Context<'_>,
         #[graphql(desc = "category id")] id: i64,
         #[graphql(desc = "category name")] name: String,
+        #[graphql(desc = "filter expression, turns category into a smart category")] rule: Option<
+            String,
+        >,
     ) -> Result<Category> {
         let _ = ctx
             .data::<Claims>()
@@ -123,13 +143,14 @@ impl CategoryMutationRoot {
 
         let category = ctx
             .data::<LibraryService<LibraryRepositoryImpl>>()?
-            .rename_category(id, &name)
+            .update_category(id, &name, rule.as_deref())
             .await?
             .into();
 
         Ok(category)
     }
 
+    #[graphql(guard = "SessionGuard::new()")]
     async fn delete_category(
         &self,
         ctx: &Context<'_>,
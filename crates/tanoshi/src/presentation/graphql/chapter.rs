@@ -6,7 +6,11 @@ use super::{
     source::Source,
 };
 use crate::{
-    domain::services::{chapter::ChapterService, image::ImageService, source::SourceService},
+    application::worker::prefetch::{Command as PrefetchCommand, PrefetchSender},
+    domain::{
+        entities::image::ImageOrigin,
+        services::{chapter::ChapterService, image::ImageService, source::SourceService},
+    },
     infrastructure::{
         auth::Claims,
         config::Config,
@@ -179,14 +183,35 @@ impl Chapter {
             .fetch_chapter_pages(self.source_id, &self.path, &self.downloaded_path)
             .await?;
 
+        let config = ctx.data::<Config>()?;
+        if config.prefetch.enabled && self.id > 0 {
+            if let Some(claims) = ctx.data_opt::<Claims>() {
+                let command = PrefetchCommand::Chapter {
+                    user_id: claims.sub,
+                    chapter_id: self.id,
+                    page: 0,
+                    pages: Some(pages.clone()),
+                };
+                if let Err(e) = ctx.data::<PrefetchSender>()?.send(command) {
+                    error!("failed to send prefetch command: {e}");
+                }
+            }
+        }
+
         let image_svc =
             ctx.data::<ImageService<ImageCacheRepositoryImpl, ImageRepositoryImpl>>()?;
 
         if encrypt {
-            let secret = &ctx.data::<Config>()?.secret;
-            pages
-                .par_iter_mut()
-                .for_each(|p| *p = image_svc.encrypt_image_url(secret, p).unwrap());
+            let claims = ctx
+                .data::<Claims>()
+                .map_err(|_| "token not exists, please login")?;
+            let secret = &config.secret;
+            let origin = ImageOrigin::page(self.source_id, self.manga_id);
+            pages.par_iter_mut().for_each(|p| {
+                *p = image_svc
+                    .encrypt_image_url(secret, p, origin, claims.sub)
+                    .unwrap()
+            });
         }
 
         Ok(pages)
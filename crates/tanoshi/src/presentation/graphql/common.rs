@@ -4,6 +4,8 @@ use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 use tanoshi_lib::prelude::Input;
 
+use crate::domain::entities::library::{LibraryPosition, LibrarySortValue};
+
 pub struct Cursor(pub i64, pub i64);
 
 impl CursorType for Cursor {
@@ -22,6 +24,43 @@ impl CursorType for Cursor {
     }
 }
 
+/// Cursor of library connection, encodes sort value, title and id of a manga as a json array
+pub struct LibraryCursor(pub LibraryPosition);
+
+impl CursorType for LibraryCursor {
+    type Error = anyhow::Error;
+
+    fn decode_cursor(s: &str) -> Result<Self, Self::Error> {
+        let (sort_value, title, id): (serde_json::Value, String, i64) =
+            serde_json::from_slice(&general_purpose::STANDARD.decode(s)?)?;
+        let sort_value = match sort_value {
+            serde_json::Value::Number(value) => LibrarySortValue::Integer(
+                value
+                    .as_i64()
+                    .ok_or_else(|| anyhow::anyhow!("invalid cursor"))?,
+            ),
+            serde_json::Value::String(value) => LibrarySortValue::Text(value),
+            _ => return Err(anyhow::anyhow!("invalid cursor")),
+        };
+
+        Ok(Self(LibraryPosition {
+            sort_value,
+            title,
+            id,
+        }))
+    }
+
+    fn encode_cursor(&self) -> String {
+        let sort_value = match &self.0.sort_value {
+            LibrarySortValue::Integer(value) => serde_json::Value::from(*value),
+            LibrarySortValue::Text(value) => serde_json::Value::from(value.as_str()),
+        };
+        let cursor = serde_json::json!([sort_value, self.0.title, self.0.id]);
+
+        general_purpose::STANDARD.encode(cursor.to_string())
+    }
+}
+
 #[derive(Debug, Clone, SimpleObject)]
 pub struct ReadProgress {
     pub at: NaiveDateTime,
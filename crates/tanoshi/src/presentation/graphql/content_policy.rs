@@ -0,0 +1,108 @@
+use super::{audit_log::record_audit, guard::AdminGuard};
+use crate::{
+    domain::{entities::audit_log::AuditAction, services::content_policy::ContentPolicyService},
+    infrastructure::domain::repositories::{
+        content_policy::ContentPolicyRepositoryImpl, source::SourceRepositoryImpl,
+    },
+};
+use async_graphql::{Context, InputObject, Object, Result, SimpleObject};
+use chrono::NaiveDateTime;
+
+#[derive(Debug, SimpleObject)]
+pub struct ContentPolicy {
+    pub user_id: i64,
+    pub hide_nsfw: bool,
+    /// null means every source is allowed
+    pub allowed_source_ids: Option<Vec<i64>>,
+    pub denied_source_ids: Vec<i64>,
+    pub blocked_genres: Vec<String>,
+    pub updated_at: NaiveDateTime,
+}
+
+impl From<crate::domain::entities::content_policy::ContentPolicy> for ContentPolicy {
+    fn from(policy: crate::domain::entities::content_policy::ContentPolicy) -> Self {
+        Self {
+            user_id: policy.user_id,
+            hide_nsfw: policy.hide_nsfw,
+            allowed_source_ids: policy.allowed_source_ids,
+            denied_source_ids: policy.denied_source_ids,
+            blocked_genres: policy.blocked_genres,
+            updated_at: policy.updated_at,
+        }
+    }
+}
+
+#[derive(InputObject)]
+struct ContentPolicyInput {
+    #[graphql(default)]
+    hide_nsfw: bool,
+    allowed_source_ids: Option<Vec<i64>>,
+    #[graphql(default)]
+    denied_source_ids: Vec<i64>,
+    #[graphql(default)]
+    blocked_genres: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct ContentPolicyRoot;
+
+#[Object]
+impl ContentPolicyRoot {
+    #[graphql(guard = "AdminGuard::new()")]
+    async fn user_content_policy(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "user id")] user_id: i64,
+    ) -> Result<ContentPolicy> {
+        let policy = ctx
+            .data::<ContentPolicyService<ContentPolicyRepositoryImpl, SourceRepositoryImpl>>()?
+            .get_policy(user_id)
+            .await?;
+
+        Ok(policy.into())
+    }
+}
+
+#[derive(Default)]
+pub struct ContentPolicyMutationRoot;
+
+#[Object]
+impl ContentPolicyMutationRoot {
+    #[graphql(guard = "AdminGuard::new()")]
+    async fn set_user_content_policy(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "user id")] user_id: i64,
+        input: ContentPolicyInput,
+    ) -> Result<ContentPolicy> {
+        let content_policy_svc =
+            ctx.data::<ContentPolicyService<ContentPolicyRepositoryImpl, SourceRepositoryImpl>>()?;
+
+        let blocked_genres = input
+            .blocked_genres
+            .into_iter()
+            .map(|genre| genre.trim().to_string())
+            .filter(|genre| !genre.is_empty())
+            .collect();
+
+        content_policy_svc
+            .set_policy(crate::domain::entities::content_policy::ContentPolicy {
+                user_id,
+                hide_nsfw: input.hide_nsfw,
+                allowed_source_ids: input.allowed_source_ids,
+                denied_source_ids: input.denied_source_ids,
+                blocked_genres,
+                ..Default::default()
+            })
+            .await?;
+
+        record_audit(
+            ctx,
+            AuditAction::SetUserContentPolicy,
+            Some(user_id.to_string()),
+        )
+        .await;
+
+        Ok(content_policy_svc.get_policy(user_id).await?.into())
+    }
+}
@@ -1,6 +1,14 @@
-use super::{chapter::Chapter, common::Cursor, guard::AdminGuard};
+use super::{
+    audit_log::record_audit,
+    chapter::Chapter,
+    common::Cursor,
+    guard::{ScopeGuard, SessionGuard},
+};
 use crate::{
-    domain::services::download::DownloadService,
+    domain::{
+        entities::{api_key::ApiKeyScope, audit_log::AuditAction},
+        services::download::DownloadService,
+    },
     infrastructure::{config::Config, domain::repositories::download::DownloadRepositoryImpl},
 };
 use async_graphql::{
@@ -8,6 +16,7 @@ use async_graphql::{
     Context, Error, Object, Result, SimpleObject,
 };
 use chrono::Utc;
+use itertools::Itertools;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 
 #[derive(Debug, SimpleObject)]
@@ -54,7 +63,7 @@ impl DownloadRoot {
         Ok(status)
     }
 
-    #[graphql(guard = "AdminGuard::new()")]
+    #[graphql(guard = "ScopeGuard::new(ApiKeyScope::DownloadsAdmin)")]
     async fn download_queue(&self, ctx: &Context<'_>) -> Result<Vec<DownloadQueueEntry>> {
         let queue = ctx
             .data::<DownloadService<DownloadRepositoryImpl>>()?
@@ -67,7 +76,7 @@ impl DownloadRoot {
         Ok(queue)
     }
 
-    #[graphql(guard = "AdminGuard::new()")]
+    #[graphql(guard = "ScopeGuard::new(ApiKeyScope::DownloadsAdmin)")]
     async fn get_downloaded_chapters(
         &self,
         ctx: &Context<'_>,
@@ -162,6 +171,7 @@ pub struct DownloadMutationRoot;
 
 #[Object]
 impl DownloadMutationRoot {
+    #[graphql(guard = "SessionGuard::new()")]
     async fn pause_download(&self, ctx: &Context<'_>) -> Result<bool> {
         let download_path = &ctx.data::<Config>()?.download_path;
 
@@ -172,6 +182,7 @@ impl DownloadMutationRoot {
         Ok(true)
     }
 
+    #[graphql(guard = "SessionGuard::new()")]
     async fn resume_download(&self, ctx: &Context<'_>) -> Result<bool> {
         let download_path = &ctx.data::<Config>()?.download_path;
 
@@ -182,7 +193,7 @@ impl DownloadMutationRoot {
         Ok(true)
     }
 
-    #[graphql(guard = "AdminGuard::new()")]
+    #[graphql(guard = "ScopeGuard::new(ApiKeyScope::DownloadsAdmin)")]
     async fn download_chapters(&self, ctx: &Context<'_>, ids: Vec<i64>) -> Result<i64> {
         let len = ids.len() as i64;
         ctx.data::<DownloadService<DownloadRepositoryImpl>>()?
@@ -192,7 +203,7 @@ impl DownloadMutationRoot {
         Ok(len)
     }
 
-    #[graphql(guard = "AdminGuard::new()")]
+    #[graphql(guard = "ScopeGuard::new(ApiKeyScope::DownloadsAdmin)")]
     async fn remove_chapters_from_queue(&self, ctx: &Context<'_>, ids: Vec<i64>) -> Result<i64> {
         let len = ids.len() as i64;
         ctx.data::<DownloadService<DownloadRepositoryImpl>>()?
@@ -202,17 +213,20 @@ impl DownloadMutationRoot {
         Ok(len)
     }
 
-    #[graphql(guard = "AdminGuard::new()")]
+    #[graphql(guard = "ScopeGuard::new(ApiKeyScope::DownloadsAdmin)")]
     async fn remove_downloaded_chapters(&self, ctx: &Context<'_>, ids: Vec<i64>) -> Result<i64> {
         let len = ids.len() as i64;
+        let target = ids.iter().join(",");
         ctx.data::<DownloadService<DownloadRepositoryImpl>>()?
             .remove_downloaded_chapters(ids)
             .await?;
 
+        record_audit(ctx, AuditAction::RemoveDownloadedChapters, Some(target)).await;
+
         Ok(len)
     }
 
-    #[graphql(guard = "AdminGuard::new()")]
+    #[graphql(guard = "ScopeGuard::new(ApiKeyScope::DownloadsAdmin)")]
     async fn update_chapter_priority(
         &self,
         ctx: &Context<'_>,
@@ -1,6 +1,6 @@
 use async_graphql::{Context, Guard, Result};
 
-use crate::infrastructure::auth::Claims;
+use crate::{domain::entities::api_key::ApiKeyScope, infrastructure::auth::Claims};
 
 #[derive(Debug, Default)]
 pub struct AdminGuard;
@@ -18,10 +18,67 @@ impl Guard for AdminGuard {
             .data::<Claims>()
             .map_err(|_| "token not exists, please login")?;
 
-        if claims.is_admin {
+        // api key only gets admin rights through scope
+        if claims.is_admin && !claims.is_api_key() {
             return Ok(());
         }
 
         Err("Forbidden".into())
     }
 }
+
+/// Allow login sessions and api keys granted the scope, admin scopes also require admin
+#[derive(Debug)]
+pub struct ScopeGuard {
+    scope: ApiKeyScope,
+}
+
+impl ScopeGuard {
+    pub fn new(scope: ApiKeyScope) -> Self {
+        Self { scope }
+    }
+}
+
+#[async_trait::async_trait]
+impl Guard for ScopeGuard {
+    async fn check(&self, ctx: &Context<'_>) -> Result<()> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+
+        if self.scope.requires_admin() && !claims.is_admin {
+            return Err("Forbidden".into());
+        }
+
+        if !claims.has_scope(self.scope) {
+            return Err(format!("api key missing scope {}", self.scope).into());
+        }
+
+        Ok(())
+    }
+}
+
+/// Only allow login sessions, api keys are rejected
+#[derive(Debug, Default)]
+pub struct SessionGuard;
+
+impl SessionGuard {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait::async_trait]
+impl Guard for SessionGuard {
+    async fn check(&self, ctx: &Context<'_>) -> Result<()> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+
+        if claims.is_api_key() {
+            return Err("Forbidden for api key".into());
+        }
+
+        Ok(())
+    }
+}
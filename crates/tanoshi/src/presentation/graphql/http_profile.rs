@@ -0,0 +1,132 @@
+use super::{audit_log::record_audit, guard::AdminGuard};
+use crate::{
+    domain::{entities::audit_log::AuditAction, services::http_profile::HttpProfileService},
+    infrastructure::domain::repositories::http_profile::HttpProfileRepositoryImpl,
+};
+use async_graphql::{Context, InputObject, Object, Result, SimpleObject};
+
+#[derive(Debug, SimpleObject, InputObject)]
+#[graphql(input_name = "HttpHeaderInput")]
+pub struct HttpHeader {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug, SimpleObject)]
+pub struct HttpProfile {
+    pub proxy: Option<String>,
+    pub user_agent: Option<String>,
+    pub headers: Vec<HttpHeader>,
+}
+
+impl From<tanoshi_lib::prelude::HttpProfile> for HttpProfile {
+    fn from(profile: tanoshi_lib::prelude::HttpProfile) -> Self {
+        let mut headers: Vec<HttpHeader> = profile
+            .headers
+            .into_iter()
+            .map(|(name, value)| HttpHeader { name, value })
+            .collect();
+        headers.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Self {
+            proxy: profile.proxy,
+            user_agent: profile.user_agent,
+            headers,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct HttpProfileRoot;
+
+#[Object]
+impl HttpProfileRoot {
+    /// Profile saved for a source, null if the source only uses config and extension values
+    #[graphql(guard = "AdminGuard::new()")]
+    async fn source_http_profile(
+        &self,
+        ctx: &Context<'_>,
+        source_id: i64,
+    ) -> Result<Option<HttpProfile>> {
+        let profile = ctx
+            .data::<HttpProfileService<HttpProfileRepositoryImpl>>()?
+            .get_source_http_profile(source_id)
+            .await?;
+
+        Ok(profile.map(HttpProfile::from))
+    }
+
+    /// Profile used when requesting a source, merged from extension, config and saved profile
+    #[graphql(guard = "AdminGuard::new()")]
+    async fn effective_http_profile(
+        &self,
+        ctx: &Context<'_>,
+        source_id: i64,
+    ) -> Result<HttpProfile> {
+        let profile = ctx
+            .data::<HttpProfileService<HttpProfileRepositoryImpl>>()?
+            .get_effective_http_profile(source_id)?;
+
+        Ok(profile.into())
+    }
+}
+
+#[derive(Default)]
+pub struct HttpProfileMutationRoot;
+
+#[Object]
+impl HttpProfileMutationRoot {
+    /// `proxy` and `user_agent` can be `none` to not use the value from config
+    #[graphql(guard = "AdminGuard::new()")]
+    async fn set_source_http_profile(
+        &self,
+        ctx: &Context<'_>,
+        source_id: i64,
+        proxy: Option<String>,
+        user_agent: Option<String>,
+        #[graphql(default)] headers: Vec<HttpHeader>,
+    ) -> Result<i64> {
+        if let Some(proxy) = proxy
+            .as_ref()
+            .filter(|proxy| !proxy.eq_ignore_ascii_case(tanoshi_lib::prelude::HttpProfile::NONE))
+        {
+            reqwest::Proxy::all(proxy)?;
+        }
+
+        let profile = tanoshi_lib::prelude::HttpProfile {
+            proxy,
+            user_agent,
+            headers: headers.into_iter().map(|h| (h.name, h.value)).collect(),
+            cookie_jar: None,
+        };
+
+        ctx.data::<HttpProfileService<HttpProfileRepositoryImpl>>()?
+            .set_source_http_profile(source_id, profile)
+            .await?;
+
+        record_audit(
+            ctx,
+            AuditAction::SetSourceHttpProfile,
+            Some(source_id.to_string()),
+        )
+        .await;
+
+        Ok(source_id)
+    }
+
+    #[graphql(guard = "AdminGuard::new()")]
+    async fn reset_source_http_profile(&self, ctx: &Context<'_>, source_id: i64) -> Result<i64> {
+        ctx.data::<HttpProfileService<HttpProfileRepositoryImpl>>()?
+            .delete_source_http_profile(source_id)
+            .await?;
+
+        record_audit(
+            ctx,
+            AuditAction::ResetSourceHttpProfile,
+            Some(source_id.to_string()),
+        )
+        .await;
+
+        Ok(source_id)
+    }
+}
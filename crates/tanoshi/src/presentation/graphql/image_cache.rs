@@ -0,0 +1,82 @@
+use super::{audit_log::record_audit, guard::AdminGuard};
+use crate::{
+    domain::{entities::audit_log::AuditAction, services::image::ImageService},
+    infrastructure::domain::repositories::{
+        image::ImageRepositoryImpl, image_cache::ImageCacheRepositoryImpl,
+    },
+};
+use async_graphql::{Context, Object, Result, SimpleObject};
+
+#[derive(Debug, SimpleObject)]
+pub struct ImageCacheStats {
+    pub entries: i64,
+    pub bytes: i64,
+    pub max_bytes: i64,
+    pub cover_entries: i64,
+    pub cover_bytes: i64,
+    pub hits: i64,
+    pub misses: i64,
+}
+
+impl From<crate::domain::entities::image::ImageCacheStats> for ImageCacheStats {
+    fn from(stats: crate::domain::entities::image::ImageCacheStats) -> Self {
+        Self {
+            entries: stats.entries,
+            bytes: stats.bytes,
+            max_bytes: stats.max_bytes,
+            cover_entries: stats.cover_entries,
+            cover_bytes: stats.cover_bytes,
+            hits: stats.hits,
+            misses: stats.misses,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ImageCacheRoot;
+
+#[Object]
+impl ImageCacheRoot {
+    #[graphql(guard = "AdminGuard::new()")]
+    async fn image_cache_stats(&self, ctx: &Context<'_>) -> Result<ImageCacheStats> {
+        let stats = ctx
+            .data::<ImageService<ImageCacheRepositoryImpl, ImageRepositoryImpl>>()?
+            .get_cache_stats()
+            .await?;
+
+        Ok(stats.into())
+    }
+}
+
+#[derive(Default)]
+pub struct ImageCacheMutationRoot;
+
+#[Object]
+impl ImageCacheMutationRoot {
+    #[graphql(guard = "AdminGuard::new()")]
+    async fn purge_image_cache(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "purge cached images of a manga")] manga_id: Option<i64>,
+        #[graphql(desc = "purge cached images of a source")] source_id: Option<i64>,
+    ) -> Result<u64> {
+        let image_svc =
+            ctx.data::<ImageService<ImageCacheRepositoryImpl, ImageRepositoryImpl>>()?;
+
+        let (removed, target) = match (manga_id, source_id) {
+            (Some(manga_id), _) => (
+                image_svc.purge_cache_by_manga_id(manga_id).await?,
+                format!("manga:{manga_id}"),
+            ),
+            (None, Some(source_id)) => (
+                image_svc.purge_cache_by_source_id(source_id).await?,
+                format!("source:{source_id}"),
+            ),
+            (None, None) => return Err("mangaId or sourceId is required".into()),
+        };
+
+        record_audit(ctx, AuditAction::PurgeImageCache, Some(target)).await;
+
+        Ok(removed)
+    }
+}
@@ -1,40 +1,122 @@
 use super::{
-    common::Cursor,
+    common::{Cursor, LibraryCursor},
+    guard::{ScopeGuard, SessionGuard},
     manga::Manga,
     recent::{RecentChapter, RecentUpdate},
 };
 use crate::{
-    application::worker::updates::{
-        ChapterUpdateCommand, ChapterUpdateCommandSender, ChapterUpdateReceiver,
+    application::worker::{
+        prefetch::{Command as PrefetchCommand, PrefetchSender},
+        updates::{ChapterUpdateCommand, ChapterUpdateCommandSender, ChapterUpdateReceiver},
     },
-    domain::services::{
-        chapter::ChapterService, history::HistoryService, library::LibraryService,
-        tracker::TrackerService,
+    domain::{
+        entities::{
+            api_key::ApiKeyScope,
+            library::{LibraryFilter, LibrarySort, LibrarySortBy, TagCount},
+            manga::MangaSearchFilter,
+        },
+        services::{
+            chapter::ChapterService, content_policy::ContentPolicyService, history::HistoryService,
+            library::LibraryService, manga::MangaService, tracker::TrackerService,
+        },
     },
     infrastructure::{
         auth::Claims,
+        config::Config,
         domain::repositories::{
-            chapter::ChapterRepositoryImpl, history::HistoryRepositoryImpl,
-            library::LibraryRepositoryImpl, tracker::TrackerRepositoryImpl,
+            chapter::ChapterRepositoryImpl, content_policy::ContentPolicyRepositoryImpl,
+            history::HistoryRepositoryImpl, library::LibraryRepositoryImpl,
+            manga::MangaRepositoryImpl, source::SourceRepositoryImpl,
+            tracker::TrackerRepositoryImpl,
         },
     },
 };
 use async_graphql::{
     connection::{query, Connection, Edge, EmptyFields},
-    Error, Subscription,
+    Error, InputObject, SimpleObject, Subscription,
 };
 use async_graphql::{Context, Object, Result};
 use chrono::Utc;
+use std::collections::HashSet;
 
 use flume::TrySendError;
 use futures::{Stream, StreamExt};
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 
+type ContentPolicySvc = ContentPolicyService<ContentPolicyRepositoryImpl, SourceRepositoryImpl>;
+
+/// Ids of manga the content policy of the user allows
+async fn allowed_manga_ids(
+    manga_svc: &MangaService<MangaRepositoryImpl>,
+    content_policy_svc: &ContentPolicySvc,
+    user_id: i64,
+    manga_ids: &[i64],
+) -> Result<HashSet<i64>> {
+    let manga = manga_svc.fetch_manga_by_ids(manga_ids).await?;
+
+    Ok(content_policy_svc
+        .filter_manga(user_id, manga)
+        .await?
+        .into_iter()
+        .map(|m| m.id)
+        .collect())
+}
+
+#[derive(Default, InputObject)]
+pub struct LibraryFilterInput {
+    /// true for manga with unread chapters, false for fully read manga
+    pub unread: Option<bool>,
+    /// true for manga with downloaded chapters
+    pub downloaded: Option<bool>,
+    /// true for manga linked to a tracker
+    pub tracked: Option<bool>,
+    pub status: Option<String>,
+    pub source_id: Option<i64>,
+    pub genre: Option<String>,
+    /// personal tag
+    pub tag: Option<String>,
+    /// manga rated at least this much
+    pub min_rating: Option<i64>,
+}
+
+impl From<LibraryFilterInput> for LibraryFilter {
+    fn from(input: LibraryFilterInput) -> Self {
+        Self {
+            unread: input.unread,
+            downloaded: input.downloaded,
+            tracked: input.tracked,
+            status: input.status,
+            source_id: input.source_id,
+            genre: input.genre,
+            tag: input.tag,
+            min_rating: input.min_rating,
+            restriction: Default::default(),
+        }
+    }
+}
+
+/// Personal tag and number of manga tagged with it
+#[derive(Debug, SimpleObject)]
+pub struct Tag {
+    pub name: String,
+    pub count: i64,
+}
+
+impl From<TagCount> for Tag {
+    fn from(val: TagCount) -> Self {
+        Self {
+            name: val.tag,
+            count: val.count,
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct LibraryRoot;
 
 #[Object(cache_control(max_age = 30, private))]
 impl LibraryRoot {
+    #[graphql(guard = "ScopeGuard::new(ApiKeyScope::LibraryRead)")]
     async fn library(
         &self,
         ctx: &Context<'_>,
@@ -47,6 +129,11 @@ impl LibraryRoot {
         let manga = ctx
             .data::<LibraryService<LibraryRepositoryImpl>>()?
             .get_manga_from_library_by_category_id(claims.sub, category_id)
+            .await?;
+
+        let manga = ctx
+            .data::<ContentPolicySvc>()?
+            .filter_manga(claims.sub, manga)
             .await?
             .into_par_iter()
             .map(|m| m.into())
@@ -55,6 +142,163 @@ impl LibraryRoot {
         Ok(manga)
     }
 
+    /// Sorted and filtered library, paginated forward with `after` and `first`
+    #[graphql(guard = "ScopeGuard::new(ApiKeyScope::LibraryRead)")]
+    #[allow(clippy::too_many_arguments)]
+    async fn library_connection(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "category id")] category_id: Option<i64>,
+        #[graphql(desc = "title, last_read, last_updated, date_added or unread_count")]
+        sort_by: Option<String>,
+        #[graphql(desc = "sort descending", default = false)] descending: bool,
+        filter: Option<LibraryFilterInput>,
+        after: Option<String>,
+        first: Option<i32>,
+    ) -> Result<Connection<LibraryCursor, Manga, EmptyFields, EmptyFields>> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+
+        let sort = LibrarySort {
+            by: sort_by
+                .as_deref()
+                .map(str::parse::<LibrarySortBy>)
+                .transpose()?
+                .unwrap_or_default(),
+            descending,
+        };
+        let mut filter: LibraryFilter = filter.map(Into::into).unwrap_or_default();
+        filter.restriction = ctx
+            .data::<ContentPolicySvc>()?
+            .get_restriction(claims.sub)
+            .await?;
+
+        let library_svc = ctx.data::<LibraryService<LibraryRepositoryImpl>>()?;
+
+        query(
+            after,
+            None,
+            first,
+            None,
+            |after: Option<LibraryCursor>, _before: Option<LibraryCursor>, first, _last| async move {
+                let after = after.map(|cursor| cursor.0);
+                let first = first.unwrap_or(50).clamp(1, 200) as i64;
+
+                // one extra to know if there is a next page, content policy is applied by the
+                // query so a page is only short when it is the last one
+                let mut page = library_svc
+                    .get_manga_from_library_page(
+                        claims.sub,
+                        category_id,
+                        sort,
+                        &filter,
+                        after.as_ref(),
+                        first + 1,
+                    )
+                    .await?;
+                let has_next_page = page.len() as i64 > first;
+                page.truncate(first as usize);
+
+                let mut connection = Connection::new(after.is_some(), has_next_page);
+                connection.edges.extend(
+                    page.into_iter()
+                        .map(|(position, m)| Edge::new(LibraryCursor(position), m.into())),
+                );
+
+                Ok::<_, Error>(connection)
+            },
+        )
+        .await
+    }
+
+    /// Full-text search on title, author, genre and description, best match first
+    #[graphql(guard = "ScopeGuard::new(ApiKeyScope::LibraryRead)")]
+    #[allow(clippy::too_many_arguments)]
+    async fn search_library(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "search query")] query: String,
+        #[graphql(desc = "search every manga known to server", default = false)]
+        include_catalogue: bool,
+        #[graphql(desc = "category id")] category_id: Option<i64>,
+        #[graphql(desc = "source id")] source_id: Option<i64>,
+        #[graphql(desc = "manga status, e.g. ongoing")] status: Option<String>,
+        #[graphql(desc = "genre")] genre: Option<String>,
+        #[graphql(desc = "personal tag")] tag: Option<String>,
+        #[graphql(desc = "only manga with unread chapters", default = false)] unread_only: bool,
+        #[graphql(desc = "max results", default = 50)] limit: i64,
+    ) -> Result<Vec<Manga>> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+
+        let library_svc = ctx.data::<LibraryService<LibraryRepositoryImpl>>()?;
+
+        // smart categories have no members of their own, results are matched against their rule
+        let mut smart_category_manga = None;
+        if let Some(id) = category_id {
+            if library_svc
+                .get_category_by_id(Some(id))
+                .await?
+                .rule
+                .is_some()
+            {
+                let ids: Vec<i64> = library_svc
+                    .get_manga_from_library_by_category_id(claims.sub, Some(id))
+                    .await?
+                    .into_iter()
+                    .map(|m| m.id)
+                    .collect();
+                smart_category_manga = Some(ids);
+            }
+        }
+
+        let filter = MangaSearchFilter {
+            library_only: !include_catalogue,
+            category_id: category_id.filter(|_| smart_category_manga.is_none()),
+            source_id,
+            status,
+            genre,
+            tag,
+            unread_only,
+            manga_ids: smart_category_manga,
+            restriction: ctx
+                .data::<ContentPolicySvc>()?
+                .get_restriction(claims.sub)
+                .await?,
+        };
+
+        let manga = ctx
+            .data::<MangaService<MangaRepositoryImpl>>()?
+            .search_manga(claims.sub, &query, &filter, limit)
+            .await?
+            .into_iter()
+            .map(|m| m.into())
+            .collect();
+
+        Ok(manga)
+    }
+
+    /// Personal tags, most used first
+    #[graphql(guard = "ScopeGuard::new(ApiKeyScope::LibraryRead)")]
+    async fn tags(&self, ctx: &Context<'_>) -> Result<Vec<Tag>> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+
+        let tags = ctx
+            .data::<LibraryService<LibraryRepositoryImpl>>()?
+            .get_tags(claims.sub)
+            .await?
+            .into_iter()
+            .map(|tag| tag.into())
+            .collect();
+
+        Ok(tags)
+    }
+
+    #[graphql(guard = "ScopeGuard::new(ApiKeyScope::LibraryRead)")]
     async fn recent_updates(
         &self,
         ctx: &Context<'_>,
@@ -68,6 +312,8 @@ impl LibraryRoot {
             .map_err(|_| "token not exists, please login")?;
 
         let library_svc = ctx.data::<LibraryService<LibraryRepositoryImpl>>()?;
+        let manga_svc = ctx.data::<MangaService<MangaRepositoryImpl>>()?;
+        let content_policy_svc = ctx.data::<ContentPolicySvc>()?;
 
         query(
             after,
@@ -122,10 +368,16 @@ impl LibraryRoot {
                         .is_empty();
                 }
 
+                let manga_ids: Vec<i64> = edges.iter().map(|e| e.manga_id).collect();
+                let allowed =
+                    allowed_manga_ids(manga_svc, content_policy_svc, claims.sub, &manga_ids)
+                        .await?;
+
                 let mut connection = Connection::new(has_previous_page, has_next_page);
                 connection.edges.extend(
                     edges
                         .into_iter()
+                        .filter(|e| allowed.contains(&e.manga_id))
                         .map(|e| Edge::new(Cursor(e.uploaded.timestamp(), e.chapter_id), e.into())),
                 );
 
@@ -135,6 +387,7 @@ impl LibraryRoot {
         .await
     }
 
+    #[graphql(guard = "ScopeGuard::new(ApiKeyScope::LibraryRead)")]
     async fn recent_chapters(
         &self,
         ctx: &Context<'_>,
@@ -149,6 +402,8 @@ impl LibraryRoot {
 
         let history_svc =
             ctx.data::<HistoryService<ChapterRepositoryImpl, HistoryRepositoryImpl>>()?;
+        let manga_svc = ctx.data::<MangaService<MangaRepositoryImpl>>()?;
+        let content_policy_svc = ctx.data::<ContentPolicySvc>()?;
 
         query(
             after,
@@ -186,10 +441,16 @@ impl LibraryRoot {
                         .is_empty();
                 }
 
+                let manga_ids: Vec<i64> = edges.iter().map(|e| e.manga_id).collect();
+                let allowed =
+                    allowed_manga_ids(manga_svc, content_policy_svc, claims.sub, &manga_ids)
+                        .await?;
+
                 let mut connection = Connection::new(has_previous_page, has_next_page);
                 connection.edges.extend(
                     edges
                         .into_iter()
+                        .filter(|e| allowed.contains(&e.manga_id))
                         .map(|e| Edge::new(Cursor(e.read_at.timestamp(), e.manga_id), e.into())),
                 );
 
@@ -205,6 +466,7 @@ pub struct LibraryMutationRoot;
 
 #[Object]
 impl LibraryMutationRoot {
+    #[graphql(guard = "SessionGuard::new()")]
     async fn add_to_library(
         &self,
         ctx: &Context<'_>,
@@ -222,6 +484,7 @@ impl LibraryMutationRoot {
         Ok(1)
     }
 
+    #[graphql(guard = "SessionGuard::new()")]
     async fn delete_from_library(
         &self,
         ctx: &Context<'_>,
@@ -238,6 +501,64 @@ impl LibraryMutationRoot {
         Ok(1)
     }
 
+    /// Replace personal tags on manga, returns saved tags
+    #[graphql(guard = "SessionGuard::new()")]
+    async fn set_manga_tags(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "manga id")] manga_id: i64,
+        #[graphql(desc = "tags")] tags: Vec<String>,
+    ) -> Result<Vec<String>> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+
+        let tags = ctx
+            .data::<LibraryService<LibraryRepositoryImpl>>()?
+            .set_manga_tags(claims.sub, manga_id, tags)
+            .await?;
+
+        Ok(tags)
+    }
+
+    #[graphql(guard = "SessionGuard::new()")]
+    async fn set_manga_rating(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "manga id")] manga_id: i64,
+        #[graphql(desc = "rating from 1 to 10, null to remove")] rating: Option<i64>,
+    ) -> Result<Option<i64>> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+
+        ctx.data::<LibraryService<LibraryRepositoryImpl>>()?
+            .set_manga_rating(claims.sub, manga_id, rating)
+            .await?;
+
+        Ok(rating)
+    }
+
+    #[graphql(guard = "SessionGuard::new()")]
+    async fn set_manga_note(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "manga id")] manga_id: i64,
+        #[graphql(desc = "private note, null or blank to remove")] note: Option<String>,
+    ) -> Result<Option<String>> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+
+        let note = ctx
+            .data::<LibraryService<LibraryRepositoryImpl>>()?
+            .set_manga_note(claims.sub, manga_id, note)
+            .await?;
+
+        Ok(note)
+    }
+
+    #[graphql(guard = "ScopeGuard::new(ApiKeyScope::HistoryWrite)")]
     async fn update_page_read_at(
         &self,
         ctx: &Context<'_>,
@@ -253,6 +574,18 @@ impl LibraryMutationRoot {
             .insert_chapter_to_history(claims.sub, chapter_id, page, is_complete)
             .await?;
 
+        if ctx.data::<Config>()?.prefetch.enabled {
+            let command = PrefetchCommand::Chapter {
+                user_id: claims.sub,
+                chapter_id,
+                page: page.max(0) as usize,
+                pages: None,
+            };
+            if let Err(e) = ctx.data::<PrefetchSender>()?.send(command) {
+                error!("failed to send prefetch command: {e}");
+            }
+        }
+
         let chapter = ctx
             .data::<ChapterService<ChapterRepositoryImpl>>()?
             .fetch_chapter_by_id(chapter_id)
@@ -287,6 +620,7 @@ impl LibraryMutationRoot {
         Ok(1)
     }
 
+    #[graphql(guard = "ScopeGuard::new(ApiKeyScope::HistoryWrite)")]
     async fn mark_chapter_as_read(
         &self,
         ctx: &Context<'_>,
@@ -303,6 +637,7 @@ impl LibraryMutationRoot {
         Ok(1)
     }
 
+    #[graphql(guard = "ScopeGuard::new(ApiKeyScope::HistoryWrite)")]
     async fn mark_chapter_as_unread(
         &self,
         ctx: &Context<'_>,
@@ -319,6 +654,7 @@ impl LibraryMutationRoot {
         Ok(1)
     }
 
+    #[graphql(guard = "SessionGuard::new()")]
     async fn refresh_chapters(
         &self,
         ctx: &Context<'_>,
@@ -360,6 +696,7 @@ pub struct LibrarySubscriptionRoot;
 
 #[Subscription]
 impl LibrarySubscriptionRoot {
+    #[graphql(guard = "ScopeGuard::new(ApiKeyScope::LibraryRead)")]
     async fn recent_updates_subscription(
         &self,
         ctx: &Context<'_>,
@@ -379,6 +716,7 @@ impl LibrarySubscriptionRoot {
                 if update.users.get(&user_id).is_some() {
                     Some(RecentUpdate {
                         manga_id: update.chapter.manga_id,
+                        source_id: update.manga.source_id,
                         chapter_id: update.chapter.id,
                         manga_title: update.manga.title,
                         cover_url: update.manga.cover_url,
@@ -1,6 +1,6 @@
 use super::{common::ReadProgress, manga::Manga};
 use crate::domain::{
-    entities::download::DownloadQueueEntry,
+    entities::{download::DownloadQueueEntry, library::MangaNote, manga::MangaOverride},
     repositories::{
         download::DownloadRepository, history::HistoryRepository, library::LibraryRepository,
         manga::MangaRepository, tracker::TrackerRepository,
@@ -425,3 +425,133 @@ where
         Ok(res)
     }
 }
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UserMangaOverrideId(pub i64, pub i64);
+
+#[async_trait::async_trait]
+impl<H, L, M, T, D> Loader<UserMangaOverrideId> for DatabaseLoader<H, L, M, T, D>
+where
+    H: HistoryRepository + 'static,
+    L: LibraryRepository + 'static,
+    M: MangaRepository + 'static,
+    T: TrackerRepository + 'static,
+    D: DownloadRepository + 'static,
+{
+    type Value = MangaOverride;
+
+    type Error = Arc<anyhow::Error>;
+
+    async fn load(
+        &self,
+        keys: &[UserMangaOverrideId],
+    ) -> Result<HashMap<UserMangaOverrideId, Self::Value>, Self::Error> {
+        let user_id = keys
+            .iter()
+            .next()
+            .map(|key| key.0)
+            .ok_or_else(|| anyhow::anyhow!("no user id"))?;
+
+        let manga_ids: Vec<i64> = keys.iter().map(|key| key.1).collect();
+
+        let res = self
+            .manga_repo
+            .get_manga_overrides(user_id, &manga_ids)
+            .await
+            .map_err(|e| Arc::new(anyhow::anyhow!("{e}")))?
+            .into_par_iter()
+            .map(|manga_override| {
+                (
+                    UserMangaOverrideId(user_id, manga_override.manga_id),
+                    manga_override,
+                )
+            })
+            .collect();
+
+        Ok(res)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UserMangaTagsId(pub i64, pub i64);
+
+#[async_trait::async_trait]
+impl<H, L, M, T, D> Loader<UserMangaTagsId> for DatabaseLoader<H, L, M, T, D>
+where
+    H: HistoryRepository + 'static,
+    L: LibraryRepository + 'static,
+    M: MangaRepository + 'static,
+    T: TrackerRepository + 'static,
+    D: DownloadRepository + 'static,
+{
+    type Value = Vec<String>;
+
+    type Error = Arc<anyhow::Error>;
+
+    async fn load(
+        &self,
+        keys: &[UserMangaTagsId],
+    ) -> Result<HashMap<UserMangaTagsId, Self::Value>, Self::Error> {
+        let user_id = keys
+            .iter()
+            .next()
+            .map(|key| key.0)
+            .ok_or_else(|| anyhow::anyhow!("no user id"))?;
+
+        let manga_ids: Vec<i64> = keys.iter().map(|key| key.1).collect();
+
+        let res = self
+            .library_repo
+            .get_manga_tags(user_id, &manga_ids)
+            .await
+            .map_err(|e| Arc::new(anyhow::anyhow!("{e}")))?
+            .into_iter()
+            .group_by(|tag| UserMangaTagsId(user_id, tag.manga_id))
+            .into_iter()
+            .map(|(key, group)| (key, group.map(|tag| tag.tag).collect()))
+            .collect();
+
+        Ok(res)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UserMangaNoteId(pub i64, pub i64);
+
+#[async_trait::async_trait]
+impl<H, L, M, T, D> Loader<UserMangaNoteId> for DatabaseLoader<H, L, M, T, D>
+where
+    H: HistoryRepository + 'static,
+    L: LibraryRepository + 'static,
+    M: MangaRepository + 'static,
+    T: TrackerRepository + 'static,
+    D: DownloadRepository + 'static,
+{
+    type Value = MangaNote;
+
+    type Error = Arc<anyhow::Error>;
+
+    async fn load(
+        &self,
+        keys: &[UserMangaNoteId],
+    ) -> Result<HashMap<UserMangaNoteId, Self::Value>, Self::Error> {
+        let user_id = keys
+            .iter()
+            .next()
+            .map(|key| key.0)
+            .ok_or_else(|| anyhow::anyhow!("no user id"))?;
+
+        let manga_ids: Vec<i64> = keys.iter().map(|key| key.1).collect();
+
+        let res = self
+            .library_repo
+            .get_manga_notes(user_id, &manga_ids)
+            .await
+            .map_err(|e| Arc::new(anyhow::anyhow!("{e}")))?
+            .into_par_iter()
+            .map(|note| (UserMangaNoteId(user_id, note.manga_id), note))
+            .collect();
+
+        Ok(res)
+    }
+}
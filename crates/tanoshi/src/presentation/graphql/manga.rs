@@ -1,14 +1,19 @@
 use super::{
     chapter::Chapter,
+    guard::{AdminGuard, SessionGuard},
     loader::{
-        UserFavoriteId, UserFavoritePath, UserLastReadId, UserTrackerMangaId, UserUnreadChaptersId,
+        UserFavoriteId, UserFavoritePath, UserLastReadId, UserMangaNoteId, UserMangaOverrideId,
+        UserMangaTagsId, UserTrackerMangaId, UserUnreadChaptersId,
     },
     source::Source,
 };
 use crate::{
-    domain::services::{
-        chapter::ChapterService, history::HistoryService, image::ImageService,
-        source::SourceService,
+    domain::{
+        entities::{image::ImageOrigin, manga::MangaOverride},
+        services::{
+            chapter::ChapterService, history::HistoryService, image::ImageService,
+            manga::MangaService, source::SourceService,
+        },
     },
     infrastructure::{
         auth::Claims,
@@ -16,12 +21,13 @@ use crate::{
         domain::repositories::{
             chapter::ChapterRepositoryImpl, history::HistoryRepositoryImpl,
             image::ImageRepositoryImpl, image_cache::ImageCacheRepositoryImpl,
-            source::SourceRepositoryImpl,
+            manga::MangaRepositoryImpl, source::SourceRepositoryImpl,
         },
     },
     presentation::graphql::schema::DatabaseLoader,
 };
 use async_graphql::{dataloader::DataLoader, Context, Object, Result, SimpleObject};
+use base64::{engine::general_purpose, Engine};
 use chrono::NaiveDateTime;
 use rayon::prelude::*;
 use tanoshi_vm::extension::ExtensionManager;
@@ -98,30 +104,124 @@ impl From<crate::domain::entities::manga::Manga> for Manga {
     }
 }
 
+/// Metadata overridden by user, unset fields come from source
+#[derive(Debug, SimpleObject)]
+pub struct MangaMetadataOverride {
+    pub title: Option<String>,
+    pub author: Option<Vec<String>>,
+    pub genre: Option<Vec<String>>,
+    pub status: Option<String>,
+    pub description: Option<String>,
+    /// whether user uploaded a cover
+    pub custom_cover: bool,
+}
+
+impl From<MangaOverride> for MangaMetadataOverride {
+    fn from(val: MangaOverride) -> Self {
+        Self {
+            title: val.title,
+            author: val.author,
+            genre: val.genre,
+            status: val.status,
+            description: val.description,
+            custom_cover: val.cover_path.is_some(),
+        }
+    }
+}
+
+/// Scanlator preference of a manga, shared by every user
+#[derive(Debug, SimpleObject)]
+pub struct ScanlatorSetting {
+    /// collapse chapters with equal number to the preferred scanlator
+    pub dedup: bool,
+    /// first is most preferred
+    pub priority: Vec<String>,
+    pub blocklist: Vec<String>,
+    /// every scanlator of the manga, including blocked ones
+    pub scanlators: Vec<String>,
+}
+
+impl ScanlatorSetting {
+    fn new(
+        val: crate::domain::entities::chapter::ScanlatorSetting,
+        scanlators: Vec<String>,
+    ) -> Self {
+        Self {
+            dedup: val.dedup,
+            priority: val.priority,
+            blocklist: val.blocklist,
+            scanlators,
+        }
+    }
+}
+
+impl Manga {
+    /// Override of the requesting user, manga not saved in database can't have one
+    async fn get_override(&self, ctx: &Context<'_>) -> Result<Option<MangaOverride>> {
+        let user = match ctx.data::<Claims>() {
+            Ok(user) if self.id != 0 => user,
+            _ => return Ok(None),
+        };
+
+        let loader = ctx.data::<DataLoader<DatabaseLoader>>()?;
+        Ok(loader
+            .load_one(UserMangaOverrideId(user.sub, self.id))
+            .await?)
+    }
+}
+
 #[Object(cache_control(max_age = 60, private))]
 impl Manga {
     async fn id(&self) -> i64 {
         self.id
     }
 
-    async fn title(&self) -> String {
-        self.title.clone()
+    async fn title(&self, ctx: &Context<'_>) -> Result<String> {
+        Ok(self
+            .get_override(ctx)
+            .await?
+            .and_then(|manga_override| manga_override.title)
+            .unwrap_or_else(|| self.title.clone()))
     }
 
-    async fn author(&self) -> Vec<String> {
-        self.author.clone()
+    async fn author(&self, ctx: &Context<'_>) -> Result<Vec<String>> {
+        Ok(self
+            .get_override(ctx)
+            .await?
+            .and_then(|manga_override| manga_override.author)
+            .unwrap_or_else(|| self.author.clone()))
     }
 
-    async fn genre(&self) -> Vec<String> {
-        self.genre.clone()
+    async fn genre(&self, ctx: &Context<'_>) -> Result<Vec<String>> {
+        Ok(self
+            .get_override(ctx)
+            .await?
+            .and_then(|manga_override| manga_override.genre)
+            .unwrap_or_else(|| self.genre.clone()))
     }
 
-    async fn status(&self) -> Option<String> {
-        self.status.clone()
+    async fn status(&self, ctx: &Context<'_>) -> Result<Option<String>> {
+        Ok(self
+            .get_override(ctx)
+            .await?
+            .and_then(|manga_override| manga_override.status)
+            .or_else(|| self.status.clone()))
     }
 
-    async fn description(&self) -> Option<String> {
-        self.description.clone()
+    async fn description(&self, ctx: &Context<'_>) -> Result<Option<String>> {
+        Ok(self
+            .get_override(ctx)
+            .await?
+            .and_then(|manga_override| manga_override.description)
+            .or_else(|| self.description.clone()))
+    }
+
+    /// Fields overridden by user, null if nothing is
+    async fn metadata_override(&self, ctx: &Context<'_>) -> Result<Option<MangaMetadataOverride>> {
+        Ok(self
+            .get_override(ctx)
+            .await?
+            .map(|manga_override| manga_override.into()))
     }
 
     async fn link(&self, ctx: &Context<'_>) -> Result<String> {
@@ -136,11 +236,24 @@ impl Manga {
     }
 
     async fn cover_url(&self, ctx: &Context<'_>) -> Result<String> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
         let secret = &ctx.data::<Config>()?.secret;
+        let cover_url = self
+            .get_override(ctx)
+            .await?
+            .and_then(|manga_override| manga_override.cover_path)
+            .unwrap_or_else(|| self.cover_url.clone());
 
         Ok(ctx
             .data::<ImageService<ImageCacheRepositoryImpl, ImageRepositoryImpl>>()?
-            .encrypt_image_url(secret, &self.cover_url)?)
+            .encrypt_image_url(
+                secret,
+                &cover_url,
+                ImageOrigin::cover(self.source_id, self.id),
+                claims.sub,
+            )?)
     }
 
     async fn is_favorite(&self, ctx: &Context<'_>) -> Result<bool> {
@@ -237,6 +350,14 @@ impl Manga {
         Ok(chapter)
     }
 
+    async fn scanlator_setting(&self, ctx: &Context<'_>) -> Result<ScanlatorSetting> {
+        let chapter_svc = ctx.data::<ChapterService<ChapterRepositoryImpl>>()?;
+        let setting = chapter_svc.get_scanlator_setting(self.id).await?;
+        let scanlators = chapter_svc.get_scanlators(self.id).await?;
+
+        Ok(ScanlatorSetting::new(setting, scanlators))
+    }
+
     async fn trackers(&self, ctx: &Context<'_>) -> Result<Vec<Tracker>> {
         let user = ctx
             .data::<Claims>()
@@ -255,4 +376,194 @@ impl Manga {
 
         Ok(data)
     }
+
+    /// Personal tags of user
+    async fn tags(&self, ctx: &Context<'_>) -> Result<Vec<String>> {
+        let user = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+        let loader = ctx.data::<DataLoader<DatabaseLoader>>()?;
+        Ok(loader
+            .load_one(UserMangaTagsId(user.sub, self.id))
+            .await?
+            .unwrap_or_default())
+    }
+
+    /// Rating of user from 1 to 10
+    async fn rating(&self, ctx: &Context<'_>) -> Result<Option<i64>> {
+        let user = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+        let loader = ctx.data::<DataLoader<DatabaseLoader>>()?;
+        Ok(loader
+            .load_one(UserMangaNoteId(user.sub, self.id))
+            .await?
+            .and_then(|note| note.rating))
+    }
+
+    /// Private note of user
+    async fn note(&self, ctx: &Context<'_>) -> Result<Option<String>> {
+        let user = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+        let loader = ctx.data::<DataLoader<DatabaseLoader>>()?;
+        Ok(loader
+            .load_one(UserMangaNoteId(user.sub, self.id))
+            .await?
+            .and_then(|note| note.note))
+    }
+}
+
+#[derive(Default)]
+pub struct MangaMutationRoot;
+
+#[Object]
+impl MangaMutationRoot {
+    /// Override metadata of a manga, empty fields revert to what source returns
+    #[graphql(guard = "SessionGuard::new()")]
+    async fn set_manga_metadata_override(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "manga id")] manga_id: i64,
+        title: Option<String>,
+        author: Option<Vec<String>>,
+        genre: Option<Vec<String>>,
+        status: Option<String>,
+        description: Option<String>,
+    ) -> Result<Option<MangaMetadataOverride>> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+
+        let manga_override = ctx
+            .data::<MangaService<MangaRepositoryImpl>>()?
+            .set_manga_override(
+                claims.sub,
+                MangaOverride {
+                    manga_id,
+                    title,
+                    author,
+                    genre,
+                    status,
+                    description,
+                    cover_path: None,
+                },
+            )
+            .await?
+            .map(|manga_override| manga_override.into());
+
+        Ok(manga_override)
+    }
+
+    /// Replace cover of a manga with an uploaded png, jpeg, gif or webp image
+    #[graphql(guard = "SessionGuard::new()")]
+    async fn upload_manga_cover(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "manga id")] manga_id: i64,
+        #[graphql(desc = "base64 encoded image, data url is accepted")] image: String,
+    ) -> Result<bool> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+
+        let encoded = match image.strip_prefix("data:") {
+            Some(data_url) => data_url
+                .split_once(',')
+                .map(|(_, encoded)| encoded)
+                .unwrap_or_default(),
+            None => image.as_str(),
+        };
+        let data = general_purpose::STANDARD
+            .decode(encoded.trim())
+            .map_err(|_| "image is not valid base64")?;
+
+        let image_svc =
+            ctx.data::<ImageService<ImageCacheRepositoryImpl, ImageRepositoryImpl>>()?;
+        let cover_path = image_svc.save_cover(claims.sub, manga_id, &data).await?;
+
+        let previous_cover_path = match ctx
+            .data::<MangaService<MangaRepositoryImpl>>()?
+            .set_manga_cover(claims.sub, manga_id, Some(cover_path.clone()))
+            .await
+        {
+            Ok(previous_cover_path) => previous_cover_path,
+            Err(e) => {
+                image_svc.remove_cover(&cover_path).await?;
+                return Err(e.into());
+            }
+        };
+        if let Some(path) = previous_cover_path {
+            image_svc.remove_cover(&path).await?;
+        }
+
+        Ok(true)
+    }
+
+    /// Go back to cover from source
+    #[graphql(guard = "SessionGuard::new()")]
+    async fn remove_manga_cover(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "manga id")] manga_id: i64,
+    ) -> Result<bool> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+
+        let previous_cover_path = ctx
+            .data::<MangaService<MangaRepositoryImpl>>()?
+            .set_manga_cover(claims.sub, manga_id, None)
+            .await?;
+        if let Some(path) = previous_cover_path {
+            ctx.data::<ImageService<ImageCacheRepositoryImpl, ImageRepositoryImpl>>()?
+                .remove_cover(&path)
+                .await?;
+        }
+
+        Ok(true)
+    }
+
+    /// Remove every override of a manga including uploaded cover
+    #[graphql(guard = "SessionGuard::new()")]
+    async fn reset_manga_metadata(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "manga id")] manga_id: i64,
+    ) -> Result<bool> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+
+        let cover_path = ctx
+            .data::<MangaService<MangaRepositoryImpl>>()?
+            .delete_manga_override(claims.sub, manga_id)
+            .await?;
+        if let Some(path) = cover_path {
+            ctx.data::<ImageService<ImageCacheRepositoryImpl, ImageRepositoryImpl>>()?
+                .remove_cover(&path)
+                .await?;
+        }
+
+        Ok(true)
+    }
+
+    /// Set scanlator priority, blocklist and dedup of a manga for every user
+    #[graphql(guard = "AdminGuard::new()")]
+    async fn set_scanlator_setting(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "manga id")] manga_id: i64,
+        #[graphql(desc = "collapse chapters with equal number", default = false)] dedup: bool,
+        #[graphql(desc = "scanlators, first is most preferred", default)] priority: Vec<String>,
+        #[graphql(desc = "scanlators to hide", default)] blocklist: Vec<String>,
+    ) -> Result<ScanlatorSetting> {
+        let chapter_svc = ctx.data::<ChapterService<ChapterRepositoryImpl>>()?;
+        let setting = chapter_svc
+            .set_scanlator_setting(manga_id, dedup, priority, blocklist)
+            .await?;
+        let scanlators = chapter_svc.get_scanlators(manga_id).await?;
+
+        Ok(ScanlatorSetting::new(setting, scanlators))
+    }
 }
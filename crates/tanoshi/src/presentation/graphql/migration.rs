@@ -0,0 +1,176 @@
+use super::{
+    guard::{ScopeGuard, SessionGuard},
+    manga::Manga,
+};
+use crate::{
+    domain::{
+        entities::api_key::ApiKeyScope,
+        services::{
+            content_policy::ContentPolicyService, manga::MangaService, migration::MigrationService,
+            source::SourceService,
+        },
+    },
+    infrastructure::{
+        auth::Claims,
+        domain::repositories::{
+            chapter::ChapterRepositoryImpl, content_policy::ContentPolicyRepositoryImpl,
+            library::LibraryRepositoryImpl, manga::MangaRepositoryImpl,
+            migration::MigrationRepositoryImpl, source::SourceRepositoryImpl,
+        },
+    },
+};
+use async_graphql::{Context, Object, Result, SimpleObject};
+use std::collections::HashSet;
+
+type ContentPolicySvc = ContentPolicyService<ContentPolicyRepositoryImpl, SourceRepositoryImpl>;
+
+type MigrationSvc = MigrationService<
+    LibraryRepositoryImpl,
+    MangaRepositoryImpl,
+    ChapterRepositoryImpl,
+    MigrationRepositoryImpl,
+>;
+
+#[derive(Debug, SimpleObject)]
+pub struct MigrationCandidate {
+    pub manga: Manga,
+    /// title similarity between 0 and 1
+    pub similarity: f64,
+}
+
+impl From<crate::domain::entities::migration::MigrationCandidate> for MigrationCandidate {
+    fn from(candidate: crate::domain::entities::migration::MigrationCandidate) -> Self {
+        Self {
+            manga: candidate.manga.into(),
+            similarity: candidate.similarity,
+        }
+    }
+}
+
+#[derive(Debug, SimpleObject)]
+pub struct MigrationResult {
+    pub manga: Manga,
+    pub categories: u64,
+    pub history: u64,
+    pub trackers: u64,
+}
+
+#[derive(Default)]
+pub struct MigrationRoot;
+
+#[Object]
+impl MigrationRoot {
+    /// Groups of manga in library with similar titles
+    #[graphql(guard = "ScopeGuard::new(ApiKeyScope::LibraryRead)")]
+    async fn duplicate_manga(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "minimum title similarity", default = 0.85)] threshold: f64,
+    ) -> Result<Vec<Vec<Manga>>> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+
+        let groups = ctx
+            .data::<MigrationSvc>()?
+            .find_duplicates(claims.sub, threshold)
+            .await?
+            .into_iter()
+            .map(|group| group.into_iter().map(Manga::from).collect())
+            .collect();
+
+        Ok(groups)
+    }
+
+    /// Search other installed sources for the same title
+    #[graphql(guard = "ScopeGuard::new(ApiKeyScope::LibraryRead)")]
+    async fn migration_candidates(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "manga id")] manga_id: i64,
+        #[graphql(desc = "minimum title similarity", default = 0.6)] threshold: f64,
+    ) -> Result<Vec<MigrationCandidate>> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+
+        let content_policy_svc = ctx.data::<ContentPolicySvc>()?;
+
+        let manga = ctx
+            .data::<MangaService<MangaRepositoryImpl>>()?
+            .fetch_manga_by_id(manga_id, false)
+            .await?;
+        content_policy_svc.check_manga(claims.sub, &manga).await?;
+
+        let sources = ctx
+            .data::<SourceService<SourceRepositoryImpl>>()?
+            .get_installed_sources(false)
+            .await?;
+        let source_ids: Vec<i64> = content_policy_svc
+            .filter_sources(claims.sub, sources)
+            .await?
+            .into_iter()
+            .map(|source| source.id)
+            .collect();
+
+        let candidates = ctx
+            .data::<MigrationSvc>()?
+            .search_migration_candidates(manga_id, &source_ids, threshold)
+            .await?;
+
+        // sources are allowed already, this drops blocked genres
+        let allowed: HashSet<(i64, String)> = content_policy_svc
+            .filter_manga(
+                claims.sub,
+                candidates.iter().map(|c| c.manga.clone()).collect(),
+            )
+            .await?
+            .into_iter()
+            .map(|m| (m.source_id, m.path))
+            .collect();
+
+        let candidates = candidates
+            .into_iter()
+            .filter(|c| allowed.contains(&(c.manga.source_id, c.manga.path.clone())))
+            .map(MigrationCandidate::from)
+            .collect();
+
+        Ok(candidates)
+    }
+}
+
+#[derive(Default)]
+pub struct MigrationMutationRoot;
+
+#[Object]
+impl MigrationMutationRoot {
+    /// Move categories, history and tracker links to manga from another source
+    #[graphql(guard = "SessionGuard::new()")]
+    async fn migrate_manga(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "manga id to migrate from")] manga_id: i64,
+        #[graphql(desc = "source id of new manga")] source_id: i64,
+        #[graphql(desc = "path of new manga")] path: String,
+    ) -> Result<MigrationResult> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+
+        ctx.data::<ContentPolicySvc>()?
+            .check_source(claims.sub, source_id)
+            .await?;
+
+        let (manga, result) = ctx
+            .data::<MigrationSvc>()?
+            .migrate_manga(claims.sub, manga_id, source_id, &path)
+            .await?;
+
+        Ok(MigrationResult {
+            manga: manga.into(),
+            categories: result.categories,
+            history: result.history,
+            trackers: result.trackers,
+        })
+    }
+}
@@ -1,45 +1,87 @@
+pub mod api_key;
+pub mod audit_log;
 pub mod catalogue;
 pub mod categories;
 pub mod chapter;
 pub mod common;
+pub mod content_policy;
 pub mod downloads;
 pub mod guard;
+pub mod http_profile;
+pub mod image_cache;
 pub mod library;
 pub mod loader;
 pub mod manga;
+pub mod migration;
 pub mod notification;
+pub mod oidc;
+pub mod preference;
 pub mod recent;
 pub mod schema;
+pub mod session;
 pub mod source;
+pub mod statistics;
 pub mod status;
 pub mod tracking;
 pub mod user;
+pub mod websocket;
 
-use crate::infrastructure::{auth, config::Config};
+use crate::{
+    domain::services::{
+        api_key::ApiKeyService, proxy_auth::ProxyAuthService, session::SessionService,
+    },
+    infrastructure::domain::repositories::{
+        api_key::ApiKeyRepositoryImpl, session::SessionRepositoryImpl, user::UserRepositoryImpl,
+    },
+};
 use async_graphql::http::{playground_source, GraphQLPlaygroundConfig, ALL_WEBSOCKET_PROTOCOLS};
 use async_graphql_axum::{GraphQLProtocol, GraphQLRequest, GraphQLResponse, GraphQLWebSocket};
 use axum::{
-    extract::{Extension, WebSocketUpgrade},
+    extract::{Extension, TypedHeader, WebSocketUpgrade},
     response::{Html, IntoResponse, Response},
 };
+use headers::UserAgent;
 use serde::Deserialize;
 
-use self::schema::TanoshiSchema;
+use self::{schema::TanoshiSchema, websocket::WebSocketCredential};
 
-use super::token::Token;
+use super::{
+    client_addr::ClientAddr,
+    token::{authenticate, Token},
+};
 
+#[allow(clippy::too_many_arguments)]
 pub async fn graphql_handler(
     token: Token,
-    Extension(config): Extension<Config>,
+    client_addr: Option<ClientAddr>,
+    user_agent: Option<TypedHeader<UserAgent>>,
+    Extension(session_svc): Extension<SessionService<SessionRepositoryImpl, UserRepositoryImpl>>,
+    Extension(api_key_svc): Extension<ApiKeyService<ApiKeyRepositoryImpl, UserRepositoryImpl>>,
+    Extension(proxy_auth_svc): Extension<ProxyAuthService<UserRepositoryImpl>>,
     Extension(schema): Extension<TanoshiSchema>,
     req: GraphQLRequest,
 ) -> GraphQLResponse {
     let mut req = req.into_inner();
 
-    if let Ok(claims) = auth::decode_jwt(&config.secret, &token.0) {
+    // enrollment token has no session, only two-factor enrollment accepts it
+    if let Token::Jwt(token) = &token {
+        if let Ok(claims) = session_svc.authenticate_totp_enrollment(token) {
+            req = req.data(claims);
+        }
+    }
+
+    if let Some(claims) = authenticate(token, &session_svc, &api_key_svc, &proxy_auth_svc).await {
         req = req.data(claims);
     }
 
+    if let Some(TypedHeader(user_agent)) = user_agent {
+        req = req.data(user_agent);
+    }
+
+    if let Some(client_addr) = client_addr {
+        req = req.data(client_addr);
+    }
+
     schema.execute(req).await.into()
 }
 
@@ -50,11 +92,20 @@ pub async fn graphql_playground() -> impl IntoResponse {
 }
 
 pub async fn graphql_ws_handler(
-    Extension(config): Extension<Config>,
+    token: Token,
+    Extension(session_svc): Extension<SessionService<SessionRepositoryImpl, UserRepositoryImpl>>,
+    Extension(api_key_svc): Extension<ApiKeyService<ApiKeyRepositoryImpl, UserRepositoryImpl>>,
+    Extension(proxy_auth_svc): Extension<ProxyAuthService<UserRepositoryImpl>>,
     Extension(schema): Extension<TanoshiSchema>,
     protocol: GraphQLProtocol,
     websocket: WebSocketUpgrade,
 ) -> Response {
+    // browsers can't set headers on websocket, but a proxy can
+    let remote_user = match token {
+        Token::RemoteUser(_) => Some(token),
+        _ => None,
+    };
+
     websocket
         .protocols(ALL_WEBSOCKET_PROTOCOLS)
         .on_upgrade(move |stream| {
@@ -65,15 +116,24 @@ pub async fn graphql_ws_handler(
                         token: String,
                     }
 
-                    if let Ok(payload) = serde_json::from_value::<Payload>(value) {
-                        let mut data = async_graphql::Data::default();
-                        if let Ok(claims) = auth::decode_jwt(&config.secret, &payload.token) {
+                    let token = match (serde_json::from_value::<Payload>(value), remote_user) {
+                        (Ok(payload), _) if !payload.token.is_empty() => payload.token.into(),
+                        (_, Some(remote_user)) => remote_user,
+                        (Ok(_), None) => Token::None,
+                        (Err(_), None) => return Err("Token is required".into()),
+                    };
+
+                    // claims are checked again before each operation, see `WebSocketAuth`
+                    let mut data = async_graphql::Data::default();
+                    if let Some(credential) =
+                        WebSocketCredential::new(token, session_svc, api_key_svc, proxy_auth_svc)
+                    {
+                        if let Some(claims) = credential.authenticate().await {
                             data.insert(claims);
+                            data.insert(credential);
                         }
-                        Ok(data)
-                    } else {
-                        Err("Token is required".into())
                     }
+                    Ok(data)
                 })
                 .serve()
         })
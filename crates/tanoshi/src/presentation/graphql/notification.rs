@@ -1,3 +1,4 @@
+use super::guard::SessionGuard;
 use crate::infrastructure::{
     auth::Claims, domain::repositories::user::UserRepositoryImpl, notification::Notification,
 };
@@ -8,6 +9,7 @@ pub struct NotificationRoot;
 
 #[Object]
 impl NotificationRoot {
+    #[graphql(guard = "SessionGuard::new()")]
     async fn test_telegram(
         &self,
         ctx: &Context<'_>,
@@ -23,6 +25,7 @@ impl NotificationRoot {
         Ok(true)
     }
 
+    #[graphql(guard = "SessionGuard::new()")]
     async fn test_pushover(
         &self,
         ctx: &Context<'_>,
@@ -38,6 +41,7 @@ impl NotificationRoot {
         Ok(true)
     }
 
+    #[graphql(guard = "SessionGuard::new()")]
     async fn test_gotify(
         &self,
         ctx: &Context<'_>,
@@ -53,6 +57,7 @@ impl NotificationRoot {
         Ok(true)
     }
 
+    #[graphql(guard = "SessionGuard::new()")]
     async fn test_desktop_notification(&self, _ctx: &Context<'_>) -> Result<bool> {
         #[cfg(feature = "desktop")]
         {
@@ -0,0 +1,138 @@
+use super::{guard::SessionGuard, session::AuthToken};
+use crate::{
+    domain::{
+        entities::audit_log::AuditAction,
+        services::{audit_log::AuditLogService, oidc::OidcService, session::SessionService},
+    },
+    infrastructure::{
+        auth::Claims,
+        domain::repositories::{
+            audit_log::AuditLogRepositoryImpl, identity::IdentityRepositoryImpl,
+            session::SessionRepositoryImpl, user::UserRepositoryImpl,
+        },
+    },
+    presentation::client_addr::ClientAddr,
+};
+use async_graphql::{Context, Object, Result, SimpleObject};
+use chrono::NaiveDateTime;
+use headers::UserAgent;
+
+#[derive(SimpleObject)]
+pub struct OidcSession {
+    pub authorize_url: String,
+    /// state the provider redirects back with
+    pub csrf_state: String,
+}
+
+#[derive(Debug, SimpleObject)]
+pub struct LinkedAccount {
+    pub issuer: String,
+    pub subject: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Default)]
+pub struct OidcRoot;
+
+#[Object]
+impl OidcRoot {
+    async fn oidc_login_start(&self, ctx: &Context<'_>) -> Result<OidcSession> {
+        let session = ctx
+            .data::<OidcService<IdentityRepositoryImpl, UserRepositoryImpl>>()?
+            .login_start()?;
+
+        Ok(OidcSession {
+            authorize_url: session.authorize_url,
+            csrf_state: session.csrf_state,
+        })
+    }
+
+    async fn oidc_login_end(
+        &self,
+        ctx: &Context<'_>,
+        code: String,
+        state: String,
+    ) -> Result<AuthToken> {
+        let user = ctx
+            .data::<OidcService<IdentityRepositoryImpl, UserRepositoryImpl>>()?
+            .login_end(code, state)
+            .await?;
+
+        ctx.data::<AuditLogService<AuditLogRepositoryImpl>>()?
+            .record(
+                Some(user.id),
+                Some(&user.username),
+                AuditAction::Login,
+                Some("oidc"),
+                ctx.data_opt::<ClientAddr>().map(|ClientAddr(addr)| *addr),
+            )
+            .await;
+
+        let user_agent = ctx
+            .data_opt::<UserAgent>()
+            .map(|user_agent| user_agent.as_str());
+        let token = ctx
+            .data::<SessionService<SessionRepositoryImpl, UserRepositoryImpl>>()?
+            .create_session(&user, user_agent)
+            .await?;
+
+        Ok(token.into())
+    }
+
+    #[graphql(guard = "SessionGuard::new()")]
+    async fn linked_accounts(&self, ctx: &Context<'_>) -> Result<Vec<LinkedAccount>> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+
+        let accounts = ctx
+            .data::<OidcService<IdentityRepositoryImpl, UserRepositoryImpl>>()?
+            .get_identities(claims.sub)
+            .await?
+            .into_iter()
+            .map(|identity| LinkedAccount {
+                issuer: identity.issuer,
+                subject: identity.subject,
+                created_at: identity.created_at,
+            })
+            .collect();
+
+        Ok(accounts)
+    }
+}
+
+#[derive(Default)]
+pub struct OidcMutationRoot;
+
+#[Object]
+impl OidcMutationRoot {
+    #[graphql(guard = "SessionGuard::new()")]
+    async fn link_oidc_account(
+        &self,
+        ctx: &Context<'_>,
+        code: String,
+        state: String,
+    ) -> Result<bool> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+
+        ctx.data::<OidcService<IdentityRepositoryImpl, UserRepositoryImpl>>()?
+            .link_account(claims.sub, code, state)
+            .await?;
+
+        Ok(true)
+    }
+
+    #[graphql(guard = "SessionGuard::new()")]
+    async fn unlink_oidc_account(&self, ctx: &Context<'_>) -> Result<u64> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+
+        Ok(ctx
+            .data::<OidcService<IdentityRepositoryImpl, UserRepositoryImpl>>()?
+            .unlink_account(claims.sub)
+            .await?)
+    }
+}
@@ -0,0 +1,163 @@
+use super::guard::SessionGuard;
+use crate::{
+    domain::services::preference::PreferenceService,
+    infrastructure::{auth::Claims, domain::repositories::preference::PreferenceRepositoryImpl},
+};
+use async_graphql::{Context, Object, Result, SimpleObject, Subscription};
+use chrono::NaiveDateTime;
+use futures::{Stream, StreamExt};
+
+#[derive(Debug, SimpleObject)]
+pub struct UserPreference {
+    pub key: String,
+    /// null for preferences that apply to every manga
+    pub manga_id: Option<i64>,
+    pub schema_version: i64,
+    /// json document
+    pub value: String,
+    pub updated_at: NaiveDateTime,
+}
+
+impl From<crate::domain::entities::preference::Preference> for UserPreference {
+    fn from(preference: crate::domain::entities::preference::Preference) -> Self {
+        Self {
+            key: preference.key,
+            manga_id: preference.manga_id,
+            schema_version: preference.schema_version,
+            value: preference.value,
+            updated_at: preference.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, SimpleObject)]
+pub struct UserPreferenceChange {
+    pub preference: UserPreference,
+    pub deleted: bool,
+}
+
+#[derive(Default)]
+pub struct PreferenceRoot;
+
+#[Object]
+impl PreferenceRoot {
+    /// Preferences of logged in user, global ones if manga id is not set
+    #[graphql(guard = "SessionGuard::new()")]
+    async fn user_preferences(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "manga id")] manga_id: Option<i64>,
+    ) -> Result<Vec<UserPreference>> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+
+        let preferences = ctx
+            .data::<PreferenceService<PreferenceRepositoryImpl>>()?
+            .get_preferences(claims.sub, manga_id)
+            .await?;
+
+        Ok(preferences.into_iter().map(UserPreference::from).collect())
+    }
+
+    #[graphql(guard = "SessionGuard::new()")]
+    async fn user_preference(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "preference key, e.g. reader")] key: String,
+        #[graphql(desc = "manga id")] manga_id: Option<i64>,
+    ) -> Result<Option<UserPreference>> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+
+        let preference = ctx
+            .data::<PreferenceService<PreferenceRepositoryImpl>>()?
+            .get_preference(claims.sub, &key, manga_id)
+            .await?;
+
+        Ok(preference.map(UserPreference::from))
+    }
+}
+
+#[derive(Default)]
+pub struct PreferenceMutationRoot;
+
+#[Object]
+impl PreferenceMutationRoot {
+    #[graphql(guard = "SessionGuard::new()")]
+    async fn set_user_preference(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "preference key, e.g. reader")] key: String,
+        #[graphql(desc = "manga id")] manga_id: Option<i64>,
+        #[graphql(desc = "version of the document layout")] schema_version: i64,
+        #[graphql(desc = "json object")] value: String,
+    ) -> Result<UserPreference> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+
+        let preference = ctx
+            .data::<PreferenceService<PreferenceRepositoryImpl>>()?
+            .set_preference(claims.sub, &key, manga_id, schema_version, &value)
+            .await?;
+
+        Ok(preference.into())
+    }
+
+    #[graphql(guard = "SessionGuard::new()")]
+    async fn delete_user_preference(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "preference key, e.g. reader")] key: String,
+        #[graphql(desc = "manga id")] manga_id: Option<i64>,
+    ) -> Result<bool> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+
+        Ok(ctx
+            .data::<PreferenceService<PreferenceRepositoryImpl>>()?
+            .delete_preference(claims.sub, &key, manga_id)
+            .await?)
+    }
+}
+
+#[derive(Default)]
+pub struct PreferenceSubscriptionRoot;
+
+#[Subscription]
+impl PreferenceSubscriptionRoot {
+    /// Preferences of logged in user saved or deleted from any device
+    #[graphql(guard = "SessionGuard::new()")]
+    async fn user_preference_changed(
+        &self,
+        ctx: &Context<'_>,
+    ) -> Result<impl Stream<Item = UserPreferenceChange>> {
+        let user_id = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?
+            .sub;
+
+        let receiver = ctx
+            .data::<PreferenceService<PreferenceRepositoryImpl>>()?
+            .subscribe();
+
+        let stream = tokio_stream::wrappers::BroadcastStream::new(receiver).filter_map(
+            move |res| async move {
+                match res {
+                    Ok(change) if change.preference.user_id == user_id => {
+                        Some(UserPreferenceChange {
+                            preference: change.preference.into(),
+                            deleted: change.deleted,
+                        })
+                    }
+                    _ => None,
+                }
+            },
+        );
+
+        Ok(stream)
+    }
+}
@@ -2,8 +2,9 @@ use async_graphql::{Context, Object, Result};
 use chrono::NaiveDateTime;
 
 use crate::{
-    domain::services::image::ImageService,
+    domain::{entities::image::ImageOrigin, services::image::ImageService},
     infrastructure::{
+        auth::Claims,
         config::Config,
         domain::repositories::{image::ImageRepositoryImpl, image_cache::ImageCacheRepositoryImpl},
     },
@@ -11,6 +12,7 @@ use crate::{
 
 pub struct RecentChapter {
     pub manga_id: i64,
+    pub source_id: i64,
     pub chapter_id: i64,
     pub manga_title: String,
     pub cover_url: String,
@@ -23,6 +25,7 @@ impl From<crate::domain::entities::history::HistoryChapter> for RecentChapter {
     fn from(other: crate::domain::entities::history::HistoryChapter) -> Self {
         Self {
             manga_id: other.manga_id,
+            source_id: other.source_id,
             chapter_id: other.chapter_id,
             manga_title: other.manga_title,
             cover_url: other.cover_url,
@@ -48,11 +51,19 @@ impl RecentChapter {
     }
 
     async fn cover_url(&self, ctx: &Context<'_>) -> Result<String> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
         let secret = &ctx.data::<Config>()?.secret;
 
         let cover_url = ctx
             .data::<ImageService<ImageCacheRepositoryImpl, ImageRepositoryImpl>>()?
-            .encrypt_image_url(secret, &self.cover_url)?;
+            .encrypt_image_url(
+                secret,
+                &self.cover_url,
+                ImageOrigin::cover(self.source_id, self.manga_id),
+                claims.sub,
+            )?;
 
         Ok(cover_url)
     }
@@ -73,6 +84,7 @@ impl RecentChapter {
 #[derive(Debug)]
 pub struct RecentUpdate {
     pub manga_id: i64,
+    pub source_id: i64,
     pub chapter_id: i64,
     pub manga_title: String,
     pub cover_url: String,
@@ -84,6 +96,7 @@ impl From<crate::domain::entities::library::LibraryUpdate> for RecentUpdate {
     fn from(other: crate::domain::entities::library::LibraryUpdate) -> Self {
         Self {
             manga_id: other.manga_id,
+            source_id: other.source_id,
             chapter_id: other.chapter_id,
             manga_title: other.manga_title,
             cover_url: other.cover_url,
@@ -108,11 +121,19 @@ impl RecentUpdate {
     }
 
     async fn cover_url(&self, ctx: &Context<'_>) -> Result<String> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
         let secret = &ctx.data::<Config>()?.secret;
 
         let cover_url = ctx
             .data::<ImageService<ImageCacheRepositoryImpl, ImageRepositoryImpl>>()?
-            .encrypt_image_url(secret, &self.cover_url)?;
+            .encrypt_image_url(
+                secret,
+                &self.cover_url,
+                ImageOrigin::cover(self.source_id, self.manga_id),
+                claims.sub,
+            )?;
 
         Ok(cover_url)
     }
@@ -6,15 +6,27 @@ use crate::infrastructure::domain::repositories::{
 };
 
 use super::{
-    catalogue::CatalogueRoot,
+    api_key::{ApiKeyMutationRoot, ApiKeyRoot},
+    audit_log::AuditLogRoot,
+    catalogue::{CatalogueRoot, CatalogueSubscriptionRoot},
     categories::{CategoryMutationRoot, CategoryRoot},
+    content_policy::{ContentPolicyMutationRoot, ContentPolicyRoot},
     downloads::{DownloadMutationRoot, DownloadRoot},
+    http_profile::{HttpProfileMutationRoot, HttpProfileRoot},
+    image_cache::{ImageCacheMutationRoot, ImageCacheRoot},
     library::{LibraryMutationRoot, LibraryRoot, LibrarySubscriptionRoot},
+    manga::MangaMutationRoot,
+    migration::{MigrationMutationRoot, MigrationRoot},
     notification::NotificationRoot,
+    oidc::{OidcMutationRoot, OidcRoot},
+    preference::{PreferenceMutationRoot, PreferenceRoot, PreferenceSubscriptionRoot},
+    session::{SessionMutationRoot, SessionRoot},
     source::{SourceMutationRoot, SourceRoot},
+    statistics::StatisticsRoot,
     status::StatusRoot,
     tracking::{TrackingMutationRoot, TrackingRoot},
     user::{UserMutationRoot, UserRoot},
+    websocket::WebSocketAuth,
 };
 
 use async_graphql::{
@@ -34,6 +46,16 @@ pub struct QueryRoot(
     NotificationRoot,
     DownloadRoot,
     TrackingRoot,
+    ImageCacheRoot,
+    HttpProfileRoot,
+    MigrationRoot,
+    SessionRoot,
+    ApiKeyRoot,
+    OidcRoot,
+    AuditLogRoot,
+    ContentPolicyRoot,
+    PreferenceRoot,
+    StatisticsRoot,
 );
 
 #[derive(MergedObject, Default)]
@@ -44,10 +66,23 @@ pub struct MutationRoot(
     SourceMutationRoot,
     DownloadMutationRoot,
     TrackingMutationRoot,
+    ImageCacheMutationRoot,
+    HttpProfileMutationRoot,
+    MigrationMutationRoot,
+    SessionMutationRoot,
+    ApiKeyMutationRoot,
+    OidcMutationRoot,
+    ContentPolicyMutationRoot,
+    PreferenceMutationRoot,
+    MangaMutationRoot,
 );
 
 #[derive(MergedSubscription, Default)]
-pub struct SubscriptionRoot(LibrarySubscriptionRoot);
+pub struct SubscriptionRoot(
+    LibrarySubscriptionRoot,
+    PreferenceSubscriptionRoot,
+    CatalogueSubscriptionRoot,
+);
 
 pub type DatabaseLoader = crate::presentation::graphql::loader::DatabaseLoader<
     HistoryRepositoryImpl,
@@ -66,7 +101,8 @@ impl Default for SchemaBuilder {
             MutationRoot::default(),
             SubscriptionRoot::default(),
         )
-        .extension(Logger);
+        .extension(Logger)
+        .extension(WebSocketAuth);
 
         Self(builder)
     }
@@ -0,0 +1,124 @@
+use super::guard::SessionGuard;
+use crate::{
+    domain::services::session::SessionService,
+    infrastructure::{
+        auth::Claims,
+        domain::repositories::{session::SessionRepositoryImpl, user::UserRepositoryImpl},
+    },
+};
+use async_graphql::{Context, Object, Result, SimpleObject};
+use chrono::NaiveDateTime;
+
+#[derive(Debug, SimpleObject)]
+pub struct AuthToken {
+    pub access_token: String,
+    pub refresh_token: String,
+    /// unix timestamp when access token expires
+    pub expires_at: i64,
+    /// access token can only enroll two-factor authentication, login again after confirming it
+    pub totp_enrollment: bool,
+}
+
+impl From<crate::domain::entities::session::AuthToken> for AuthToken {
+    fn from(token: crate::domain::entities::session::AuthToken) -> Self {
+        Self {
+            access_token: token.access_token,
+            refresh_token: token.refresh_token,
+            expires_at: token.expires_at,
+            totp_enrollment: token.totp_enrollment,
+        }
+    }
+}
+
+#[derive(Debug, SimpleObject)]
+pub struct UserSession {
+    pub id: i64,
+    pub user_agent: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub last_used_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    /// true if this is the session making the request
+    pub current: bool,
+}
+
+#[derive(Default)]
+pub struct SessionRoot;
+
+#[Object]
+impl SessionRoot {
+    #[graphql(guard = "SessionGuard::new()")]
+    async fn sessions(&self, ctx: &Context<'_>) -> Result<Vec<UserSession>> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+
+        let sessions = ctx
+            .data::<SessionService<SessionRepositoryImpl, UserRepositoryImpl>>()?
+            .get_sessions(claims.sub)
+            .await?
+            .into_iter()
+            .map(|session| UserSession {
+                id: session.id,
+                user_agent: session.user_agent,
+                created_at: session.created_at,
+                last_used_at: session.last_used_at,
+                expires_at: session.expires_at,
+                current: session.id == claims.sid,
+            })
+            .collect();
+
+        Ok(sessions)
+    }
+}
+
+#[derive(Default)]
+pub struct SessionMutationRoot;
+
+#[Object]
+impl SessionMutationRoot {
+    /// Exchange refresh token for a new access token, the refresh token can only be used once
+    async fn refresh_token(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(secret)] refresh_token: String,
+    ) -> Result<AuthToken> {
+        let token = ctx
+            .data::<SessionService<SessionRepositoryImpl, UserRepositoryImpl>>()?
+            .refresh_session(&refresh_token)
+            .await?;
+
+        Ok(token.into())
+    }
+
+    #[graphql(guard = "SessionGuard::new()")]
+    async fn revoke_session(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "session id")] id: i64,
+    ) -> Result<u64> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+
+        Ok(ctx
+            .data::<SessionService<SessionRepositoryImpl, UserRepositoryImpl>>()?
+            .revoke_session(claims.sub, id)
+            .await?)
+    }
+
+    #[graphql(guard = "SessionGuard::new()")]
+    async fn revoke_all_sessions(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "keep current session", default = false)] except_current: bool,
+    ) -> Result<u64> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+
+        Ok(ctx
+            .data::<SessionService<SessionRepositoryImpl, UserRepositoryImpl>>()?
+            .revoke_all_sessions(claims.sub, except_current.then_some(claims.sid))
+            .await?)
+    }
+}
@@ -1,11 +1,17 @@
-use super::{common::InputList, guard::AdminGuard};
+use super::{audit_log::record_audit, common::InputList, guard::AdminGuard};
 use crate::{
-    domain::services::source::SourceService,
+    domain::{
+        entities::audit_log::AuditAction,
+        services::{content_policy::ContentPolicyService, source::SourceService},
+    },
     infrastructure::{
-        auth::Claims, config::Config, domain::repositories::source::SourceRepositoryImpl,
+        auth::Claims,
+        domain::repositories::{
+            content_policy::ContentPolicyRepositoryImpl, source::SourceRepositoryImpl,
+        },
     },
 };
-use async_graphql::{Context, Object, Result};
+use async_graphql::{Context, Object, Result, SimpleObject};
 use serde::Deserialize;
 use tanoshi_vm::extension::ExtensionManager;
 
@@ -19,7 +25,13 @@ pub struct Source {
     pub lib_version: String,
     pub icon: String,
     #[serde(default)]
+    pub nsfw: bool,
+    #[serde(default)]
     pub has_update: bool,
+    #[serde(default)]
+    pub repository: Option<String>,
+    #[serde(default)]
+    pub conflicts: Vec<String>,
 }
 
 impl From<crate::domain::entities::source::Source> for Source {
@@ -32,7 +44,10 @@ impl From<crate::domain::entities::source::Source> for Source {
             rustc_version: s.rustc_version,
             lib_version: s.lib_version,
             icon: s.icon,
+            nsfw: s.nsfw,
             has_update: s.has_update,
+            repository: s.repository,
+            conflicts: s.conflicts,
         }
     }
 }
@@ -59,10 +74,24 @@ impl Source {
         self.icon.clone()
     }
 
+    async fn nsfw(&self) -> bool {
+        self.nsfw
+    }
+
     async fn has_update(&self) -> bool {
         self.has_update
     }
 
+    /// Repository the source is installed or available from
+    async fn repository(&self) -> Option<String> {
+        self.repository.clone()
+    }
+
+    /// Other repositories providing a source with the same id
+    async fn conflicts(&self) -> Vec<String> {
+        self.conflicts.clone()
+    }
+
     async fn filters(&self, ctx: &Context<'_>) -> Result<InputList> {
         let filters = ctx.data::<ExtensionManager>()?.filter_list(self.id)?;
 
@@ -76,6 +105,31 @@ impl Source {
     }
 }
 
+#[derive(SimpleObject)]
+pub struct ExtensionRepository {
+    pub name: String,
+    /// url or local directory
+    pub url: String,
+    /// built in or defined in config, can't be removed
+    pub from_config: bool,
+    /// base64 encoded ed25519 public keys trusted to sign extensions
+    pub trusted_keys: Vec<String>,
+    /// extensions without a valid signature of a trusted key can be installed
+    pub allow_unsigned: bool,
+}
+
+impl From<crate::domain::entities::source::ExtensionRepository> for ExtensionRepository {
+    fn from(repository: crate::domain::entities::source::ExtensionRepository) -> Self {
+        Self {
+            name: repository.name,
+            url: repository.url,
+            from_config: repository.from_config,
+            trusted_keys: repository.trust.trusted_keys,
+            allow_unsigned: repository.trust.allow_unsigned,
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct SourceRoot;
 
@@ -86,13 +140,16 @@ impl SourceRoot {
         ctx: &Context<'_>,
         check_update: bool,
     ) -> Result<Vec<Source>> {
-        let _ = ctx.data::<Claims>()?;
-
-        let repo_url = &ctx.data::<Config>()?.extension_repository;
+        let claims = ctx.data::<Claims>()?;
 
         let sources = ctx
             .data::<SourceService<SourceRepositoryImpl>>()?
-            .get_installed_sources(repo_url, check_update)
+            .get_installed_sources(check_update)
+            .await?;
+
+        let sources = ctx
+            .data::<ContentPolicyService<ContentPolicyRepositoryImpl, SourceRepositoryImpl>>()?
+            .filter_sources(claims.sub, sources)
             .await?
             .into_iter()
             .map(Source::from)
@@ -104,11 +161,9 @@ impl SourceRoot {
     async fn available_sources(&self, ctx: &Context<'_>) -> Result<Vec<Source>> {
         let _ = ctx.data::<Claims>()?;
 
-        let repo_url = &ctx.data::<Config>()?.extension_repository;
-
         let sources = ctx
             .data::<SourceService<SourceRepositoryImpl>>()?
-            .get_available_sources(repo_url)
+            .get_available_sources()
             .await?
             .into_iter()
             .map(Source::from)
@@ -117,8 +172,25 @@ impl SourceRoot {
         Ok(sources)
     }
 
+    #[graphql(guard = "AdminGuard::new()")]
+    async fn extension_repositories(&self, ctx: &Context<'_>) -> Result<Vec<ExtensionRepository>> {
+        let repositories = ctx
+            .data::<SourceService<SourceRepositoryImpl>>()?
+            .get_extension_repositories()
+            .await?
+            .into_iter()
+            .map(ExtensionRepository::from)
+            .collect();
+
+        Ok(repositories)
+    }
+
     async fn source(&self, ctx: &Context<'_>, source_id: i64) -> Result<Source> {
-        let _ = ctx.data::<Claims>()?;
+        let claims = ctx.data::<Claims>()?;
+
+        ctx.data::<ContentPolicyService<ContentPolicyRepositoryImpl, SourceRepositoryImpl>>()?
+            .check_source(claims.sub, source_id)
+            .await?;
 
         let source = ctx
             .data::<SourceService<SourceRepositoryImpl>>()?
@@ -136,17 +208,23 @@ pub struct SourceMutationRoot;
 #[Object]
 impl SourceMutationRoot {
     #[graphql(guard = "AdminGuard::new()")]
-    async fn install_source(&self, ctx: &Context<'_>, source_id: i64) -> Result<i64> {
+    async fn install_source(
+        &self,
+        ctx: &Context<'_>,
+        source_id: i64,
+        #[graphql(desc = "repository name, default to the first one providing the source")]
+        repository: Option<String>,
+    ) -> Result<i64> {
         if ctx.data::<ExtensionManager>()?.exists(source_id).await? {
             return Err("source installed, use updateSource to update".into());
         }
 
-        let repo_url = &ctx.data::<Config>()?.extension_repository;
-
         ctx.data::<SourceService<SourceRepositoryImpl>>()?
-            .install_source(repo_url, source_id)
+            .install_source(source_id, repository.as_deref())
             .await?;
 
+        record_audit(ctx, AuditAction::InstallSource, Some(source_id.to_string())).await;
+
         Ok(source_id)
     }
 
@@ -156,20 +234,65 @@ impl SourceMutationRoot {
             .uninstall_source(source_id)
             .await?;
 
+        record_audit(
+            ctx,
+            AuditAction::UninstallSource,
+            Some(source_id.to_string()),
+        )
+        .await;
+
         Ok(source_id)
     }
 
     #[graphql(guard = "AdminGuard::new()")]
     async fn update_source(&self, ctx: &Context<'_>, source_id: i64) -> Result<i64> {
-        let repo_url = &ctx.data::<Config>()?.extension_repository;
-
         ctx.data::<SourceService<SourceRepositoryImpl>>()?
-            .update_source(repo_url, source_id)
+            .update_source(source_id)
             .await?;
 
+        record_audit(ctx, AuditAction::UpdateSource, Some(source_id.to_string())).await;
+
         Ok(source_id)
     }
 
+    #[graphql(guard = "AdminGuard::new()")]
+    async fn add_extension_repository(
+        &self,
+        ctx: &Context<'_>,
+        name: String,
+        #[graphql(desc = "url or local directory containing index.json")] url: String,
+    ) -> Result<ExtensionRepository> {
+        let repository = ctx
+            .data::<SourceService<SourceRepositoryImpl>>()?
+            .add_extension_repository(&name, &url)
+            .await?;
+
+        record_audit(
+            ctx,
+            AuditAction::AddExtensionRepository,
+            Some(repository.name.clone()),
+        )
+        .await;
+
+        Ok(repository.into())
+    }
+
+    #[graphql(guard = "AdminGuard::new()")]
+    async fn remove_extension_repository(&self, ctx: &Context<'_>, name: String) -> Result<String> {
+        ctx.data::<SourceService<SourceRepositoryImpl>>()?
+            .remove_extension_repository(&name)
+            .await?;
+
+        record_audit(
+            ctx,
+            AuditAction::RemoveExtensionRepository,
+            Some(name.clone()),
+        )
+        .await;
+
+        Ok(name)
+    }
+
     #[graphql(guard = "AdminGuard::new()")]
     async fn set_preferences(
         &self,
@@ -181,6 +304,13 @@ impl SourceMutationRoot {
             .set_preferences(source_id, preferences.0)
             .await?;
 
+        record_audit(
+            ctx,
+            AuditAction::SetSourcePreferences,
+            Some(source_id.to_string()),
+        )
+        .await;
+
         Ok(source_id)
     }
 }
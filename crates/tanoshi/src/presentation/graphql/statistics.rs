@@ -0,0 +1,294 @@
+use super::guard::ScopeGuard;
+use crate::{
+    domain::{
+        entities::{api_key::ApiKeyScope, statistics::ActivityPeriod},
+        services::{source::SourceService, statistics::StatisticsService},
+    },
+    infrastructure::{
+        auth::Claims,
+        domain::repositories::{
+            source::SourceRepositoryImpl, statistics::StatisticsRepositoryImpl,
+        },
+    },
+};
+use async_graphql::{Context, Object, Result, SimpleObject};
+use chrono::{NaiveDate, NaiveDateTime};
+
+#[derive(Debug, SimpleObject)]
+pub struct ReadingSummary {
+    pub chapters: i64,
+    pub pages: i64,
+    pub manga: i64,
+    pub completed_manga: i64,
+    /// days with at least one chapter read
+    pub days: i64,
+    /// chapters read less than 30 minutes apart belong to the same session
+    pub sessions: i64,
+    pub average_chapters_per_session: f64,
+}
+
+impl From<crate::domain::entities::statistics::ReadingSummary> for ReadingSummary {
+    fn from(summary: crate::domain::entities::statistics::ReadingSummary) -> Self {
+        Self {
+            chapters: summary.chapters,
+            pages: summary.pages,
+            manga: summary.manga,
+            completed_manga: summary.completed_manga,
+            days: summary.days,
+            sessions: summary.sessions,
+            average_chapters_per_session: summary.average_chapters_per_session,
+        }
+    }
+}
+
+#[derive(Debug, SimpleObject)]
+pub struct ReadingActivity {
+    pub period: String,
+    pub chapters: i64,
+    pub pages: i64,
+}
+
+impl From<crate::domain::entities::statistics::ReadingActivity> for ReadingActivity {
+    fn from(activity: crate::domain::entities::statistics::ReadingActivity) -> Self {
+        Self {
+            period: activity.period,
+            chapters: activity.chapters,
+            pages: activity.pages,
+        }
+    }
+}
+
+#[derive(Debug, SimpleObject)]
+pub struct ReadingStreak {
+    pub current: i64,
+    pub longest: i64,
+    pub longest_start: Option<NaiveDate>,
+    pub longest_end: Option<NaiveDate>,
+}
+
+impl From<crate::domain::entities::statistics::ReadingStreak> for ReadingStreak {
+    fn from(streak: crate::domain::entities::statistics::ReadingStreak) -> Self {
+        Self {
+            current: streak.current,
+            longest: streak.longest,
+            longest_start: streak.longest_start,
+            longest_end: streak.longest_end,
+        }
+    }
+}
+
+#[derive(Debug, SimpleObject)]
+pub struct ReadingCount {
+    pub name: String,
+    pub chapters: i64,
+}
+
+impl From<crate::domain::entities::statistics::ReadingCount> for ReadingCount {
+    fn from(count: crate::domain::entities::statistics::ReadingCount) -> Self {
+        Self {
+            name: count.name,
+            chapters: count.chapters,
+        }
+    }
+}
+
+#[derive(Debug, SimpleObject)]
+pub struct SourceReadingCount {
+    pub source_id: i64,
+    /// null if the source is no longer installed
+    pub name: Option<String>,
+    pub chapters: i64,
+}
+
+#[derive(Debug, SimpleObject)]
+pub struct MangaCompletion {
+    pub manga_id: i64,
+    pub title: String,
+    pub chapters_read: i64,
+    pub chapters_total: i64,
+    pub started_at: NaiveDateTime,
+    pub last_read_at: NaiveDateTime,
+    pub completed_at: Option<NaiveDateTime>,
+}
+
+impl From<crate::domain::entities::statistics::MangaCompletion> for MangaCompletion {
+    fn from(completion: crate::domain::entities::statistics::MangaCompletion) -> Self {
+        Self {
+            manga_id: completion.manga_id,
+            title: completion.title,
+            chapters_read: completion.chapters_read,
+            chapters_total: completion.chapters_total,
+            started_at: completion.started_at,
+            last_read_at: completion.last_read_at,
+            completed_at: completion.completed_at,
+        }
+    }
+}
+
+pub struct ReadingStatistics {
+    user_id: i64,
+    from: Option<NaiveDateTime>,
+    to: Option<NaiveDateTime>,
+    utc_offset_minutes: i32,
+}
+
+#[Object]
+impl ReadingStatistics {
+    async fn from(&self) -> Option<NaiveDateTime> {
+        self.from
+    }
+
+    async fn to(&self) -> Option<NaiveDateTime> {
+        self.to
+    }
+
+    async fn summary(&self, ctx: &Context<'_>) -> Result<ReadingSummary> {
+        let summary = ctx
+            .data::<StatisticsService<StatisticsRepositoryImpl>>()?
+            .get_reading_summary(self.user_id, self.utc_offset_minutes, self.from, self.to)
+            .await?;
+
+        Ok(summary.into())
+    }
+
+    async fn activity(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "one of day, week, month or year")] period: Option<String>,
+    ) -> Result<Vec<ReadingActivity>> {
+        let period = period
+            .map(|period| period.parse::<ActivityPeriod>())
+            .transpose()?
+            .unwrap_or_default();
+
+        let activity = ctx
+            .data::<StatisticsService<StatisticsRepositoryImpl>>()?
+            .get_reading_activity(
+                self.user_id,
+                period,
+                self.utc_offset_minutes,
+                self.from,
+                self.to,
+            )
+            .await?;
+
+        Ok(activity.into_iter().map(ReadingActivity::from).collect())
+    }
+
+    async fn streak(&self, ctx: &Context<'_>) -> Result<ReadingStreak> {
+        let streak = ctx
+            .data::<StatisticsService<StatisticsRepositoryImpl>>()?
+            .get_reading_streak(self.user_id, self.utc_offset_minutes, self.from, self.to)
+            .await?;
+
+        Ok(streak.into())
+    }
+
+    async fn top_genres(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default = 10)] limit: i64,
+    ) -> Result<Vec<ReadingCount>> {
+        let genres = ctx
+            .data::<StatisticsService<StatisticsRepositoryImpl>>()?
+            .get_top_genres(self.user_id, self.from, self.to, limit)
+            .await?;
+
+        Ok(genres.into_iter().map(ReadingCount::from).collect())
+    }
+
+    async fn top_authors(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default = 10)] limit: i64,
+    ) -> Result<Vec<ReadingCount>> {
+        let authors = ctx
+            .data::<StatisticsService<StatisticsRepositoryImpl>>()?
+            .get_top_authors(self.user_id, self.from, self.to, limit)
+            .await?;
+
+        Ok(authors.into_iter().map(ReadingCount::from).collect())
+    }
+
+    async fn top_sources(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default = 10)] limit: i64,
+    ) -> Result<Vec<SourceReadingCount>> {
+        let source_svc = ctx.data::<SourceService<SourceRepositoryImpl>>()?;
+
+        let counts = ctx
+            .data::<StatisticsService<StatisticsRepositoryImpl>>()?
+            .get_top_sources(self.user_id, self.from, self.to, limit)
+            .await?;
+
+        let mut sources = vec![];
+        for count in counts {
+            let name = source_svc
+                .get_source_by_id(count.source_id)
+                .await
+                .ok()
+                .map(|source| source.name);
+
+            sources.push(SourceReadingCount {
+                source_id: count.source_id,
+                name,
+                chapters: count.chapters,
+            });
+        }
+
+        Ok(sources)
+    }
+
+    async fn manga_completion(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default = 50)] limit: i64,
+    ) -> Result<Vec<MangaCompletion>> {
+        let completion = ctx
+            .data::<StatisticsService<StatisticsRepositoryImpl>>()?
+            .get_manga_completion(self.user_id, self.from, self.to, limit)
+            .await?;
+
+        Ok(completion.into_iter().map(MangaCompletion::from).collect())
+    }
+}
+
+#[derive(Default)]
+pub struct StatisticsRoot;
+
+#[Object]
+impl StatisticsRoot {
+    /// Reading statistics of logged in user, derived from reading history
+    #[graphql(guard = "ScopeGuard::new(ApiKeyScope::LibraryRead)")]
+    async fn reading_statistics(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "only count chapters read at or after this time, in utc")] from: Option<
+            NaiveDateTime,
+        >,
+        #[graphql(desc = "only count chapters read before this time, in utc")] to: Option<
+            NaiveDateTime,
+        >,
+        #[graphql(
+            desc = "offset of client timezone from utc in minutes, used to group by day",
+            default = 0
+        )]
+        utc_offset_minutes: i32,
+    ) -> Result<ReadingStatistics> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+
+        if !(-14 * 60..=14 * 60).contains(&utc_offset_minutes) {
+            return Err("invalid utc offset".into());
+        }
+
+        Ok(ReadingStatistics {
+            user_id: claims.sub,
+            from,
+            to,
+            utc_offset_minutes,
+        })
+    }
+}
@@ -1,8 +1,11 @@
 use async_graphql::{Context, Object, Result, SimpleObject};
 
 use crate::{
-    domain::services::user::UserService,
-    infrastructure::{auth::Claims, domain::repositories::user::UserRepositoryImpl},
+    domain::services::{oidc::OidcService, user::UserService},
+    infrastructure::{
+        auth::Claims,
+        domain::repositories::{identity::IdentityRepositoryImpl, user::UserRepositoryImpl},
+    },
 };
 
 #[derive(Debug, SimpleObject)]
@@ -10,6 +13,8 @@ struct Status {
     activated: bool,
     version: String,
     loggedin: bool,
+    oidc_enabled: bool,
+    password_login_enabled: bool,
 }
 
 #[derive(Default)]
@@ -27,10 +32,14 @@ impl StatusRoot {
             .is_empty();
         let version = env!("CARGO_PKG_VERSION").to_string();
 
+        let oidc_svc = ctx.data::<OidcService<IdentityRepositoryImpl, UserRepositoryImpl>>()?;
+
         Ok(Status {
             activated,
             version,
             loggedin,
+            oidc_enabled: oidc_svc.is_enabled(),
+            password_login_enabled: !oidc_svc.is_password_login_disabled(),
         })
     }
 }
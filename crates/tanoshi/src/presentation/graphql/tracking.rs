@@ -1,6 +1,7 @@
 use async_graphql::{Context, InputObject, Object, Result, SimpleObject};
 use chrono::NaiveDateTime;
 
+use super::guard::SessionGuard;
 use crate::domain::services::tracker::TrackerService;
 use crate::infrastructure::auth::Claims;
 use crate::infrastructure::domain::repositories::tracker::TrackerRepositoryImpl;
@@ -77,6 +78,7 @@ pub struct TrackingRoot;
 
 #[Object]
 impl TrackingRoot {
+    #[graphql(guard = "SessionGuard::new()")]
     async fn myanimelist_login_start(&self, ctx: &Context<'_>) -> Result<Session> {
         let _ = ctx
             .data::<Claims>()
@@ -95,6 +97,7 @@ impl TrackingRoot {
         })
     }
 
+    #[graphql(guard = "SessionGuard::new()")]
     async fn myanimelist_login_end(
         &self,
         ctx: &Context<'_>,
@@ -121,6 +124,7 @@ impl TrackingRoot {
         Ok("Success".to_string())
     }
 
+    #[graphql(guard = "SessionGuard::new()")]
     async fn anilist_login_start(&self, ctx: &Context<'_>) -> Result<Session> {
         let _ = ctx
             .data::<Claims>()
@@ -139,6 +143,7 @@ impl TrackingRoot {
         })
     }
 
+    #[graphql(guard = "SessionGuard::new()")]
     async fn anilist_login_end(&self, ctx: &Context<'_>, code: String) -> Result<String> {
         let claim = ctx
             .data::<Claims>()
@@ -151,6 +156,7 @@ impl TrackingRoot {
         Ok("Success".to_string())
     }
 
+    #[graphql(guard = "SessionGuard::new()")]
     async fn search_tracker_manga(
         &self,
         ctx: &Context<'_>,
@@ -172,6 +178,7 @@ impl TrackingRoot {
         Ok(manga)
     }
 
+    #[graphql(guard = "SessionGuard::new()")]
     async fn manga_tracker_status(
         &self,
         ctx: &Context<'_>,
@@ -198,6 +205,7 @@ pub struct TrackingMutationRoot;
 
 #[Object]
 impl TrackingMutationRoot {
+    #[graphql(guard = "SessionGuard::new()")]
     async fn track_manga(
         &self,
         ctx: &Context<'_>,
@@ -216,6 +224,7 @@ impl TrackingMutationRoot {
         Ok(1)
     }
 
+    #[graphql(guard = "SessionGuard::new()")]
     async fn untrack_manga(
         &self,
         ctx: &Context<'_>,
@@ -233,6 +242,7 @@ impl TrackingMutationRoot {
         Ok(1)
     }
 
+    #[graphql(guard = "SessionGuard::new()")]
     async fn update_tracker_status(
         &self,
         ctx: &Context<'_>,
@@ -260,6 +270,7 @@ impl TrackingMutationRoot {
         Ok(true)
     }
 
+    #[graphql(guard = "SessionGuard::new()")]
     async fn tracker_logout(&self, ctx: &Context<'_>, tracker: String) -> Result<u64> {
         let claims = ctx
             .data::<Claims>()
@@ -1,13 +1,30 @@
-use super::guard::AdminGuard;
+use std::net::IpAddr;
+
+use super::{
+    audit_log::record_audit,
+    guard::{AdminGuard, SessionGuard},
+    session::AuthToken,
+};
 use crate::{
-    domain::services::{tracker::TrackerService, user::UserService},
+    domain::{
+        entities::audit_log::AuditAction,
+        services::{
+            audit_log::AuditLogService, login_throttle::LoginThrottleService, oidc::OidcService,
+            session::SessionService, totp::TotpService, tracker::TrackerService, user::UserService,
+        },
+    },
     infrastructure::{
-        auth::{self, Claims},
-        config::Config,
-        domain::repositories::{tracker::TrackerRepositoryImpl, user::UserRepositoryImpl},
+        auth::{Claims, TotpEnrollmentClaims},
+        domain::repositories::{
+            audit_log::AuditLogRepositoryImpl, identity::IdentityRepositoryImpl,
+            session::SessionRepositoryImpl, totp::TotpRepositoryImpl,
+            tracker::TrackerRepositoryImpl, user::UserRepositoryImpl,
+        },
     },
+    presentation::client_addr::ClientAddr,
 };
-use async_graphql::{Context, InputObject, Object, Result};
+use async_graphql::{Context, Error, ErrorExtensions, InputObject, Object, Result, SimpleObject};
+use headers::UserAgent;
 use tanoshi_tracker::{anilist, myanimelist};
 
 #[derive(Debug)]
@@ -85,6 +102,20 @@ impl User {
             .is_ok())
     }
 
+    async fn totp_enabled(&self, ctx: &Context<'_>) -> Result<bool> {
+        Ok(ctx
+            .data::<TotpService<TotpRepositoryImpl, UserRepositoryImpl>>()?
+            .is_enabled(self.id)
+            .await?)
+    }
+
+    async fn recovery_codes_count(&self, ctx: &Context<'_>) -> Result<i64> {
+        Ok(ctx
+            .data::<TotpService<TotpRepositoryImpl, UserRepositoryImpl>>()?
+            .get_recovery_codes_count(self.id)
+            .await?)
+    }
+
     async fn anilist_status(&self, ctx: &Context<'_>) -> Result<bool> {
         let user = ctx
             .data::<Claims>()
@@ -112,6 +143,21 @@ struct LoginInput {
     password: String,
 }
 
+#[derive(SimpleObject)]
+pub struct TotpEnrollment {
+    pub secret: String,
+    pub uri: String,
+}
+
+impl From<crate::domain::entities::totp::TotpEnrollment> for TotpEnrollment {
+    fn from(val: crate::domain::entities::totp::TotpEnrollment) -> Self {
+        Self {
+            secret: val.secret,
+            uri: val.uri,
+        }
+    }
+}
+
 #[derive(InputObject)]
 struct ChangePasswordInput {
     #[graphql(secret)]
@@ -120,31 +166,124 @@ struct ChangePasswordInput {
     new_password: String,
 }
 
+/// Count failed login towards lockout and record it in audit log
+async fn record_login_failure(
+    ctx: &Context<'_>,
+    username: &str,
+    user_id: Option<i64>,
+    ip: Option<IpAddr>,
+) -> Result<()> {
+    let locked = ctx
+        .data::<LoginThrottleService>()?
+        .record_failure(username, ip);
+
+    let audit_log_svc = ctx.data::<AuditLogService<AuditLogRepositoryImpl>>()?;
+    audit_log_svc
+        .record(user_id, Some(username), AuditAction::LoginFailed, None, ip)
+        .await;
+    if locked {
+        audit_log_svc
+            .record(user_id, Some(username), AuditAction::LoginLocked, None, ip)
+            .await;
+    }
+
+    Ok(())
+}
+
+/// User enrolling two-factor authentication, from a login session or an enrollment token
+fn totp_enrollment_user_id(ctx: &Context<'_>) -> Result<i64> {
+    if let Ok(claims) = ctx.data::<TotpEnrollmentClaims>() {
+        return Ok(claims.sub);
+    }
+
+    let claims = ctx
+        .data::<Claims>()
+        .map_err(|_| "token not exists, please login")?;
+    if claims.is_api_key() {
+        return Err("Forbidden for api key".into());
+    }
+
+    Ok(claims.sub)
+}
+
 #[derive(Default)]
 pub struct UserRoot;
 
 #[Object]
 impl UserRoot {
-    async fn login(&self, ctx: &Context<'_>, login: LoginInput) -> Result<String> {
+    async fn login(
+        &self,
+        ctx: &Context<'_>,
+        login: LoginInput,
+        #[graphql(secret, desc = "code from authenticator app or a recovery code")]
+        totp_code: Option<String>,
+    ) -> Result<AuthToken> {
+        if ctx
+            .data::<OidcService<IdentityRepositoryImpl, UserRepositoryImpl>>()?
+            .is_password_login_disabled()
+        {
+            return Err("password login is disabled, use single sign-on".into());
+        }
+
+        let ip = ctx.data_opt::<ClientAddr>().map(|ClientAddr(addr)| *addr);
+        let throttle_svc = ctx.data::<LoginThrottleService>()?;
+        throttle_svc.check(&login.username, ip)?;
+
         let user_svc = ctx.data::<UserService<UserRepositoryImpl>>()?;
 
-        user_svc
+        if let Err(e) = user_svc
             .verify_password(&login.username, &login.password)
-            .await?;
+            .await
+        {
+            record_login_failure(ctx, &login.username, None, ip).await?;
+            return Err(e.into());
+        }
 
         let user = user_svc.fetch_user_by_username(&login.username).await?;
 
-        let secret = &ctx.data::<Config>()?.secret;
-        let current_time = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?;
-        let claims = Claims {
-            sub: user.id,
-            username: user.username,
-            is_admin: user.is_admin,
-            exp: (current_time + std::time::Duration::from_secs(2678400)).as_secs() as usize, // 31 days
-        };
-        let token = auth::encode_jwt(secret, &claims)?;
+        let totp_svc = ctx.data::<TotpService<TotpRepositoryImpl, UserRepositoryImpl>>()?;
+        if totp_svc.is_enabled(user.id).await? {
+            match totp_code {
+                Some(code) => {
+                    if let Err(e) = totp_svc.verify(user.id, &code).await {
+                        record_login_failure(ctx, &login.username, Some(user.id), ip).await?;
+                        return Err(e.into());
+                    }
+                }
+                None => {
+                    return Err(Error::new("two-factor code required")
+                        .extend_with(|_, e| e.set("code", "TOTP_REQUIRED")))
+                }
+            }
+        }
+
+        let totp_enrollment = totp_svc.is_enrollment_required(&user).await?;
+
+        throttle_svc.record_success(&login.username);
+        ctx.data::<AuditLogService<AuditLogRepositoryImpl>>()?
+            .record(
+                Some(user.id),
+                Some(&user.username),
+                AuditAction::Login,
+                totp_enrollment.then_some("totp enrollment"),
+                ip,
+            )
+            .await;
+
+        let session_svc =
+            ctx.data::<SessionService<SessionRepositoryImpl, UserRepositoryImpl>>()?;
+
+        // no session until two-factor authentication is enrolled
+        if totp_enrollment {
+            return Ok(session_svc.create_totp_enrollment_token(&user)?.into());
+        }
+
+        let user_agent = ctx
+            .data_opt::<UserAgent>()
+            .map(|user_agent| user_agent.as_str());
+        let token = session_svc.create_session(&user, user_agent).await?;
 
-        Ok(token)
+        Ok(token.into())
     }
 
     #[graphql(guard = "AdminGuard::new()")]
@@ -157,6 +296,7 @@ impl UserRoot {
         Ok(users.into_iter().map(|user| user.into()).collect())
     }
 
+    #[graphql(guard = "SessionGuard::new()")]
     async fn me(&self, ctx: &Context<'_>) -> Result<User> {
         let claim = ctx
             .data::<Claims>()
@@ -187,14 +327,18 @@ impl UserMutationRoot {
 
         let user_count = user_svc.fetch_all_users().await?.len();
         if let Ok(claim) = ctx.data::<Claims>() {
-            if user_count > 0 && !claim.is_admin {
+            if user_count > 0 && (!claim.is_admin || claim.is_api_key()) {
                 return Err("Forbidden".into());
             }
         }
 
-        Ok(user_svc
+        let user_id = user_svc
             .create_user(&login.username, &login.password, is_admin)
-            .await?)
+            .await?;
+
+        record_audit(ctx, AuditAction::Register, Some(login.username)).await;
+
+        Ok(user_id)
     }
 
     #[graphql(guard = "AdminGuard::new()")]
@@ -203,13 +347,20 @@ impl UserMutationRoot {
         ctx: &Context<'_>,
         #[graphql(desc = "user id")] user_id: i64,
     ) -> Result<i64> {
+        ctx.data::<SessionService<SessionRepositoryImpl, UserRepositoryImpl>>()?
+            .revoke_all_sessions(user_id, None)
+            .await?;
+
         ctx.data::<UserService<UserRepositoryImpl>>()?
             .delete_user(user_id)
             .await?;
 
+        record_audit(ctx, AuditAction::DeleteUser, Some(user_id.to_string())).await;
+
         Ok(1)
     }
 
+    #[graphql(guard = "SessionGuard::new()")]
     async fn change_password(&self, ctx: &Context<'_>, input: ChangePasswordInput) -> Result<u64> {
         let claims = ctx
             .data::<Claims>()
@@ -219,9 +370,86 @@ impl UserMutationRoot {
             .change_password(claims.sub, &input.old_password, &input.new_password)
             .await?;
 
+        // log out every device, including the one changing password
+        ctx.data::<SessionService<SessionRepositoryImpl, UserRepositoryImpl>>()?
+            .revoke_all_sessions(claims.sub, None)
+            .await?;
+
         Ok(1)
     }
 
+    #[graphql(guard = "AdminGuard::new()")]
+    async fn reset_user_totp(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "user id")] user_id: i64,
+    ) -> Result<u64> {
+        let removed = ctx
+            .data::<TotpService<TotpRepositoryImpl, UserRepositoryImpl>>()?
+            .reset(user_id)
+            .await?;
+
+        // sessions were created with the old second factor
+        ctx.data::<SessionService<SessionRepositoryImpl, UserRepositoryImpl>>()?
+            .revoke_all_sessions(user_id, None)
+            .await?;
+
+        record_audit(ctx, AuditAction::ResetUserTotp, Some(user_id.to_string())).await;
+
+        Ok(removed)
+    }
+
+    /// Also accepts the enrollment token `login` returns
+    async fn enroll_totp(&self, ctx: &Context<'_>) -> Result<TotpEnrollment> {
+        let user_id = totp_enrollment_user_id(ctx)?;
+
+        Ok(ctx
+            .data::<TotpService<TotpRepositoryImpl, UserRepositoryImpl>>()?
+            .enroll(user_id)
+            .await?
+            .into())
+    }
+
+    /// Returns recovery codes, they are not shown again. Also accepts the enrollment token
+    /// `login` returns, login again afterwards to get a session
+    async fn confirm_totp(&self, ctx: &Context<'_>, code: String) -> Result<Vec<String>> {
+        let user_id = totp_enrollment_user_id(ctx)?;
+
+        Ok(ctx
+            .data::<TotpService<TotpRepositoryImpl, UserRepositoryImpl>>()?
+            .confirm(user_id, &code)
+            .await?)
+    }
+
+    #[graphql(guard = "SessionGuard::new()")]
+    async fn disable_totp(&self, ctx: &Context<'_>, code: String) -> Result<u64> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+
+        let totp_svc = ctx.data::<TotpService<TotpRepositoryImpl, UserRepositoryImpl>>()?;
+        totp_svc.verify(claims.sub, &code).await?;
+
+        Ok(totp_svc.reset(claims.sub).await?)
+    }
+
+    #[graphql(guard = "SessionGuard::new()")]
+    async fn regenerate_recovery_codes(
+        &self,
+        ctx: &Context<'_>,
+        code: String,
+    ) -> Result<Vec<String>> {
+        let claims = ctx
+            .data::<Claims>()
+            .map_err(|_| "token not exists, please login")?;
+
+        let totp_svc = ctx.data::<TotpService<TotpRepositoryImpl, UserRepositoryImpl>>()?;
+        totp_svc.verify(claims.sub, &code).await?;
+
+        Ok(totp_svc.generate_recovery_codes(claims.sub).await?)
+    }
+
+    #[graphql(guard = "SessionGuard::new()")]
     async fn update_profile(&self, ctx: &Context<'_>, input: ProfileInput) -> Result<u64> {
         let claims = ctx
             .data::<Claims>()
@@ -239,6 +467,7 @@ impl UserMutationRoot {
         Ok(1)
     }
 
+    #[graphql(guard = "SessionGuard::new()")]
     async fn tracker_logout(&self, ctx: &Context<'_>, tracker: String) -> Result<u64> {
         let claims = ctx
             .data::<Claims>()
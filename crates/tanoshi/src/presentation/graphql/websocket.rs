@@ -0,0 +1,134 @@
+use std::sync::Arc;
+
+use async_graphql::{
+    extensions::{
+        Extension, ExtensionContext, ExtensionFactory, NextPrepareRequest, NextSubscribe,
+    },
+    Request, Response, ServerError, ServerResult,
+};
+use futures::{future, stream::BoxStream, StreamExt};
+
+use crate::{
+    domain::services::{
+        api_key::ApiKeyService, proxy_auth::ProxyAuthService, session::SessionService,
+    },
+    infrastructure::{
+        auth::Claims,
+        domain::repositories::{
+            api_key::ApiKeyRepositoryImpl, session::SessionRepositoryImpl, user::UserRepositoryImpl,
+        },
+    },
+    presentation::token::{authenticate, Token},
+};
+
+/// Credential a websocket connection was opened with, kept in connection data
+/// so every operation authenticates again instead of trusting claims from connection init
+#[derive(Clone)]
+pub struct WebSocketCredential {
+    token: Credential,
+    session_svc: SessionService<SessionRepositoryImpl, UserRepositoryImpl>,
+    api_key_svc: ApiKeyService<ApiKeyRepositoryImpl, UserRepositoryImpl>,
+    proxy_auth_svc: ProxyAuthService<UserRepositoryImpl>,
+}
+
+#[derive(Clone)]
+enum Credential {
+    Token(String),
+    RemoteUser(String),
+}
+
+impl WebSocketCredential {
+    pub fn new(
+        token: Token,
+        session_svc: SessionService<SessionRepositoryImpl, UserRepositoryImpl>,
+        api_key_svc: ApiKeyService<ApiKeyRepositoryImpl, UserRepositoryImpl>,
+        proxy_auth_svc: ProxyAuthService<UserRepositoryImpl>,
+    ) -> Option<Self> {
+        let token = match token {
+            Token::Jwt(token) | Token::ApiKey(token) => Credential::Token(token),
+            Token::RemoteUser(username) => Credential::RemoteUser(username),
+            Token::None => return None,
+        };
+
+        Some(Self {
+            token,
+            session_svc,
+            api_key_svc,
+            proxy_auth_svc,
+        })
+    }
+
+    /// Claims of the credential, `None` once the session is revoked or the token expired
+    pub async fn authenticate(&self) -> Option<Claims> {
+        let token = match &self.token {
+            Credential::Token(token) => Token::from(token.clone()),
+            Credential::RemoteUser(username) => Token::RemoteUser(username.clone()),
+        };
+
+        authenticate(
+            token,
+            &self.session_svc,
+            &self.api_key_svc,
+            &self.proxy_auth_svc,
+        )
+        .await
+    }
+}
+
+/// Authenticate every operation on a websocket connection again, an operation
+/// of a revoked or expired session is rejected and a running subscription ends
+pub struct WebSocketAuth;
+
+impl ExtensionFactory for WebSocketAuth {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(WebSocketAuthExtension)
+    }
+}
+
+struct WebSocketAuthExtension;
+
+#[async_trait::async_trait]
+impl Extension for WebSocketAuthExtension {
+    async fn prepare_request(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        request: Request,
+        next: NextPrepareRequest<'_>,
+    ) -> ServerResult<Request> {
+        let credential = match ctx.data_opt::<WebSocketCredential>() {
+            Some(credential) => credential,
+            None => return next.run(ctx, request).await,
+        };
+
+        // claims in request data take precedence over claims from connection init
+        match credential.authenticate().await {
+            Some(claims) => next.run(ctx, request.data(claims)).await,
+            None => Err(ServerError::new(
+                "session expired or revoked, please login",
+                None,
+            )),
+        }
+    }
+
+    fn subscribe<'s>(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        stream: BoxStream<'s, Response>,
+        next: NextSubscribe<'_>,
+    ) -> BoxStream<'s, Response> {
+        let stream = next.run(ctx, stream);
+        let credential = match ctx.data_opt::<WebSocketCredential>() {
+            Some(credential) => credential.clone(),
+            None => return stream,
+        };
+
+        stream
+            .then(move |response| {
+                let credential = credential.clone();
+                async move { credential.authenticate().await.map(|_| response) }
+            })
+            .take_while(|response| future::ready(response.is_some()))
+            .filter_map(future::ready)
+            .boxed()
+    }
+}
@@ -1,5 +1,6 @@
 #[cfg(feature = "embed")]
 pub mod assets;
+pub mod client_addr;
 pub mod graphql;
 pub mod rest;
 pub mod token;
@@ -26,20 +27,30 @@ use self::{
 use crate::{
     application::worker::{
         downloads::DownloadSender,
+        prefetch::PrefetchSender,
         updates::{ChapterUpdateCommandSender, ChapterUpdateReceiver},
     },
     domain::services::{
-        chapter::ChapterService, download::DownloadService, history::HistoryService,
-        image::ImageService, library::LibraryService, manga::MangaService, source::SourceService,
-        tracker::TrackerService, user::UserService,
+        api_key::ApiKeyService, audit_log::AuditLogService, chapter::ChapterService,
+        content_policy::ContentPolicyService, download::DownloadService, history::HistoryService,
+        http_profile::HttpProfileService, image::ImageService, library::LibraryService,
+        login_throttle::LoginThrottleService, manga::MangaService, migration::MigrationService,
+        oidc::OidcService, preference::PreferenceService, proxy_auth::ProxyAuthService,
+        session::SessionService, source::SourceService, statistics::StatisticsService,
+        totp::TotpService, tracker::TrackerService, user::UserService,
     },
     infrastructure::{
         config::Config,
         domain::repositories::{
-            chapter::ChapterRepositoryImpl, download::DownloadRepositoryImpl,
-            history::HistoryRepositoryImpl, image::ImageRepositoryImpl,
-            image_cache::ImageCacheRepositoryImpl, library::LibraryRepositoryImpl,
-            manga::MangaRepositoryImpl, source::SourceRepositoryImpl,
+            api_key::ApiKeyRepositoryImpl, audit_log::AuditLogRepositoryImpl,
+            chapter::ChapterRepositoryImpl, content_policy::ContentPolicyRepositoryImpl,
+            download::DownloadRepositoryImpl, history::HistoryRepositoryImpl,
+            http_profile::HttpProfileRepositoryImpl, identity::IdentityRepositoryImpl,
+            image::ImageRepositoryImpl, image_cache::ImageCacheRepositoryImpl,
+            library::LibraryRepositoryImpl, manga::MangaRepositoryImpl,
+            migration::MigrationRepositoryImpl, preference::PreferenceRepositoryImpl,
+            session::SessionRepositoryImpl, source::SourceRepositoryImpl,
+            statistics::StatisticsRepositoryImpl, totp::TotpRepositoryImpl,
             tracker::TrackerRepositoryImpl, user::UserRepositoryImpl,
         },
         notification::Notification,
@@ -51,6 +62,17 @@ use tanoshi_vm::extension::ExtensionManager;
 pub struct ServerBuilder {
     config: Option<Config>,
     user_svc: Option<UserService<UserRepositoryImpl>>,
+    session_svc: Option<SessionService<SessionRepositoryImpl, UserRepositoryImpl>>,
+    api_key_svc: Option<ApiKeyService<ApiKeyRepositoryImpl, UserRepositoryImpl>>,
+    oidc_svc: Option<OidcService<IdentityRepositoryImpl, UserRepositoryImpl>>,
+    proxy_auth_svc: Option<ProxyAuthService<UserRepositoryImpl>>,
+    totp_svc: Option<TotpService<TotpRepositoryImpl, UserRepositoryImpl>>,
+    login_throttle_svc: Option<LoginThrottleService>,
+    audit_log_svc: Option<AuditLogService<AuditLogRepositoryImpl>>,
+    content_policy_svc:
+        Option<ContentPolicyService<ContentPolicyRepositoryImpl, SourceRepositoryImpl>>,
+    preference_svc: Option<PreferenceService<PreferenceRepositoryImpl>>,
+    statistics_svc: Option<StatisticsService<StatisticsRepositoryImpl>>,
     tracker_svc: Option<TrackerService<TrackerRepositoryImpl>>,
     source_svc: Option<SourceService<SourceRepositoryImpl>>,
     manga_svc: Option<MangaService<MangaRepositoryImpl>>,
@@ -59,8 +81,18 @@ pub struct ServerBuilder {
     library_svc: Option<LibraryService<LibraryRepositoryImpl>>,
     history_svc: Option<HistoryService<ChapterRepositoryImpl, HistoryRepositoryImpl>>,
     download_svc: Option<DownloadService<DownloadRepositoryImpl>>,
+    http_profile_svc: Option<HttpProfileService<HttpProfileRepositoryImpl>>,
+    migration_svc: Option<
+        MigrationService<
+            LibraryRepositoryImpl,
+            MangaRepositoryImpl,
+            ChapterRepositoryImpl,
+            MigrationRepositoryImpl,
+        >,
+    >,
     ext_manager: Option<ExtensionManager>,
     download_tx: Option<DownloadSender>,
+    prefetch_tx: Option<PrefetchSender>,
     notifier: Option<Notification<UserRepositoryImpl>>,
     loader: Option<DatabaseLoader>,
     chapter_update_receiver: Option<ChapterUpdateReceiver>,
@@ -87,6 +119,100 @@ impl ServerBuilder {
         }
     }
 
+    pub fn with_session_svc(
+        self,
+        session_svc: SessionService<SessionRepositoryImpl, UserRepositoryImpl>,
+    ) -> Self {
+        Self {
+            session_svc: Some(session_svc),
+            ..self
+        }
+    }
+
+    pub fn with_api_key_svc(
+        self,
+        api_key_svc: ApiKeyService<ApiKeyRepositoryImpl, UserRepositoryImpl>,
+    ) -> Self {
+        Self {
+            api_key_svc: Some(api_key_svc),
+            ..self
+        }
+    }
+
+    pub fn with_oidc_svc(
+        self,
+        oidc_svc: OidcService<IdentityRepositoryImpl, UserRepositoryImpl>,
+    ) -> Self {
+        Self {
+            oidc_svc: Some(oidc_svc),
+            ..self
+        }
+    }
+
+    pub fn with_proxy_auth_svc(self, proxy_auth_svc: ProxyAuthService<UserRepositoryImpl>) -> Self {
+        Self {
+            proxy_auth_svc: Some(proxy_auth_svc),
+            ..self
+        }
+    }
+
+    pub fn with_totp_svc(
+        self,
+        totp_svc: TotpService<TotpRepositoryImpl, UserRepositoryImpl>,
+    ) -> Self {
+        Self {
+            totp_svc: Some(totp_svc),
+            ..self
+        }
+    }
+
+    pub fn with_login_throttle_svc(self, login_throttle_svc: LoginThrottleService) -> Self {
+        Self {
+            login_throttle_svc: Some(login_throttle_svc),
+            ..self
+        }
+    }
+
+    pub fn with_audit_log_svc(
+        self,
+        audit_log_svc: AuditLogService<AuditLogRepositoryImpl>,
+    ) -> Self {
+        Self {
+            audit_log_svc: Some(audit_log_svc),
+            ..self
+        }
+    }
+
+    pub fn with_content_policy_svc(
+        self,
+        content_policy_svc: ContentPolicyService<ContentPolicyRepositoryImpl, SourceRepositoryImpl>,
+    ) -> Self {
+        Self {
+            content_policy_svc: Some(content_policy_svc),
+            ..self
+        }
+    }
+
+    pub fn with_preference_svc(
+        self,
+        preference_svc: PreferenceService<PreferenceRepositoryImpl>,
+    ) -> Self {
+        Self {
+            preference_svc: Some(preference_svc),
+            ..self
+        }
+    }
+
+    pub fn with_statistics_svc(
+        self,
+        statistics_svc: StatisticsService<StatisticsRepositoryImpl>,
+    ) -> Self {
+        Self {
+            statistics_svc: Some(statistics_svc),
+            ..self
+        }
+    }
+
     pub fn with_tracker_svc(self, tracker_svc: TrackerService<TrackerRepositoryImpl>) -> Self {
         Self {
             tracker_svc: Some(tracker_svc),
@@ -149,6 +275,31 @@ impl ServerBuilder {
         }
     }
 
+    pub fn with_http_profile_svc(
+        self,
+        http_profile_svc: HttpProfileService<HttpProfileRepositoryImpl>,
+    ) -> Self {
+        Self {
+            http_profile_svc: Some(http_profile_svc),
+            ..self
+        }
+    }
+
+    pub fn with_migration_svc(
+        self,
+        migration_svc: MigrationService<
+            LibraryRepositoryImpl,
+            MangaRepositoryImpl,
+            ChapterRepositoryImpl,
+            MigrationRepositoryImpl,
+        >,
+    ) -> Self {
+        Self {
+            migration_svc: Some(migration_svc),
+            ..self
+        }
+    }
+
     pub fn with_ext_manager(self, ext_manager: ExtensionManager) -> Self {
         Self {
             ext_manager: Some(ext_manager),
@@ -163,6 +314,13 @@ impl ServerBuilder {
         }
     }
 
+    pub fn with_prefetch_tx(self, prefetch_tx: PrefetchSender) -> Self {
+        Self {
+            prefetch_tx: Some(prefetch_tx),
+            ..self
+        }
+    }
+
     pub fn with_notifier(self, notifier: Notification<UserRepositoryImpl>) -> Self {
         Self {
             notifier: Some(notifier),
@@ -201,6 +359,32 @@ impl ServerBuilder {
     pub async fn serve<A: Into<SocketAddr>>(self, addr: A) -> Result<(), anyhow::Error> {
         let config = self.config.ok_or_else(|| anyhow!("no config"))?;
         let user_svc = self.user_svc.ok_or_else(|| anyhow!("no user service"))?;
+        let session_svc = self
+            .session_svc
+            .ok_or_else(|| anyhow!("no session service"))?;
+        let api_key_svc = self
+            .api_key_svc
+            .ok_or_else(|| anyhow!("no api key service"))?;
+        let oidc_svc = self.oidc_svc.ok_or_else(|| anyhow!("no oidc service"))?;
+        let proxy_auth_svc = self
+            .proxy_auth_svc
+            .ok_or_else(|| anyhow!("no proxy auth service"))?;
+        let totp_svc = self.totp_svc.ok_or_else(|| anyhow!("no totp service"))?;
+        let login_throttle_svc = self
+            .login_throttle_svc
+            .ok_or_else(|| anyhow!("no login throttle service"))?;
+        let audit_log_svc = self
+            .audit_log_svc
+            .ok_or_else(|| anyhow!("no audit log service"))?;
+        let content_policy_svc = self
+            .content_policy_svc
+            .ok_or_else(|| anyhow!("no content policy service"))?;
+        let preference_svc = self
+            .preference_svc
+            .ok_or_else(|| anyhow!("no preference service"))?;
+        let statistics_svc = self
+            .statistics_svc
+            .ok_or_else(|| anyhow!("no statistics service"))?;
         let tracker_svc = self
             .tracker_svc
             .ok_or_else(|| anyhow!("no tracker service"))?;
@@ -221,12 +405,21 @@ impl ServerBuilder {
         let download_svc = self
             .download_svc
             .ok_or_else(|| anyhow!("no download service"))?;
+        let http_profile_svc = self
+            .http_profile_svc
+            .ok_or_else(|| anyhow!("no http profile service"))?;
+        let migration_svc = self
+            .migration_svc
+            .ok_or_else(|| anyhow!("no migration service"))?;
         let extension_manager = self
             .ext_manager
             .ok_or_else(|| anyhow!("no extension manager"))?;
         let download_tx = self
             .download_tx
             .ok_or_else(|| anyhow!("no download sender"))?;
+        let prefetch_tx = self
+            .prefetch_tx
+            .ok_or_else(|| anyhow!("no prefetch sender"))?;
         let notifier = self.notifier.ok_or_else(|| anyhow!("no notifier"))?;
         let chapter_update_receiver = self
             .chapter_update_receiver
@@ -239,17 +432,29 @@ impl ServerBuilder {
         let schema = SchemaBuilder::new()
             .data(config.clone())
             .data(user_svc)
+            .data(session_svc.clone())
+            .data(api_key_svc.clone())
+            .data(oidc_svc)
+            .data(totp_svc)
+            .data(login_throttle_svc)
+            .data(audit_log_svc)
+            .data(content_policy_svc.clone())
+            .data(preference_svc)
+            .data(statistics_svc)
             .data(tracker_svc)
             .data(source_svc)
-            .data(manga_svc)
+            .data(manga_svc.clone())
             .data(chapter_svc)
             .data(image_svc.clone())
             .data(library_svc)
             .data(history_svc)
             .data(download_svc)
+            .data(http_profile_svc)
+            .data(migration_svc)
             .loader(loader)
             .data(extension_manager)
             .data(download_tx)
+            .data(prefetch_tx)
             .data(notifier)
             .data(chapter_update_receiver)
             .data(chapter_update_command_tx)
@@ -275,6 +480,11 @@ impl ServerBuilder {
 
         router = router
             .layer(Extension(config))
+            .layer(Extension(session_svc))
+            .layer(Extension(api_key_svc))
+            .layer(Extension(proxy_auth_svc))
+            .layer(Extension(content_policy_svc))
+            .layer(Extension(manga_svc))
             .layer(Extension(schema))
             .layer(
                 CorsLayer::new()
@@ -290,7 +500,7 @@ impl ServerBuilder {
         }
 
         axum::Server::bind(&addr.into())
-            .serve(router.into_make_service())
+            .serve(router.into_make_service_with_connect_info::<SocketAddr>())
             .await?;
 
         Ok(())
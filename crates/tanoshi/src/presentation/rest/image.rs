@@ -1,18 +1,40 @@
+use std::{ops::Bound, time::SystemTime};
+
 use axum::{
     body::Body,
-    extract::{Extension, Path, Query},
-    http::{Response, StatusCode},
+    extract::{Extension, Path, Query, TypedHeader},
+    http::{HeaderValue, Response, StatusCode},
     response::IntoResponse,
 };
-
+use chrono::{TimeZone, Utc};
+use headers::{
+    AcceptRanges, ContentRange, ETag, HeaderMapExt, IfModifiedSince, IfNoneMatch, LastModified,
+    Range,
+};
 use serde::Deserialize;
 
 use crate::{
-    domain::services::image::ImageService,
+    domain::{
+        repositories::image::ImageRepositoryError,
+        services::{
+            api_key::ApiKeyService,
+            content_policy::ContentPolicyService,
+            image::{ImageError, ImageService},
+            manga::MangaService,
+            proxy_auth::ProxyAuthService,
+            session::SessionService,
+        },
+    },
     infrastructure::{
         config::Config,
-        domain::repositories::{image::ImageRepositoryImpl, image_cache::ImageCacheRepositoryImpl},
+        domain::repositories::{
+            api_key::ApiKeyRepositoryImpl, content_policy::ContentPolicyRepositoryImpl,
+            image::ImageRepositoryImpl, image_cache::ImageCacheRepositoryImpl,
+            manga::MangaRepositoryImpl, session::SessionRepositoryImpl,
+            source::SourceRepositoryImpl, user::UserRepositoryImpl,
+        },
     },
+    presentation::token::{authenticate, Token},
 };
 
 #[derive(Debug, Deserialize)]
@@ -20,21 +42,270 @@ pub struct Params {
     referer: Option<String>,
 }
 
+#[derive(Debug, PartialEq, Eq)]
+enum ByteRange {
+    Full,
+    Partial(u64, u64),
+    Unsatisfiable,
+}
+
+/// Resolve a `Range` header into an inclusive byte range, only a single range is
+/// supported and multiple ranges fall back to the full content.
+fn byte_range(range: Option<&Range>, len: u64) -> ByteRange {
+    let ranges = match range {
+        Some(range) => range.iter().collect::<Vec<_>>(),
+        None => return ByteRange::Full,
+    };
+
+    let (start, end) = match ranges.as_slice() {
+        [(Bound::Included(start), Bound::Included(end))] => {
+            (*start, (*end).min(len.saturating_sub(1)))
+        }
+        [(Bound::Included(start), Bound::Unbounded)] => (*start, len.saturating_sub(1)),
+        [(Bound::Unbounded, Bound::Included(suffix))] if *suffix > 0 => {
+            (len.saturating_sub(*suffix), len.saturating_sub(1))
+        }
+        [(Bound::Unbounded, Bound::Included(_))] => return ByteRange::Unsatisfiable,
+        _ => return ByteRange::Full,
+    };
+
+    if len == 0 || start > end || start >= len {
+        return ByteRange::Unsatisfiable;
+    }
+
+    ByteRange::Partial(start, end)
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn fetch_image(
     Path(encrypted_url): Path<String>,
     Query(params): Query<Params>,
     Extension(config): Extension<Config>,
     Extension(svc): Extension<ImageService<ImageCacheRepositoryImpl, ImageRepositoryImpl>>,
+    Extension(session_svc): Extension<SessionService<SessionRepositoryImpl, UserRepositoryImpl>>,
+    Extension(api_key_svc): Extension<ApiKeyService<ApiKeyRepositoryImpl, UserRepositoryImpl>>,
+    Extension(proxy_auth_svc): Extension<ProxyAuthService<UserRepositoryImpl>>,
+    Extension(content_policy_svc): Extension<
+        ContentPolicyService<ContentPolicyRepositoryImpl, SourceRepositoryImpl>,
+    >,
+    Extension(manga_svc): Extension<MangaService<MangaRepositoryImpl>>,
+    token: Token,
+    if_none_match: Option<TypedHeader<IfNoneMatch>>,
+    if_modified_since: Option<TypedHeader<IfModifiedSince>>,
+    range: Option<TypedHeader<Range>>,
 ) -> Result<impl IntoResponse, StatusCode> {
-    let image = svc
-        .fetch_image(&config.secret, &encrypted_url, params.referer.as_ref())
+    let (_, origin, user_id) = svc
+        .verify_image_url(&config.secret, &encrypted_url)
+        .map_err(|e| match e {
+            ImageError::Expired => StatusCode::GONE,
+            _ => StatusCode::FORBIDDEN,
+        })?;
+
+    // signed url is enough on its own, but a request sent with a token, or through an
+    // authenticating proxy, has to come from the user the url was created for
+    let requester = match token {
+        Token::None => None,
+        token => {
+            let claims = authenticate(token, &session_svc, &api_key_svc, &proxy_auth_svc)
+                .await
+                .ok_or(StatusCode::UNAUTHORIZED)?;
+            Some(claims.sub)
+        }
+    };
+
+    // url is bound to the user it was created for, apply their content policy. Manga that
+    // is no longer stored has no genre to check, only its source is checked then.
+    let manga = match origin.manga_id {
+        Some(manga_id) => manga_svc.fetch_manga_by_id(manga_id, false).await.ok(),
+        None => None,
+    };
+    if let Some(manga) = manga {
+        content_policy_svc
+            .check_manga(user_id, &manga)
+            .await
+            .map_err(|_| StatusCode::FORBIDDEN)?;
+    } else if let Some(source_id) = origin.source_id {
+        content_policy_svc
+            .check_source(user_id, source_id)
+            .await
+            .map_err(|_| StatusCode::FORBIDDEN)?;
+    }
+
+    let content = svc
+        .fetch_image(
+            &config.secret,
+            &encrypted_url,
+            params.referer.as_ref(),
+            requester,
+        )
         .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    Ok(Response::builder()
-        .header("Content-Type", image.content_type)
-        .header("Content-Length", image.data.len())
-        .header("Cache-Control", "max-age=864000")
-        .body(Body::from(image.data))
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?)
+        .map_err(|e| match e {
+            ImageError::InvalidUrl | ImageError::UserMismatch => StatusCode::FORBIDDEN,
+            ImageError::Expired => StatusCode::GONE,
+            ImageError::RepositoryError(ImageRepositoryError::HostNotAllowed(_)) => {
+                StatusCode::FORBIDDEN
+            }
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        })?;
+
+    let etag = content.etag().parse::<ETag>().ok();
+    let last_modified = content
+        .last_modified()
+        .map(|last_modified| SystemTime::from(Utc.from_utc_datetime(&last_modified)));
+
+    // If-None-Match takes precedence over If-Modified-Since
+    let not_modified = if let Some(TypedHeader(if_none_match)) = if_none_match {
+        etag.as_ref()
+            .map(|etag| !if_none_match.precondition_passes(etag))
+            .unwrap_or(false)
+    } else if let Some(TypedHeader(if_modified_since)) = if_modified_since {
+        last_modified
+            .map(|last_modified| !if_modified_since.is_modified(last_modified))
+            .unwrap_or(false)
+    } else {
+        false
+    };
+
+    let mut content_range = None;
+    let mut response = if not_modified {
+        Response::builder()
+            .status(StatusCode::NOT_MODIFIED)
+            .body(Body::empty())
+    } else {
+        let content = svc
+            .extract_image(content)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        let len = content.size().unwrap_or_default();
+        let content_type = content.content_type();
+
+        match byte_range(range.as_ref().map(|TypedHeader(range)| range), len) {
+            ByteRange::Full => {
+                let data = svc
+                    .read_image(&content, 0, len)
+                    .await
+                    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+                Response::builder()
+                    .header("Content-Type", content_type)
+                    .header("Content-Length", data.len())
+                    .body(Body::from(data))
+            }
+            ByteRange::Partial(start, end) => {
+                let data = svc
+                    .read_image(&content, start, end - start + 1)
+                    .await
+                    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+                content_range = ContentRange::bytes(start..=end, len).ok();
+                Response::builder()
+                    .status(StatusCode::PARTIAL_CONTENT)
+                    .header("Content-Type", content_type)
+                    .header("Content-Length", data.len())
+                    .body(Body::from(data))
+            }
+            ByteRange::Unsatisfiable => {
+                content_range = Some(ContentRange::unsatisfied_bytes(len));
+                Response::builder()
+                    .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                    .body(Body::empty())
+            }
+        }
+    }
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let headers = response.headers_mut();
+    headers.insert("Cache-Control", HeaderValue::from_static("max-age=864000"));
+    headers.typed_insert(AcceptRanges::bytes());
+    if let Some(etag) = etag {
+        headers.typed_insert(etag);
+    }
+    if let Some(last_modified) = last_modified {
+        headers.typed_insert(LastModified::from(last_modified));
+    }
+    if let Some(content_range) = content_range {
+        headers.typed_insert(content_range);
+    }
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod test {
+    use headers::Header;
+
+    use super::*;
+
+    fn range(value: &'static str) -> Range {
+        Range::decode(&mut std::iter::once(&HeaderValue::from_static(value))).unwrap()
+    }
+
+    #[test]
+    fn test_byte_range_without_header() {
+        assert_eq!(byte_range(None, 10), ByteRange::Full);
+    }
+
+    #[test]
+    fn test_byte_range_closed() {
+        assert_eq!(
+            byte_range(Some(&range("bytes=2-5")), 10),
+            ByteRange::Partial(2, 5)
+        );
+        // end past the content is clamped to the last byte
+        assert_eq!(
+            byte_range(Some(&range("bytes=2-50")), 10),
+            ByteRange::Partial(2, 9)
+        );
+    }
+
+    #[test]
+    fn test_byte_range_open_ended() {
+        assert_eq!(
+            byte_range(Some(&range("bytes=4-")), 10),
+            ByteRange::Partial(4, 9)
+        );
+        assert_eq!(
+            byte_range(Some(&range("bytes=0-")), 10),
+            ByteRange::Partial(0, 9)
+        );
+    }
+
+    #[test]
+    fn test_byte_range_suffix() {
+        assert_eq!(
+            byte_range(Some(&range("bytes=-3")), 10),
+            ByteRange::Partial(7, 9)
+        );
+        // suffix longer than the content is the whole content
+        assert_eq!(
+            byte_range(Some(&range("bytes=-30")), 10),
+            ByteRange::Partial(0, 9)
+        );
+        assert_eq!(
+            byte_range(Some(&range("bytes=-0")), 10),
+            ByteRange::Unsatisfiable
+        );
+    }
+
+    #[test]
+    fn test_byte_range_out_of_range() {
+        assert_eq!(
+            byte_range(Some(&range("bytes=10-")), 10),
+            ByteRange::Unsatisfiable
+        );
+        assert_eq!(
+            byte_range(Some(&range("bytes=20-30")), 10),
+            ByteRange::Unsatisfiable
+        );
+        assert_eq!(
+            byte_range(Some(&range("bytes=0-")), 0),
+            ByteRange::Unsatisfiable
+        );
+    }
+
+    #[test]
+    fn test_byte_range_multiple_ranges() {
+        assert_eq!(
+            byte_range(Some(&range("bytes=0-1, 4-5")), 10),
+            ByteRange::Full
+        );
+    }
 }
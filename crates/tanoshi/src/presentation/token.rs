@@ -1,12 +1,48 @@
+use std::net::SocketAddr;
+
 use axum::{
     async_trait,
-    extract::{FromRequestParts, TypedHeader},
+    extract::{ConnectInfo, Extension, FromRequestParts, TypedHeader},
     http::request::Parts,
     RequestPartsExt,
 };
 use headers::{authorization::Bearer, Authorization};
 
-pub struct Token(pub String);
+use crate::{
+    domain::services::{
+        api_key::{is_api_key, ApiKeyService},
+        proxy_auth::ProxyAuthService,
+        session::SessionService,
+    },
+    infrastructure::{
+        auth::Claims,
+        domain::repositories::{
+            api_key::ApiKeyRepositoryImpl, session::SessionRepositoryImpl, user::UserRepositoryImpl,
+        },
+    },
+};
+
+pub enum Token {
+    /// Access token issued on login
+    Jwt(String),
+    /// Personal api key
+    ApiKey(String),
+    /// Username set by a trusted reverse proxy
+    RemoteUser(String),
+    None,
+}
+
+impl From<String> for Token {
+    fn from(token: String) -> Self {
+        if token.is_empty() {
+            Token::None
+        } else if is_api_key(&token) {
+            Token::ApiKey(token)
+        } else {
+            Token::Jwt(token)
+        }
+    }
+}
 
 #[async_trait]
 impl<S> FromRequestParts<S> for Token
@@ -17,12 +53,66 @@ where
 
     async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
         // Extract the token from the authorization header
+        if let Ok(TypedHeader(Authorization(bearer))) =
+            parts.extract::<TypedHeader<Authorization<Bearer>>>().await
+        {
+            return Ok(Token::from(bearer.token().to_string()));
+        }
+
+        // Api key can also be sent in its own header
         let token = parts
-            .extract::<TypedHeader<Authorization<Bearer>>>()
-            .await
-            .map(|TypedHeader(Authorization(bearer))| Token(bearer.token().to_string()))
-            .unwrap_or_else(|_| Token("".to_string()));
+            .headers
+            .get("x-api-key")
+            .and_then(|value| value.to_str().ok())
+            .filter(|value| is_api_key(value))
+            .map(|value| Token::ApiKey(value.to_string()));
+        if let Some(token) = token {
+            return Ok(token);
+        }
+
+        // Username header is only accepted from trusted proxies
+        let addr = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+        if let (Some(addr), Ok(Extension(proxy_auth_svc))) = (
+            addr,
+            parts
+                .extract::<Extension<ProxyAuthService<UserRepositoryImpl>>>()
+                .await,
+        ) {
+            let username = proxy_auth_svc
+                .trusted_header(addr)
+                .and_then(|header| parts.headers.get(header))
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.trim())
+                .filter(|value| !value.is_empty());
+            if let Some(username) = username {
+                return Ok(Token::RemoteUser(username.to_string()));
+            }
+        }
+
+        Ok(Token::None)
+    }
+}
 
-        Ok(token)
+/// Claims of the user a token belongs to, `None` without a token or when it is not valid
+pub async fn authenticate(
+    token: Token,
+    session_svc: &SessionService<SessionRepositoryImpl, UserRepositoryImpl>,
+    api_key_svc: &ApiKeyService<ApiKeyRepositoryImpl, UserRepositoryImpl>,
+    proxy_auth_svc: &ProxyAuthService<UserRepositoryImpl>,
+) -> Option<Claims> {
+    match token {
+        Token::Jwt(token) => session_svc.authenticate(&token).await.ok(),
+        Token::ApiKey(key) => api_key_svc.authenticate(&key).await.ok(),
+        Token::RemoteUser(username) => match proxy_auth_svc.authenticate(&username).await {
+            Ok(claims) => Some(claims),
+            Err(e) => {
+                warn!("proxy auth failed: {e}");
+                None
+            }
+        },
+        Token::None => None,
     }
 }